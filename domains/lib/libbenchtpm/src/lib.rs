@@ -30,7 +30,9 @@ pub fn test_tpm(tpm: &dyn UsrTpm) {
     println!("validate locality {}", tpm.tpm_validate_locality(locality).unwrap());
 
     // Get 1 byte of random value
-    println!("random {}", tpm.tpm_get_random(locality, 1).unwrap());
+    let mut random: Vec<u8> = Vec::new();
+    println!("random {}", tpm.tpm_get_random(locality, 1, &mut random).unwrap());
+    println!("random bytes {:x?}", random);
 
     // PCR extend
     // First we obtain "banks" that are allocated in the TPM.
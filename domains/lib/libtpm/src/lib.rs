@@ -368,7 +368,12 @@ pub fn tpm_transmit_cmd(tpm: &dyn TpmDev, locality: u32, buf: &mut Vec<u8>) {
 /// Table 3:68 - TPM2_GetRandom Command
 /// Get a random number from TPM.
 /// `num_octets` represents the length of the random number in bytes
-pub fn tpm_get_random(tpm: &dyn TpmDev, locality: u32, num_octets: usize) -> bool {
+pub fn tpm_get_random(
+    tpm: &dyn TpmDev,
+    locality: u32,
+    num_octets: usize,
+    random: &mut Vec<u8>,
+) -> bool {
     let mut buf: Vec<u8>;
 
     // header: TPM_HEADER
@@ -388,7 +393,17 @@ pub fn tpm_get_random(tpm: &dyn TpmDev, locality: u32, num_octets: usize) -> boo
     // Send command
     println!("presend: {:x?}", buf);
     tpm_transmit_cmd(tpm, locality, &mut buf);
+
+    // Parse response
+    // randomBytes: TPM2B_DIGEST, may be shorter than requested
     println!("postsend: {:x?}", buf);
+    if buf.len() >= 2 {
+        let size = BigEndian::read_u16(&buf[0..2]) as usize;
+        random.extend_from_slice(&buf[2..(2 + size).min(buf.len())]);
+    } else {
+        println!("Didn't receive any response from TPM!");
+        return false;
+    }
     true
 }
 
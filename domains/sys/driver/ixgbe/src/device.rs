@@ -17,6 +17,9 @@ const PACKET_SIZE: usize = 60;
 
 pub struct Intel8259x {
     pub device: IxgbeDevice,
+    // The statistics registers clear on read. Counts `peek_stats` read that the next
+    // `get_stats` still has to return.
+    peeked: NetworkStats,
 }
 
 impl Intel8259x {
@@ -25,6 +28,7 @@ impl Intel8259x {
         #[rustfmt::skip]
         let mut module = Intel8259x {
             device: IxgbeDevice::new(bar),
+            peeked: NetworkStats::new(),
         };
 
         println!("Calling module.init for ixgbe");
@@ -463,7 +467,21 @@ impl Intel8259x {
         self.dump_stats();
     }
 
-    pub fn get_stats(&self) -> NetworkStats {
+    pub fn get_stats(&mut self) -> NetworkStats {
+        let mut stats = self.read_stats();
+        stats.add(&self.peeked);
+        self.peeked = NetworkStats::new();
+        stats
+    }
+
+    pub fn peek_stats(&mut self) -> NetworkStats {
+        let stats = self.read_stats();
+        self.peeked.add(&stats);
+        self.peeked
+    }
+
+    // Counts since the registers were last read
+    fn read_stats(&self) -> NetworkStats {
         NetworkStats {
             tx_count: self.read_reg(IxgbeRegs::GPTC),
             rx_count: self.read_reg(IxgbeRegs::GPRC),
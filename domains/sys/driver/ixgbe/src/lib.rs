@@ -183,6 +183,16 @@ impl interface::net::Net for Ixgbe {
         })())
     }
 
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>> {
+        Ok((|| {
+            let ixgbe = self.lock();
+
+            let device = &mut ixgbe.device.borrow_mut();
+            let device = device.as_mut().ok_or(ErrorKind::UninitializedDevice)?;
+            Ok(device.peek_stats())
+        })())
+    }
+
     fn test_domain_crossing(&self) -> RpcResult<()> {
         Ok(())
     }
@@ -244,4 +244,8 @@ impl NvmeDev {
         self.device.stats.reset_stats();
         (s, c)
     }
+
+    pub fn peek_stats(&self) -> (u64, u64) {
+        self.device.stats.get_stats()
+    }
 }
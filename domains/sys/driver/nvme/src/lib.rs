@@ -107,6 +107,14 @@ impl interface::bdev::NvmeBDev for Nvme {
             Ok(device.get_stats())
         })())
     }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        Ok((|| {
+            let device = &self.device.borrow();
+            let device = device.as_ref().ok_or(ErrorKind::UninitializedDevice)?;
+            Ok(device.peek_stats())
+        })())
+    }
 }
 
 impl pci_driver::PciDriver for Nvme {
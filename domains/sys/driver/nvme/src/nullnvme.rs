@@ -29,4 +29,8 @@ impl NvmeBDev for NullNvme {
     fn get_stats(&self) -> RpcResult<Result<(u64, u64)>> {
         Ok(Ok((0, 0)))
     }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        Ok(Ok((0, 0)))
+    }
 }
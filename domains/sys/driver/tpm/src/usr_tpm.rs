@@ -91,8 +91,14 @@ generate_tpm!(
     
         /// Table 3:68 - TPM2_GetRandom Command
         /// Get a random number from TPM.
-        /// `num_octets` represents the length of the random number in bytes
-        fn tpm_get_random(&self, locality: u32, num_octets: usize) -> RpcResult<bool>;
+        /// `num_octets` represents the length of the random number in bytes, the bytes are appended
+        /// to `random`. The TPM may return fewer bytes than requested
+        fn tpm_get_random(
+            &self,
+            locality: u32,
+            num_octets: usize,
+            random: &mut Vec<u8>,
+        ) -> RpcResult<bool>;
     
         /// Table 3:114 - TPM2_PCR_Read Command
         /// Read a PCR register.
@@ -71,6 +71,11 @@ impl interface::bdev::NvmeBDev for VirtioBlock {
         // Dummy Data
         Ok(Ok((9, 9)))
     }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        // Dummy Data
+        Ok(Ok((9, 9)))
+    }
 }
 
 pub fn main(pci: Box<dyn interface::pci::PCI>) -> Box<dyn interface::bdev::NvmeBDev> {
@@ -108,6 +108,10 @@ impl interface::net::Net for VirtioNet {
         }))
     }
 
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>> {
+        self.get_stats()
+    }
+
     fn test_domain_crossing(&self) -> RpcResult<()> {
         unimplemented!()
     }
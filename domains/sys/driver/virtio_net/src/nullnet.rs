@@ -62,6 +62,10 @@ impl interface::net::Net for NullNet {
         Ok(Ok(NetworkStats::new()))
     }
 
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>> {
+        Ok(Ok(NetworkStats::new()))
+    }
+
     fn test_domain_crossing(&self) -> RpcResult<()> {
         Ok(())
     }
@@ -12,6 +12,7 @@ use alloc::sync::Arc;
 use alloc::vec::Vec;
 use console::println;
 use core::panic::PanicInfo;
+use interface::domain_create;
 use interface::domain_create::*;
 use interface::rref::RRefVec;
 use libsyscalls::syscalls::{
@@ -100,6 +101,7 @@ pub fn trusted_entry(
     heap: Box<dyn syscalls::Heap + Send + Sync>,
     ints: Box<dyn syscalls::Interrupt + Send + Sync>,
     create_proxy: Arc<dyn interface::domain_create::CreateProxy>,
+    create_domain: Arc<dyn interface::domain_create::CreateDomain>,
     create_pci: Arc<dyn interface::domain_create::CreatePCI>,
     create_membdev: Arc<dyn interface::domain_create::CreateMemBDev>,
     create_bdev_shadow: Arc<dyn interface::domain_create::CreateBDevShadow>,
@@ -183,6 +185,7 @@ pub fn trusted_entry(
 
     println!("about to create proxy");
     let (_dom_proxy, proxy) = create_proxy.create_domain_proxy(
+        create_domain,
         create_pci,
         // create_ahci,
         create_membdev,
@@ -296,8 +299,35 @@ pub fn trusted_entry(
         .as_domain_create_CreateBenchnvme()
         .create_domain_benchnvme(nvme);
 
-    #[cfg(not(any(feature = "benchnet", feature = "benchnvme")))]
+    #[cfg(feature = "crashfs")]
     {
+        println!("Starting the rv6 crash-consistency test");
+        let (_dom_crashfs, ()) = domain_create::create_domain::<entry::CrashFs>(
+            &*proxy.as_domain_create_CreateDomain(),
+            (proxy.as_domain_create_CreateRv6FS(), bdev),
+        )
+        .expect("failed to create crashfs");
+    }
+
+    #[cfg(not(any(feature = "benchnet", feature = "benchnvme", feature = "crashfs")))]
+    {
+        // Run the rv6 file system on NVMe. rv6's raw block device interface then gets the
+        // virtio-block domain, which is a null device unless its driver is enabled.
+        #[cfg(feature = "rv6_nvme")]
+        let (bdev, nvme) = {
+            #[cfg(not(feature = "virtio_block"))]
+            let (_, virtio_block) = proxy
+                .as_domain_create_CreateVirtioBlock()
+                .create_domain_virtio_block(pci.pci_clone().unwrap());
+            println!("Creating nvme_bdev");
+            let (_dom_nvme_bdev, bdev) = domain_create::create_domain::<entry::NvmeBDevAdapter>(
+                &*proxy.as_domain_create_CreateDomain(),
+                (nvme,),
+            )
+            .expect("failed to create nvme_bdev");
+            (bdev, virtio_block)
+        };
+
         println!("Starting xv6 kernel");
         let (_dom_xv6, rv6) = proxy.as_domain_create_CreateRv6().create_domain_xv6kernel(
             ints_clone,
@@ -314,7 +344,8 @@ pub fn trusted_entry(
         rv6.sys_spawn_domain(
             rv6.clone_rv6().unwrap(),
             RRefVec::from_slice("/init".as_bytes()),
-            RRefVec::from_slice("/init".as_bytes()),
+            RRefVec::from_slice("/init\0".as_bytes()),
+            RRefVec::from_slice("PATH=/\0".as_bytes()),
             array_init::array_init(|_| None),
         )
         .unwrap()
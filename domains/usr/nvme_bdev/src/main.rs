@@ -0,0 +1,149 @@
+#![no_std]
+#![no_main]
+extern crate alloc;
+extern crate malloc;
+
+use syscalls::{Heap, Syscall};
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use console::println;
+
+use core::panic::PanicInfo;
+
+use interface::bdev::{BDev, BlkReq, NvmeBDev, BSIZE};
+use interface::error::Result;
+use interface::rpc::RpcResult;
+use interface::rref::{RRef, RRefDeque};
+use spin::Mutex;
+
+type BlkQueue = RRefDeque<BlkReq, 128>;
+
+struct Adapter {
+    nvme: Box<dyn NvmeBDev>,
+    // Queue the device hands completed requests back in
+    collect: Option<BlkQueue>,
+    // Empty queues, reused for submitting and returning batches
+    queues: Vec<BlkQueue>,
+    // Request buffers for single block reads and writes
+    requests: Vec<RRef<BlkReq>>,
+}
+
+impl Adapter {
+    fn new(nvme: Box<dyn NvmeBDev>) -> Self {
+        Self {
+            nvme,
+            collect: Some(BlkQueue::default()),
+            queues: Vec::new(),
+            requests: Vec::new(),
+        }
+    }
+
+    fn queue(&mut self) -> BlkQueue {
+        self.queues.pop().unwrap_or_default()
+    }
+
+    // Submits `reqs` and polls the device until all of them completed.
+    // Completions come back in whatever order the device finished them.
+    fn submit(&mut self, reqs: BlkQueue, write: bool) -> Result<BlkQueue> {
+        let mut outstanding = reqs.len();
+        let mut submit = reqs;
+        let mut collect = self.collect.take().unwrap_or_default();
+        let mut done = self.queue();
+
+        while outstanding > 0 {
+            let (_, submit_, mut collect_) =
+                self.nvme.submit_and_poll_rref(submit, collect, write)??;
+            while let Some(req) = collect_.pop_front() {
+                assert!(done.push_back(req).is_none());
+                outstanding -= 1;
+            }
+            submit = submit_;
+            collect = collect_;
+        }
+
+        self.collect = Some(collect);
+        self.queues.push(submit);
+        Ok(done)
+    }
+
+    fn submit_one(&mut self, block: u32, data: Option<&[u8; BSIZE]>) -> RRef<BlkReq> {
+        let mut req = self
+            .requests
+            .pop()
+            .unwrap_or_else(|| RRef::new(BlkReq::new()));
+        req.block = block as u64;
+        if let Some(data) = data {
+            req.data = *data;
+        }
+
+        let mut batch = self.queue();
+        batch.push_back(req);
+
+        let write = data.is_some();
+        let mut done = match self.submit(batch, write) {
+            Ok(done) => done,
+            Err(e) => panic!("nvme_bdev: {} of block {} failed: {:?}", if write { "write" } else { "read" }, block, e),
+        };
+        let req = done.pop_front().unwrap();
+        self.queues.push(done);
+        req
+    }
+
+    fn read(&mut self, block: u32, mut data: RRef<[u8; BSIZE]>) -> RRef<[u8; BSIZE]> {
+        let req = self.submit_one(block, None);
+        *data = req.data;
+        self.requests.push(req);
+        data
+    }
+
+    fn write(&mut self, block: u32, data: &RRef<[u8; BSIZE]>) {
+        let req = self.submit_one(block, Some(&**data));
+        self.requests.push(req);
+    }
+}
+
+/// Exposes an `NvmeBDev` (NVMe or virtio-block) as a `BDev`, so that the rv6 file system can
+/// run on it. Batches from `read_blocks`/`write_blocks` go to the device in one submission.
+struct NvmeBDevAdapter(Mutex<Adapter>);
+
+impl BDev for NvmeBDevAdapter {
+    fn read(&self, block: u32, data: RRef<[u8; BSIZE]>) -> RpcResult<RRef<[u8; BSIZE]>> {
+        Ok(self.0.lock().read(block, data))
+    }
+
+    fn write(&self, block: u32, data: &RRef<[u8; BSIZE]>) -> RpcResult<()> {
+        Ok(self.0.lock().write(block, data))
+    }
+
+    fn read_blocks(&self, reqs: BlkQueue) -> RpcResult<Result<BlkQueue>> {
+        Ok(self.0.lock().submit(reqs, false))
+    }
+
+    fn write_blocks(&self, reqs: BlkQueue) -> RpcResult<Result<BlkQueue>> {
+        Ok(self.0.lock().submit(reqs, true))
+    }
+}
+
+#[no_mangle]
+pub fn trusted_entry(
+    s: Box<dyn Syscall + Send + Sync>,
+    heap: Box<dyn Heap + Send + Sync>,
+    nvme: Box<dyn NvmeBDev>,
+) -> Box<dyn BDev> {
+    libsyscalls::syscalls::init(s);
+    interface::rref::init(heap, libsyscalls::syscalls::sys_get_current_domain_id());
+
+    println!("Init domain nvme_bdev");
+
+    Box::new(NvmeBDevAdapter(Mutex::new(Adapter::new(nvme))))
+}
+
+// This function is called on panic.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    println!("nvme_bdev panic: {:?}", info);
+    libsyscalls::syscalls::sys_backtrace();
+    loop {}
+}
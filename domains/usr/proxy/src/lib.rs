@@ -16,6 +16,7 @@ use libsyscalls;
 use syscalls;
 
 pub fn main(
+    create_domain: alloc::sync::Arc<dyn interface::domain_create::CreateDomain>,
     create_pci: alloc::sync::Arc<dyn interface::domain_create::CreatePCI>,
     create_membdev: alloc::sync::Arc<dyn interface::domain_create::CreateMemBDev>,
     create_bdev_shadow: alloc::sync::Arc<dyn interface::domain_create::CreateBDevShadow>,
@@ -37,6 +38,7 @@ pub fn main(
     create_tpm: alloc::sync::Arc<dyn interface::domain_create::CreateTpm>,
 ) -> Arc<dyn interface::proxy::Proxy> {
     Arc::new(interface::proxy::ProxyObject::new(
+        create_domain,
         create_pci,
         create_membdev,
         create_bdev_shadow,
@@ -12,10 +12,11 @@ use console::println;
 
 use core::panic::PanicInfo;
 
-use interface::rref::RRef;
+use interface::rref::{RRef, RRefDeque};
 
-use interface::bdev::{BDev, BSIZE};
+use interface::bdev::{BDev, BlkReq, BSIZE};
 use interface::domain_create::CreateMemBDev;
+use interface::error::Result;
 use interface::rpc::RpcResult;
 use spin::Mutex;
 
@@ -106,6 +107,23 @@ impl ShadowInternal {
             break r;
         }
     }
+
+    // A batch is lost if membdev crashes while handling it, so we forward it one block at a
+    // time and retry each block on its own. Batching buys nothing for a memory disk anyway.
+    fn read_blocks(&mut self, mut reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        for req in reqs.iter_mut() {
+            let data = self.read(req.block as u32, RRef::new([0u8; BSIZE]))?;
+            req.data = *data;
+        }
+        Ok(Ok(reqs))
+    }
+
+    fn write_blocks(&mut self, reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        for req in reqs.iter() {
+            self.write(req.block as u32, &RRef::new(req.data))?;
+        }
+        Ok(Ok(reqs))
+    }
 }
 
 struct Shadow {
@@ -128,6 +146,14 @@ impl BDev for Shadow {
     fn write(&self, block: u32, data: &RRef<[u8; BSIZE]>) -> RpcResult<()> {
         self.shadow.lock().write(block, data)
     }
+
+    fn read_blocks(&self, reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        self.shadow.lock().read_blocks(reqs)
+    }
+
+    fn write_blocks(&self, reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        self.shadow.lock().write_blocks(reqs)
+    }
 }
 
 pub fn main(create_bdev: Arc<dyn CreateMemBDev>) -> Box<dyn BDev> {
@@ -99,6 +99,10 @@ impl Net for Shadow {
         self.shadow.lock().net.get_stats()
     }
 
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>> {
+        self.shadow.lock().net.peek_stats()
+    }
+
     fn test_domain_crossing(&self) -> RpcResult<()> {
         self.shadow.lock().net.test_domain_crossing()
     }
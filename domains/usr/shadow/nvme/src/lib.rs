@@ -75,6 +75,10 @@ impl NvmeBDev for Shadow {
     fn get_stats(&self) -> RpcResult<Result<(u64, u64)>> {
         self.shadow.lock().nvme.get_stats()
     }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        self.shadow.lock().nvme.peek_stats()
+    }
 }
 
 pub fn main(create: Arc<dyn CreateNvme>, pci: Box<dyn PCI>) -> Box<dyn NvmeBDev> {
@@ -0,0 +1,124 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+use interface::bdev::{BDev, BlkReq, BSIZE};
+use interface::error::Result;
+use interface::rpc::RpcResult;
+use interface::rref::{RRef, RRefDeque};
+
+type BlkQueue = RRefDeque<BlkReq, 128>;
+
+// `BDev` addresses 512-byte sectors, the file system 4K blocks
+pub const SECTORS_PER_BLOCK: u32 = (BSIZE / 512) as u32;
+
+pub type Block = Box<[u8; BSIZE]>;
+
+/// A block write, as (first sector, data)
+pub type Write = (u32, Block);
+
+/// The state of the disk: the base device, which is never written to, plus the blocks written
+/// on top of it.
+pub struct Disk {
+    base: Arc<dyn BDev>,
+    written: BTreeMap<u32, Block>,
+    // Every write in the order it reached the device, while recording
+    trace: Option<Vec<Write>>,
+}
+
+impl Disk {
+    /// A disk that records every write
+    pub fn recording(base: Arc<dyn BDev>) -> Self {
+        Self {
+            base,
+            written: BTreeMap::new(),
+            trace: Some(Vec::new()),
+        }
+    }
+
+    /// The disk as it was when the power was cut after `writes` reached it
+    pub fn after(base: Arc<dyn BDev>, writes: &[Write]) -> Self {
+        let mut written = BTreeMap::new();
+        for (sector, data) in writes {
+            written.insert(*sector, data.clone());
+        }
+        Self {
+            base,
+            written,
+            trace: None,
+        }
+    }
+
+    pub fn take_trace(&mut self) -> Vec<Write> {
+        self.trace.take().unwrap_or_default()
+    }
+
+    /// Reads file system block `block`
+    pub fn read_block(&self, block: u32) -> Block {
+        let sector = block * SECTORS_PER_BLOCK;
+        match self.written.get(&sector) {
+            Some(data) => data.clone(),
+            None => {
+                let data = self
+                    .base
+                    .read(sector, RRef::new([0u8; BSIZE]))
+                    .expect("crashfs: read from the base device failed");
+                Box::new(*data)
+            }
+        }
+    }
+
+    fn write(&mut self, sector: u32, data: &[u8; BSIZE]) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push((sector, Box::new(*data)));
+        }
+        self.written.insert(sector, Box::new(*data));
+    }
+}
+
+/// The block device the file system under test runs on
+pub struct CrashBDev(pub Arc<Mutex<Disk>>);
+
+impl BDev for CrashBDev {
+    fn read(&self, block: u32, mut data: RRef<[u8; BSIZE]>) -> RpcResult<RRef<[u8; BSIZE]>> {
+        let disk = self.0.lock();
+        match disk.written.get(&block) {
+            Some(written) => {
+                *data = **written;
+                Ok(data)
+            }
+            None => disk.base.read(block, data),
+        }
+    }
+
+    fn write(&self, block: u32, data: &RRef<[u8; BSIZE]>) -> RpcResult<()> {
+        self.0.lock().write(block, &**data);
+        Ok(())
+    }
+
+    fn read_blocks(&self, reqs: BlkQueue) -> RpcResult<Result<BlkQueue>> {
+        let disk = self.0.lock();
+        let mut reqs = match disk.base.read_blocks(reqs)? {
+            Ok(reqs) => reqs,
+            Err(e) => return Ok(Err(e)),
+        };
+        for req in reqs.iter_mut() {
+            if let Some(written) = disk.written.get(&(req.block as u32)) {
+                req.data = **written;
+            }
+        }
+        Ok(Ok(reqs))
+    }
+
+    // The requests of a batch are recorded one by one, in the order they were queued
+    fn write_blocks(&self, reqs: BlkQueue) -> RpcResult<Result<BlkQueue>> {
+        let mut disk = self.0.lock();
+        for req in reqs.iter() {
+            assert_eq!(req.data_len, BSIZE, "crashfs: partial block write");
+            disk.write(req.block as u32, &req.data);
+        }
+        Ok(Ok(reqs))
+    }
+}
@@ -0,0 +1,147 @@
+#![no_std]
+#![no_main]
+#[macro_use]
+extern crate alloc;
+extern crate malloc;
+
+use syscalls::{Domain, Heap, Syscall};
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use console::println;
+
+use core::panic::PanicInfo;
+
+use interface::bdev::BDev;
+use interface::domain_create::CreateRv6FS;
+use interface::vfs::VFS;
+use spin::Mutex;
+
+mod check;
+mod disk;
+mod workload;
+
+use disk::{CrashBDev, Disk, Write};
+use workload::{Snapshot, WORKLOAD};
+
+// Problems printed per crash point
+const MAX_PRINTED: usize = 5;
+
+// Mounts a fresh instance of the file system on `disk`, which recovers from the log. The
+// instance replaces the one in `dom`, whose `VFS` must be dropped by now.
+fn mount(
+    create: &dyn CreateRv6FS,
+    dom: &mut Option<Box<dyn Domain>>,
+    disk: &Arc<Mutex<Disk>>,
+) -> Box<dyn VFS> {
+    let bdev: Box<dyn BDev> = Box::new(CrashBDev(disk.clone()));
+    let (new_dom, vfs) = match dom.take() {
+        Some(old_dom) => create.recreate_domain_xv6fs(old_dom, bdev, None, None, None),
+        None => create.create_domain_xv6fs(bdev, None, None, None),
+    };
+    *dom = Some(new_dom);
+    vfs
+}
+
+// Runs the workload once and returns the writes it made and the state after each call
+fn record(
+    create: &dyn CreateRv6FS,
+    dom: &mut Option<Box<dyn Domain>>,
+    base: &Arc<dyn BDev>,
+) -> (Vec<Write>, Vec<Snapshot>) {
+    let disk = Arc::new(Mutex::new(Disk::recording(base.clone())));
+    let vfs = mount(create, dom, &disk);
+
+    let mut snapshots = vec![workload::snapshot(&*vfs).expect("crashfs: snapshot failed")];
+    for op in WORKLOAD {
+        if let Err(e) = op.apply(&*vfs) {
+            panic!("crashfs: {:?} failed: {:?}", op, e);
+        }
+        snapshots.push(workload::snapshot(&*vfs).expect("crashfs: snapshot failed"));
+    }
+
+    let trace = disk.lock().take_trace();
+    (trace, snapshots)
+}
+
+// Cuts the power after every write of the workload, recovers, and checks that the disk is
+// consistent and holds the state before or after each call. Returns the number of crash
+// points with problems.
+fn run(create: &dyn CreateRv6FS, base: Arc<dyn BDev>) -> usize {
+    // The one xv6fs domain, recreated for each crash point
+    let mut dom = None;
+    let (trace, snapshots) = record(create, &mut dom, &base);
+    println!(
+        "crashfs: {} calls made {} writes, crashing after each of them",
+        WORKLOAD.len(),
+        trace.len()
+    );
+
+    let mut failed = 0;
+    // Index of the last call found on disk. A later crash must not lose it.
+    let mut durable = 0;
+    for cut in 0..=trace.len() {
+        let disk = Arc::new(Mutex::new(Disk::after(base.clone(), &trace[..cut])));
+        let vfs = mount(create, &mut dom, &disk);
+
+        let mut problems = check::check(&disk.lock());
+        match workload::snapshot(&*vfs) {
+            Ok(state) => match snapshots.iter().skip(durable).position(|s| *s == state) {
+                Some(i) => durable += i,
+                None => problems.push(format!("the files match no state the workload went through: {:?}", state)),
+            },
+            Err(e) => problems.push(format!("reading the files failed: {:?}", e)),
+        }
+        // The workload ends with a sync, after which calls only repeat the same state
+        if cut == trace.len() && snapshots[durable] != snapshots[WORKLOAD.len()] {
+            problems.push(format!("only {} of {} calls made it to the disk", durable, WORKLOAD.len()));
+        }
+
+        if !problems.is_empty() {
+            failed += 1;
+            println!("crashfs: crash after write {} (sector {}):", cut, cut.checked_sub(1).map_or(0, |i| trace[i].0));
+            for problem in problems.iter().take(MAX_PRINTED) {
+                println!("    {}", problem);
+            }
+            if problems.len() > MAX_PRINTED {
+                println!("    and {} more", problems.len() - MAX_PRINTED);
+            }
+        }
+    }
+
+    println!("crashfs: {} crash points, {} with problems", trace.len() + 1, failed);
+    failed
+}
+
+/// Crash-consistency test of the rv6 file system. `bdev` holds the image to start from and is
+/// never written to: the file system under test runs on a copy-on-write view of it.
+///
+/// Each crash point replaces the xv6fs domain with a fresh one, as rebooting would.
+#[no_mangle]
+pub fn trusted_entry(
+    s: Box<dyn Syscall + Send + Sync>,
+    heap: Box<dyn Heap + Send + Sync>,
+    create_xv6fs: Arc<dyn CreateRv6FS>,
+    bdev: Box<dyn BDev>,
+) {
+    libsyscalls::syscalls::init(s);
+    interface::rref::init(heap, libsyscalls::syscalls::sys_get_current_domain_id());
+
+    println!("Init domain crashfs");
+
+    if run(&*create_xv6fs, Arc::from(bdev)) == 0 {
+        println!("crashfs: passed");
+    } else {
+        println!("crashfs: FAILED");
+    }
+}
+
+// This function is called on panic.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    println!("crashfs panic: {:?}", info);
+    libsyscalls::syscalls::sys_backtrace();
+    loop {}
+}
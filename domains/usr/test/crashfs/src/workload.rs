@@ -0,0 +1,161 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use interface::bdev::BSIZE;
+use interface::error::{ErrorKind, Result};
+use interface::rref::RRefVec;
+use interface::vfs::{FileMode, INodeFileType, MAXPATH, VFS};
+
+// Everything the workload touches lives here, so that snapshots leave out the rest of the image
+pub const ROOT: &str = "/crash";
+
+/// A file system call of the workload. Each of them must be atomic: after a crash, the file
+/// system is in the state before or after it.
+#[derive(Debug)]
+pub enum Op {
+    Mkdir(&'static str),
+    Create(&'static str),
+    /// Writes `len` copies of a byte at the start of a file
+    Write(&'static str, usize, u8),
+    Truncate(&'static str, u64),
+    Link(&'static str, &'static str),
+    Rename(&'static str, &'static str),
+    Symlink(&'static str, &'static str),
+    Unlink(&'static str),
+    /// Commits the log. Calls in between are committed together.
+    Sync,
+}
+
+// Writes up to 25 blocks fit in a single transaction, see `OpenedFile::write`
+pub const WORKLOAD: &[Op] = &[
+    Op::Mkdir(ROOT),
+    Op::Sync,
+    Op::Create("/crash/a"),
+    Op::Write("/crash/a", 3 * BSIZE, b'a'),
+    Op::Sync,
+    Op::Write("/crash/a", 100, b'b'),
+    Op::Sync,
+    Op::Mkdir("/crash/d"),
+    Op::Link("/crash/a", "/crash/d/b"),
+    Op::Rename("/crash/a", "/crash/d/c"),
+    Op::Sync,
+    Op::Symlink("/crash/d/c", "/crash/s"),
+    Op::Sync,
+    Op::Truncate("/crash/d/c", 10),
+    Op::Unlink("/crash/d/b"),
+    Op::Sync,
+    Op::Mkdir("/crash/e"),
+    Op::Rename("/crash/d", "/crash/e/d"),
+    Op::Sync,
+    Op::Unlink("/crash/e/d/c"),
+    Op::Unlink("/crash/s"),
+    Op::Sync,
+];
+
+fn path(path: &str) -> RRefVec<u8> {
+    RRefVec::from_slice(path.as_bytes())
+}
+
+impl Op {
+    pub fn apply(&self, vfs: &dyn VFS) -> Result<()> {
+        match *self {
+            Op::Mkdir(p) => vfs.sys_mkdir(path(p))?,
+            Op::Create(p) => {
+                let (fd, _) = vfs.sys_open(path(p), FileMode::READWRITE | FileMode::CREATE)??;
+                vfs.sys_close(fd)?
+            }
+            Op::Write(p, len, byte) => {
+                let (fd, _) = vfs.sys_open(path(p), FileMode::WRITE)??;
+                let (written, _) = vfs.sys_write(fd, RRefVec::new(byte, len))??;
+                assert_eq!(written, len);
+                vfs.sys_close(fd)?
+            }
+            Op::Truncate(p, size) => {
+                let (fd, _) = vfs.sys_open(path(p), FileMode::WRITE)??;
+                vfs.sys_ftruncate(fd, size)??;
+                vfs.sys_close(fd)?
+            }
+            Op::Link(old, new) => vfs.sys_link(path(old), path(new))?,
+            Op::Rename(old, new) => vfs.sys_rename(path(old), path(new))?,
+            Op::Symlink(target, p) => vfs.sys_symlink(path(target), path(p))?,
+            Op::Unlink(p) => vfs.sys_unlink(path(p))?,
+            Op::Sync => vfs.sys_sync()?,
+        }
+    }
+}
+
+/// A file as seen through the file system. Timestamps are left out, since they differ between
+/// the recording and the crash runs.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    path: String,
+    file_type: INodeFileType,
+    nlink: i16,
+    // File content or symbolic link target
+    data: Vec<u8>,
+}
+
+/// The files under `ROOT`, sorted by path
+pub type Snapshot = Vec<Entry>;
+
+pub fn snapshot(vfs: &dyn VFS) -> Result<Snapshot> {
+    let mut entries = Vec::new();
+    match add_entry(vfs, String::from(ROOT), &mut entries) {
+        // Before the workload created it
+        Err(ErrorKind::FileNotFound) => {}
+        result => result?,
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn add_entry(vfs: &dyn VFS, p: String, entries: &mut Snapshot) -> Result<()> {
+    // Opening a symbolic link would follow it
+    if let Ok((len, target)) = vfs.sys_readlink(path(&p), RRefVec::new(0, MAXPATH))? {
+        entries.push(Entry {
+            path: p,
+            file_type: INodeFileType::Symlink,
+            nlink: 1,
+            data: target.as_slice()[..len].to_vec(),
+        });
+        return Ok(());
+    }
+
+    let (fd, _) = vfs.sys_open(path(&p), FileMode::READ)??;
+    let result = add_open_entry(vfs, fd, p, entries);
+    vfs.sys_close(fd)??;
+    result
+}
+
+fn add_open_entry(vfs: &dyn VFS, fd: usize, p: String, entries: &mut Snapshot) -> Result<()> {
+    let stat = vfs.sys_fstat(fd)??;
+    let mut data = Vec::new();
+
+    if stat.file_type == INodeFileType::Directory {
+        while let Some(dirent) = vfs.sys_readdir(fd)?? {
+            let len = dirent.name.iter().position(|c| *c == 0).unwrap_or(dirent.name.len());
+            let name = core::str::from_utf8(&dirent.name[..len])?;
+            if name != "." && name != ".." {
+                add_entry(vfs, format!("{}/{}", p, name), entries)?;
+            }
+        }
+    } else {
+        let mut buffer = RRefVec::new(0, BSIZE);
+        loop {
+            let (len, buffer_) = vfs.sys_read(fd, buffer)??;
+            if len == 0 {
+                break;
+            }
+            data.extend_from_slice(&buffer_.as_slice()[..len]);
+            buffer = buffer_;
+        }
+    }
+
+    entries.push(Entry {
+        path: p,
+        file_type: stat.file_type,
+        nlink: stat.nlink,
+        data,
+    });
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use alloc::boxed::Box;
+
+use interface::rref::RRef;
+
+use interface::rpc::RpcResult;
+
+pub struct DomC {}
+
+impl DomC {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl interface::dom_c::DomC for DomC {
+    fn no_arg(&self) -> RpcResult<()> {
+        Ok(())
+    }
+
+    fn one_arg(&self, x: usize) -> RpcResult<usize> {
+        #[cfg(feature = "unwind")]
+        {
+            let start = libtime::get_rdtsc();
+            assert!((start & 0x100) != 0x100);
+        }
+        Ok(x.wrapping_add(1))
+    }
+
+    fn one_rref(&self, mut x: RRef<usize>) -> RpcResult<RRef<usize>> {
+        *x = x.wrapping_add(1);
+        Ok(x)
+    }
+
+    fn init_dom_c(&self, c: Box<dyn interface::dom_c::DomC>) -> RpcResult<()> {
+        Ok(())
+    }
+}
@@ -11,41 +11,9 @@ use console::println;
 
 use core::panic::PanicInfo;
 
-use interface::rref::RRef;
+mod dom_c;
 
-use interface::rpc::RpcResult;
-
-struct DomC {}
-
-impl DomC {
-    fn new() -> Self {
-        Self {}
-    }
-}
-
-impl interface::dom_c::DomC for DomC {
-    fn no_arg(&self) -> RpcResult<()> {
-        Ok(())
-    }
-
-    fn one_arg(&self, x: usize) -> RpcResult<usize> {
-        #[cfg(feature = "unwind")]
-        {
-            let start = libtime::get_rdtsc();
-            assert!((start & 0x100) != 0x100);
-        }
-        Ok(x + 1)
-    }
-
-    fn one_rref(&self, mut x: RRef<usize>) -> RpcResult<RRef<usize>> {
-        *x += 1;
-        Ok(x)
-    }
-
-    fn init_dom_c(&self, c: Box<dyn interface::dom_c::DomC>) -> RpcResult<()> {
-        Ok(())
-    }
-}
+use dom_c::DomC;
 
 pub fn main() -> Box<dyn interface::dom_c::DomC> {
     println!("Init domain C");
@@ -1,7 +1,7 @@
 #![no_std]
 #![no_main]
 #![forbid(unsafe_code)]
-#![feature(box_syntax, untagged_unions)]
+#![feature(box_syntax, str_strip, untagged_unions)]
 
 mod rv6_syscalls;
 mod thread;
@@ -10,6 +10,7 @@ extern crate alloc;
 extern crate malloc;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
+use spin::Mutex;
 
 use core::panic::PanicInfo;
 
@@ -37,8 +38,15 @@ pub fn main(
 
     println!("init xv6/core");
 
-    // Init fs
-    let (_dom_xv6fs, fs) = create_xv6fs.create_domain_xv6fs(bdev);
+    // Init fs, it reads the network and NVMe statistics for /proc and random bytes from the TPM
+    // for /dev/random
+    let nvme = Arc::new(Mutex::new(nvme));
+    let (_dom_xv6fs, fs) = create_xv6fs.create_domain_xv6fs(
+        bdev,
+        Some(net.clone_net().unwrap()),
+        Some(box rv6_syscalls::SharedNvme(nvme.clone())),
+        Some(usr_tpm.clone_usrtpm().unwrap()),
+    );
     // Init usrnet
     #[cfg(feature = "shadow")]
     let (_dom_xv6net, usrnet) =
@@ -46,6 +54,7 @@ pub fn main(
     #[cfg(not(feature = "shadow"))]
     let (_dom_xv6net, usrnet) = create_xv6net.create_domain_xv6net(net.clone_net().unwrap());
     // Init kernel
+    thread::start_interrupt_thread();
     box rv6_syscalls::Rv6Syscalls::new(create_xv6usr, fs, usrnet, net, nvme, usr_tpm)
 }
 
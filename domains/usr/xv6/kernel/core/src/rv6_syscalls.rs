@@ -1,6 +1,8 @@
 use alloc::borrow::ToOwned;
 use alloc::boxed::Box;
 use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
 
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -11,11 +13,33 @@ use interface::bdev::{BlkReq, NvmeBDev};
 use interface::domain_create::CreateRv6Usr;
 use interface::net::{Net, NetworkStats};
 use interface::rpc::RpcResult;
-use interface::rref::{RRefDeque, RRefVec};
-use interface::rv6::{Rv6, Thread};
+use interface::rref::{RRefDeque, RRefSharedVec, RRefVec};
+use interface::rv6::{ErrorKind, Rv6, Thread, EXIT_SUCCESS};
 use interface::tpm::UsrTpm;
 use interface::usrnet::UsrNet;
-use interface::vfs::{FileMode, FileStat, Result, UsrVFS, NFILE, VFS};
+use interface::vfs::{
+    DirectoryEntry, FileMode, FileStat, FsStats, Permissions, Result, SeekFrom, UsrVFS, NFILE, VFS,
+};
+
+// Time slice in which a sleeping thread checks whether it has been killed
+const SLEEP_SLICE: u64 = 10_000_000;
+
+// System calls of a killed thread fail with `Killed`, which makes usrlib unwind the
+// user domain. That includes the call the thread was waiting in when it got killed.
+macro_rules! killable {
+    ($call:expr) => {
+        match crate::thread::killed() {
+            true => Ok(Err(ErrorKind::Killed)),
+            false => {
+                let result = $call;
+                match crate::thread::killed() {
+                    true => Ok(Err(ErrorKind::Killed)),
+                    false => result,
+                }
+            }
+        }
+    };
+}
 
 pub struct Rv6Syscalls {
     create_xv6usr: Arc<dyn CreateRv6Usr>,
@@ -33,7 +57,7 @@ impl Rv6Syscalls {
         fs: Box<dyn VFS>,
         usrnet: Box<dyn UsrNet>,
         net: Box<dyn Net>,
-        nvme: Box<dyn NvmeBDev>,
+        nvme: Arc<Mutex<Box<dyn NvmeBDev>>>,
         usrtpm: Box<dyn UsrTpm>,
     ) -> Self {
         Self {
@@ -41,7 +65,7 @@ impl Rv6Syscalls {
             fs,
             usrnet,
             net,
-            nvme: Arc::new(Mutex::new(nvme)),
+            nvme,
             usrtpm,
             start_time: libtime::get_ns_time(),
         }
@@ -58,6 +82,59 @@ impl Rv6Syscalls {
             usrtpm: self.usrtpm.clone_usrtpm()?,
         })
     }
+
+    // Opens the binary of `sys_spawn_domain`. A path without a `/` is looked up in the
+    // directories of `PATH`, or in the root directory if `envp` has no `PATH`.
+    // The fd is in the caller's table, so other threads of the caller must not pass it on.
+    fn open_binary(&self, path: &str, envp: &[String]) -> Result<(usize, String)> {
+        let mode = FileMode::READ | FileMode::CLOSE_ON_EXEC;
+        if path.contains('/') {
+            let fd = self.fs.sys_open(RRefVec::from_slice(path.as_bytes()), mode)??.0;
+            return Ok((fd, path.to_owned()));
+        }
+
+        let dirs = envp
+            .iter()
+            .find_map(|var| var.strip_prefix("PATH="))
+            .unwrap_or("/");
+        for dir in dirs.split(':').filter(|dir| !dir.is_empty()) {
+            let candidate = match dir.ends_with('/') {
+                true => format!("{}{}", dir, path),
+                false => format!("{}/{}", dir, path),
+            };
+            match self.fs.sys_open(RRefVec::from_slice(candidate.as_bytes()), mode)? {
+                Ok((fd, _)) => return Ok((fd, candidate)),
+                Err(ErrorKind::FileNotFound) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ErrorKind::FileNotFound)
+    }
+
+    // Copies the binary out of the buffer cache block by block, without a read buffer in
+    // between. Fails with `InvalidFileType` if it is not a regular file.
+    fn read_binary(&self, fd: usize) -> Result<Vec<u8>> {
+        let mut blob = Vec::new();
+        loop {
+            let block = self.fs.sys_mmap(fd, blob.len())??;
+            if block.size() == 0 {
+                return Ok(blob);
+            }
+            blob.extend_from_slice(block.as_slice());
+        }
+    }
+}
+
+// Splits the NUL terminated strings of the `argv` and `envp` of `sys_spawn_domain`
+fn unpack_strs(buffer: &[u8]) -> Result<Vec<String>> {
+    match buffer.split_last() {
+        None => Ok(Vec::new()),
+        Some((0, strs)) => strs
+            .split(|&b| b == 0)
+            .map(|s| Ok(core::str::from_utf8(s)?.to_owned()))
+            .collect(),
+        Some(_) => Err(ErrorKind::InvalidParameter),
+    }
 }
 
 impl Rv6 for Rv6Syscalls {
@@ -94,57 +171,68 @@ impl Rv6 for Rv6Syscalls {
         name: RRefVec<u8>,
         func: Box<dyn FnOnce() + Send>,
     ) -> RpcResult<Result<Box<dyn Thread>>> {
-        Ok((|| {
+        killable!(Ok((|| {
             let name = core::str::from_utf8(name.as_slice())?;
+            // The thread shares the files of the caller's process
+            let fs_copy = self.fs.clone()?;
+            let process = fs_copy.sys_share_process()??;
             Ok(crate::thread::spawn_thread(
-                self.fs.clone().unwrap(),
+                self.fs.clone()?,
                 &name,
-                func,
+                Box::new(move || {
+                    fs_copy.sys_enter_process(process).unwrap();
+                    func();
+                    EXIT_SUCCESS
+                }),
             ))
-        })())
+        })()))
     }
 
     fn sys_spawn_domain(
         &self,
         rv6: Box<dyn Rv6>,
         path: RRefVec<u8>,
-        args: RRefVec<u8>,
+        argv: RRefVec<u8>,
+        envp: RRefVec<u8>,
         fds: [Option<usize>; NFILE],
     ) -> RpcResult<Result<Box<dyn Thread>>> {
-        Ok((|| {
-            // Load bin into memory
-            let path_slice = core::str::from_utf8(path.as_slice())?;
-            let args_slice = core::str::from_utf8(args.as_slice())?;
-            println!("sys_spawn_domain {} {}", path_slice, args_slice);
-            let path_copy = path_slice.to_owned();
-            let args_copy = args_slice.to_owned();
-
-            let (fd, path) = self.fs.sys_open(path, FileMode::READ)??;
-            let size = self.fs.sys_fstat(fd)??.size; // fstat will filter out non INode files
-            let blob = RRefVec::new(0, size as usize);
-            let (bytes_read, blob) = self.fs.sys_read(fd, blob)??;
-            assert_eq!(bytes_read, size as usize);
+        killable!(Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            let argv = unpack_strs(argv.as_slice())?;
+            let envp = unpack_strs(envp.as_slice())?;
+            println!("sys_spawn_domain {} {:?}", path, argv);
+
+            let (fd, path) = self.open_binary(path, &envp)?;
+            let blob = self.read_binary(fd);
+            self.fs.sys_close(fd)??;
+            let blob = blob?;
 
             // Create a seperate copy of all the objects we want to pass to the new thread
             // and transfer the ownership over
             let fs_copy = self.fs.clone()?;
             let create_copy = self.create_xv6usr.clone();
-            let tmp_storage_id = fs_copy.sys_save_threadlocal(fds)??;
-            Ok(self
-                .sys_spawn_thread(
-                    path,
-                    Box::new(move || {
-                        fs_copy.sys_set_threadlocal(tmp_storage_id).unwrap();
-                        create_copy.create_domain_xv6usr(
-                            &path_copy,
-                            blob.as_slice(),
-                            rv6,
-                            &args_copy,
-                        );
-                    }),
-                )?
-                .unwrap())
-        })())
+            // The files of the new process, it is cleaned up when its last thread exits
+            let process = fs_copy.sys_spawn_process(fds)??;
+            let path_copy = path.clone();
+            // Not through sys_spawn_thread, which would drop the exit status of the domain
+            Ok(crate::thread::spawn_thread(
+                self.fs.clone()?,
+                &path,
+                Box::new(move || {
+                    fs_copy.sys_enter_process(process).unwrap();
+                    let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+                    let envp: Vec<&str> = envp.iter().map(String::as_str).collect();
+                    let (_dom, status) = create_copy.create_domain_xv6usr(
+                        &path_copy,
+                        &blob,
+                        rv6,
+                        &argv,
+                        &envp,
+                    );
+                    status
+                }),
+            ))
+        })()))
     }
 
     fn sys_getpid(&self) -> RpcResult<Result<u64>> {
@@ -155,12 +243,27 @@ impl Rv6 for Rv6Syscalls {
         Ok({ Ok(libtime::get_ns_time() - self.start_time) })
     }
 
-    fn sys_sleep(&self, ns: u64) -> RpcResult<Result<()>> {
-        Ok({
-            libtime::sys_ns_sleep(ns);
-            Ok(())
+    fn sys_kill(&self, pid: u64) -> RpcResult<Result<()>> {
+        Ok(match crate::thread::kill(pid) {
+            true => Ok(()),
+            false => Err(ErrorKind::InvalidParameter),
         })
     }
+
+    fn sys_sleep(&self, ns: u64) -> RpcResult<Result<()>> {
+        // Sleep in slices, so that a long sleep can be killed
+        let end = libtime::get_ns_time() + ns;
+        loop {
+            if crate::thread::killed() {
+                return Ok(Err(ErrorKind::Killed));
+            }
+            let now = libtime::get_ns_time();
+            if now >= end {
+                return Ok(Ok(()));
+            }
+            libtime::sys_ns_sleep(core::cmp::min(end - now, SLEEP_SLICE));
+        }
+    }
 }
 
 impl UsrVFS for Rv6Syscalls {
@@ -169,43 +272,86 @@ impl UsrVFS for Rv6Syscalls {
         path: RRefVec<u8>,
         mode: FileMode,
     ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
-        self.fs.sys_open(path, mode)
+        killable!(self.fs.sys_open(path, mode))
     }
     fn sys_close(&self, fd: usize) -> RpcResult<Result<()>> {
-        self.fs.sys_close(fd)
+        killable!(self.fs.sys_close(fd))
     }
     fn sys_read(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
-        self.fs.sys_read(fd, buffer)
+        killable!(self.fs.sys_read(fd, buffer))
     }
     fn sys_write(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
-        self.fs.sys_write(fd, buffer)
+        killable!(self.fs.sys_write(fd, buffer))
     }
-    fn sys_seek(&self, fd: usize, offset: usize) -> RpcResult<Result<()>> {
-        self.fs.sys_seek(fd, offset)
+    fn sys_seek(&self, fd: usize, pos: SeekFrom) -> RpcResult<Result<usize>> {
+        killable!(self.fs.sys_seek(fd, pos))
     }
     fn sys_fstat(&self, fd: usize) -> RpcResult<Result<FileStat>> {
-        self.fs.sys_fstat(fd)
+        killable!(self.fs.sys_fstat(fd))
     }
     fn sys_mknod(&self, path: RRefVec<u8>, major: i16, minor: i16) -> RpcResult<Result<()>> {
-        self.fs.sys_mknod(path, major, minor)
+        killable!(self.fs.sys_mknod(path, major, minor))
     }
     fn sys_dup(&self, fd: usize) -> RpcResult<Result<usize>> {
-        self.fs.sys_dup(fd)
+        killable!(self.fs.sys_dup(fd))
+    }
+    fn sys_dup2(&self, fd: usize, new_fd: usize) -> RpcResult<Result<usize>> {
+        killable!(self.fs.sys_dup2(fd, new_fd))
+    }
+    fn sys_set_close_on_exec(&self, fd: usize, close_on_exec: bool) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_set_close_on_exec(fd, close_on_exec))
     }
     fn sys_pipe(&self) -> RpcResult<Result<(usize, usize)>> {
-        self.fs.sys_pipe()
+        killable!(self.fs.sys_pipe())
     }
     fn sys_link(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>> {
-        self.fs.sys_link(old_path, new_path)
+        killable!(self.fs.sys_link(old_path, new_path))
     }
     fn sys_unlink(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
-        self.fs.sys_unlink(path)
+        killable!(self.fs.sys_unlink(path))
     }
     fn sys_mkdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
-        self.fs.sys_mkdir(path)
+        killable!(self.fs.sys_mkdir(path))
+    }
+    fn sys_rename(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_rename(old_path, new_path))
+    }
+    fn sys_ftruncate(&self, fd: usize, size: u64) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_ftruncate(fd, size))
+    }
+    fn sys_mmap(&self, fd: usize, offset: usize) -> RpcResult<Result<RRefSharedVec<u8>>> {
+        killable!(self.fs.sys_mmap(fd, offset))
+    }
+    fn sys_readdir(&self, fd: usize) -> RpcResult<Result<Option<DirectoryEntry>>> {
+        killable!(self.fs.sys_readdir(fd))
+    }
+    fn sys_chdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_chdir(path))
+    }
+    fn sys_getcwd(&self, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        killable!(self.fs.sys_getcwd(buffer))
+    }
+    fn sys_symlink(&self, target: RRefVec<u8>, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_symlink(target, path))
+    }
+    fn sys_readlink(
+        &self,
+        path: RRefVec<u8>,
+        buffer: RRefVec<u8>,
+    ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        killable!(self.fs.sys_readlink(path, buffer))
+    }
+    fn sys_chmod(&self, path: RRefVec<u8>, permissions: Permissions) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_chmod(path, permissions))
+    }
+    fn sys_sync(&self) -> RpcResult<Result<()>> {
+        killable!(self.fs.sys_sync())
+    }
+    fn sys_fs_stats(&self) -> RpcResult<Result<FsStats>> {
+        killable!(self.fs.sys_fs_stats())
     }
     fn sys_dump_inode(&self) -> RpcResult<Result<()>> {
-        self.fs.sys_dump_inode()
+        killable!(self.fs.sys_dump_inode())
     }
 }
 
@@ -214,32 +360,32 @@ impl UsrNet for Rv6Syscalls {
         self.usrnet.clone_usrnet()
     }
     fn listen(&self, socket: usize, port: u16) -> RpcResult<Result<()>> {
-        self.usrnet.listen(socket, port)
+        killable!(self.usrnet.listen(socket, port))
     }
     fn create(&self) -> RpcResult<Result<usize>> {
-        self.usrnet.create()
+        killable!(self.usrnet.create())
     }
     fn poll(&self, tx: bool) -> RpcResult<Result<()>> {
-        self.usrnet.poll(tx)
+        killable!(self.usrnet.poll(tx))
     }
     fn can_recv(&self, server: usize) -> RpcResult<Result<bool>> {
-        self.usrnet.can_recv(server)
+        killable!(self.usrnet.can_recv(server))
     }
     fn is_listening(&self, server: usize) -> RpcResult<Result<bool>> {
-        self.usrnet.is_listening(server)
+        killable!(self.usrnet.is_listening(server))
     }
     fn is_active(&self, server: usize) -> RpcResult<Result<bool>> {
-        self.usrnet.is_active(server)
+        killable!(self.usrnet.is_active(server))
     }
     fn close(&self, server: usize) -> RpcResult<Result<()>> {
-        self.usrnet.close(server)
+        killable!(self.usrnet.close(server))
     }
     fn read_socket(
         &self,
         socket: usize,
         buffer: RRefVec<u8>,
     ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
-        self.usrnet.read_socket(socket, buffer)
+        killable!(self.usrnet.read_socket(socket, buffer))
     }
     fn write_socket(
         &self,
@@ -247,7 +393,7 @@ impl UsrNet for Rv6Syscalls {
         buffer: RRefVec<u8>,
         size: usize,
     ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
-        self.usrnet.write_socket(socket, buffer, size)
+        killable!(self.usrnet.write_socket(socket, buffer, size))
     }
 }
 
@@ -261,11 +407,11 @@ impl Net for Rv6Syscalls {
         reap_queue: &mut VecDeque<Vec<u8>>,
         tx: bool,
     ) -> RpcResult<Result<usize>> {
-        self.net.submit_and_poll(packets, reap_queue, tx)
+        killable!(self.net.submit_and_poll(packets, reap_queue, tx))
     }
 
     fn poll(&self, collect: &mut VecDeque<Vec<u8>>, tx: bool) -> RpcResult<Result<usize>> {
-        self.net.poll(collect, tx)
+        killable!(self.net.poll(collect, tx))
     }
 
     fn submit_and_poll_rref(
@@ -275,7 +421,7 @@ impl Net for Rv6Syscalls {
         tx: bool,
         pkt_len: usize,
     ) -> RpcResult<Result<(usize, RRefDeque<[u8; 1514], 32>, RRefDeque<[u8; 1514], 32>)>> {
-        self.net.submit_and_poll_rref(packets, collect, tx, pkt_len)
+        killable!(self.net.submit_and_poll_rref(packets, collect, tx, pkt_len))
     }
 
     fn poll_rref(
@@ -283,11 +429,15 @@ impl Net for Rv6Syscalls {
         collect: RRefDeque<[u8; 1514], 512>,
         tx: bool,
     ) -> RpcResult<Result<(usize, RRefDeque<[u8; 1514], 512>)>> {
-        self.net.poll_rref(collect, tx)
+        killable!(self.net.poll_rref(collect, tx))
     }
 
     fn get_stats(&self) -> RpcResult<Result<NetworkStats>> {
-        self.net.get_stats()
+        killable!(self.net.get_stats())
+    }
+
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>> {
+        killable!(self.net.peek_stats())
     }
 
     fn test_domain_crossing(&self) -> RpcResult<()> {
@@ -295,6 +445,35 @@ impl Net for Rv6Syscalls {
     }
 }
 
+// The NVMe device of rv6, handed to the file system for /proc/nvme
+pub struct SharedNvme(pub Arc<Mutex<Box<dyn NvmeBDev>>>);
+
+impl NvmeBDev for SharedNvme {
+    fn submit_and_poll_rref(
+        &self,
+        submit: RRefDeque<BlkReq, 128>,
+        collect: RRefDeque<BlkReq, 128>,
+        write: bool,
+    ) -> RpcResult<Result<(usize, RRefDeque<BlkReq, 128>, RRefDeque<BlkReq, 128>)>> {
+        self.0.lock().submit_and_poll_rref(submit, collect, write)
+    }
+
+    fn poll_rref(
+        &self,
+        collect: RRefDeque<BlkReq, 1024>,
+    ) -> RpcResult<Result<(usize, RRefDeque<BlkReq, 1024>)>> {
+        self.0.lock().poll_rref(collect)
+    }
+
+    fn get_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        self.0.lock().get_stats()
+    }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        self.0.lock().peek_stats()
+    }
+}
+
 impl NvmeBDev for Rv6Syscalls {
     fn submit_and_poll_rref(
         &self,
@@ -302,19 +481,24 @@ impl NvmeBDev for Rv6Syscalls {
         collect: RRefDeque<BlkReq, 128>,
         write: bool,
     ) -> RpcResult<Result<(usize, RRefDeque<BlkReq, 128>, RRefDeque<BlkReq, 128>)>> {
-        self.nvme
+        killable!(self
+            .nvme
             .lock()
-            .submit_and_poll_rref(submit, collect, write)
+            .submit_and_poll_rref(submit, collect, write))
     }
 
     fn poll_rref(
         &self,
         collect: RRefDeque<BlkReq, 1024>,
     ) -> RpcResult<Result<(usize, RRefDeque<BlkReq, 1024>)>> {
-        self.nvme.lock().poll_rref(collect)
+        killable!(self.nvme.lock().poll_rref(collect))
     }
 
     fn get_stats(&self) -> RpcResult<Result<(u64, u64)>> {
-        self.nvme.lock().get_stats()
+        killable!(self.nvme.lock().get_stats())
+    }
+
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>> {
+        killable!(self.nvme.lock().peek_stats())
     }
 }
@@ -1,30 +1,48 @@
 use alloc::boxed::Box;
-use alloc::collections::VecDeque;
-use alloc::string::{String, ToString};
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::lazy_static;
 use spin::Mutex;
 
 use libsyscalls::sync::CondVar;
-use libsyscalls::syscalls::sys_create_thread;
+use libsyscalls::syscalls::{
+    sys_console_interrupt, sys_create_thread, sys_current_thread, sys_current_thread_id,
+    sys_interrupted,
+};
 use interface::rpc::RpcResult;
-use interface::rv6::Thread;
+use interface::rv6::{ErrorKind, Result, Thread, EXIT_KILLED, EXIT_PANIC};
 use interface::vfs::VFS;
 
+// How often Ctrl-C on the console is checked for
+const INTERRUPT_POLL_INTERVAL: u64 = 10_000_000;
+
 lazy_static! {
     static ref thread_queue: Mutex<VecDeque<ThreadContext>> = Default::default();
+    // Running threads by pid, which is the id of the kernel thread
+    static ref threads: Mutex<BTreeMap<u64, Running>> = Default::default();
+}
+
+// A thread that has started, see `kill`
+struct Running {
+    handle: ThreadHandle,
+    thread: Box<dyn syscalls::Thread>,
 }
 
 pub struct ThreadHandleInternal {
-    finished: Mutex<bool>,
+    // Exit status, once the thread finished
+    status: Mutex<Option<i32>>,
     cv: CondVar,
+    // Number of threads joining this one. Whatever the shell waits for is the foreground job.
+    joiners: AtomicUsize,
 }
 
 impl ThreadHandleInternal {
     fn new() -> Self {
         Self {
-            finished: Mutex::new(false),
+            status: Mutex::new(None),
             cv: CondVar::new(),
+            joiners: AtomicUsize::new(0),
         }
     }
 }
@@ -38,12 +56,16 @@ impl ThreadHandle {
 }
 
 impl Thread for ThreadHandle {
-    fn join(&self) -> RpcResult<()> {
-        {
-            let pred = |finished: &mut bool| *finished;
-            self.cv.sleep_until(&self.finished, pred);
-        };
-        Ok(())
+    fn join(&self) -> RpcResult<Result<i32>> {
+        self.joiners.fetch_add(1, Ordering::AcqRel);
+        let pred = |status: &mut Option<i32>| status.is_some();
+        let status = self.cv.sleep_until_interruptible(&self.status, pred);
+        self.joiners.fetch_sub(1, Ordering::AcqRel);
+        Ok(status.map(|status| status.unwrap()).ok_or(ErrorKind::Killed))
+    }
+
+    fn try_join(&self) -> RpcResult<Option<i32>> {
+        Ok(*self.status.lock())
     }
 }
 
@@ -62,21 +84,18 @@ impl core::clone::Clone for ThreadHandle {
 
 struct ThreadContext {
     fs: Box<dyn VFS>,
-    name: String,
-    entry: Box<dyn FnOnce() + Send>,
+    entry: Box<dyn FnOnce() -> i32 + Send>,
     handle: ThreadHandle,
 }
 
 impl ThreadContext {
     fn new(
         fs: Box<dyn VFS>,
-        name: String,
-        entry: Box<dyn FnOnce() + Send>,
+        entry: Box<dyn FnOnce() -> i32 + Send>,
         handle: ThreadHandle,
     ) -> Self {
         Self {
             fs,
-            name,
             entry,
             handle,
         }
@@ -85,25 +104,77 @@ impl ThreadContext {
 
 extern "C" fn thread_entry() {
     let context = thread_queue.lock().pop_front().unwrap();
-    (context.entry)();
+    let pid = sys_current_thread_id();
+    threads.lock().insert(
+        pid,
+        Running {
+            handle: context.handle.clone(),
+            thread: sys_current_thread(),
+        },
+    );
+    // A user domain that panics or gets killed unwinds back to here
+    let status = unwind::catch_unwind(context.entry);
+    let status = match sys_interrupted() {
+        true => EXIT_KILLED,
+        false => status.unwrap_or(EXIT_PANIC),
+    };
+    threads.lock().remove(&pid);
+    // Closes whatever files the thread left open
     context.fs.sys_thread_exit();
-    *context.handle.finished.lock() = true;
-    context.handle.cv.wakeup();
-    console::println!("Thread {} exits", context.name);
+    *context.handle.status.lock() = Some(status);
+    context.handle.cv.wakeup_all();
 }
 
 pub fn spawn_thread(
     fs: Box<dyn VFS>,
     name: &str,
-    func: Box<dyn FnOnce() + Send>,
+    func: Box<dyn FnOnce() -> i32 + Send>,
 ) -> Box<dyn Thread> {
     let handle = ThreadHandle::new();
-    thread_queue.lock().push_back(ThreadContext::new(
-        fs,
-        name.to_string(),
-        func,
-        handle.clone(),
-    ));
+    thread_queue
+        .lock()
+        .push_back(ThreadContext::new(fs, func, handle.clone()));
     sys_create_thread(name, thread_entry);
     box handle
 }
+
+// Interrupts the thread: the kernel wakes it if it sleeps and unwinds it if it runs the code
+// of a single-threaded user domain. Otherwise its system call fails with `Killed` on the way
+// out, and usrlib unwinds it then. Returns false if there is no rv6 thread with the pid.
+pub fn kill(pid: u64) -> bool {
+    match threads.lock().get(&pid) {
+        Some(running) => {
+            running.thread.interrupt();
+            true
+        }
+        None => false,
+    }
+}
+
+// Whether the calling thread has been killed. The flag is kept by the kernel for each thread.
+pub fn killed() -> bool {
+    sys_interrupted()
+}
+
+// Kills every thread that is being joined, i.e. the foreground job and whatever it waits for
+fn kill_foreground() {
+    for running in threads.lock().values() {
+        if running.handle.joiners.load(Ordering::Acquire) > 0 {
+            running.thread.interrupt();
+        }
+    }
+}
+
+// Starts the thread that delivers Ctrl-C on the console to the foreground job
+pub fn start_interrupt_thread() {
+    sys_create_thread("rv6[interrupt]", interrupt_thread);
+}
+
+extern "C" fn interrupt_thread() {
+    loop {
+        libtime::sys_ns_sleep(INTERRUPT_POLL_INTERVAL);
+        if sys_console_interrupt() {
+            kill_foreground();
+        }
+    }
+}
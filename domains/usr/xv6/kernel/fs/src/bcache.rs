@@ -1,47 +1,111 @@
 // Based on bio.c from xv6.
 // The entire ownership system is a mess and error-prone(no one is the owner).
 // Need to revisit this and fix it one day.
+//
+// Unlike xv6, the cache is split into shards picked by block number, each with its own lock,
+// hash map and LRU list, so that lookups don't scan every buffer under a single lock.
 
-use crate::params::{BSIZE, NBUF, SECTOR_SIZE};
+use crate::params::{self, BSIZE, SECTOR_SIZE};
 
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use console::println;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+use hashbrown::HashMap;
 use spin::{Mutex, Once};
 
-use interface::rref::RRef;
-use interface::bdev::BDev;
+use interface::rref::{RRef, RRefDeque, RRefSharedVec};
+use interface::bdev::{BDev, BlkReq};
+use interface::vfs::{ErrorKind, FsStats, Result};
+use libsyscalls::sync::CondVar;
+
+// Max number of blocks in a single request to the block device
+const BATCH_SIZE: usize = 128;
 
 pub static BCACHE: Once<BufferCache> = Once::new();
 
 pub type BufferBlock = RRef<[u8; BSIZE]>;
 
-pub struct BufferBlockWrapper(Option<BufferBlock>);
+pub struct BufferBlockWrapper {
+    // Allocated when the buffer is first filled, most buffers of a large cache never are
+    block: Option<BufferBlock>,
+    // Set once `view` shared the block, which `block` is then only an alias of. The block is
+    // copied before it changes, so that the views keep what they saw.
+    view: Option<RRefSharedVec<u8>>,
+}
 
 impl BufferBlockWrapper {
+    fn new() -> Self {
+        Self {
+            block: None,
+            view: None,
+        }
+    }
+
+    // Hands the block to the device to read into, allocating it on first use
     fn take(&mut self) -> BufferBlock {
-        self.0.take().unwrap()
+        self.unshare();
+        self.block.take().unwrap_or_else(|| RRef::new([0u8; BSIZE]))
+    }
+
+    fn fill(&mut self, data: &[u8; BSIZE]) {
+        self.unshare();
+        match self.block.as_mut() {
+            Some(block) => **block = *data,
+            None => self.block = Some(RRef::new(*data)),
+        }
+    }
+
+    // Returns a read-only view of the block as it is now, without copying it
+    pub fn view(&mut self) -> RRefSharedVec<u8> {
+        if self.view.is_none() {
+            let view = RRefSharedVec::from_array(self.block.take().unwrap());
+            self.block = Some(unsafe { view.as_array() });
+            self.view = Some(view);
+        }
+        self.view.as_ref().unwrap().share()
+    }
+
+    // Gives the buffer its own copy of a block it shares with views
+    fn unshare(&mut self) {
+        if let Some(view) = self.view.take() {
+            let alias = self.block.take().unwrap();
+            self.block = Some(RRef::new(*alias));
+            // The memory belongs to the views
+            core::mem::forget(alias);
+            drop(view);
+        }
+    }
+}
+
+impl Drop for BufferBlockWrapper {
+    fn drop(&mut self) {
+        if self.view.is_some() {
+            core::mem::forget(self.block.take());
+        }
     }
 }
 
 impl Deref for BufferBlockWrapper {
     type Target = BufferBlock;
     fn deref(&self) -> &Self::Target {
-        self.0.as_ref().unwrap()
+        self.block.as_ref().unwrap()
     }
 }
 
 impl DerefMut for BufferBlockWrapper {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.0.as_mut().unwrap()
+        self.unshare();
+        self.block.as_mut().unwrap()
     }
 }
 
 pub struct BufferGuard {
     dev: u32,
     block_number: u32,
+    shard: usize,
     index: usize,
     buffer: Arc<Mutex<BufferBlockWrapper>>,
     bcache: &'static BufferCache,
@@ -57,11 +121,11 @@ impl BufferGuard {
     }
 
     pub fn pin(&self) {
-        self.bcache.pin(self.index)
+        self.bcache.pin(self.shard, self.index)
     }
 
     pub fn unpin(&self) {
-        self.bcache.unpin(self.index)
+        self.bcache.unpin(self.shard, self.index)
     }
 }
 
@@ -69,7 +133,7 @@ impl BufferGuard {
 // But I don't want to deal with the lifetime for now. Might do it later
 impl Drop for BufferGuard {
     fn drop(&mut self) {
-        self.bcache.release(self.index)
+        self.bcache.release(self.shard, self.index)
     }
 }
 
@@ -89,6 +153,10 @@ struct Buffer {
     dev: u32,
     block_number: u32,
     reference_count: u32,
+    // The data has been read from the disk
+    valid: bool,
+    // Reading the data from the disk failed
+    error: Option<ErrorKind>,
     // The actual data
     // TODO: use a sleep mutex
     data: Arc<Mutex<BufferBlockWrapper>>,
@@ -102,6 +170,8 @@ impl core::fmt::Debug for Buffer {
             .field("dev", &self.dev)
             .field("block_number", &self.block_number)
             .field("reference_count", &self.reference_count)
+            .field("valid", &self.valid)
+            .field("error", &self.error)
             .finish()
     }
 }
@@ -112,35 +182,45 @@ impl Buffer {
             dev: 0,
             block_number: 0,
             reference_count: 0,
+            valid: false,
+            error: None,
             prev: index as i32 - 1,
             next: index as i32 + 1,
-            data: Arc::new(Mutex::new(BufferBlockWrapper(Some(RRef::new(
-                [0u8; BSIZE],
-            ))))),
+            data: Arc::new(Mutex::new(BufferBlockWrapper::new())),
         }
     }
 }
 
+// What a lookup found in the cache
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Status {
+    // The buffer holds the block
+    Valid,
+    // Another thread is reading the block into the buffer
+    Loading,
+    // The buffer was recycled for the block, the caller has to read it from the disk
+    Empty,
+}
+
+// One shard of the cache. Its buffers form a list from the most to the least recently used.
 #[derive(Debug)]
 pub struct BufferCacheInternal {
     buffers: Vec<Buffer>,
     head: usize,
-    #[cfg(feature = "hashmap")]
     map: HashMap<(u32, u32), usize>,
 }
 
 impl BufferCacheInternal {
-    pub fn new() -> Self {
+    pub fn new(nbuf: usize) -> Self {
         let mut buffers = vec![];
-        for i in 0..NBUF {
+        for i in 0..nbuf {
             buffers.push(Buffer::new(i));
         }
-        buffers[0].prev = NBUF as i32 - 1;
-        buffers[NBUF - 1].next = 0;
+        buffers[0].prev = nbuf as i32 - 1;
+        buffers[nbuf - 1].next = 0;
         Self {
             buffers,
             head: 0,
-            #[cfg(feature = "hashmap")]
             map: HashMap::new(),
         }
     }
@@ -152,64 +232,59 @@ impl BufferCacheInternal {
         &mut self,
         dev: u32,
         block_number: u32,
-    ) -> (bool, usize, Arc<Mutex<BufferBlockWrapper>>) {
-        #[cfg(feature = "hashmap")]
-        let index = self.map.get(&(dev, block_number)).map(|index| *index);
-        // TODO: change this to iter_mut
-        #[cfg(not(feature = "hashmap"))]
-        let index = self
-            .iter()
-            .find(|buffer| buffer.dev == dev && buffer.block_number == block_number)
-            .map(|buffer| {
-                ((buffer as *const _ as usize) - (self.buffers.as_ptr() as *const _ as usize))
-                    / core::mem::size_of::<Buffer>()
-            });
-
-        match index {
-            Some(index) => {
-                let buffer = &mut self.buffers[index];
-                #[cfg(feature = "hashmap")]
-                {
-                    assert!(buffer.dev == dev);
-                    assert!(buffer.block_number == block_number);
-                }
-                buffer.reference_count += 1;
-                (true, index, buffer.data.clone())
-            }
+    ) -> (Status, usize, Arc<Mutex<BufferBlockWrapper>>) {
+        if let Some((status, index)) = self.lookup(dev, block_number) {
+            let buffer = &mut self.buffers[index];
+            buffer.reference_count += 1;
+            return (status, index, buffer.data.clone());
+        }
+        match self.recycle(dev, block_number) {
+            Some(index) => (Status::Empty, index, self.buffers[index].data.clone()),
             None => {
-                // Not cached; recycle an unused buffer.
-                let mut curr = self.buffers[self.head].prev;
-                for _ in 0..NBUF {
-                    let buffer = &mut self.buffers[curr as usize];
-                    if buffer.reference_count == 0 {
-                        // Move it out from the map
-                        #[cfg(feature = "hashmap")]
-                        if buffer.block_number != 0 {
-                            assert!(self
-                                .map
-                                .remove(&(buffer.dev, buffer.block_number))
-                                .is_some());
-                        }
-
-                        // Clear the buffer and return it
-                        buffer.dev = dev;
-                        buffer.block_number = block_number;
-                        buffer.reference_count = 1;
-                        #[cfg(feature = "hashmap")]
-                        assert!(self
-                            .map
-                            .insert((dev, block_number), curr as usize)
-                            .is_none());
-                        return (false, curr as usize, buffer.data.clone());
-                    }
-                    curr = buffer.prev;
-                }
                 println!("{:?}", self);
                 panic!("No free block in bcache");
             }
         }
     }
 
+    fn lookup(&self, dev: u32, block_number: u32) -> Option<(Status, usize)> {
+        self.map.get(&(dev, block_number)).map(|&index| {
+            let buffer = &self.buffers[index];
+            assert!(buffer.dev == dev);
+            assert!(buffer.block_number == block_number);
+            let status = if buffer.valid { Status::Valid } else { Status::Loading };
+            (status, index)
+        })
+    }
+
+    // Not cached; recycle the least recently used buffer that is not in use.
+    // Returns None if every buffer is in use.
+    fn recycle(&mut self, dev: u32, block_number: u32) -> Option<usize> {
+        let mut curr = self.buffers[self.head].prev as usize;
+        for _ in 0..self.buffers.len() {
+            let buffer = &mut self.buffers[curr];
+            if buffer.reference_count == 0 {
+                // Move it out from the map
+                let old = (buffer.dev, buffer.block_number);
+                if self.map.get(&old) == Some(&curr) {
+                    self.map.remove(&old);
+                }
+
+                // Clear the buffer and return it
+                let buffer = &mut self.buffers[curr];
+                buffer.dev = dev;
+                buffer.block_number = block_number;
+                buffer.reference_count = 1;
+                buffer.valid = false;
+                buffer.error = None;
+                assert!(self.map.insert((dev, block_number), curr).is_none());
+                return Some(curr);
+            }
+            curr = buffer.prev as usize;
+        }
+        None
+    }
+
     fn release(&mut self, index: usize) {
         self.buffers[index].reference_count -= 1;
         if self.buffers[index].reference_count == 0 {
@@ -232,73 +307,107 @@ impl BufferCacheInternal {
             self.head = index;
         }
     }
+}
 
-    fn iter(&self) -> Iter {
-        Iter {
-            bcache: self,
-            curr: self.head,
-            size: self.buffers.len(),
-        }
-    }
+// Counters behind `FsStats`
+#[derive(Default)]
+struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    readahead: AtomicU64,
+    read_requests: AtomicU64,
+    write_requests: AtomicU64,
+}
 
-    fn rev_iter(&self) -> RevIter {
-        RevIter {
-            bcache: self,
-            curr: self.buffers[self.head].prev as usize,
-            size: self.buffers.len(),
-        }
-    }
+pub struct BufferCache {
+    shards: Vec<Mutex<BufferCacheInternal>>,
+    // Signalled when a buffer of the shard was read from the disk, or failed to be
+    loaded: Vec<CondVar>,
+    bdev: Box<dyn BDev>,
+    // Request buffers for batched I/O, reused across batches
+    requests: Mutex<Vec<RRef<BlkReq>>>,
+    stats: Stats,
 }
 
-struct Iter<'a> {
-    bcache: &'a BufferCacheInternal,
-    curr: usize,
-    size: usize,
+pub fn sector(block_number: u32) -> u32 {
+    block_number * (BSIZE / SECTOR_SIZE) as u32
 }
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = &'a Buffer;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.size == 0 {
-            return None;
+impl BufferCache {
+    // `shard_size` buffers in each of `BCACHE_SHARDS` shards
+    pub fn new(bdev: Box<dyn BDev>, shard_size: usize) -> Self {
+        Self {
+            shards: (0..params::BCACHE_SHARDS)
+                .map(|_| Mutex::new(BufferCacheInternal::new(shard_size)))
+                .collect(),
+            loaded: (0..params::BCACHE_SHARDS).map(|_| CondVar::new()).collect(),
+            bdev,
+            requests: Mutex::new(Vec::new()),
+            stats: Stats::default(),
         }
-        self.size -= 1;
-        let buffer = &self.bcache.buffers[self.curr];
-        self.curr = buffer.next as usize;
-        Some(buffer)
     }
-}
 
-struct RevIter<'a> {
-    bcache: &'a BufferCacheInternal,
-    curr: usize,
-    size: usize,
-}
+    // Consecutive blocks go to different shards, so that a batch spreads over all of them
+    fn shard(&self, block_number: u32) -> usize {
+        block_number as usize % self.shards.len()
+    }
 
-impl<'a> Iterator for RevIter<'a> {
-    type Item = &'a Buffer;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.size == 0 {
-            return None;
-        }
-        self.size -= 1;
-        let buffer = &self.bcache.buffers[self.curr];
-        self.curr = buffer.prev as usize;
-        Some(buffer)
+    fn get(&'static self, device: u32, block_number: u32) -> (Status, BufferGuard) {
+        let shard = self.shard(block_number);
+        let (status, index, buffer) = self.shards[shard].lock().get(device, block_number);
+        let counter = if status == Status::Empty { &self.stats.misses } else { &self.stats.hits };
+        counter.fetch_add(1, Ordering::Relaxed);
+        let guard = BufferGuard {
+            dev: device,
+            block_number,
+            shard,
+            index,
+            buffer,
+            bcache: self,
+        };
+        (status, guard)
     }
-}
 
-pub struct BufferCache {
-    internal: Mutex<BufferCacheInternal>,
-    bdev: Box<dyn BDev>,
-}
+    // Called once the data of an `Empty` buffer has been read from the disk
+    fn set_valid(&self, guard: &BufferGuard) {
+        self.shards[guard.shard].lock().buffers[guard.index].valid = true;
+        self.loaded[guard.shard].wakeup_all();
+    }
 
-impl BufferCache {
-    pub fn new(bdev: Box<dyn BDev>) -> Self {
-        Self {
-            internal: Mutex::new(BufferCacheInternal::new()),
-            bdev,
+    // Called if the data of an `Empty` buffer could not be read. The threads waiting for it
+    // get the error, later lookups of the block try to read it again.
+    fn set_failed(&self, guard: &BufferGuard, error: ErrorKind) {
+        {
+            let mut internal = self.shards[guard.shard].lock();
+            internal.buffers[guard.index].error = Some(error);
+            let key = (guard.dev, guard.block_number);
+            if internal.map.get(&key) == Some(&guard.index) {
+                internal.map.remove(&key);
+            }
         }
+        self.loaded[guard.shard].wakeup_all();
+    }
+
+    // Wait for the thread that found the buffer `Empty` to read it
+    fn wait_valid(&self, guard: &BufferGuard) -> Result<()> {
+        let internal = self.loaded[guard.shard].sleep_until(&self.shards[guard.shard], |internal| {
+            let buffer = &internal.buffers[guard.index];
+            buffer.valid || buffer.error.is_some()
+        });
+        match internal.buffers[guard.index].error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn request(&self, block_number: u32) -> RRef<BlkReq> {
+        let mut req = self
+            .requests
+            .lock()
+            .pop()
+            .unwrap_or_else(|| RRef::new(BlkReq::new()));
+        req.block = sector(block_number) as u64;
+        req
     }
 
     // Return a unlocked buffer with the contents of the indicated block.
@@ -307,23 +416,39 @@ impl BufferCache {
     // Since it's hard to pass a locked buffer around in Rust, we choose to
     // get an unlocked buffer from `bget`, lock the buffer and sync it with the disk,
     // then unlock it and return it to the caller.
-    // This is okay because the buffer will become valid only if it is a reused buffer.
+    // Other threads looking the block up in the meantime wait until it is marked valid.
     // We can also merge `bread` with `bget` since `bget` is only a helper for `bread`
+    // Like xv6, panics if the disk can't be read, see `try_read`.
     pub fn read(&'static self, device: u32, block_number: u32) -> BufferGuard {
+        self.try_read(device, block_number)
+            .unwrap_or_else(|e| panic!("bcache: cannot read block {}: {:?}", block_number, e))
+    }
+
+    pub fn try_read(&'static self, device: u32, block_number: u32) -> Result<BufferGuard> {
         // println!("bread dev#{} block#{}", device, block_number);
-        let (valid, index, buffer) = self.internal.lock().get(device, block_number);
-        if !valid {
-            let sector = block_number * (BSIZE / SECTOR_SIZE) as u32;
-            let mut guard = buffer.lock();
-            (*guard).0 = Some(self.bdev.read(sector, guard.take()).unwrap());
-        }
-        BufferGuard {
-            dev: device,
-            block_number,
-            index,
-            buffer,
-            bcache: self,
+        let (status, guard) = self.get(device, block_number);
+        match status {
+            Status::Valid => {}
+            Status::Loading => self.wait_valid(&guard)?,
+            Status::Empty => {
+                let result = {
+                    let mut buffer = guard.lock();
+                    let block = buffer.take();
+                    self.bdev
+                        .read(sector(block_number), block)
+                        .map(|block| buffer.block = Some(block))
+                };
+                self.stats.read_requests.fetch_add(1, Ordering::Relaxed);
+                match result {
+                    Ok(()) => self.set_valid(&guard),
+                    Err(_) => {
+                        self.set_failed(&guard, ErrorKind::RpcError);
+                        return Err(ErrorKind::RpcError);
+                    }
+                }
+            }
         }
+        Ok(guard)
     }
 
     // Write b's contents to disk
@@ -333,29 +458,153 @@ impl BufferCache {
     // TODO: address the issue above by refactoring the `BufferGuard`
     pub fn write(&self, block_number: u32, buffer_data: &mut BufferBlockWrapper) {
         // println!("bwrite block#{}", block_number);
-        let sector = block_number * (BSIZE / SECTOR_SIZE) as u32;
-        self.bdev.write(sector, &*buffer_data).unwrap();
+        self.bdev.write(sector(block_number), &*buffer_data).unwrap();
+        self.stats.write_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Same as calling `try_read` on each block, but blocks that are not cached are
+    // fetched from the disk with batched requests.
+    pub fn read_many(
+        &'static self,
+        device: u32,
+        block_numbers: &[u32],
+    ) -> Result<Vec<BufferGuard>> {
+        let mut guards = Vec::with_capacity(block_numbers.len());
+        let mut missing = Vec::new();
+        let mut loading = Vec::new();
+        for &block_number in block_numbers {
+            let (status, guard) = self.get(device, block_number);
+            match status {
+                Status::Valid => {}
+                Status::Loading => loading.push(guards.len()),
+                Status::Empty => missing.push(guards.len()),
+            }
+            guards.push(guard);
+        }
+
+        let missing: Vec<&BufferGuard> = missing.iter().map(|&i| &guards[i]).collect();
+        self.fill(&missing)?;
+        for &i in loading.iter() {
+            self.wait_valid(&guards[i])?;
+        }
+        Ok(guards)
+    }
+
+    // Reads blocks of a file ahead of time, so that a sequential reader finds them cached.
+    // Blocks that are cached already, or whose shard has no buffer to spare, are skipped.
+    pub fn prefetch(&'static self, device: u32, block_numbers: &[u32]) {
+        let mut guards = Vec::new();
+        for &block_number in block_numbers {
+            let shard = self.shard(block_number);
+            let mut internal = self.shards[shard].lock();
+            if internal.lookup(device, block_number).is_some() {
+                continue;
+            }
+            if let Some(index) = internal.recycle(device, block_number) {
+                guards.push(BufferGuard {
+                    dev: device,
+                    block_number,
+                    shard,
+                    index,
+                    buffer: internal.buffers[index].data.clone(),
+                    bcache: self,
+                });
+            }
+        }
+
+        self.stats.readahead.fetch_add(guards.len() as u64, Ordering::Relaxed);
+        let guards: Vec<&BufferGuard> = guards.iter().collect();
+        // Readahead is only a hint, whoever reads the blocks gets the error
+        let _ = self.fill(&guards);
+    }
+
+    // Read `Empty` buffers from the disk with batched requests, and mark them valid. If a
+    // batch fails, it and the remaining buffers are marked failed.
+    fn fill(&self, guards: &[&BufferGuard]) -> Result<()> {
+        for (i, batch) in guards.chunks(BATCH_SIZE).enumerate() {
+            let mut reqs = RRefDeque::<BlkReq, BATCH_SIZE>::default();
+            for guard in batch {
+                reqs.push_back(self.request(guard.block_number));
+            }
+
+            self.stats.read_requests.fetch_add(1, Ordering::Relaxed);
+            let result = match self.bdev.read_blocks(reqs) {
+                Ok(result) => result,
+                Err(_) => Err(ErrorKind::RpcError),
+            };
+            let mut reqs = match result {
+                Ok(reqs) => reqs,
+                Err(error) => {
+                    for guard in guards[i * BATCH_SIZE..].iter() {
+                        self.set_failed(guard, error);
+                    }
+                    return Err(error);
+                }
+            };
+            while let Some(req) = reqs.pop_front() {
+                // Requests can complete in any order
+                let guard = batch
+                    .iter()
+                    .find(|guard| sector(guard.block_number) as u64 == req.block)
+                    .unwrap();
+                guard.lock().fill(&req.data);
+                self.set_valid(guard);
+                self.requests.lock().push(req);
+            }
+        }
+        Ok(())
+    }
+
+    // Write the buffers to disk with batched requests
+    pub fn write_many(&self, guards: &[BufferGuard]) {
+        for batch in guards.chunks(BATCH_SIZE) {
+            let mut reqs = RRefDeque::<BlkReq, BATCH_SIZE>::default();
+            for guard in batch {
+                let mut req = self.request(guard.block_number);
+                req.data = ***guard.lock();
+                reqs.push_back(req);
+            }
+
+            let mut reqs = self.bdev.write_blocks(reqs).unwrap().unwrap();
+            self.stats.write_requests.fetch_add(1, Ordering::Relaxed);
+            while let Some(req) = reqs.pop_front() {
+                self.requests.lock().push(req);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> FsStats {
+        FsStats {
+            bcache_hits: self.stats.hits.load(Ordering::Relaxed),
+            bcache_misses: self.stats.misses.load(Ordering::Relaxed),
+            readahead_blocks: self.stats.readahead.load(Ordering::Relaxed),
+            read_requests: self.stats.read_requests.load(Ordering::Relaxed),
+            write_requests: self.stats.write_requests.load(Ordering::Relaxed),
+        }
     }
 
     // This is confusing since it doesn't match xv6's brelse exactly so there could be a bug.
     // Check xv6 for details
     // TODO(tianjiao): fix this
-    fn release(&self, index: usize) {
+    fn release(&self, shard: usize, index: usize) {
         // println!("brelse {}", index);
-        self.internal.lock().release(index);
+        self.shards[shard].lock().release(index);
     }
 
-    fn pin(&self, index: usize) {
-        self.internal.lock().buffers[index].reference_count += 1;
+    fn pin(&self, shard: usize, index: usize) {
+        self.shards[shard].lock().buffers[index].reference_count += 1;
     }
 
-    fn unpin(&self, index: usize) {
-        self.internal.lock().buffers[index].reference_count -= 1;
+    fn unpin(&self, shard: usize, index: usize) {
+        self.shards[shard].lock().buffers[index].reference_count -= 1;
     }
 }
 
 impl core::fmt::Debug for BufferCache {
     fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
-        writeln!(fmt, "{:?}", *self.internal.lock())
+        for shard in self.shards.iter() {
+            writeln!(fmt, "{:?}", *shard.lock())?;
+        }
+        Ok(())
     }
 }
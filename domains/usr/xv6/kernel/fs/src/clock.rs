@@ -0,0 +1,13 @@
+use spin::Once;
+
+// Wall clock time and time stamp counter (in ns) when the clock was first read
+static START: Once<(u64, u64)> = Once::new();
+
+// Current time in seconds since the Unix epoch.
+// The RTC is only read once; afterwards the clock advances with the time stamp counter,
+// the same way sys_uptime does.
+pub fn now() -> u64 {
+    let (start_time, start_ns) =
+        *START.call_once(|| (libsyscalls::syscalls::sys_get_time(), libtime::get_ns_time()));
+    start_time + (libtime::get_ns_time() - start_ns) / 1_000_000_000
+}
@@ -1,12 +1,10 @@
-use alloc::boxed::Box;
 use alloc::collections::VecDeque;
-use alloc::vec::Vec;
 use spin::Mutex;
 
 use pc_keyboard::DecodedKey;
 
 use console::print;
-use libsyscalls::syscalls::{sys_readch_kbd, sys_yield};
+use libsyscalls::syscalls::{sys_interrupted, sys_readch, sys_yield};
 use interface::rv6::File;
 
 struct ConsoleDeviceInternal {
@@ -25,7 +23,7 @@ impl ConsoleDeviceInternal {
 
     fn populate_buffer_until_eol(&mut self) {
         loop {
-            let key = match sys_readch_kbd() {
+            let key = match sys_readch() {
                 Err(_e) => {
                     // println!("{}", e);
                     sys_yield();
@@ -35,6 +33,10 @@ impl ConsoleDeviceInternal {
             };
             match key {
                 None => {
+                    // A killed reader gives up on the line, rv6 fails its read
+                    if sys_interrupted() {
+                        return;
+                    }
                     sys_yield();
                     continue;
                 }
@@ -55,6 +57,16 @@ impl ConsoleDeviceInternal {
                                 self.reached_eol = true;
                                 return;
                             }
+                            // Ctrl-C, the kernel already flagged it for rv6 to interrupt the
+                            // foreground job. Throw away the line and hand the reader an
+                            // empty one.
+                            b'\x03' => {
+                                self.write(b"^C\n");
+                                self.buffer.clear();
+                                self.buffer.push_back(b'\n');
+                                self.reached_eol = true;
+                                return;
+                            }
                             _ => {}
                         }
                     }
@@ -79,7 +91,7 @@ impl ConsoleDeviceInternal {
                 Some(c) => *d = c,
                 None => {
                     self.reached_eol = false;
-                    return i + 1;
+                    return i;
                 }
             }
         }
@@ -102,7 +114,7 @@ impl ConsoleDeviceInternal {
 pub struct ConsoleDevice(Mutex<ConsoleDeviceInternal>);
 
 impl ConsoleDevice {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self(Mutex::new(ConsoleDeviceInternal::new()))
     }
 }
@@ -116,8 +128,3 @@ impl File for ConsoleDevice {
         self.0.lock().write(data)
     }
 }
-
-lazy_static! {
-    // xv6 equivalent: devsw
-    pub static ref DEVICES: Vec<Option<Box<dyn File + Send + Sync>>> = alloc::vec![None, Some(box ConsoleDevice::new())];
-}
@@ -0,0 +1,118 @@
+// The drivers behind device inodes, picked by the major number of the inode. The nodes in /dev
+// are created by rv6-mkfs, /proc has its own file type (see proc_device.rs).
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use spin::{Mutex, Once};
+
+use interface::rv6::File;
+use interface::tpm::UsrTpm;
+use interface::vfs::{ErrorKind, Result};
+
+use crate::console_device::ConsoleDevice;
+use crate::params;
+
+// xv6 equivalent: devsw
+static DEVICES: Once<Vec<Option<Box<dyn File + Send + Sync>>>> = Once::new();
+
+// Registers the devices, `tpm` is where /dev/random gets its bytes from
+pub fn init(tpm: Option<Box<dyn UsrTpm>>) {
+    DEVICES.call_once(|| {
+        let mut devices: Vec<Option<Box<dyn File + Send + Sync>>> = Vec::new();
+        devices.resize_with(params::NDEV as usize, || None);
+        devices[params::CONSOLEMAJOR as usize] = Some(box ConsoleDevice::new());
+        devices[params::NULLMAJOR as usize] = Some(box NullDevice);
+        devices[params::ZEROMAJOR as usize] = Some(box ZeroDevice);
+        devices[params::RANDOMMAJOR as usize] = Some(box RandomDevice::new(tpm));
+        devices
+    });
+}
+
+pub fn get(major: usize) -> Result<&'static (dyn File + Send + Sync)> {
+    DEVICES
+        .r#try()
+        .and_then(|devices| devices.get(major))
+        .and_then(|device| device.as_deref())
+        .ok_or(ErrorKind::InvalidMajor)
+}
+
+// /dev/null: always at end of file, swallows everything written to it
+struct NullDevice;
+
+impl File for NullDevice {
+    fn read(&self, _data: &mut [u8]) -> usize {
+        0
+    }
+
+    fn write(&self, data: &[u8]) -> usize {
+        data.len()
+    }
+}
+
+// /dev/zero: an endless stream of zeros, swallows everything written to it
+struct ZeroDevice;
+
+impl File for ZeroDevice {
+    fn read(&self, data: &mut [u8]) -> usize {
+        data.fill(0);
+        data.len()
+    }
+
+    fn write(&self, data: &[u8]) -> usize {
+        data.len()
+    }
+}
+
+// /dev/random: random bytes from the TPM. Without a TPM, or if the TPM fails, reads hit the end
+// of file instead of handing out bytes that aren't random.
+struct RandomDevice {
+    tpm: Option<Mutex<Box<dyn UsrTpm>>>,
+    // Whether the TPM granted us the locality, asked on the first read
+    locality: Once<bool>,
+}
+
+impl RandomDevice {
+    const LOCALITY: u32 = 0;
+
+    fn new(tpm: Option<Box<dyn UsrTpm>>) -> Self {
+        Self {
+            tpm: tpm.map(Mutex::new),
+            locality: Once::new(),
+        }
+    }
+}
+
+impl File for RandomDevice {
+    fn read(&self, data: &mut [u8]) -> usize {
+        let tpm = match &self.tpm {
+            Some(tpm) => tpm.lock(),
+            None => return 0,
+        };
+        let ready = *self
+            .locality
+            .call_once(|| tpm.tpm_request_locality(Self::LOCALITY).unwrap_or(false));
+        if !ready {
+            return 0;
+        }
+
+        // The TPM hands out at most a digest worth of bytes per command, and may return fewer
+        let mut read = 0;
+        let mut random = Vec::with_capacity(params::RANDOM_CHUNK);
+        while read < data.len() {
+            random.clear();
+            let wanted = core::cmp::min(data.len() - read, params::RANDOM_CHUNK);
+            match tpm.tpm_get_random(Self::LOCALITY, wanted, &mut random) {
+                Ok(true) if !random.is_empty() => {}
+                _ => break,
+            }
+            let bytes = core::cmp::min(random.len(), wanted);
+            data[read..read + bytes].copy_from_slice(&random[..bytes]);
+            read += bytes;
+        }
+        read
+    }
+
+    // The TPM collects its own entropy, so there is nothing to feed it
+    fn write(&self, data: &[u8]) -> usize {
+        data.len()
+    }
+}
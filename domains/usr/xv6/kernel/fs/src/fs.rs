@@ -2,9 +2,10 @@ use alloc::boxed::Box;
 use byteorder::{ByteOrder, LittleEndian};
 use spin::Once;
 
-use interface::bdev::BDev;
+use interface::bdev::{BDev, BSIZE};
+use interface::rref::RRef;
 
-use crate::bcache::{BufferCache, BCACHE};
+use crate::bcache::{self, BufferCache, BCACHE};
 use crate::log::{Log, LOG};
 use crate::params;
 
@@ -25,6 +26,7 @@ pub struct SuperBlock {
     pub inodestart: u32,
     // Block number of first inode block
     pub bmapstart: u32, // Block number of first free map block
+    pub magic: u32,     // Must be FSMAGIC
 }
 
 impl SuperBlock {
@@ -37,15 +39,50 @@ impl SuperBlock {
             logstart: LittleEndian::read_u32(&bytes[16..20]),
             inodestart: LittleEndian::read_u32(&bytes[20..24]),
             bmapstart: LittleEndian::read_u32(&bytes[24..28]),
+            magic: LittleEndian::read_u32(&bytes[28..32]),
         }
     }
+
+    // The geometry comes from whoever built the image, so check that it is one we can mount
+    fn validate(&self) {
+        let ninodeblocks = self.ninodes / params::IPB as u32 + 1;
+        let nbitmap = self.size / params::BPB as u32 + 1;
+        assert!(
+            self.magic == params::FSMAGIC,
+            "superblock: magic {:#x}, expected {:#x}; rebuild the image with this tree's mkfs",
+            self.magic,
+            params::FSMAGIC
+        );
+        assert!(self.size > self.nblocks, "superblock: no room for metadata: {:?}", self);
+        assert!(
+            self.nlog as usize > params::MAXOPBLOCKS && self.nlog as usize <= params::MAXLOGSIZE + 1,
+            "superblock: unsupported log size {}",
+            self.nlog
+        );
+        // Inode numbers are 16 bits wide
+        assert!(
+            self.ninodes > params::ROOTINO as u32 && self.ninodes <= u16::MAX as u32,
+            "superblock: unsupported inode count {}",
+            self.ninodes
+        );
+        assert!(
+            self.inodestart >= self.logstart + self.nlog
+                && self.bmapstart >= self.inodestart + ninodeblocks
+                && self.size - self.nblocks >= self.bmapstart + nbitmap,
+            "superblock: overlapping regions: {:?}",
+            self
+        );
+    }
 }
 
-// TODO: load super block from disk
-fn read_superblock(dev: u32) -> SuperBlock {
-    let buffer = BCACHE.r#try().unwrap().read(dev, 1);
-    let superblock = SuperBlock::from_bytes(&***buffer.lock());
+// The block cache is sized by the log in the superblock, so read it straight from the device
+fn read_superblock(dev: &dyn BDev) -> SuperBlock {
+    let buffer = dev
+        .read(bcache::sector(1), RRef::new([0u8; BSIZE]))
+        .expect("superblock: read failed");
+    let superblock = SuperBlock::from_bytes(&*buffer);
     console::println!("Superblock read from disk: {:?}", superblock);
+    superblock.validate();
     superblock
 }
 
@@ -55,7 +92,7 @@ pub fn block_num_for_node(inum: u16, super_block: &SuperBlock) -> u32 {
 }
 
 pub fn fsinit(dev_no: u32, dev: Box<dyn BDev>) {
-    BCACHE.call_once(|| BufferCache::new(dev));
-    SUPER_BLOCK.call_once(|| read_superblock(dev_no));
-    LOG.call_once(|| Log::new(dev_no, SUPER_BLOCK.r#try().unwrap()));
+    let super_block = SUPER_BLOCK.call_once(|| read_superblock(&*dev));
+    BCACHE.call_once(|| BufferCache::new(dev, params::shard_size(super_block.nlog as usize)));
+    LOG.call_once(|| Log::new(dev_no, super_block)).start_flush_thread();
 }
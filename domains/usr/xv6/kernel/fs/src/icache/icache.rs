@@ -1,3 +1,4 @@
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::mem;
@@ -6,8 +7,9 @@ use spin::Mutex;
 
 use interface::vfs::{ErrorKind, Result};
 
-use super::inode::{DINode, INode, INodeFileType};
+use super::inode::{DINode, INode, INodeFileType, Permissions};
 use crate::bcache::BCACHE;
+use crate::clock;
 use crate::cwd::CWD;
 use crate::fs::{block_num_for_node, SUPER_BLOCK};
 use crate::log::Transaction;
@@ -84,6 +86,8 @@ impl ICache {
         node_mut.meta.device = device;
         node_mut.meta.inum = inum;
         node_mut.meta.valid.store(false, Ordering::Relaxed);
+        node_mut.meta.next_read.store(0, Ordering::Relaxed);
+        node_mut.meta.readahead_end.store(0, Ordering::Relaxed);
         Ok(node.clone())
     }
 
@@ -111,11 +115,13 @@ impl ICache {
 
     // Look up and return the inode for a path.
     // If parent is true, return the inode for the parent and the final path element.
+    // Symbolic links are followed, except for the final path element when `follow` is false.
     // Must be called inside a transaction since it calls iput().
     fn namex<'a, 'b>(
         trans: &'a mut Transaction,
         path: &'b str,
         parent: bool,
+        follow: bool,
     ) -> Result<(Arc<INode>, &'b str)> {
         let mut inode = if path.starts_with('/') {
             ICACHE.lock().get(params::ROOTDEV, params::ROOTINO)?
@@ -123,10 +129,18 @@ impl ICache {
             CWD.with(|cwd| cwd.clone())
         };
 
-        let components: Vec<&str> = path.split('/').filter(|n| !n.is_empty()).collect();
-
-        let mut components_iter = components.iter().peekable();
-        while let Some(component) = components_iter.next() {
+        // Components still to be resolved, in reverse order. The targets of symbolic links are
+        // spliced in, so the final path element always stays at the bottom.
+        let mut components: Vec<String> = path
+            .split('/')
+            .filter(|n| !n.is_empty())
+            .rev()
+            .map(String::from)
+            .collect();
+        let last = path.split('/').filter(|n| !n.is_empty()).last().unwrap_or("");
+        let mut links = 0;
+
+        while let Some(component) = components.pop() {
             let mut iguard = inode.lock();
 
             // only the last path component can be a file
@@ -137,19 +151,54 @@ impl ICache {
             }
 
             // return the parent of the last path component
-            if parent && components_iter.peek().is_none() {
+            if parent && components.is_empty() {
                 drop(iguard);
-                return Ok((inode, component));
+                return Ok((inode, last));
             }
 
-            let next = iguard.dirlookup(trans, component);
+            let next = iguard.dirlookup(trans, &component);
             drop(iguard);
-            Self::put(trans, inode);
 
-            match next {
-                Ok((_, next)) => inode = next,
-                Err(e) => return Err(e),
+            let next = match next {
+                Ok((_, next)) => next,
+                Err(e) => {
+                    Self::put(trans, inode);
+                    return Err(e);
+                }
+            };
+
+            let mut next_guard = next.lock();
+            if next_guard.data.file_type != INodeFileType::Symlink
+                || (!follow && components.is_empty())
+            {
+                drop(next_guard);
+                Self::put(trans, inode);
+                inode = next;
+                continue;
             }
+
+            // Resolve the rest of the path from the directory holding the link
+            links += 1;
+            let target = if links > params::MAXSYMLINKS {
+                Err(ErrorKind::TooManySymlinks)
+            } else {
+                next_guard.readlink(trans)
+            };
+            drop(next_guard);
+            Self::put(trans, next);
+
+            let target = match target {
+                Ok(target) => target,
+                Err(e) => {
+                    Self::put(trans, inode);
+                    return Err(e);
+                }
+            };
+            if target.starts_with('/') {
+                Self::put(trans, inode);
+                inode = ICACHE.lock().get(params::ROOTDEV, params::ROOTINO)?;
+            }
+            components.extend(target.split('/').filter(|n| !n.is_empty()).rev().map(String::from));
         }
 
         if parent {
@@ -158,18 +207,23 @@ impl ICache {
         }
 
         // if we have a last component, return it along with the last inode
-        Ok((inode, components.last().unwrap_or(&"")))
+        Ok((inode, last))
     }
 
     pub fn namei(trans: &mut Transaction, path: &str) -> Result<Arc<INode>> {
-        Self::namex(trans, path, false).map(|(inode, _)| inode)
+        Self::namex(trans, path, false, true).map(|(inode, _)| inode)
+    }
+
+    // Like namei, but returns a symbolic link itself if it is the final path element
+    pub fn namei_nofollow(trans: &mut Transaction, path: &str) -> Result<Arc<INode>> {
+        Self::namex(trans, path, false, false).map(|(inode, _)| inode)
     }
 
     pub fn nameiparent<'a, 'b>(
         trans: &'a mut Transaction,
         path: &'b str,
     ) -> Result<(Arc<INode>, &'b str)> {
-        Self::namex(trans, path, true)
+        Self::namex(trans, path, true, false)
     }
 
     pub fn create(
@@ -204,6 +258,9 @@ impl ICache {
         iguard.data.major = major;
         iguard.data.minor = minor;
         iguard.data.nlink = 1;
+        iguard.data.uid = params::ROOTUID;
+        iguard.data.permissions = default_permissions(file_type);
+        iguard.data.mtime = clock::now();
         iguard.update(trans);
 
         if file_type == INodeFileType::Directory {
@@ -223,6 +280,16 @@ impl ICache {
     }
 }
 
+// Permissions of newly created inodes, like with a umask of 022
+fn default_permissions(file_type: INodeFileType) -> Permissions {
+    Permissions::from_bits_truncate(match file_type {
+        INodeFileType::Directory => 0o755,
+        INodeFileType::Device => 0o666,
+        INodeFileType::Symlink => 0o777,
+        _ => 0o644,
+    })
+}
+
 lazy_static! {
     pub static ref ICACHE: Mutex<ICache> = Mutex::new(ICache::new());
 }
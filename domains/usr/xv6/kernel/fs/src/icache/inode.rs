@@ -1,16 +1,21 @@
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use byteorder::{ByteOrder, LittleEndian};
 use core::convert::TryInto;
 use core::mem;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use num_traits::FromPrimitive;
 use spin::{Mutex, MutexGuard};
 
-pub use interface::vfs::{DirectoryEntry, DirectoryEntryRef, INodeFileType};
+use interface::rref::RRefSharedVec;
+pub use interface::vfs::{DirectoryEntry, DirectoryEntryRef, INodeFileType, Permissions};
 use interface::vfs::{ErrorKind, Result};
 
-use crate::bcache::BCACHE;
+use crate::bcache::{BufferGuard, BCACHE};
 use crate::block;
+use crate::clock;
 use crate::fs::{block_num_for_node, SUPER_BLOCK};
 use crate::icache::{ICache, ICACHE};
 use crate::log::Transaction;
@@ -25,6 +30,11 @@ pub struct INodeMeta {
     pub inum: u16,
     // inode has been read from disk?
     pub valid: AtomicBool,
+    // Readahead state, only updated with the data locked.
+    // Block holding the byte after the last read, where a sequential read starts
+    pub next_read: AtomicU32,
+    // Blocks before this one were read ahead already
+    pub readahead_end: AtomicU32,
 }
 
 #[repr(C)]
@@ -40,6 +50,14 @@ pub struct INodeData {
     pub nlink: i16,
     // Size of file (bytes)
     pub size: u32,
+    // User id of the owner
+    pub uid: u16,
+    // Access permission bits
+    pub permissions: Permissions,
+    // Last modification of the content (seconds since the Unix epoch)
+    pub mtime: u64,
+    // Last change of the inode (seconds since the Unix epoch)
+    pub ctime: u64,
     // Data block addresses
     pub addresses: [u32; params::NDIRECT + 1],
 }
@@ -52,6 +70,10 @@ impl INodeData {
             minor: 0,
             nlink: 0,
             size: 0,
+            uid: 0,
+            permissions: Permissions::empty(),
+            mtime: 0,
+            ctime: 0,
             addresses: [0; params::NDIRECT + 1],
         }
     }
@@ -75,6 +97,18 @@ impl INodeData {
         self.size = LittleEndian::read_u32(&bytes[offset..]);
         offset += mem::size_of_val(&self.size);
 
+        self.uid = LittleEndian::read_u16(&bytes[offset..]);
+        offset += mem::size_of_val(&self.uid);
+
+        self.permissions = Permissions::from_bits_truncate(LittleEndian::read_u16(&bytes[offset..]));
+        offset += mem::size_of_val(&self.permissions);
+
+        self.mtime = LittleEndian::read_u64(&bytes[offset..]);
+        offset += mem::size_of_val(&self.mtime);
+
+        self.ctime = LittleEndian::read_u64(&bytes[offset..]);
+        offset += mem::size_of_val(&self.ctime);
+
         for a in &mut self.addresses {
             *a = LittleEndian::read_u32(&bytes[offset..]);
             offset += mem::size_of_val(a);
@@ -104,6 +138,18 @@ impl INodeData {
         LittleEndian::write_u32(&mut bytes[offset..], self.size);
         offset += mem::size_of_val(&self.size);
 
+        LittleEndian::write_u16(&mut bytes[offset..], self.uid);
+        offset += mem::size_of_val(&self.uid);
+
+        LittleEndian::write_u16(&mut bytes[offset..], self.permissions.bits());
+        offset += mem::size_of_val(&self.permissions);
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.mtime);
+        offset += mem::size_of_val(&self.mtime);
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.ctime);
+        offset += mem::size_of_val(&self.ctime);
+
         for a in &self.addresses {
             LittleEndian::write_u32(&mut bytes[offset..], *a);
             offset += mem::size_of_val(a);
@@ -127,9 +173,12 @@ impl<'a> Drop for INodeDataGuard<'a> {
 
 impl INodeDataGuard<'_> {
     // Copy a modified in-memory inode to disk (ie flush)
-    // Call after every modification to Inode.data
+    // Call after every modification to Inode.data, which also makes it the time the
+    // inode changed.
     // xv6 equivalent: iupdate()
-    pub fn update(&self, trans: &mut Transaction) {
+    pub fn update(&mut self, trans: &mut Transaction) {
+        self.data.ctime = clock::now();
+
         // TODO: global superblock
         let super_block = SUPER_BLOCK.r#try().expect("fs not initialized");
 
@@ -153,35 +202,120 @@ impl INodeDataGuard<'_> {
     // Only called when node has no links and no other in-memory references to it
     // xv6 equivalent: itrunc
     pub fn truncate(&mut self, trans: &mut Transaction) {
-        for i in 0..params::NDIRECT {
+        self.free_blocks_from(trans, 0);
+        self.data.size = 0;
+        self.update(trans);
+    }
+
+    // Grow or shrink the file to `size` bytes.
+    // Growing leaves a hole that reads back as zeros. Shrinking frees at most TRUNCATE_BATCH
+    // blocks, so that the transaction stays within MAXOPBLOCKS. Returns false if the file is
+    // not down to `size` yet, the caller then calls again in a new transaction.
+    pub fn resize(&mut self, trans: &mut Transaction, size: usize) -> Result<bool> {
+        if size > params::MAXFILESIZE {
+            return Err(ErrorKind::FileTooLarge);
+        }
+
+        let nblocks = (self.data.size as usize + params::BSIZE - 1) / params::BSIZE;
+        let keep = (size + params::BSIZE - 1) / params::BSIZE;
+        if nblocks > keep + params::TRUNCATE_BATCH {
+            // Cut whole blocks off the end, the file is consistent after each step
+            let end = nblocks - params::TRUNCATE_BATCH;
+            self.free_blocks_from(trans, end);
+            self.data.size = (end * params::BSIZE) as u32;
+            self.data.mtime = clock::now();
+            self.update(trans);
+            return Ok(false);
+        }
+
+        if size < self.data.size as usize {
+            // Zero the tail of the last block we keep, so it doesn't come back if the file grows
+            let tail = size % params::BSIZE;
+            if tail != 0 {
+                let bguard = BCACHE.r#try().unwrap().read(
+                    self.node.meta.device,
+                    self.block_map(trans, (size / params::BSIZE) as u32),
+                );
+                let mut buffer = bguard.lock();
+                for v in buffer[tail..].iter_mut() {
+                    *v = 0;
+                }
+                trans.write(&bguard);
+                drop(buffer);
+            }
+
+            self.free_blocks_from(trans, (size + params::BSIZE - 1) / params::BSIZE);
+        }
+
+        self.data.size = size as u32;
+        self.data.mtime = clock::now();
+        self.update(trans);
+        Ok(true)
+    }
+
+    // Free every data block from the `first`th block of the file on, along with
+    // the indirect tables that become empty
+    fn free_blocks_from(&mut self, trans: &mut Transaction, first: usize) {
+        let device = self.node.meta.device;
+
+        for i in core::cmp::min(first, params::NDIRECT)..params::NDIRECT {
             if self.data.addresses[i] != 0 {
-                block::free(trans, self.node.meta.device, self.data.addresses[i]);
+                block::free(trans, device, self.data.addresses[i]);
                 self.data.addresses[i] = 0;
             }
         }
 
-        if self.data.addresses[params::NDIRECT] != 0 {
-            let bguard = BCACHE
-                .r#try()
-                .unwrap()
-                .read(self.node.meta.device, self.data.addresses[params::NDIRECT]);
-            let buffer = bguard.lock();
+        let indirect = self.data.addresses[params::NDIRECT];
+        if indirect == 0 {
+            return;
+        }
 
-            let mut chunks_iter = buffer.chunks_exact(core::mem::size_of::<u32>());
-            for _ in 0..params::NINDIRECT {
-                let chunk = chunks_iter.next().unwrap();
-                let block = u32::from_ne_bytes(chunk.try_into().unwrap());
+        // Index of the first block to free within the 2-layer indirect table
+        let first = first.saturating_sub(params::NDIRECT);
+
+        let l1_bguard = BCACHE.r#try().unwrap().read(device, indirect);
+        let mut l1_buffer = l1_bguard.lock();
+        let mut l1_dirty = false;
+        for table_index in 0..params::NINDIRECT {
+            let table = read_address(&l1_buffer[..], table_index);
+            // Skip tables that are missing or whose blocks are all kept
+            if table == 0 || (table_index + 1) * params::NINDIRECT <= first {
+                continue;
+            }
+            // Number of entries we keep in this table
+            let keep = first.saturating_sub(table_index * params::NINDIRECT);
+
+            let l2_bguard = BCACHE.r#try().unwrap().read(device, table);
+            let mut l2_buffer = l2_bguard.lock();
+            for entry in keep..params::NINDIRECT {
+                let block = read_address(&l2_buffer[..], entry);
                 if block != 0 {
-                    block::free(trans, self.node.meta.device, block);
+                    block::free(trans, device, block);
+                    write_address(&mut l2_buffer[..], entry, 0);
                 }
             }
-            drop(buffer);
+            if keep > 0 {
+                trans.write(&l2_bguard);
+            }
+            drop(l2_buffer);
 
-            self.data.addresses[params::NDIRECT] = 0;
+            if keep == 0 {
+                block::free(trans, device, table);
+                write_address(&mut l1_buffer[..], table_index, 0);
+                l1_dirty = true;
+            }
         }
 
-        self.data.size = 0;
-        self.update(trans);
+        if first == 0 {
+            drop(l1_buffer);
+            block::free(trans, device, indirect);
+            self.data.addresses[params::NDIRECT] = 0;
+        } else {
+            if l1_dirty {
+                trans.write(&l1_bguard);
+            }
+            drop(l1_buffer);
+        }
     }
 
     // xv6 equivalent: stati
@@ -192,13 +326,17 @@ impl INodeDataGuard<'_> {
             file_type: self.data.file_type,
             nlink: self.data.nlink,
             size: self.data.size as u64,
+            uid: self.data.uid,
+            permissions: self.data.permissions,
+            mtime: self.data.mtime,
+            ctime: self.data.ctime,
         }
     }
 
     // The content (data) associated with each inode is stored
     // in blocks on the disk. The first NDIRECT block numbers
-    // are listed in self.data.addresses. The next NINDIRECT blocks are
-    // listed in block self.data.addresses[NDIRECT].
+    // are listed in self.data.addresses. The remaining NINDIRECT * NINDIRECT
+    // blocks go through a two-level table rooted at self.data.addresses[NDIRECT].
     // Return the disk block address of the nth block in self,
     // if there is no such block, block_map allocates one.
     // xv6 equivalent: bmap
@@ -222,7 +360,7 @@ impl INodeDataGuard<'_> {
             block_number < params::NINDIRECT * params::NINDIRECT,
             "bmap: out of range"
         );
-        // Load level 1 indirect block, allocating if necessary.
+        // Level 1 indirect table, allocating if necessary.
         let mut address = self.data.addresses[params::NDIRECT];
         if address == 0 {
             address =
@@ -230,44 +368,62 @@ impl INodeDataGuard<'_> {
             self.data.addresses[params::NDIRECT] = address;
         }
 
-        let bguard = BCACHE.r#try().unwrap().read(self.node.meta.device, address);
-        let buffer = bguard.lock();
-
-        // The index of the level 1 table entry that this block belongs to
+        // Load level 1 indirect block, allocating the level 2 table if necessary.
         let table_index = block_number / params::NINDIRECT;
-        // get 4-byte slice from offset block_number * 4
-        let mut address = {
-            let start_index = table_index * core::mem::size_of::<u32>();
-            let end_index = (table_index + 1) * core::mem::size_of::<u32>();
-            let chunk = &buffer[start_index..end_index];
-            u32::from_ne_bytes(chunk.try_into().unwrap())
-        };
+        let address = self.table_entry(trans, address, table_index);
 
-        if address == 0 {
-            address =
-                block::alloc(trans, self.node.meta.device).expect("block::alloc out of blocks");
-            trans.write(&bguard);
-        }
+        // Load level 2 indirect block, allocating the data block if necessary.
+        let table_index = block_number % params::NINDIRECT;
+        self.table_entry(trans, address, table_index)
+    }
 
-        drop(buffer);
+    // Like block_map on each block of the range, but returns 0 instead of allocating a missing
+    // block. Each indirect table is read once for the whole range.
+    fn block_lookups(&self, blocks: Range<usize>) -> Vec<u32> {
+        let bcache = BCACHE.r#try().unwrap();
+        let read_table = |address: u32| match address {
+            0 => None,
+            address => Some(bcache.read(self.node.meta.device, address)),
+        };
+        let entry = |table: &Option<BufferGuard>, index: usize| match table {
+            Some(bguard) => read_address(&bguard.lock()[..], index),
+            None => 0,
+        };
 
-        // Load level 2 indirect block, allocating if necessary.
-        let bguard = BCACHE.r#try().unwrap().read(self.node.meta.device, address);
-        let buffer = bguard.lock();
+        let mut addresses = Vec::with_capacity(blocks.len());
+        // The level 1 table once read, and the index of the level 2 table last read
+        let mut level1 = None;
+        let mut level2 = None;
+        for block_number in blocks {
+            if block_number < params::NDIRECT {
+                addresses.push(self.data.addresses[block_number]);
+                continue;
+            }
 
-        // The index of the level 1 table entry that this block belongs to
-        let table_index = block_number - params::NINDIRECT * table_index;
-        // get 4-byte slice from offset block_number * 4
-        let mut address = {
-            let start_index = table_index * core::mem::size_of::<u32>();
-            let end_index = (table_index + 1) * core::mem::size_of::<u32>();
-            let chunk = &buffer[start_index..end_index];
-            u32::from_ne_bytes(chunk.try_into().unwrap())
-        };
+            let block_number = block_number - params::NDIRECT;
+            let level1 =
+                level1.get_or_insert_with(|| read_table(self.data.addresses[params::NDIRECT]));
+            let table_index = block_number / params::NINDIRECT;
+            if level2.as_ref().map(|(index, _)| *index) != Some(table_index) {
+                level2 = Some((table_index, read_table(entry(level1, table_index))));
+            }
+            let (_, table) = level2.as_ref().unwrap();
+            addresses.push(entry(table, block_number % params::NINDIRECT));
+        }
+        addresses
+    }
+
+    // Return the block address stored at `index` of the indirect table in block `table`.
+    // If the entry is empty, a new block is allocated and recorded in the table.
+    fn table_entry(&mut self, trans: &mut Transaction, table: u32, index: usize) -> u32 {
+        let bguard = BCACHE.r#try().unwrap().read(self.node.meta.device, table);
+        let mut buffer = bguard.lock();
 
+        let mut address = read_address(&buffer[..], index);
         if address == 0 {
             address =
                 block::alloc(trans, self.node.meta.device).expect("block::alloc out of blocks");
+            write_address(&mut buffer[..], index, address);
             trans.write(&bguard);
         }
 
@@ -332,6 +488,10 @@ impl INodeDataGuard<'_> {
 
     // Write a new directory entry (name, inum) into the directory.
     pub fn dirlink(&mut self, trans: &mut Transaction, name: &str, inum: u16) -> Result<()> {
+        if name.len() > params::DIRSIZ {
+            return Err(ErrorKind::InvalidParameter);
+        }
+
         // check that the name is not present
         if let Ok((_, inode)) = self.dirlookup(trans, name) {
             ICache::put(trans, inode);
@@ -342,7 +502,8 @@ impl INodeDataGuard<'_> {
         const SIZE_OF_DIRENT: usize = core::mem::size_of::<DirectoryEntry>();
         let mut buffer = [0; SIZE_OF_DIRENT];
 
-        let mut empty_offset = 0;
+        // append to the directory if there is no empty dirent
+        let mut empty_offset = self.data.size as usize;
         for offset in (0usize..self.data.size as usize).step_by(SIZE_OF_DIRENT) {
             self.read(trans, &mut buffer[..], offset)?;
             let dirent = DirectoryEntryRef::from_bytes(&buffer[..]);
@@ -352,6 +513,18 @@ impl INodeDataGuard<'_> {
             }
         }
 
+        self.write_dirent(trans, empty_offset, name, inum)
+    }
+
+    // Overwrite the directory entry at `offset`
+    pub fn write_dirent(
+        &mut self,
+        trans: &mut Transaction,
+        offset: usize,
+        name: &str,
+        inum: u16,
+    ) -> Result<()> {
+        const SIZE_OF_DIRENT: usize = core::mem::size_of::<DirectoryEntry>();
         let mut cloned_name = name.as_bytes().clone().to_vec();
         for _ in cloned_name.len()..params::DIRSIZ {
             cloned_name.push(0);
@@ -361,11 +534,44 @@ impl INodeDataGuard<'_> {
             inum,
         };
 
-        buffer = dirent.as_bytes();
-        self.write(trans, &mut buffer[..], empty_offset)?;
+        let mut buffer: [u8; SIZE_OF_DIRENT] = dirent.as_bytes();
+        self.write(trans, &mut buffer[..], offset)?;
         Ok(())
     }
 
+    // Find the name `inum` is linked as in this directory, ignoring "." and ".."
+    pub fn dirname(&mut self, trans: &mut Transaction, inum: u16) -> Result<String> {
+        if self.data.file_type != INodeFileType::Directory {
+            panic!("dirname not DIR");
+        }
+
+        const SIZE_OF_DIRENT: usize = core::mem::size_of::<DirectoryEntry>();
+        for offset in (0usize..self.data.size as usize)
+            .step_by(SIZE_OF_DIRENT)
+            .skip(2)
+        {
+            let mut buffer = [0; SIZE_OF_DIRENT];
+            self.read(trans, &mut buffer[..], offset)?;
+            let dirent = DirectoryEntryRef::from_bytes(&buffer[..]);
+            if dirent.inum == inum {
+                return utils::cstr::to_string(dirent.name).map_err(|_| ErrorKind::Utf8Error);
+            }
+        }
+
+        Err(ErrorKind::FileNotFound)
+    }
+
+    // Read the path a symbolic link points to
+    pub fn readlink(&mut self, trans: &mut Transaction) -> Result<String> {
+        if self.data.file_type != INodeFileType::Symlink {
+            return Err(ErrorKind::InvalidFileType);
+        }
+
+        let mut buffer = alloc::vec![0; self.data.size as usize];
+        self.read(trans, &mut buffer[..], 0)?;
+        String::from_utf8(buffer).map_err(|_| ErrorKind::Utf8Error)
+    }
+
     // Read data from inode
     // Returns number of bytes read, or None upon overflow
     // xv6 equivalent: readi
@@ -377,6 +583,10 @@ impl INodeDataGuard<'_> {
     ) -> Result<usize> {
         let mut bytes_to_read = user_buffer.len();
 
+        // Nothing to read past the end of the file
+        if offset >= self.data.size as usize {
+            return Ok(0);
+        }
         // We ask Rust to always check overflow so we don't need to check it manually
         if offset + bytes_to_read > self.data.size as usize {
             bytes_to_read = self.data.size as usize - offset;
@@ -384,30 +594,93 @@ impl INodeDataGuard<'_> {
 
         let mut total = 0usize;
         let mut user_offset = 0usize;
+        let first_block = offset / params::BSIZE;
 
         while total < bytes_to_read {
-            let bguard = BCACHE.r#try().unwrap().read(
-                self.node.meta.device,
-                self.block_map(trans, (offset / params::BSIZE) as u32),
-            );
-            let buffer = bguard.lock();
+            // Fetch the next few blocks of the range with a single request
+            let first = offset / params::BSIZE;
+            let last = (offset + bytes_to_read - total - 1) / params::BSIZE;
+            let count = core::cmp::min(last - first + 1, params::READ_BATCH);
+            let block_numbers: Vec<u32> = (first..first + count)
+                .map(|block| self.block_map(trans, block as u32))
+                .collect();
+            let bguards = BCACHE
+                .r#try()
+                .unwrap()
+                .read_many(self.node.meta.device, &block_numbers)?;
 
-            let start = offset % params::BSIZE;
-            let bytes_read = core::cmp::min(bytes_to_read - total, params::BSIZE - start);
+            for bguard in bguards.iter() {
+                let buffer = bguard.lock();
 
-            user_buffer[user_offset..(user_offset + bytes_read)]
-                .copy_from_slice(&buffer[start..(start + bytes_read)]);
+                let start = offset % params::BSIZE;
+                let bytes_read = core::cmp::min(bytes_to_read - total, params::BSIZE - start);
 
-            drop(buffer);
+                user_buffer[user_offset..(user_offset + bytes_read)]
+                    .copy_from_slice(&buffer[start..(start + bytes_read)]);
+
+                drop(buffer);
 
-            total += bytes_read;
-            offset += bytes_read;
-            user_offset += bytes_read;
+                total += bytes_read;
+                offset += bytes_read;
+                user_offset += bytes_read;
+            }
         }
 
+        self.readahead(first_block, offset / params::BSIZE);
         Ok(bytes_to_read)
     }
 
+    // Called after a read of the blocks from `first` on, where the next read would start at
+    // block `next`. As long as reads are sequential, keep at least READAHEAD / 2 blocks after
+    // `next` in the cache, fetching up to READAHEAD at a time.
+    fn readahead(&self, first: usize, next: usize) {
+        let meta = &self.node.meta;
+        let sequential = meta.next_read.swap(next as u32, Ordering::Relaxed) as usize == first;
+        if !sequential {
+            meta.readahead_end.store(0, Ordering::Relaxed);
+            return;
+        }
+
+        let nblocks = (self.data.size as usize + params::BSIZE - 1) / params::BSIZE;
+        let start = core::cmp::max(meta.readahead_end.load(Ordering::Relaxed) as usize, next);
+        let end = core::cmp::min(next + params::READAHEAD, nblocks);
+        if start >= end || start >= next + params::READAHEAD / 2 {
+            return;
+        }
+
+        let mut block_numbers = self.block_lookups(start..end);
+        block_numbers.retain(|address| *address != 0);
+        BCACHE
+            .r#try()
+            .unwrap()
+            .prefetch(self.node.meta.device, &block_numbers);
+        meta.readahead_end.store(end as u32, Ordering::Relaxed);
+    }
+
+    // Returns a read-only view of the file from `offset` to the end of the block holding it,
+    // or an empty one past the end of the file. The view shares the block with the buffer
+    // cache, which copies the block before it changes. Not in xv6
+    pub fn view(&mut self, trans: &mut Transaction, offset: usize) -> RRefSharedVec<u8> {
+        let size = self.data.size as usize;
+        if offset >= size {
+            return RRefSharedVec::from_slice(&[]);
+        }
+
+        let block = offset / params::BSIZE;
+        let start = offset % params::BSIZE;
+        let end = core::cmp::min(params::BSIZE, size - block * params::BSIZE);
+        let block_number = self.block_map(trans, block as u32);
+        let bguard = BCACHE
+            .r#try()
+            .unwrap()
+            .read(self.node.meta.device, block_number);
+        let view = bguard.lock().view();
+        drop(bguard);
+
+        self.readahead(block, block + 1);
+        view.slice(start..end)
+    }
+
     // Write data to inode
     // Returns number of bytes written, or None upon overflow
     // xv6 equivalent: writei
@@ -418,6 +691,10 @@ impl INodeDataGuard<'_> {
         mut offset: usize,
     ) -> Result<usize> {
         let bytes_to_write = user_buffer.len();
+        if offset + bytes_to_write > params::MAXFILESIZE {
+            return Err(ErrorKind::FileTooLarge);
+        }
+
         let mut total = 0usize;
         let mut user_offset = 0usize;
 
@@ -444,6 +721,7 @@ impl INodeDataGuard<'_> {
 
         if bytes_to_write > 0 {
             self.data.size = core::cmp::max(offset as u32, self.data.size);
+            self.data.mtime = clock::now();
             // write the node back to disk even if size didn't change, because block_map
             // could have added a new block to self.addresses
             self.update(trans)
@@ -563,6 +841,8 @@ impl INode {
                 device: 0,
                 inum: 0,
                 valid: AtomicBool::new(false),
+                next_read: AtomicU32::new(0),
+                readahead_end: AtomicU32::new(0),
             },
             data: Mutex::new(INodeData {
                 file_type: INodeFileType::Unitialized,
@@ -570,6 +850,10 @@ impl INode {
                 minor: 0,
                 nlink: 0,
                 size: 0,
+                uid: 0,
+                permissions: Permissions::empty(),
+                mtime: 0,
+                ctime: 0,
                 addresses: [0; params::NDIRECT + 1],
             }),
         }
@@ -608,3 +892,14 @@ impl INode {
         INodeDataGuard { node: &self, data }
     }
 }
+
+// Block address stored in the `index`th slot of an indirect table
+fn read_address(table: &[u8], index: usize) -> u32 {
+    let start = index * core::mem::size_of::<u32>();
+    u32::from_ne_bytes(table[start..start + core::mem::size_of::<u32>()].try_into().unwrap())
+}
+
+fn write_address(table: &mut [u8], index: usize, address: u32) {
+    let start = index * core::mem::size_of::<u32>();
+    table[start..start + core::mem::size_of::<u32>()].copy_from_slice(&address.to_ne_bytes());
+}
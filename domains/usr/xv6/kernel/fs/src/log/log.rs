@@ -1,9 +1,17 @@
 // See https://github.com/mit-pdos/xv6-public/blob/master/log.c
+//
+// Unlike xv6, the log does group commit: a system call returns once its blocks are in the
+// log, and the log is only written to disk when it fills up, when `Log::sync` is called, or
+// every `LOG_FLUSH_INTERVAL` by the flush thread. Transactions in between are committed
+// together, and a block they all write goes to disk once per commit.
+use alloc::collections::BTreeSet;
 use alloc::sync::Arc;
-use core::mem::size_of;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
 use spin::{Mutex, Once};
 
 use libsyscalls::sync::CondVar;
+use libsyscalls::syscalls::sys_create_thread;
 
 use crate::bcache::{BufferGuard, BCACHE};
 use crate::fs::SuperBlock;
@@ -16,6 +24,8 @@ pub static LOG: Once<Log> = Once::new();
 
 pub struct Log {
     log: Arc<(Mutex<LogInternal>, CondVar)>,
+    // Cleared to make the flush thread exit
+    flushing: AtomicBool,
 }
 
 impl Log {
@@ -25,73 +35,243 @@ impl Log {
                 Mutex::new(LogInternal::new(dev, superblock)),
                 CondVar::new(),
             )),
+            flushing: AtomicBool::new(false),
         }
     }
 
     pub fn begin_transaction(&self) -> Transaction {
         Transaction::new(self.log.clone())
     }
+
+    // Writes every transaction that ended before the call to disk
+    pub fn sync(&self) {
+        let (log_internal, cv) = &*self.log;
+        let target = {
+            let mut guard = log_internal.lock();
+            if guard.committing {
+                // Every transaction that ended is part of the commit in progress
+                guard.commits + 1
+            } else if guard.contents().logheader.n == 0 {
+                return;
+            } else if guard.outstanding == 0 {
+                let contents = guard.start_commit();
+                drop(guard);
+                commit(log_internal, contents);
+                cv.wakeup_all();
+                return;
+            } else {
+                // The last outstanding transaction commits when it ends
+                guard.flush_requested = true;
+                guard.commits + 1
+            }
+        };
+        drop(cv.sleep_until(log_internal, |log| log.commits >= target));
+    }
+
+    // Starts the thread that commits the log every `LOG_FLUSH_INTERVAL`
+    pub fn start_flush_thread(&self) {
+        self.flushing.store(true, Ordering::SeqCst);
+        sys_create_thread("xv6fs[log flush]", flush_thread);
+    }
+
+    // The flush thread exits within `LOG_FLUSH_INTERVAL`, without committing again. Transactions
+    // that ended since the last commit stay in memory, as after a crash.
+    pub fn stop_flush_thread(&self) {
+        self.flushing.store(false, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn flush_thread() {
+    let log = LOG.r#try().unwrap();
+    loop {
+        libtime::sys_ns_sleep(params::LOG_FLUSH_INTERVAL);
+        if !log.flushing.load(Ordering::SeqCst) {
+            return;
+        }
+        log.sync();
+    }
+}
+
+// Writes the log to disk and installs it, without holding the log lock. `contents` comes from
+// `LogInternal::start_commit`, whose `committing` flag holds back new transactions until then.
+// The caller wakes up the waiting threads afterwards.
+pub fn commit(log_internal: &Mutex<LogInternal>, mut contents: LogContents) {
+    contents.commit();
+    log_internal.lock().finish_commit(contents);
+}
+
+pub enum BeginOp {
+    Started,
+    // Try again once the log changes
+    Wait,
+    // The log has to be committed first, see `commit`
+    Commit(LogContents),
 }
 
 #[derive(Debug)]
 pub struct LogInternal {
-    start: u32,
-    size: u32,
     outstanding: u32, // how many FS sys calls are executing.
     committing: bool, // in commit(), please wait.
+    flush_requested: bool, // commit once the outstanding ops end, and hold back new ones until then.
+    commits: u64, // how many times the log was committed.
+    contents: Option<LogContents>, // taken out while committing.
+}
+
+// The blocks in the log and the on-disk log they go to. A commit takes them out of
+// `LogInternal` and writes them to disk without holding the log lock, like xv6 does.
+#[derive(Debug)]
+pub struct LogContents {
+    start: u32,
+    size: u32,
+    capacity: u32, // data blocks in the log
     dev: u32,
     logheader: LogHeader,
+    logged: BTreeSet<u32>, // blocks in logheader, to absorb repeated writes.
 }
 
 impl LogInternal {
     pub fn new(dev: u32, superblock: &SuperBlock) -> Self {
+        // The first block of the log is the header
+        let capacity = superblock.nlog - 1;
         assert!(
-            size_of::<LogHeader>() < params::BSIZE,
+            capacity as usize <= params::MAXLOGSIZE,
             "initlog: too big logheader"
         );
-        let mut log = Self {
+        let mut contents = LogContents {
             start: superblock.logstart,
             size: superblock.nlog,
-            outstanding: 0,
-            committing: false,
+            capacity,
             dev,
-            logheader: LogHeader {
-                n: 123_456,
-                block_nums: [123_456; params::LOGSIZE],
-            },
+            logheader: LogHeader::new(capacity as usize),
+            logged: BTreeSet::new(),
         };
-        log.recover_from_log();
-        log
+        contents.recover_from_log();
+        Self {
+            outstanding: 0,
+            committing: false,
+            flush_requested: false,
+            commits: 0,
+            contents: Some(contents),
+        }
+    }
+
+    // Only while no commit is in progress
+    fn contents(&self) -> &LogContents {
+        self.contents.as_ref().expect("log: commit in progress")
+    }
+
+    // called at the start of each FS system call.
+    // Caller should repeatly call this function until it returns `Started`, committing the log
+    // when asked to.
+    pub fn try_begin_op(&mut self) -> BeginOp {
+        // console::println!("try_begin_op; {:?}", self);
+        if self.committing {
+            return BeginOp::Wait;
+        }
+        let full = self.contents().logheader.n
+            + (self.outstanding + 1) * params::MAXOPBLOCKS as u32
+            > self.contents().capacity;
+        if self.flush_requested || full {
+            if self.outstanding > 0 {
+                // Wait for the last outstanding op to commit
+                self.flush_requested = true;
+                return BeginOp::Wait;
+            }
+            return BeginOp::Commit(self.start_commit());
+        }
+        // console::println!("op begin");
+        self.outstanding += 1;
+        BeginOp::Started
+    }
+
+    // called at the end of each FS system call.
+    // Returns the log to commit if this was the last outstanding operation and a commit was
+    // requested.
+    pub fn end_op(&mut self) -> Option<LogContents> {
+        // console::println!("end_op; outstanding {}", self.outstanding);
+
+        self.outstanding -= 1;
+        assert!(!self.committing, "log.commiting");
+        if self.outstanding == 0 && self.flush_requested {
+            return Some(self.start_commit());
+        }
+        None
+    }
+
+    // Must only be called when no op is outstanding. Hands the log to `commit`.
+    fn start_commit(&mut self) -> LogContents {
+        assert_eq!(self.outstanding, 0, "commit with outstanding ops");
+        self.committing = true;
+        self.contents.take().unwrap()
+    }
+
+    fn finish_commit(&mut self, contents: LogContents) {
+        self.contents = Some(contents);
+        self.committing = false;
+        self.flush_requested = false;
+        self.commits += 1;
+    }
+
+    // Caller has modified b->data and is done with the buffer.
+    // Record the block number and pin in the cache by increasing refcnt.
+    // commit()/write_log() will do the disk write.
+    //
+    // log_write() replaces bwrite(); a typical use is:
+    //   bp = bread(...)
+    //   modify bp->data[]
+    //   log_write(bp)
+    //   brelse(bp)
+    pub fn log_write(&mut self, buffer: &BufferGuard) {
+        assert!(self.outstanding >= 1, "log_write outside of trans");
+        let contents = self.contents.as_mut().unwrap();
+
+        // console::println!("writing {} to transaction", buffer.block_number());
+        // Log absorbtion: if the block is already in the log, don't need to do anything,
+        // the commit copies its latest content from the cache.
+        //  Else, add the new block to the log
+        if contents.logged.insert(buffer.block_number()) {
+            assert!(
+                contents.logheader.n < contents.capacity,
+                "too big a transaction"
+            );
+            buffer.pin();
+            contents.logheader.block_nums[contents.logheader.n as usize] = buffer.block_number();
+            contents.logheader.n += 1;
+        }
     }
+}
 
+impl LogContents {
     // Copy committed blocks from log to their home location
     fn install_trans(&mut self) {
-        for tail in 0..self.logheader.n {
-            // console::println!("committing {} to {}", self.start + tail + 1, self.logheader.block_nums[tail as usize]);
-            let lbuf = BCACHE
-                .r#try()
-                .unwrap()
-                .read(self.dev, self.start + tail + 1);
-            let dbuf = BCACHE
-                .r#try()
-                .unwrap()
-                .read(self.dev, self.logheader.block_nums[tail as usize]);
-            {
-                let mut locked_dbuf = dbuf.lock();
-                ***locked_dbuf = ***lbuf.lock();
-                BCACHE
-                    .r#try()
-                    .unwrap()
-                    .write(dbuf.block_number(), &mut locked_dbuf); // write dst to disk
-            }
+        let bcache = BCACHE.r#try().unwrap();
+        let n = self.logheader.n as usize;
+        let lbufs = bcache
+            .read_many(self.dev, &self.log_blocks())
+            .expect("log: cannot read log blocks"); // log blocks
+        let dbufs = bcache
+            .read_many(self.dev, &self.logheader.block_nums[..n])
+            .expect("log: cannot read dst blocks"); // dst blocks
+        for (lbuf, dbuf) in lbufs.iter().zip(dbufs.iter()) {
+            // console::println!("committing {} to {}", lbuf.block_number(), dbuf.block_number());
+            ***dbuf.lock() = ***lbuf.lock();
+        }
+        bcache.write_many(&dbufs); // write dst to disk
+        for dbuf in dbufs.iter() {
             dbuf.unpin();
         }
     }
 
+    // Block numbers of the used part of the on-disk log
+    fn log_blocks(&self) -> Vec<u32> {
+        (0..self.logheader.n).map(|tail| self.start + tail + 1).collect()
+    }
+
     // Read the log header from disk into the in-memory log header
     fn read_head(&mut self) {
         let buf = BCACHE.r#try().unwrap().read(self.dev, self.start);
         self.logheader.from_buffer_block(&buf.lock());
+        assert!(self.logheader.n <= self.capacity, "Log::read_head: corrupted log header");
         console::println!("Log::read_head: {:?}", self);
     }
 
@@ -117,70 +297,21 @@ impl LogInternal {
         self.write_head();
     }
 
-    // called at the start of each FS system call.
-    // Caller should repeatly call this function until this function returns true.
-    // A better implementation of this function would be that
-    // this functions returns a guard that can be used to write_log.
-    // And end_op will be called when the guard is dropped.
-    // TODO(tianjiao): fix this
-    pub fn try_begin_op(&mut self) -> bool {
-        // console::println!("try_begin_op; {:?}", self);
-        if self.committing {
-            return false;
-        }
-        if self.logheader.n + (self.outstanding + 1) * params::MAXOPBLOCKS as u32
-            > params::LOGSIZE as u32
-        {
-            return false;
-        }
-        // console::println!("op begin");
-        self.outstanding += 1;
-        true
-    }
-
-    // called at the end of each FS system call.
-    // commits if this was the last outstanding operation.
-    pub fn end_op(&mut self) {
-        let mut do_commit: bool = false;
-
-        // console::println!("end_op; outstanding {}", self.outstanding);
-
-        self.outstanding -= 1;
-        assert!(!self.committing, "log.commiting");
-        if self.outstanding == 0 {
-            do_commit = true;
-            self.committing = true;
-        }
-
-        if do_commit {
-            self.commit();
-            self.committing = false;
-        }
-    }
-
     // Copy modified blocks from cache to log.
     fn write_log(&mut self) {
-        for tail in 0..self.logheader.n {
-            // console::println!("logging {} to {}", self.logheader.block_nums[tail as usize], self.start + tail + 1);
-            let to = BCACHE
-                .r#try()
-                .unwrap()
-                .read(self.dev, self.start + tail + 1); // log block
-            let from = BCACHE
-                .r#try()
-                .unwrap()
-                .read(self.dev, self.logheader.block_nums[tail as usize]); // cache block
-            {
-                let mut locked_to = to.lock();
-                ***locked_to = ***from.lock();
-                BCACHE
-                    .r#try()
-                    .unwrap()
-                    .write(to.block_number(), &mut locked_to); // write the log
-            }
+        let bcache = BCACHE.r#try().unwrap();
+        let tos = bcache
+            .read_many(self.dev, &self.log_blocks())
+            .expect("log: cannot read log blocks"); // log blocks
+        for (tail, to) in tos.iter().enumerate() {
+            // console::println!("logging {} to {}", self.logheader.block_nums[tail], to.block_number());
+            let from = bcache.read(self.dev, self.logheader.block_nums[tail]); // cache block
+            ***to.lock() = ***from.lock();
         }
+        bcache.write_many(&tos); // write the log
     }
 
+    // Write the log and install it
     fn commit(&mut self) {
         if self.logheader.n > 0 {
             // console::println!("committing");
@@ -188,38 +319,8 @@ impl LogInternal {
             self.write_head(); // Write header to disk -- the real commit
             self.install_trans(); // Now install writes to home locations
             self.logheader.n = 0;
+            self.logged.clear();
             self.write_head(); // Erase the transaction from the log
         }
     }
-
-    // Caller has modified b->data and is done with the buffer.
-    // Record the block number and pin in the cache by increasing refcnt.
-    // commit()/write_log() will do the disk write.
-    //
-    // log_write() replaces bwrite(); a typical use is:
-    //   bp = bread(...)
-    //   modify bp->data[]
-    //   log_write(bp)
-    //   brelse(bp)
-    pub fn log_write(&mut self, buffer: &BufferGuard) {
-        assert!(
-            self.logheader.n < params::LOGSIZE as u32 || self.logheader.n < self.size - 1,
-            "too big a transaction"
-        );
-        assert!(self.outstanding >= 1, "log_write outside of trans");
-
-        // console::println!("writing {} to transaction", buffer.block_number());
-        // Find the index that the block should belong to.
-        // Log absorbtion: if the block is already in the log, don't need to do anything.
-        //  Else, add the new block to the log
-        self.logheader.block_nums[self.logheader.n as usize] = buffer.block_number();
-        let current_blocks = &self.logheader.block_nums[0..self.logheader.n as usize];
-        let i = current_blocks
-            .iter()
-            .position(|&x| x == buffer.block_number());
-        if i.is_none() {
-            buffer.pin();
-            self.logheader.n += 1;
-        }
-    }
 }
@@ -1,17 +1,26 @@
 use byteorder::{ByteOrder, LittleEndian};
 
+use alloc::vec::Vec;
+
 use crate::bcache::BufferBlock;
-use crate::params;
 
 // Contents of the header block, used for both the on-disk header block
 // and to keep track in memory of logged block# before commit.
 #[derive(Debug)]
 pub struct LogHeader {
     pub n: u32,
-    pub block_nums: [u32; params::LOGSIZE],
+    // One slot per data block of the on-disk log
+    pub block_nums: Vec<u32>,
 }
 
 impl LogHeader {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            n: 0,
+            block_nums: vec![0; capacity],
+        }
+    }
+
     pub fn from_buffer_block(&mut self, buffer: &BufferBlock) {
         let mut offset = 0;
         self.n = LittleEndian::read_u32(&buffer[offset..offset + 4]);
@@ -4,7 +4,7 @@ use spin::Mutex;
 use libsyscalls::sync::CondVar;
 
 use crate::bcache::BufferGuard;
-use crate::log::log::LogInternal;
+use crate::log::log::{commit, BeginOp, LogInternal};
 
 pub struct Transaction {
     log: Arc<(Mutex<LogInternal>, CondVar)>,
@@ -14,12 +14,25 @@ impl Transaction {
     pub fn new(log: Arc<(Mutex<LogInternal>, CondVar)>) -> Self {
         let (log_internal, cv) = &*log;
         loop {
-            let mut guard = log_internal.lock();
-            if guard.try_begin_op() {
-                break;
+            // Commits run after `sleep_until` returns, with the lock released
+            let mut contents = None;
+            drop(
+                cv.sleep_until(log_internal, |log| match log.try_begin_op() {
+                    BeginOp::Started => true,
+                    BeginOp::Wait => false,
+                    BeginOp::Commit(log_contents) => {
+                        contents = Some(log_contents);
+                        true
+                    }
+                }),
+            );
+            match contents {
+                Some(contents) => {
+                    commit(log_internal, contents);
+                    cv.wakeup_all();
+                }
+                None => break,
             }
-            drop(guard);
-            cv.sleep();
         }
 
         Self { log }
@@ -34,7 +47,11 @@ impl Transaction {
 impl core::ops::Drop for Transaction {
     fn drop(&mut self) {
         let (log_internal, cv) = &*self.log;
-        log_internal.lock().end_op();
-        cv.wakeup();
+        let contents = log_internal.lock().end_op();
+        if let Some(contents) = contents {
+            commit(log_internal, contents);
+        }
+        // Both new transactions and `Log::sync` may be waiting for the commit
+        cv.wakeup_all();
     }
 }
@@ -1,23 +1,19 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use spin::Once;
 
-use tls::ThreadLocal;
-use interface::vfs::{ErrorKind, Result};
+use interface::rref::RRefSharedVec;
+use interface::vfs::{DirectoryEntry, ErrorKind, Result, SeekFrom};
 
-use crate::console_device::DEVICES;
-use crate::icache::{ICache, INode};
+use crate::devices;
+use crate::icache::{ICache, INode, INodeFileType};
 use crate::log::LOG;
 use crate::params;
 use crate::pipe::Pipe;
+use crate::proc_device;
 use crate::sysfile::FileStat;
 
-pub type FDTable = [Option<Arc<OpenedFile>>; params::NFILE];
-
-lazy_static! {
-    pub static ref FD_TABLE: ThreadLocal<FDTable> =
-        ThreadLocal::new(|| array_init::array_init(|_| None));
-}
-
 // We want to avoid
 #[derive(Debug)]
 pub enum FileType {
@@ -34,6 +30,14 @@ pub enum FileType {
         // Set once then read-only
         major: AtomicUsize,
     },
+    // A file in /proc
+    Proc {
+        inode: Arc<INode>,
+        minor: i16,
+        // Generated on the first read, so opening the file (e.g. in `ls`) is cheap
+        contents: Once<Vec<u8>>,
+        offset: AtomicUsize,
+    },
     // Socket {
     //     socket: Option<Socket>,
     // },
@@ -52,7 +56,9 @@ pub struct OpenedFile {
 impl Drop for OpenedFile {
     fn drop(&mut self) {
         match &self.file_type {
-            FileType::INode { inode, .. } | FileType::Device { inode, .. } => {
+            FileType::INode { inode, .. }
+            | FileType::Device { inode, .. }
+            | FileType::Proc { inode, .. } => {
                 let mut trans = LOG.r#try().unwrap().begin_transaction();
                 ICache::put(&mut trans, inode.clone());
             }
@@ -62,6 +68,9 @@ impl Drop for OpenedFile {
     }
 }
 
+// Like the system calls in sysfile.rs, operations on inodes begin their transaction before they
+// lock the inode. The log holds back new transactions until the outstanding ones end, so waiting
+// for one while holding an inode lock deadlocks with a transaction that waits for the inode.
 impl OpenedFile {
     pub fn new(file_type: FileType, readable: bool, writable: bool) -> Self {
         Self {
@@ -71,26 +80,108 @@ impl OpenedFile {
         }
     }
 
-    pub fn seek(&self, new_offset: usize) -> Result<()> {
+    // Returns the new offset
+    pub fn seek(&self, pos: SeekFrom) -> Result<usize> {
         match &self.file_type {
             FileType::INode { inode, offset } => {
-                let _iguard = inode.lock();
-                offset.store(new_offset, Ordering::SeqCst);
-                Ok(())
+                let iguard = inode.lock();
+                seek_offset(offset, iguard.data.size as usize, pos)
+            }
+            FileType::Proc {
+                minor,
+                contents,
+                offset,
+                ..
+            } => {
+                let contents = contents.call_once(|| proc_device::contents(*minor));
+                seek_offset(offset, contents.len(), pos)
             }
             _ => Err(ErrorKind::UnsupportedOperation),
         }
     }
 
-    pub fn stat(&self) -> Result<FileStat> {
+    // Grow or shrink the file to `size` bytes. Not in xv6; equivalent of ftruncate
+    pub fn truncate(&self, size: usize) -> Result<()> {
+        if !self.writable.load(Ordering::SeqCst) {
+            return Err(ErrorKind::PermissionDenied);
+        }
+
+        match &self.file_type {
+            // A large file shrinks over several transactions, see `resize`
+            FileType::INode { inode, .. } => loop {
+                let mut trans = LOG.r#try().unwrap().begin_transaction();
+                let mut iguard = inode.lock();
+                if iguard.data.file_type != INodeFileType::File {
+                    return Err(ErrorKind::InvalidFileType);
+                }
+                if iguard.resize(&mut trans, size)? {
+                    return Ok(());
+                }
+            },
+            _ => Err(ErrorKind::UnsupportedOperation),
+        }
+    }
+
+    // Returns a read-only view of the file from `offset` to the end of its block, see
+    // `INodeDataGuard::view`. Not in xv6
+    pub fn mmap(&self, offset: usize) -> Result<RRefSharedVec<u8>> {
+        if !self.readable.load(Ordering::SeqCst) {
+            return Err(ErrorKind::PermissionDenied);
+        }
+
+        match &self.file_type {
+            FileType::INode { inode, .. } => {
+                let mut trans = LOG.r#try().unwrap().begin_transaction();
+                let mut iguard = inode.lock();
+                if iguard.data.file_type != INodeFileType::File {
+                    return Err(ErrorKind::InvalidFileType);
+                }
+                Ok(iguard.view(&mut trans, offset))
+            }
+            // Only regular files live in the buffer cache
+            _ => Err(ErrorKind::InvalidFileType),
+        }
+    }
+
+    // Returns the next used entry of a directory and moves the offset past it
+    pub fn readdir(&self) -> Result<Option<DirectoryEntry>> {
+        if !self.readable.load(Ordering::SeqCst) {
+            return Err(ErrorKind::PermissionDenied);
+        }
+
         match &self.file_type {
-            FileType::INode { inode, .. } | FileType::Device { inode, .. } => {
-                Ok(inode.lock().stat())
+            FileType::INode { inode, offset } => {
+                let mut trans = LOG.r#try().unwrap().begin_transaction();
+                let mut iguard = inode.lock();
+                if iguard.data.file_type != INodeFileType::Directory {
+                    return Err(ErrorKind::InvalidFileType);
+                }
+
+                const SIZE_OF_DIRENT: usize = core::mem::size_of::<DirectoryEntry>();
+                let mut buffer = [0u8; SIZE_OF_DIRENT];
+                while offset.load(Ordering::SeqCst) + SIZE_OF_DIRENT <= iguard.data.size as usize {
+                    iguard.read(&mut trans, &mut buffer, offset.load(Ordering::SeqCst))?;
+                    offset.fetch_add(SIZE_OF_DIRENT, Ordering::SeqCst);
+                    let dirent = DirectoryEntry::from_bytes(&buffer);
+                    if dirent.inum != 0 {
+                        return Ok(Some(dirent));
+                    }
+                }
+                Ok(None)
             }
             _ => Err(ErrorKind::InvalidFileType),
         }
     }
 
+    pub fn stat(&self) -> Result<FileStat> {
+        match &self.file_type {
+            FileType::INode { inode, .. }
+            | FileType::Device { inode, .. }
+            | FileType::Proc { inode, .. } => Ok(inode.lock().stat()),
+            _ => Err(ErrorKind::InvalidFileType),
+        }
+    }
+
     // Reads bytes from file into user_buffer.
     // Returns number of bytes read, or None if lacking read permissions or upon overflow.
     // xv6 equivalent: fileread
@@ -101,20 +192,27 @@ impl OpenedFile {
 
         match &self.file_type {
             FileType::INode { inode, offset } => {
-                let mut iguard = inode.lock();
                 let mut trans = LOG.r#try().unwrap().begin_transaction();
+                let mut iguard = inode.lock();
                 let bytes = iguard.read(&mut trans, user_buffer, offset.load(Ordering::SeqCst))?;
                 offset.fetch_add(bytes, Ordering::SeqCst);
                 Ok(bytes)
             }
             FileType::Device { inode: _, major } => {
-                DEVICES
-                    .get(major.load(Ordering::SeqCst))
-                    .ok_or(ErrorKind::InvalidMajor)?
-                    .as_ref()
-                    .ok_or(ErrorKind::InvalidMajor)?
-                    .read(user_buffer);
-                Ok(user_buffer.len())
+                Ok(devices::get(major.load(Ordering::SeqCst))?.read(user_buffer))
+            }
+            FileType::Proc {
+                minor,
+                contents,
+                offset,
+                ..
+            } => {
+                let contents = contents.call_once(|| proc_device::contents(*minor));
+                let start = core::cmp::min(offset.load(Ordering::SeqCst), contents.len());
+                let bytes = core::cmp::min(user_buffer.len(), contents.len() - start);
+                user_buffer[..bytes].copy_from_slice(&contents[start..start + bytes]);
+                offset.fetch_add(bytes, Ordering::SeqCst);
+                Ok(bytes)
             }
             FileType::Pipe { pipe } => pipe.read(user_buffer),
         }
@@ -130,7 +228,9 @@ impl OpenedFile {
 
         match &self.file_type {
             FileType::INode { inode, offset } => {
-                let max = (params::MAXOPBLOCKS - 1 - 1 - 2 / 2) * params::BSIZE;
+                // Each transaction may log the inode, the level 1 table, two level 2 tables,
+                // two bitmap blocks and one extra data block for an unaligned write
+                let max = (params::MAXOPBLOCKS - 1 - 1 - 2 - 2 - 1) * params::BSIZE;
                 let mut i = 0;
                 while i < user_buffer.len() {
                     let bytes_to_write = core::cmp::min(user_buffer.len() - i, max);
@@ -151,15 +251,25 @@ impl OpenedFile {
                 Ok(i)
             }
             FileType::Device { inode: _, major } => {
-                DEVICES
-                    .get(major.load(Ordering::SeqCst))
-                    .ok_or(ErrorKind::InvalidMajor)?
-                    .as_ref()
-                    .ok_or(ErrorKind::InvalidMajor)?
-                    .write(user_buffer);
-                Ok(user_buffer.len())
+                Ok(devices::get(major.load(Ordering::SeqCst))?.write(user_buffer))
             }
+            FileType::Proc { .. } => Err(ErrorKind::PermissionDenied),
             FileType::Pipe { pipe } => pipe.write(user_buffer),
         }
     }
 }
+
+// Moves `offset` of a file of `size` bytes to `pos` and returns the new offset
+fn seek_offset(offset: &AtomicUsize, size: usize, pos: SeekFrom) -> Result<usize> {
+    let (base, delta) = match pos {
+        SeekFrom::Start(start) => (0, start as i64),
+        SeekFrom::End(delta) => (size as i64, delta),
+        SeekFrom::Current(delta) => (offset.load(Ordering::SeqCst) as i64, delta),
+    };
+    let new_offset = base
+        .checked_add(delta)
+        .filter(|new_offset| *new_offset >= 0)
+        .ok_or(ErrorKind::InvalidParameter)? as usize;
+    offset.store(new_offset, Ordering::SeqCst);
+    Ok(new_offset)
+}
@@ -4,28 +4,62 @@ use core::mem;
 // https://github.com/mit-pdos/xv6-public/blob/master/param.h
 pub use interface::bdev::BSIZE;
 pub use interface::vfs::NFILE; // open files per system // block size
+pub use interface::vfs::MAXPATH; // max length of a path
 
 pub const NOFILE: usize = 16; // open files per process
 pub const NINODE: usize = 50; // maximum number of active i-nodes
 pub const NDEV: i16 = 10; // maximum major device number
+pub const CONSOLEMAJOR: i16 = 1; // major device number of the console
+pub const PROCMAJOR: i16 = 2; // major device number of the files in /proc
+pub const NULLMAJOR: i16 = 3; // major device number of /dev/null
+pub const ZEROMAJOR: i16 = 4; // major device number of /dev/zero
+pub const RANDOMMAJOR: i16 = 5; // major device number of /dev/random
+pub const RANDOM_CHUNK: usize = 32; // max # of bytes /dev/random asks the TPM for at once
 pub const ROOTDEV: u32 = 1; // device number of file system root disk
-pub const MAXOPBLOCKS: usize = 10; // max # of blocks any FS op writes
-pub const LOGSIZE: usize = MAXOPBLOCKS * 3; // max data blocks in on-disk log
-pub const NBUF: usize = MAXOPBLOCKS * 3; // size of disk block cache
+pub const MAXOPBLOCKS: usize = 32; // max # of blocks any FS op writes
+// max # of data blocks a transaction of a truncate frees. Each may dirty its own bitmap block,
+// next to the inode, the indirect tables and the bitmap blocks of the tables it frees.
+pub const TRUNCATE_BATCH: usize = MAXOPBLOCKS - 8;
+// The header block holds the count followed by one block number per logged block
+pub const MAXLOGSIZE: usize = BSIZE / mem::size_of::<u32>() - 1; // max data blocks in on-disk log
+pub const LOG_FLUSH_INTERVAL: u64 = 100_000_000; // ns between background commits of the log
+pub const READ_BATCH: usize = 8; // max # of blocks an inode read fetches with one request
+pub const READAHEAD: usize = 32; // max # of blocks a sequential read fetches ahead of time
+pub const BCACHE_SHARDS: usize = 4; // independently locked parts of the disk block cache
+pub const BCACHE_BLOCKS: usize = 1024; // blocks cached for reads, next to the ones the log pins
 pub const SECTOR_SIZE: usize = 512;
 
 pub const BPB: usize = BSIZE * 8; // bits per block
-pub const FSSIZE: usize = 1000; // size of file system in blocks
 
 // https://github.com/mit-pdos/xv6-riscv/blob/riscv/kernel/fs.h
 pub const ROOTINO: u16 = 1; // root i-number
-pub const FSMAGIC: usize = 0x1020_3040;
-pub const NDIRECT: usize = 12;
+pub const ROOTUID: u16 = 0; // rv6 has no users yet, every inode is owned by root
+// Stored in the superblock. Bump it whenever the on-disk layout changes, so that an image made
+// for another layout is refused instead of misread. 0x1020_3041: 88-byte DINode with owner,
+// permissions and timestamps.
+pub const FSMAGIC: u32 = 0x1020_3041;
+pub use interface::vfs::NDIRECT;
 pub const NINDIRECT: usize = BSIZE / mem::size_of::<u32>();
 pub const MAXFILE: usize = NDIRECT + NINDIRECT * NINDIRECT;
+// Largest file the 32-bit size field of an inode can describe
+pub const MAXFILESIZE: usize = if MAXFILE * BSIZE < u32::MAX as usize {
+    MAXFILE * BSIZE
+} else {
+    u32::MAX as usize
+};
+pub const MAXSYMLINKS: usize = 10; // max # of symbolic links followed while resolving a path
 
 // Inodes per block.
 pub const IPB: usize = BSIZE / mem::size_of::<icache::DINode>();
+// Whatever reads images on its own relies on the layout in `interface::vfs`
+const _: () = assert!(mem::size_of::<icache::DINode>() == interface::vfs::DINODE_SIZE);
 
 // Directory is a file containing a sequence of dirent structures.
-pub const DIRSIZ: usize = 14;
+pub use interface::vfs::DIRSIZ;
+
+// Buffers in each shard of the disk block cache.
+// Logged blocks stay pinned in the cache until they are installed and may all fall into the
+// same shard, and a commit also holds all log blocks, which are spread over the shards
+pub fn shard_size(log_size: usize) -> usize {
+    log_size + (log_size + MAXOPBLOCKS + BCACHE_BLOCKS) / BCACHE_SHARDS
+}
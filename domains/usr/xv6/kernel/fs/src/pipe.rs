@@ -79,29 +79,32 @@ impl Pipe {
         let mut pipe = self.pipe.lock();
         if writable {
             pipe.writeopen = false;
-            self.can_read.wakeup();
         } else {
             pipe.readopen = false;
+        }
+        drop(pipe);
+
+        // Waking up takes the lock of the sleepers, so not while holding the pipe
+        if writable {
+            self.can_read.wakeup();
+        } else {
             self.can_write.wakeup();
         }
 
         // The arc will drop the pipe if the ref count is zero
     }
 
-    // TODO: handle myproc()->killed
+    // Fails with `Killed` if the thread is killed while waiting for the reader
     pub fn write(&self, data: &[u8]) -> Result<usize> {
         let pred = |pipe: &mut PipeInternal| -> bool {
             // Stop waiting if pipe is closed for reading or the buffer is not full
-            if !pipe.readopen || pipe.nwrite < pipe.nread + PIPESIZE {
-                return true;
-            }
-
-            // Notify the read end to read
-            self.can_read.wakeup();
-            false
+            !pipe.readopen || pipe.nwrite < pipe.nread + PIPESIZE
         };
 
-        let mut pipe = self.can_write.sleep_until(&self.pipe, pred);
+        let mut pipe = self
+            .can_write
+            .sleep_until_interruptible(&self.pipe, pred)
+            .ok_or(ErrorKind::Killed)?;
         if !pipe.readopen {
             return Err(ErrorKind::BrokenPipe);
         }
@@ -109,7 +112,12 @@ impl Pipe {
             // Wait while the buffer is full
             if pipe.nwrite == pipe.nread + PIPESIZE {
                 drop(pipe);
-                pipe = self.can_write.sleep_until(&self.pipe, pred);
+                // Notify the read end to read
+                self.can_read.wakeup();
+                pipe = self
+                    .can_write
+                    .sleep_until_interruptible(&self.pipe, pred)
+                    .ok_or(ErrorKind::Killed)?;
             }
 
             // If the readend is close, no one will wake us up
@@ -132,7 +140,7 @@ impl Pipe {
         Ok(data.len())
     }
 
-    // TODO: handle myproc()->killed
+    // Fails with `Killed` if the thread is killed while waiting for the writer
     pub fn read(&self, data: &mut [u8]) -> Result<usize> {
         let pred = |pipe: &mut PipeInternal| {
             // Stop waiting if pipe is closed for writing or the buffer is not empty
@@ -140,7 +148,10 @@ impl Pipe {
         };
 
         // Sleep until there's something to read or write end is closed
-        let mut pipe = self.can_read.sleep_until(&self.pipe, pred);
+        let mut pipe = self
+            .can_read
+            .sleep_until_interruptible(&self.pipe, pred)
+            .ok_or(ErrorKind::Killed)?;
 
         // Copy data over
         let mut bytes_read = 0;
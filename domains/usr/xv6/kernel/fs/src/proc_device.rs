@@ -0,0 +1,202 @@
+// /proc: read-only files with live kernel and driver state, so that `cat` and `ls` can be
+// used for debugging. Like the console, the files are device inodes (major `PROCMAJOR`, the
+// minor picks the file), but their contents are generated on the first read of each open file.
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use spin::{Mutex, Once};
+
+use interface::bdev::NvmeBDev;
+use interface::net::Net;
+use interface::vfs::{ErrorKind, Permissions, Result};
+use libsyscalls::syscalls::{sys_domains, sys_memory, sys_threads};
+use syscalls::{DomainInfo, ThreadInfo};
+
+use crate::icache::{ICache, INodeFileType};
+use crate::log::LOG;
+use crate::params;
+
+struct Devices {
+    net: Option<Box<dyn Net>>,
+    nvme: Option<Mutex<Box<dyn NvmeBDev>>>,
+}
+
+static DEVICES: Once<Devices> = Once::new();
+
+// The files in /proc, the minor number of a file is its index
+const FILES: [(&str, fn() -> String); 5] = [
+    ("domains", domains),
+    ("threads", threads),
+    ("meminfo", meminfo),
+    ("net", net),
+    ("nvme", nvme),
+];
+
+// Creates /proc and its files unless they exist. The devices are where the network and NVMe
+// statistics come from, a file system without them says so in /proc/net and /proc/nvme.
+pub fn init(net: Option<Box<dyn Net>>, nvme: Option<Box<dyn NvmeBDev>>) -> Result<()> {
+    DEVICES.call_once(|| Devices {
+        net,
+        nvme: nvme.map(Mutex::new),
+    });
+
+    let read_only = Permissions::OWNER_READ | Permissions::GROUP_READ | Permissions::OTHER_READ;
+    let exec = Permissions::OWNER_EXEC | Permissions::GROUP_EXEC | Permissions::OTHER_EXEC;
+    create("/proc", INodeFileType::Directory, 0, 0, read_only | exec)?;
+    for (minor, (name, _)) in FILES.iter().enumerate() {
+        let path = format!("/proc/{}", name);
+        create(
+            &path,
+            INodeFileType::Device,
+            params::PROCMAJOR,
+            minor as i16,
+            read_only,
+        )?;
+    }
+    Ok(())
+}
+
+fn create(
+    path: &str,
+    file_type: INodeFileType,
+    major: i16,
+    minor: i16,
+    permissions: Permissions,
+) -> Result<()> {
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    match ICache::namei(&mut trans, path) {
+        Ok(inode) => ICache::put(&mut trans, inode),
+        Err(ErrorKind::FileNotFound) => {
+            let inode = ICache::create(&mut trans, path, file_type, major, minor)?;
+            let mut iguard = inode.lock();
+            iguard.data.permissions = permissions;
+            iguard.update(&mut trans);
+            drop(iguard);
+            ICache::put(&mut trans, inode);
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+pub fn is_file(minor: i16) -> bool {
+    minor >= 0 && (minor as usize) < FILES.len()
+}
+
+pub fn contents(minor: i16) -> Vec<u8> {
+    (FILES[minor as usize].1)().into_bytes()
+}
+
+// Calls `sys_domains` or `sys_threads` with a slice that fits all entries
+fn snapshot<T: Copy + Default>(get: fn(&mut [T]) -> usize) -> Vec<T> {
+    let mut entries = vec![T::default(); 32];
+    loop {
+        let count = get(&mut entries);
+        if count <= entries.len() {
+            entries.truncate(count);
+            return entries;
+        }
+        entries.resize(count, T::default());
+    }
+}
+
+fn domains() -> String {
+    let mut s = String::new();
+    writeln!(
+        s,
+        "{:>4} {:<20} {:>7} {:>11} {:>8}",
+        "ID", "NAME", "THREADS", "SHARED_HEAP", "LOADS"
+    )
+    .unwrap();
+    for d in snapshot::<DomainInfo>(sys_domains) {
+        writeln!(
+            s,
+            "{:>4} {:<20} {:>7} {:>11} {:>8}",
+            d.id,
+            d.name.as_str(),
+            d.threads,
+            d.shared_heap,
+            d.loads
+        )
+        .unwrap();
+    }
+    s
+}
+
+fn threads() -> String {
+    let domains = snapshot::<DomainInfo>(sys_domains);
+    let domain_name = |id: u64| {
+        domains
+            .iter()
+            .find(|d| d.id == id)
+            .map_or_else(|| format!("{}", id), |d| String::from(d.name.as_str()))
+    };
+
+    let mut s = String::new();
+    writeln!(
+        s,
+        "{:>4} {:<10} {:>3} {:<16} {:<16} {}",
+        "ID", "STATE", "CPU", "DOMAIN", "IN_DOMAIN", "NAME"
+    )
+    .unwrap();
+    for t in snapshot::<ThreadInfo>(sys_threads) {
+        writeln!(
+            s,
+            "{:>4} {:<10} {:>3} {:<16} {:<16} {}",
+            t.id,
+            t.state,
+            t.cpu,
+            domain_name(t.domain_id),
+            domain_name(t.current_domain_id),
+            t.name.as_str()
+        )
+        .unwrap();
+    }
+    s
+}
+
+fn meminfo() -> String {
+    let memory = sys_memory();
+    format!(
+        "total: {} kB\nfree: {} kB\nshared_heap: {} kB\nshared_heap_allocations: {}\n",
+        memory.total / 1024,
+        memory.free / 1024,
+        memory.shared_heap / 1024,
+        memory.shared_heap_allocations
+    )
+}
+
+fn net() -> String {
+    let net = match DEVICES.r#try().and_then(|devices| devices.net.as_ref()) {
+        Some(net) => net,
+        None => return String::from("no network device\n"),
+    };
+    match net.peek_stats() {
+        Ok(Ok(stats)) => format!(
+            "tx_count: {}\ntx_dma_ok: {}\nrx_count: {}\nrx_dma_ok: {}\nrx_missed: {}\nrx_crc_err: {}\n",
+            stats.tx_count,
+            stats.tx_dma_ok,
+            stats.rx_count,
+            stats.rx_dma_ok,
+            stats.rx_missed,
+            stats.rx_crc_err
+        ),
+        Ok(Err(e)) => format!("error: {:?}\n", e),
+        Err(e) => format!("error: {:?}\n", e),
+    }
+}
+
+fn nvme() -> String {
+    let nvme = match DEVICES.r#try().and_then(|devices| devices.nvme.as_ref()) {
+        Some(nvme) => nvme,
+        None => return String::from("no NVMe device\n"),
+    };
+    match nvme.lock().peek_stats() {
+        Ok(Ok((submitted, completed))) => {
+            format!("submitted: {}\ncompleted: {}\n", submitted, completed)
+        }
+        Ok(Err(e)) => format!("error: {:?}\n", e),
+        Err(e) => format!("error: {:?}\n", e),
+    }
+}
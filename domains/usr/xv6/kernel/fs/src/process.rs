@@ -0,0 +1,188 @@
+// The file descriptor table of an rv6 process, i.e. of a user domain and the threads it spawns.
+// Every thread calling into the file system belongs to a process. The rv6 kernel moves a new
+// thread into its process with `enter`, and the files of a process are closed once its last
+// thread exited.
+use alloc::sync::Arc;
+use spin::Mutex;
+
+use interface::vfs::{ErrorKind, Result, NFILE};
+use tls::ThreadLocal;
+
+use crate::cross_thread_temp_store::CrossThreadTempStorage;
+use crate::cwd::CWD;
+use crate::icache::{ICache, INode};
+use crate::log::LOG;
+use crate::opened_file::OpenedFile;
+
+struct Fd {
+    file: Arc<OpenedFile>,
+    // Closed instead of inherited in the processes spawned by this one
+    close_on_exec: bool,
+}
+
+type FdTable = [Option<Fd>; NFILE];
+
+pub struct Process {
+    files: Mutex<FdTable>,
+}
+
+lazy_static! {
+    // A thread that never entered a process, like the first thread of rv6, has one of its own
+    static ref PROCESS: ThreadLocal<Arc<Process>> =
+        ThreadLocal::new(|| Arc::new(Process::new(array_init::array_init(|_| None))));
+    // Processes and the working directory their thread starts in, until the thread enters them
+    static ref PENDING: CrossThreadTempStorage<(Arc<Process>, Arc<INode>)> =
+        CrossThreadTempStorage::new();
+}
+
+// The process of the calling thread
+pub fn current() -> Arc<Process> {
+    PROCESS.with(|process| process.clone())
+}
+
+// Creates the process of a domain spawned by the calling thread, see `Process::spawn`.
+// Returns the id to `enter` it with.
+pub fn spawn(fds: &[Option<usize>; NFILE]) -> Result<usize> {
+    let child = current().spawn(fds)?;
+    let cwd = CWD.with(|cwd| cwd.clone());
+    Ok(PENDING.put((Arc::new(child), cwd)))
+}
+
+// Returns the id to `enter` the process of the calling thread with, from another thread
+pub fn share() -> usize {
+    let cwd = CWD.with(|cwd| cwd.clone());
+    PENDING.put((current(), cwd))
+}
+
+// Moves the calling thread into a process created by `spawn` or `share`
+pub fn enter(id: usize) -> Result<()> {
+    let (process, cwd) = PENDING.get(id).ok_or(ErrorKind::InvalidCTTSId)?;
+    let old_process = PROCESS.with(|current| core::mem::replace(current, process));
+    // Closing the files of the old process may write to the disk, so not inside `with`
+    drop(old_process);
+    let old_cwd = CWD.with(|my_cwd| core::mem::replace(my_cwd, cwd));
+    ICache::put(&mut LOG.r#try().unwrap().begin_transaction(), old_cwd);
+    Ok(())
+}
+
+// The calling thread is exiting. If it was the last thread of its process, this closes the
+// files the process left open.
+pub fn exit() {
+    let process = current();
+    PROCESS.drop();
+    drop(process);
+    let cwd = CWD.with(|cwd| cwd.clone());
+    CWD.drop();
+    ICache::put(&mut LOG.r#try().unwrap().begin_transaction(), cwd);
+}
+
+impl Process {
+    fn new(files: FdTable) -> Self {
+        Self {
+            files: Mutex::new(files),
+        }
+    }
+
+    // The file behind `fd`. Operations on it don't hold the table, so e.g. a read blocked on a
+    // pipe doesn't keep other threads of the process from using their files.
+    pub fn file(&self, fd: usize) -> Result<Arc<OpenedFile>> {
+        self.files
+            .lock()
+            .get(fd)
+            .and_then(Option::as_ref)
+            .map(|fd| fd.file.clone())
+            .ok_or(ErrorKind::InvalidFileDescriptor)
+    }
+
+    // Allocates the lowest free file descriptor for `file`
+    // xv6 equivalent: fdalloc
+    pub fn alloc(&self, file: Arc<OpenedFile>, close_on_exec: bool) -> Result<usize> {
+        let mut files = self.files.lock();
+        let fd = files
+            .iter()
+            .position(Option::is_none)
+            .ok_or(ErrorKind::TooManyOpenedFiles)?;
+        files[fd] = Some(Fd {
+            file,
+            close_on_exec,
+        });
+        Ok(fd)
+    }
+
+    pub fn close(&self, fd: usize) -> Result<()> {
+        let fd = self
+            .files
+            .lock()
+            .get_mut(fd)
+            .and_then(Option::take)
+            .ok_or(ErrorKind::InvalidFileDescriptor)?;
+        // Closing the last reference to a file may write to the disk, so not while holding
+        // the table
+        drop(fd);
+        Ok(())
+    }
+
+    // The new file descriptor is inherited by spawned processes, even if `fd` is not
+    pub fn dup(&self, fd: usize) -> Result<usize> {
+        let file = self.file(fd)?;
+        self.alloc(file, false)
+    }
+
+    // Makes `new_fd` refer to the file of `fd`, closing whatever `new_fd` referred to before.
+    // Like `dup`, `new_fd` is inherited by spawned processes.
+    pub fn dup2(&self, fd: usize, new_fd: usize) -> Result<usize> {
+        let mut files = self.files.lock();
+        let file = files
+            .get(fd)
+            .and_then(Option::as_ref)
+            .map(|fd| fd.file.clone())
+            .ok_or(ErrorKind::InvalidFileDescriptor)?;
+        if new_fd >= files.len() {
+            return Err(ErrorKind::InvalidFileDescriptor);
+        }
+        if new_fd == fd {
+            return Ok(new_fd);
+        }
+        let old = files[new_fd].replace(Fd {
+            file,
+            close_on_exec: false,
+        });
+        drop(files);
+        drop(old);
+        Ok(new_fd)
+    }
+
+    pub fn set_close_on_exec(&self, fd: usize, close_on_exec: bool) -> Result<()> {
+        self.files
+            .lock()
+            .get_mut(fd)
+            .and_then(Option::as_mut)
+            .ok_or(ErrorKind::InvalidFileDescriptor)?
+            .close_on_exec = close_on_exec;
+        Ok(())
+    }
+
+    // The process of a domain spawned by this one. Its fd `i` refers to the file of our fd
+    // `fds[i]` if that is given. Otherwise it inherits our fd `i`, unless that is marked
+    // close-on-exec.
+    fn spawn(&self, fds: &[Option<usize>; NFILE]) -> Result<Self> {
+        let files = self.files.lock();
+        let mut child: FdTable = array_init::array_init(|_| None);
+        for (i, (slot, fd)) in child.iter_mut().zip(fds.iter()).enumerate() {
+            let inherited = match fd {
+                Some(fd) => Some(
+                    files
+                        .get(*fd)
+                        .and_then(Option::as_ref)
+                        .ok_or(ErrorKind::InvalidFileDescriptor)?,
+                ),
+                None => files[i].as_ref().filter(|fd| !fd.close_on_exec),
+            };
+            *slot = inherited.map(|fd| Fd {
+                file: fd.file.clone(),
+                close_on_exec: false,
+            });
+        }
+        Ok(Self::new(child))
+    }
+}
@@ -0,0 +1,207 @@
+// The file system's `VFS` implementation. Kept apart from the domain's entry point, so that
+// `tools/proxy-fuzz` can build it for the host.
+use alloc::boxed::Box;
+use console::println;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use interface::bdev::{BDev, NvmeBDev};
+use interface::net::Net;
+use interface::rpc::RpcResult;
+use interface::rref::{RRefSharedVec, RRefVec};
+use interface::tpm::UsrTpm;
+use interface::vfs::{Result, NFILE, VFS};
+
+use crate::sysfile::{self, DirectoryEntry, FileMode, FileStat, FsStats, Permissions, SeekFrom};
+use crate::{devices, fs, log, params, proc_device};
+
+// Live handles to the file system. Once the last one is dropped, e.g. when crashfs replaces the
+// domain with a fresh instance, nothing can reach the file system and the flush thread stops.
+static HANDLES: AtomicUsize = AtomicUsize::new(0);
+
+struct Rv6FS {}
+
+impl Rv6FS {
+    fn new() -> Self {
+        HANDLES.fetch_add(1, Ordering::SeqCst);
+        Self {}
+    }
+}
+
+impl Drop for Rv6FS {
+    fn drop(&mut self) {
+        if HANDLES.fetch_sub(1, Ordering::SeqCst) == 1 {
+            log::LOG.r#try().unwrap().stop_flush_thread();
+        }
+    }
+}
+
+impl VFS for Rv6FS {
+    fn clone(&self) -> RpcResult<Box<dyn VFS>> {
+        Ok(box Self::new())
+    }
+
+    // KernelVFS part
+    fn sys_spawn_process(&self, fds: [Option<usize>; NFILE]) -> RpcResult<Result<usize>> {
+        Ok(sysfile::sys_spawn_process(fds))
+    }
+    fn sys_share_process(&self) -> RpcResult<Result<usize>> {
+        Ok(sysfile::sys_share_process())
+    }
+    fn sys_enter_process(&self, id: usize) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_enter_process(id))
+    }
+    fn sys_thread_exit(&self) -> RpcResult<()> {
+        Ok(sysfile::sys_thread_exit())
+    }
+
+    // UsrVFS part
+    fn sys_open(
+        &self,
+        path: RRefVec<u8>,
+        mode: FileMode,
+    ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        Ok((|| {
+            let fd = sysfile::sys_open(core::str::from_utf8(path.as_slice())?, mode)?;
+            Ok((fd, path))
+        })())
+    }
+    fn sys_close(&self, fd: usize) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_close(fd))
+    }
+    fn sys_read(
+        &self,
+        fd: usize,
+        mut buffer: RRefVec<u8>,
+    ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        Ok((|| {
+            let bytes_read = sysfile::sys_read(fd, buffer.as_mut_slice())?;
+            Ok((bytes_read, buffer))
+        })())
+    }
+    fn sys_write(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        Ok((|| {
+            let bytes_read = sysfile::sys_write(fd, buffer.as_slice())?;
+            Ok((bytes_read, buffer))
+        })())
+    }
+    fn sys_seek(&self, fd: usize, pos: SeekFrom) -> RpcResult<Result<usize>> {
+        Ok(sysfile::sys_seek(fd, pos))
+    }
+    fn sys_fstat(&self, fd: usize) -> RpcResult<Result<FileStat>> {
+        Ok(sysfile::sys_fstat(fd))
+    }
+    fn sys_mknod(&self, path: RRefVec<u8>, major: i16, minor: i16) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_mknod(path, major, minor)
+        })())
+    }
+    fn sys_dup(&self, fd: usize) -> RpcResult<Result<usize>> {
+        Ok(sysfile::sys_dup(fd))
+    }
+    fn sys_dup2(&self, fd: usize, new_fd: usize) -> RpcResult<Result<usize>> {
+        Ok(sysfile::sys_dup2(fd, new_fd))
+    }
+    fn sys_set_close_on_exec(&self, fd: usize, close_on_exec: bool) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_set_close_on_exec(fd, close_on_exec))
+    }
+    fn sys_pipe(&self) -> RpcResult<Result<(usize, usize)>> {
+        Ok(sysfile::sys_pipe())
+    }
+    fn sys_link(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let old_path = core::str::from_utf8(old_path.as_slice())?;
+            let new_path = core::str::from_utf8(new_path.as_slice())?;
+            sysfile::sys_link(&old_path, &new_path)
+        })())
+    }
+    fn sys_unlink(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_unlink(&path)
+        })())
+    }
+    fn sys_mkdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_mkdir(path)
+        })())
+    }
+    fn sys_rename(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let old_path = core::str::from_utf8(old_path.as_slice())?;
+            let new_path = core::str::from_utf8(new_path.as_slice())?;
+            sysfile::sys_rename(old_path, new_path)
+        })())
+    }
+    fn sys_ftruncate(&self, fd: usize, size: u64) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_ftruncate(fd, size))
+    }
+    fn sys_mmap(&self, fd: usize, offset: usize) -> RpcResult<Result<RRefSharedVec<u8>>> {
+        Ok(sysfile::sys_mmap(fd, offset))
+    }
+    fn sys_readdir(&self, fd: usize) -> RpcResult<Result<Option<DirectoryEntry>>> {
+        Ok(sysfile::sys_readdir(fd))
+    }
+    fn sys_chdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_chdir(path)
+        })())
+    }
+    fn sys_getcwd(&self, mut buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        Ok((|| {
+            let len = sysfile::sys_getcwd(buffer.as_mut_slice())?;
+            Ok((len, buffer))
+        })())
+    }
+    fn sys_symlink(&self, target: RRefVec<u8>, path: RRefVec<u8>) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let target = core::str::from_utf8(target.as_slice())?;
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_symlink(target, path)
+        })())
+    }
+    fn sys_readlink(
+        &self,
+        path: RRefVec<u8>,
+        mut buffer: RRefVec<u8>,
+    ) -> RpcResult<Result<(usize, RRefVec<u8>)>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            let len = sysfile::sys_readlink(path, buffer.as_mut_slice())?;
+            Ok((len, buffer))
+        })())
+    }
+    fn sys_chmod(&self, path: RRefVec<u8>, permissions: Permissions) -> RpcResult<Result<()>> {
+        Ok((|| {
+            let path = core::str::from_utf8(path.as_slice())?;
+            sysfile::sys_chmod(path, permissions)
+        })())
+    }
+    fn sys_sync(&self) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_sync())
+    }
+    fn sys_fs_stats(&self) -> RpcResult<Result<FsStats>> {
+        Ok(sysfile::sys_fs_stats())
+    }
+    fn sys_dump_inode(&self) -> RpcResult<Result<()>> {
+        Ok(sysfile::sys_dump_inode())
+    }
+}
+
+pub fn init(
+    bdev: Box<dyn BDev>,
+    net: Option<Box<dyn Net>>,
+    nvme: Option<Box<dyn NvmeBDev>>,
+    tpm: Option<Box<dyn UsrTpm>>,
+) -> Box<dyn VFS> {
+    println!("init xv6 filesystem");
+    devices::init(tpm);
+    fs::fsinit(params::ROOTDEV, bdev);
+    if let Err(e) = proc_device::init(net, nvme) {
+        println!("xv6fs: cannot create /proc. {:?}", e);
+    }
+    println!("finish init xv6 filesystem");
+    Box::new(Rv6FS::new())
+}
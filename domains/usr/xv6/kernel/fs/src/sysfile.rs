@@ -3,89 +3,74 @@
 // Arguments are checked
 //
 
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::sync::atomic::AtomicUsize;
+use spin::Once;
 
-pub use interface::vfs::{DirectoryEntry, ErrorKind, FileMode, FileStat, Result, NFILE};
+use interface::rref::RRefSharedVec;
 
-use crate::cross_thread_temp_store::CrossThreadTempStorage;
+pub use interface::vfs::{
+    DirectoryEntry, ErrorKind, FileMode, FileStat, FsStats, Permissions, Result, SeekFrom, NFILE,
+};
+
+use crate::bcache::BCACHE;
+use crate::cwd::CWD;
 use crate::icache::{ICache, INode, INodeFileType, ICACHE};
-use crate::log::LOG;
-use crate::opened_file::{FDTable, FileType, OpenedFile, FD_TABLE};
+use crate::log::{Transaction, LOG};
+use crate::opened_file::{FileType, OpenedFile};
 use crate::params;
 use crate::pipe::Pipe;
+use crate::proc_device;
+use crate::process;
 
-// TODO: We need to duplicate the file object. Fix this
 pub fn sys_dup(fd: usize) -> Result<usize> {
     // console::println!("sys_dup {}", fd);
-    FD_TABLE.with(|fdtable| {
-        let f = fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .as_mut()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?;
-        let f1 = f.clone();
-        Ok(_fdalloc(fdtable, f1)?)
-    })
+    process::current().dup(fd)
+}
+
+pub fn sys_dup2(fd: usize, new_fd: usize) -> Result<usize> {
+    process::current().dup2(fd, new_fd)
+}
+
+pub fn sys_set_close_on_exec(fd: usize, close_on_exec: bool) -> Result<()> {
+    process::current().set_close_on_exec(fd, close_on_exec)
 }
 
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> Result<usize> {
     // console::println!("sys_read {} {}", fd, buffer.len());
-    FD_TABLE.with(|fdtable| {
-        fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .as_mut()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .read(buffer)
-    })
+    process::current().file(fd)?.read(buffer)
 }
 
 pub fn sys_write(fd: usize, buffer: &[u8]) -> Result<usize> {
     // console::println!("sys_write {} {}", fd, buffer.len());
-    FD_TABLE.with(|fdtable| {
-        fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .as_mut()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .write(buffer)
-    })
+    process::current().file(fd)?.write(buffer)
 }
 
 pub fn sys_close(fd: usize) -> Result<()> {
-    FD_TABLE.with(|fdtable| {
-        let f = fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .take()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?;
-        drop(f);
-        Ok(())
-    })
-}
-
-pub fn sys_seek(fd: usize, offset: usize) -> Result<()> {
-    FD_TABLE.with(|fdtable| {
-        fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .as_mut()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .seek(offset)
-    })
+    process::current().close(fd)
+}
+
+pub fn sys_seek(fd: usize, pos: SeekFrom) -> Result<usize> {
+    process::current().file(fd)?.seek(pos)
+}
+
+pub fn sys_ftruncate(fd: usize, size: u64) -> Result<()> {
+    process::current().file(fd)?.truncate(size as usize)
+}
+
+pub fn sys_mmap(fd: usize, offset: usize) -> Result<RRefSharedVec<u8>> {
+    process::current().file(fd)?.mmap(offset)
+}
+
+pub fn sys_readdir(fd: usize) -> Result<Option<DirectoryEntry>> {
+    process::current().file(fd)?.readdir()
 }
 
 pub fn sys_fstat(fd: usize) -> Result<FileStat> {
     // console::println!("sys_fstat {}", fd);
-    FD_TABLE.with(|fdtable| {
-        fdtable
-            .get_mut(fd)
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .as_mut()
-            .ok_or(ErrorKind::InvalidFileDescriptor)?
-            .stat()
-    })
+    process::current().file(fd)?.stat()
 }
 
 // TODO(tianjiao): this can be cleaned up a bit
@@ -175,6 +160,151 @@ pub fn sys_unlink(path: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn sys_rename(old_path: &str, new_path: &str) -> Result<()> {
+    // console::println!("sys_rename {} {}", old_path, new_path);
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let (old_parent, old_name) = ICache::nameiparent(&mut trans, old_path)?;
+    if old_name == "." || old_name == ".." {
+        ICache::put(&mut trans, old_parent);
+        return Err(ErrorKind::InvalidParameter);
+    }
+
+    let inode = old_parent.lock().dirlookup(&mut trans, old_name);
+    let inode = match inode {
+        Ok((_, inode)) => inode,
+        Err(e) => {
+            ICache::put(&mut trans, old_parent);
+            return Err(e);
+        }
+    };
+
+    let new_parent = ICache::nameiparent(&mut trans, new_path);
+    let (new_parent, new_name) = match new_parent {
+        Ok(new_parent) => new_parent,
+        Err(e) => {
+            ICache::put(&mut trans, inode);
+            ICache::put(&mut trans, old_parent);
+            return Err(e);
+        }
+    };
+
+    let result = rename(&mut trans, &old_parent, old_name, &inode, &new_parent, new_name);
+
+    ICache::put(&mut trans, new_parent);
+    ICache::put(&mut trans, inode);
+    ICache::put(&mut trans, old_parent);
+    result
+}
+
+// Moves `inode`, linked as `old_name` in `old_parent`, to `new_name` in `new_parent`.
+// An existing file at the destination is replaced; directories are not.
+fn rename(
+    trans: &mut Transaction,
+    old_parent: &Arc<INode>,
+    old_name: &str,
+    inode: &Arc<INode>,
+    new_parent: &Arc<INode>,
+    new_name: &str,
+) -> Result<()> {
+    if new_name == "." || new_name == ".." || new_name.len() > params::DIRSIZ {
+        return Err(ErrorKind::InvalidParameter);
+    }
+    if old_parent.meta.device != new_parent.meta.device {
+        return Err(ErrorKind::InvalidParameter);
+    }
+
+    let is_directory = inode.lock().data.file_type == INodeFileType::Directory;
+    let same_parent = old_parent.meta.inum == new_parent.meta.inum;
+
+    // A directory can't be moved into itself or one of its subdirectories
+    if is_directory && !same_parent && is_ancestor(trans, inode.meta.inum, new_parent)? {
+        return Err(ErrorKind::InvalidParameter);
+    }
+
+    // Both parents stay locked until the move is done. They are locked in inum order, so that
+    // renames between the same two directories in opposite directions don't deadlock, and
+    // before the inodes in them, like everywhere else.
+    let (mut old_iguard, mut new_iguard) = match old_parent.meta.inum.cmp(&new_parent.meta.inum) {
+        core::cmp::Ordering::Equal => (old_parent.lock(), None),
+        core::cmp::Ordering::Less => {
+            let old_iguard = old_parent.lock();
+            (old_iguard, Some(new_parent.lock()))
+        }
+        core::cmp::Ordering::Greater => {
+            let new_iguard = new_parent.lock();
+            (old_parent.lock(), Some(new_iguard))
+        }
+    };
+
+    {
+        let new_dir = new_iguard.as_mut().unwrap_or(&mut old_iguard);
+
+        // Drop the file that's in the way
+        if let Ok((offset, target)) = new_dir.dirlookup(trans, new_name) {
+            if target.meta.inum == inode.meta.inum {
+                ICache::put(trans, target);
+                return Ok(());
+            }
+
+            let mut tguard = target.lock();
+            if is_directory || tguard.data.file_type == INodeFileType::Directory {
+                drop(tguard);
+                ICache::put(trans, target);
+                return Err(ErrorKind::FileAlreadyExists);
+            }
+
+            let buffer = [0u8; core::mem::size_of::<DirectoryEntry>()];
+            new_dir.write(trans, &buffer, offset)?;
+
+            tguard.data.nlink -= 1;
+            tguard.update(trans);
+            drop(tguard);
+            ICache::put(trans, target);
+        }
+
+        new_dir.dirlink(trans, new_name, inode.meta.inum)?;
+    }
+
+    // Remove the old entry. Look it up again, the new link may be in the same directory.
+    let (offset, old) = old_iguard.dirlookup(trans, old_name)?;
+    ICache::put(trans, old);
+    let buffer = [0u8; core::mem::size_of::<DirectoryEntry>()];
+    old_iguard.write(trans, &buffer, offset)?;
+
+    if let Some(new_iguard) = new_iguard.as_mut().filter(|_| is_directory) {
+        // The directory's ".." moves from the old parent to the new one
+        old_iguard.data.nlink -= 1;
+        old_iguard.update(trans);
+
+        let mut iguard = inode.lock();
+        let (offset, dotdot) = iguard.dirlookup(trans, "..")?;
+        ICache::put(trans, dotdot);
+        iguard.write_dirent(trans, offset, "..", new_parent.meta.inum)?;
+        drop(iguard);
+
+        new_iguard.data.nlink += 1;
+        new_iguard.update(trans);
+    }
+
+    Ok(())
+}
+
+// Whether the directory `inum` is `dir` or one of its parents
+fn is_ancestor(trans: &mut Transaction, inum: u16, dir: &Arc<INode>) -> Result<bool> {
+    let mut current = dir.clone();
+    loop {
+        if current.meta.inum == inum || current.meta.inum == params::ROOTINO {
+            let found = current.meta.inum == inum;
+            ICache::put(trans, current);
+            return Ok(found);
+        }
+
+        let parent = current.lock().dirlookup(trans, "..");
+        ICache::put(trans, current);
+        current = parent?.1;
+    }
+}
+
 pub fn sys_mknod(path: &str, major: i16, minor: i16) -> Result<()> {
     // console::println!("sys_mknod {} {}", major, minor);
     let mut trans = LOG.r#try().unwrap().begin_transaction();
@@ -186,10 +316,13 @@ pub fn sys_mknod(path: &str, major: i16, minor: i16) -> Result<()> {
 pub fn sys_open(path: &str, mode: FileMode) -> Result<usize> {
     // console::println!("sys_open {} {:?}", path, mode);
     let mut trans = LOG.r#try().unwrap().begin_transaction();
-    let inode: Arc<INode> = match mode.contains(FileMode::CREATE) {
-        true => ICache::create(&mut trans, path, INodeFileType::File, 0, 0),
-        false => {
-            let inode = ICache::namei(&mut trans, path)?;
+    // Opening an existing file follows symbolic links, even with CREATE
+    let inode: Arc<INode> = match ICache::namei(&mut trans, path) {
+        Err(ErrorKind::FileNotFound) if mode.contains(FileMode::CREATE) => {
+            ICache::create(&mut trans, path, INodeFileType::File, 0, 0)
+        }
+        Err(e) => Err(e),
+        Ok(inode) => {
             let is_directory = inode.lock().data.file_type == INodeFileType::Directory;
             if is_directory && (mode != FileMode::READ) {
                 ICache::put(&mut trans, inode);
@@ -210,7 +343,35 @@ pub fn sys_open(path: &str, mode: FileMode) -> Result<usize> {
         return Err(ErrorKind::InvalidMajor);
     }
 
+    // rv6 has no users yet, so every thread is checked against the owner's permissions
+    let permissions = iguard.data.permissions;
+    if (mode.contains(FileMode::READ) && !permissions.contains(Permissions::OWNER_READ))
+        || (mode.contains(FileMode::WRITE) && !permissions.contains(Permissions::OWNER_WRITE))
+    {
+        drop(iguard);
+        ICache::put(&mut trans, inode);
+        return Err(ErrorKind::PermissionDenied);
+    }
+
     let file = match iguard.data.file_type {
+        INodeFileType::Device if iguard.data.major == params::PROCMAJOR => {
+            if !proc_device::is_file(iguard.data.minor) {
+                drop(iguard);
+                ICache::put(&mut trans, inode);
+                return Err(ErrorKind::InvalidMajor);
+            }
+            // Even if someone chmods them, /proc files are never writable
+            OpenedFile::new(
+                FileType::Proc {
+                    inode: inode.clone(),
+                    minor: iguard.data.minor,
+                    contents: Once::new(),
+                    offset: AtomicUsize::new(0),
+                },
+                mode.contains(FileMode::READ),
+                false,
+            )
+        }
         INodeFileType::Device => OpenedFile::new(
             FileType::Device {
                 inode: inode.clone(),
@@ -229,57 +390,24 @@ pub fn sys_open(path: &str, mode: FileMode) -> Result<usize> {
         ),
     };
 
-    let fd = fdalloc(Arc::new(file));
+    let fd = process::current().alloc(Arc::new(file), mode.contains(FileMode::CLOSE_ON_EXEC));
 
     drop(iguard);
 
     fd
 }
 
-// Allocate a file descriptor for the given file.
-// Takes over file reference from caller on success.
-fn _fdalloc(fd_table: &mut FDTable, f: Arc<OpenedFile>) -> Result<usize> {
-    let fd = fd_table
-        .iter()
-        .position(|f| f.is_none())
-        .ok_or(ErrorKind::TooManyOpenedFiles)?;
-    fd_table[fd].replace(f);
-    Ok(fd)
-}
-
-fn fdalloc(f: Arc<OpenedFile>) -> Result<usize> {
-    FD_TABLE.with(|fd_table| _fdalloc(fd_table, f))
-}
-
 pub fn sys_pipe() -> Result<(usize, usize)> {
     let (rf, wf) = Pipe::pipealloc();
-
-    // We need a custom fdalloc here because we want rf back if fdalloc failed
-    let fd0 = FD_TABLE.with(|fdtable| {
-        let fd = match fdtable.iter().position(|f| f.is_none()) {
-            Some(fd) => fd,
-            None => return Err(ErrorKind::TooManyOpenedFiles),
-        };
-        fdtable[fd].replace(rf);
-        Ok(fd)
-    });
-
-    let fd0 = match fd0 {
-        Ok(fd) => fd,
-        Err(e) => return Err(e),
-    };
-
-    let fd1 = match fdalloc(wf) {
-        Ok(fd) => fd,
+    let process = process::current();
+    let fd0 = process.alloc(rf, false)?;
+    match process.alloc(wf, false) {
+        Ok(fd1) => Ok((fd0, fd1)),
         Err(e) => {
-            FD_TABLE.with(|fdtable| {
-                fdtable[fd0].take().unwrap();
-            });
-            return Err(e);
+            process.close(fd0).unwrap();
+            Err(e)
         }
-    };
-
-    Ok((fd0, fd1))
+    }
 }
 
 pub fn sys_mkdir(path: &str) -> Result<()> {
@@ -290,6 +418,119 @@ pub fn sys_mkdir(path: &str) -> Result<()> {
     Ok(())
 }
 
+// Create a symbolic link at `path` pointing to `target`.
+// The target is stored as is and does not need to exist.
+pub fn sys_symlink(target: &str, path: &str) -> Result<()> {
+    if target.is_empty() || target.len() > params::MAXPATH {
+        return Err(ErrorKind::InvalidParameter);
+    }
+
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let inode = ICache::create(&mut trans, path, INodeFileType::Symlink, 0, 0)?;
+    let mut iguard = inode.lock();
+    let result = iguard.write(&mut trans, target.as_bytes(), 0);
+    drop(iguard);
+    ICache::put(&mut trans, inode);
+    result.map(|_| ())
+}
+
+// Writes the target of the symbolic link at `path` into `buffer` and returns its length
+pub fn sys_readlink(path: &str, buffer: &mut [u8]) -> Result<usize> {
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let inode = ICache::namei_nofollow(&mut trans, path)?;
+    let target = inode.lock().readlink(&mut trans);
+    ICache::put(&mut trans, inode);
+
+    let target = target?;
+    let target = target.as_bytes();
+    if target.len() > buffer.len() {
+        return Err(ErrorKind::InvalidParameter);
+    }
+    buffer[..target.len()].copy_from_slice(target);
+    Ok(target.len())
+}
+
+pub fn sys_chmod(path: &str, permissions: Permissions) -> Result<()> {
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let inode = ICache::namei(&mut trans, path)?;
+    let mut iguard = inode.lock();
+    iguard.data.permissions = permissions;
+    iguard.update(&mut trans);
+    drop(iguard);
+    ICache::put(&mut trans, inode);
+    Ok(())
+}
+
+pub fn sys_chdir(path: &str) -> Result<()> {
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let inode = ICache::namei(&mut trans, path)?;
+    let is_directory = inode.lock().data.file_type == INodeFileType::Directory;
+    if !is_directory {
+        ICache::put(&mut trans, inode);
+        return Err(ErrorKind::InvalidFileType);
+    }
+
+    let old_cwd = CWD.with(|cwd| core::mem::replace(cwd, inode));
+    ICache::put(&mut trans, old_cwd);
+    Ok(())
+}
+
+// Rebuilds the path of the working directory by walking up the ".." entries.
+// Returns the length of the path written into `buffer`.
+pub fn sys_getcwd(buffer: &mut [u8]) -> Result<usize> {
+    let mut trans = LOG.r#try().unwrap().begin_transaction();
+    let mut current = CWD.with(|cwd| cwd.clone());
+    let mut components: Vec<String> = Vec::new();
+
+    while current.meta.inum != params::ROOTINO {
+        let parent = current.lock().dirlookup(&mut trans, "..");
+        let parent = match parent {
+            Ok((_, parent)) => parent,
+            Err(e) => {
+                ICache::put(&mut trans, current);
+                return Err(e);
+            }
+        };
+
+        let name = parent.lock().dirname(&mut trans, current.meta.inum);
+        ICache::put(&mut trans, current);
+        current = parent;
+        match name {
+            Ok(name) => components.push(name),
+            Err(e) => {
+                ICache::put(&mut trans, current);
+                return Err(e);
+            }
+        }
+    }
+    ICache::put(&mut trans, current);
+
+    let mut path = String::new();
+    for component in components.iter().rev() {
+        path.push('/');
+        path.push_str(component);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+
+    let path = path.as_bytes();
+    if path.len() > buffer.len() {
+        return Err(ErrorKind::InvalidParameter);
+    }
+    buffer[..path.len()].copy_from_slice(path);
+    Ok(path.len())
+}
+
+pub fn sys_sync() -> Result<()> {
+    LOG.r#try().unwrap().sync();
+    Ok(())
+}
+
+pub fn sys_fs_stats() -> Result<FsStats> {
+    Ok(BCACHE.r#try().unwrap().stats())
+}
+
 pub fn sys_dump_inode() -> Result<()> {
     let inode = ICACHE.lock().get(params::ROOTDEV, params::ROOTINO).unwrap();
     inode
@@ -301,48 +542,18 @@ pub fn sys_dump_inode() -> Result<()> {
 //------------------------------------
 // fork related stuff
 //------------------------------------
-lazy_static! {
-    static ref CROSS_THREAD_TEMP_STORE: CrossThreadTempStorage<FDTable> =
-        CrossThreadTempStorage::new();
-}
-
-// TODO: save CWD
-pub fn sys_save_threadlocal(fds: [Option<usize>; NFILE]) -> Result<usize> {
-    FD_TABLE.with(|fdtable| {
-        let mut new_fdtable: FDTable = array_init::array_init(|_| None);
-        for (fd, ofile) in fds.iter().zip(new_fdtable.iter_mut()) {
-            match fd {
-                None => continue,
-                Some(fd) => core::mem::swap(
-                    ofile,
-                    &mut fdtable
-                        .get(*fd)
-                        .ok_or(ErrorKind::InvalidFileDescriptor)?
-                        .clone(),
-                ),
-            }
-        }
-        Ok(CROSS_THREAD_TEMP_STORE.put(new_fdtable))
-    })
-}
-
-// TODO: set CWD
-pub fn sys_set_threadlocal(id: usize) -> Result<()> {
-    let mut fdtable = CROSS_THREAD_TEMP_STORE
-        .get(id)
-        .ok_or(ErrorKind::InvalidCTTSId)?;
-    FD_TABLE.with(|my_fdtable| {
-        my_fdtable
-            .iter_mut()
-            .zip(fdtable.iter_mut())
-            .for_each(|(my_fd, fd)| {
-                assert!(core::mem::replace(my_fd, fd.take()).is_none());
-            });
-        Ok(())
-    })
-}
-
-// TODO: take care of CWD
+pub fn sys_spawn_process(fds: [Option<usize>; NFILE]) -> Result<usize> {
+    process::spawn(&fds)
+}
+
+pub fn sys_share_process() -> Result<usize> {
+    Ok(process::share())
+}
+
+pub fn sys_enter_process(id: usize) -> Result<()> {
+    process::enter(id)
+}
+
 pub fn sys_thread_exit() {
-    FD_TABLE.drop()
+    process::exit();
 }
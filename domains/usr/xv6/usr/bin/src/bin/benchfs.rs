@@ -10,13 +10,15 @@ pub fn trusted_entry(
     s: Box<dyn syscalls::Syscall + Send + Sync>,
     heap: Box<dyn syscalls::Heap + Send + Sync>,
     rv6: Box<dyn interface::rv6::Rv6>,
-    args: &str,
-) {
+    argv: &[&str],
+    envp: &[&str],
+) -> i32 {
     libsyscalls::syscalls::init(s);
     interface::rref::init(heap, libsyscalls::syscalls::sys_get_current_domain_id());
     usrlib::init(rv6.clone_rv6().unwrap());
+    usrlib::env::init(argv, envp);
 
-    usrlib::benchfs::main(rv6, args)
+    usrlib::benchfs::main(rv6, argv)
 }
 
 // This function is called on panic.
@@ -24,5 +26,6 @@ pub fn trusted_entry(
 fn panic(info: &PanicInfo) -> ! {
     println!("benchfs panic: {:?}", info);
     libsyscalls::syscalls::sys_backtrace();
-    loop {}
+    // Unwind back to the rv6 thread, which exits with EXIT_PANIC
+    usrlib::exit::unwind()
 }
@@ -0,0 +1,31 @@
+#![no_std]
+#![no_main]
+extern crate alloc;
+use alloc::boxed::Box;
+use core::panic::PanicInfo;
+use usrlib::println;
+
+#[no_mangle]
+pub fn trusted_entry(
+    s: Box<dyn syscalls::Syscall + Send + Sync>,
+    heap: Box<dyn syscalls::Heap + Send + Sync>,
+    rv6: Box<dyn interface::rv6::Rv6>,
+    argv: &[&str],
+    envp: &[&str],
+) -> i32 {
+    libsyscalls::syscalls::init(s);
+    interface::rref::init(heap, libsyscalls::syscalls::sys_get_current_domain_id());
+    usrlib::init(rv6.clone_rv6().unwrap());
+    usrlib::env::init(argv, envp);
+
+    usrlib::grep::main(argv)
+}
+
+// This function is called on panic.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    println!("grep panic: {:?}", info);
+    libsyscalls::syscalls::sys_backtrace();
+    // Unwind back to the rv6 thread, which exits with EXIT_PANIC
+    usrlib::exit::unwind()
+}
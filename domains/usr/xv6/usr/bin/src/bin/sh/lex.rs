@@ -0,0 +1,258 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::shell::Shell;
+
+#[derive(Debug)]
+pub enum Part {
+    Literal(String),
+    // `$NAME`, `${NAME}` or `$?`
+    Var(String),
+}
+
+/// A word of the command line, with its quotes removed but its variables not yet expanded.
+/// Variables are expanded when the command runs, so that `$?` sees the command before it.
+#[derive(Debug)]
+pub struct Word(Vec<Part>);
+
+impl Word {
+    pub fn expand(&self, shell: &Shell) -> String {
+        let mut s = String::new();
+        for part in self.0.iter() {
+            match part {
+                Part::Literal(literal) => s.push_str(literal),
+                Part::Var(name) => s.push_str(&shell.var(name)),
+            }
+        }
+        s
+    }
+
+    fn push(&mut self, c: char) {
+        match self.0.last_mut() {
+            Some(Part::Literal(literal)) => literal.push(c),
+            _ => {
+                let mut literal = String::new();
+                literal.push(c);
+                self.0.push(Part::Literal(literal));
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Pipe,
+    And,
+    Or,
+    Background,
+    Seq,
+    // `[n]< file`, `[n]> file`, `[n]<&m` and `[n]>&m`, with the fd `n` they redirect
+    RedirIn(usize),
+    RedirOut(usize),
+    DupIn(usize),
+    DupOut(usize),
+}
+
+impl core::fmt::Display for Op {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Op::Pipe => f.write_str("|"),
+            Op::And => f.write_str("&&"),
+            Op::Or => f.write_str("||"),
+            Op::Background => f.write_str("&"),
+            Op::Seq => f.write_str(";"),
+            Op::RedirIn(fd) => write!(f, "{}<", fd),
+            Op::RedirOut(fd) => write!(f, "{}>", fd),
+            Op::DupIn(fd) => write!(f, "{}<&", fd),
+            Op::DupOut(fd) => write!(f, "{}>&", fd),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TokenKind {
+    Word(Word),
+    Op(Op),
+}
+
+#[derive(Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    // Byte range of the token in the line
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Splits a line into words and operators. Single quotes keep everything literal, double quotes
+/// still expand variables, and a backslash escapes the next character. `#` starts a comment.
+pub fn lex(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    // The word being read and where it started
+    let mut word: Option<(usize, Word)> = None;
+    let mut chars = line.char_indices().peekable();
+
+    macro_rules! end_word {
+        ($end:expr) => {
+            if let Some((start, w)) = word.take() {
+                tokens.push(Token {
+                    kind: TokenKind::Word(w),
+                    start,
+                    end: $end,
+                });
+            }
+        };
+    }
+
+    while let Some((i, c)) = chars.next() {
+        let op = match c {
+            '|' | '&' | ';' | '\n' => {
+                // `||` and `&&`
+                let double = (c == '|' || c == '&') && chars.peek().map(|&(_, n)| n) == Some(c);
+                if double {
+                    chars.next();
+                }
+                Some(match (c, double) {
+                    ('|', false) => Op::Pipe,
+                    ('|', true) => Op::Or,
+                    ('&', false) => Op::Background,
+                    ('&', true) => Op::And,
+                    _ => Op::Seq,
+                })
+            }
+            '<' | '>' => {
+                // Unquoted digits right before the operator name the fd, as in `2>&1`
+                let fd = match &word {
+                    Some((start, _)) => line[*start..i].parse::<usize>().ok(),
+                    None => None,
+                };
+                let start = match fd {
+                    Some(_) => word.take().map_or(i, |(start, _)| start),
+                    None => i,
+                };
+                let dup = chars.peek().map(|&(_, n)| n) == Some('&');
+                if dup {
+                    chars.next();
+                }
+                let op = match (c, dup) {
+                    ('<', false) => Op::RedirIn(fd.unwrap_or(0)),
+                    ('<', true) => Op::DupIn(fd.unwrap_or(0)),
+                    (_, false) => Op::RedirOut(fd.unwrap_or(1)),
+                    (_, true) => Op::DupOut(fd.unwrap_or(1)),
+                };
+                end_word!(i);
+                let end = chars.peek().map_or(line.len(), |&(j, _)| j);
+                tokens.push(Token {
+                    kind: TokenKind::Op(op),
+                    start,
+                    end,
+                });
+                continue;
+            }
+            _ => None,
+        };
+        if let Some(op) = op {
+            end_word!(i);
+            let end = chars.peek().map_or(line.len(), |&(j, _)| j);
+            tokens.push(Token {
+                kind: TokenKind::Op(op),
+                start: i,
+                end,
+            });
+            continue;
+        }
+
+        if c.is_whitespace() {
+            end_word!(i);
+            continue;
+        }
+        if c == '#' && word.is_none() {
+            break;
+        }
+
+        let w = &mut word.get_or_insert_with(|| (i, Word(Vec::new()))).1;
+        match c {
+            '\'' => loop {
+                match chars.next() {
+                    Some((_, '\'')) => {
+                        // Even '' is a word
+                        if w.0.is_empty() {
+                            w.0.push(Part::Literal(String::new()));
+                        }
+                        break;
+                    }
+                    Some((_, c)) => w.push(c),
+                    None => return Err(String::from("unterminated '")),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some((_, '"')) => {
+                        if w.0.is_empty() {
+                            w.0.push(Part::Literal(String::new()));
+                        }
+                        break;
+                    }
+                    Some((_, '\\')) => match chars.peek() {
+                        Some(&(_, c)) if c == '"' || c == '\\' || c == '$' => {
+                            chars.next();
+                            w.push(c);
+                        }
+                        _ => w.push('\\'),
+                    },
+                    Some((_, '$')) => lex_var(&mut chars, w)?,
+                    Some((_, c)) => w.push(c),
+                    None => return Err(String::from("unterminated \"")),
+                }
+            },
+            '\\' => match chars.next() {
+                Some((_, c)) => w.push(c),
+                None => return Err(String::from("nothing to escape after \\")),
+            },
+            '$' => lex_var(&mut chars, w)?,
+            c => w.push(c),
+        }
+    }
+    end_word!(line.len());
+
+    Ok(tokens)
+}
+
+// Reads the variable name after a `$`. A `$` that isn't followed by a name is kept as is.
+fn lex_var(
+    chars: &mut core::iter::Peekable<core::str::CharIndices>,
+    word: &mut Word,
+) -> Result<(), String> {
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut name = String::new();
+    match chars.peek() {
+        Some(&(_, '?')) => {
+            chars.next();
+            name.push('?');
+        }
+        Some(&(_, '{')) => {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some((_, '}')) if !name.is_empty() => break,
+                    Some((_, c)) if is_name(c) => name.push(c),
+                    _ => return Err(String::from("bad ${} substitution")),
+                }
+            }
+        }
+        _ => {
+            while let Some(&(_, c)) = chars.peek() {
+                if !is_name(c) {
+                    break;
+                }
+                chars.next();
+                name.push(c);
+            }
+        }
+    }
+
+    match name.is_empty() {
+        true => word.push('$'),
+        false => word.0.push(Part::Var(name)),
+    }
+    Ok(())
+}
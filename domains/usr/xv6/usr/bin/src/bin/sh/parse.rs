@@ -1,10 +1,18 @@
 use alloc::boxed::Box;
 use alloc::collections::LinkedList;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
-use interface::rv6::Thread;
+use interface::rpc::RpcResult;
+use interface::rv6::{Thread, EXIT_FAILURE, EXIT_SUCCESS};
 use interface::vfs::FileMode;
-use usrlib::syscalls::{sys_close, sys_open_slice_slow, sys_spawn_domain_slice_slow};
+use usrlib::eprintln;
+use usrlib::syscalls::{
+    sys_close, sys_join, sys_open_slice_slow, sys_set_close_on_exec, sys_spawn_domain_slice_slow,
+};
+
+use crate::lex::{lex, Op, Token, TokenKind, Word};
+use crate::shell::Shell;
 
 #[derive(Debug)]
 pub struct Redir {
@@ -29,201 +37,432 @@ impl Redir {
             stderr: self.stderr,
         }
     }
+
+    // The shell's fd that the command gets as its fd `fd`
+    fn get_mut(&mut self, fd: usize) -> Option<&mut usize> {
+        match fd {
+            0 => Some(&mut self.stdin),
+            1 => Some(&mut self.stdout),
+            2 => Some(&mut self.stderr),
+            _ => None,
+        }
+    }
 }
 
 pub trait Command: core::fmt::Debug {
-    fn run(&self, redir: Redir) -> LinkedList<Box<dyn Thread>>;
+    fn run(&self, shell: &mut Shell, redir: Redir) -> LinkedList<Box<dyn Thread>>;
 }
 
 impl dyn Command {
-    pub fn parse(line: &str) -> (Box<dyn Command>, &str) {
-        PipeCommand::parse(line)
+    /// Parses a line, returning `None` if there is nothing to run. Errors are meant for the
+    /// user.
+    pub fn parse(line: &str) -> Result<Option<Box<dyn Command>>, String> {
+        let mut parser = Parser {
+            line,
+            tokens: lex(line)?.into_iter().peekable(),
+            end: 0,
+        };
+        parser.list()
     }
 }
 
-#[derive(Debug)]
-struct NoopCommand {}
+/// Waits for all the threads of a command, returns the exit status of the last one
+pub fn wait(threads: LinkedList<Box<dyn Thread>>) -> i32 {
+    threads
+        .iter()
+        .fold(EXIT_SUCCESS, |_, t| sys_join(&**t).unwrap())
+}
 
-impl Command for NoopCommand {
-    fn run(&self, _redir: Redir) -> LinkedList<Box<dyn Thread>> {
-        LinkedList::new()
+// Stands in for the thread of a command that never got to spawn one, e.g. a builtin
+struct Exited(i32);
+
+impl Thread for Exited {
+    fn join(&self) -> RpcResult<interface::rv6::Result<i32>> {
+        Ok(Ok(self.0))
+    }
+
+    fn try_join(&self) -> RpcResult<Option<i32>> {
+        Ok(Some(self.0))
     }
 }
 
-#[derive(Debug)]
-struct ExecCommand {
-    cmd: String,
-    args: String,
+fn exited(status: i32) -> LinkedList<Box<dyn Thread>> {
+    let mut ll = LinkedList::<Box<dyn Thread>>::new();
+    ll.push_back(box Exited(status));
+    ll
 }
 
-impl ExecCommand {
-    fn new(line: &str) -> Self {
-        Self {
-            cmd: line.split_ascii_whitespace().next().unwrap().to_string(),
-            args: line.to_string(),
+// Recursive descent over the tokens of a line:
+//
+//   list     := and_or ((';' | '&') and_or)* [';' | '&']
+//   and_or   := pipeline (('&&' | '||') pipeline)*
+//   pipeline := simple ['|' pipeline]
+//   simple   := (word | redir word)+
+//   redir    := [n]'<' | [n]'>' | [n]'<&' | [n]'>&'
+struct Parser<'a> {
+    line: &'a str,
+    tokens: core::iter::Peekable<alloc::vec::IntoIter<Token>>,
+    // End of the last token taken
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.next();
+        if let Some(token) = &token {
+            self.end = token.end;
+        }
+        token
+    }
+
+    fn peek_op(&mut self) -> Option<Op> {
+        match self.tokens.peek() {
+            Some(Token {
+                kind: TokenKind::Op(op),
+                ..
+            }) => Some(*op),
+            _ => None,
+        }
+    }
+
+    // Takes the next token if it is a word
+    fn word(&mut self) -> Option<Word> {
+        match self.tokens.peek() {
+            Some(Token {
+                kind: TokenKind::Word(_),
+                ..
+            }) => match self.next() {
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    ..
+                }) => Some(word),
+                _ => None,
+            },
+            _ => None,
         }
     }
 
-    fn parse(line: &str) -> (Box<dyn Command>, &str) {
-        let line = line.strip_suffix(char::is_whitespace).unwrap_or(line);
+    fn unexpected(&mut self) -> String {
+        match self.tokens.peek() {
+            Some(token) => alloc::format!(
+                "syntax error near `{}`",
+                &self.line[token.start..token.end]
+            ),
+            None => String::from("syntax error at end of line"),
+        }
+    }
 
-        if line.is_empty() {
-            return (box NoopCommand {}, line);
+    fn list(&mut self) -> Result<Option<Box<dyn Command>>, String> {
+        let mut items = Vec::new();
+        while let Some(token) = self.tokens.peek() {
+            let start = token.start;
+            let cmd = self.and_or()?;
+            let text = self.line[start..self.end].to_string();
+            let job = match self.peek_op() {
+                Some(Op::Background) => Some(text),
+                Some(Op::Seq) | None => None,
+                Some(_) => return Err(self.unexpected()),
+            };
+            self.next();
+            items.push((cmd, job));
         }
 
-        assert!(line.find(&['|', '<', '>'][..]).is_none());
-        (box Self::new(line), &line[line.len()..])
+        Ok(match items.len() {
+            0 => None,
+            1 if items[0].1.is_none() => items.pop().map(|(cmd, _)| cmd),
+            _ => Some(box ListCommand { items }),
+        })
+    }
+
+    fn and_or(&mut self) -> Result<Box<dyn Command>, String> {
+        let mut cmd = self.pipeline()?;
+        loop {
+            let op = match self.peek_op() {
+                Some(Op::And) => AndOr::And,
+                Some(Op::Or) => AndOr::Or,
+                _ => return Ok(cmd),
+            };
+            self.next();
+            cmd = box AndOrCommand::new(cmd, op, self.pipeline()?);
+        }
+    }
+
+    fn pipeline(&mut self) -> Result<Box<dyn Command>, String> {
+        let left = self.simple()?;
+        match self.peek_op() {
+            Some(Op::Pipe) => {
+                self.next();
+                Ok(box PipeCommand::new(left, self.pipeline()?))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    // Redirections may come anywhere in the command, e.g. `ls > foo -a`
+    fn simple(&mut self) -> Result<Box<dyn Command>, String> {
+        let mut words = Vec::new();
+        let mut redirs = Vec::new();
+        loop {
+            if let Some(word) = self.word() {
+                words.push(word);
+                continue;
+            }
+            let (mode, fd) = match self.peek_op() {
+                Some(Op::RedirIn(fd)) => (Some(FileMode::READ), fd),
+                Some(Op::RedirOut(fd)) => (Some(FileMode::WRITE | FileMode::CREATE), fd),
+                Some(Op::DupIn(fd)) | Some(Op::DupOut(fd)) => (None, fd),
+                _ => break,
+            };
+            self.next();
+            match self.word() {
+                Some(target) => redirs.push((target, mode, fd)),
+                None => return Err(self.unexpected()),
+            }
+        }
+
+        if words.is_empty() {
+            return Err(self.unexpected());
+        }
+        // Redirections apply from left to right, so `> out 2>&1` sends both to `out`. The
+        // outermost command applies its redirection first.
+        let mut cmd: Box<dyn Command> = box ExecCommand::new(words);
+        for (target, mode, fd) in redirs.into_iter().rev() {
+            cmd = box RedirCommand::new(cmd, target, mode, fd);
+        }
+        Ok(cmd)
+    }
+}
+
+#[derive(Debug)]
+struct ExecCommand {
+    words: Vec<Word>,
+}
+
+impl ExecCommand {
+    fn new(words: Vec<Word>) -> Self {
+        Self { words }
+    }
+}
+
+/// Splits `NAME=value` if `NAME` is a valid variable name
+pub fn assignment(word: &str) -> Option<(&str, &str)> {
+    let i = word.find('=')?;
+    let name = &word[..i];
+    let valid = name
+        .chars()
+        .enumerate()
+        .all(|(j, c)| c == '_' || c.is_ascii_alphabetic() || (j > 0 && c.is_ascii_digit()));
+    match valid && !name.is_empty() {
+        true => Some((name, &word[i + 1..])),
+        false => None,
     }
 }
 
 impl Command for ExecCommand {
-    fn run(&self, redir: Redir) -> LinkedList<Box<dyn Thread>> {
+    fn run(&self, shell: &mut Shell, redir: Redir) -> LinkedList<Box<dyn Thread>> {
+        let words: Vec<String> = self.words.iter().map(|w| w.expand(shell)).collect();
+
+        // A command made only of `NAME=value` sets shell variables
+        if words.iter().all(|w| assignment(w).is_some()) {
+            for (name, value) in words.iter().filter_map(|w| assignment(w)) {
+                shell.set_var(name, value);
+            }
+            return exited(EXIT_SUCCESS);
+        }
+
+        if let Some(status) = shell.builtin(&words) {
+            return exited(status);
+        }
+
+        // The kernel looks the binary up in the `PATH` we pass
         let result = sys_spawn_domain_slice_slow(
-            &self.cmd,
-            &self.args,
+            &words[0],
+            &words,
+            &shell.env(),
             &[Some(redir.stdin), Some(redir.stdout), Some(redir.stderr)],
         );
-        let mut ll = LinkedList::new();
-        ll.push_back(result.unwrap());
-        ll
+        match result {
+            Ok(thread) => {
+                let mut ll = LinkedList::new();
+                ll.push_back(thread);
+                ll
+            }
+            Err(e) => {
+                eprintln!("sh: cannot run {}. {:?}", words[0], e);
+                exited(EXIT_FAILURE)
+            }
+        }
     }
 }
 
+// `a; b & c` runs the commands one after the other, except those followed by `&` which are
+// started as background jobs
 #[derive(Debug)]
-pub struct PipeCommand {
+struct ListCommand {
+    // The command line of each background job
+    items: Vec<(Box<dyn Command>, Option<String>)>,
+}
+
+impl Command for ListCommand {
+    fn run(&self, shell: &mut Shell, redir: Redir) -> LinkedList<Box<dyn Thread>> {
+        // Threads of the last command in the foreground, the caller waits for them
+        let mut threads = LinkedList::new();
+        for (cmd, job) in self.items.iter() {
+            if !threads.is_empty() {
+                shell.status = wait(core::mem::take(&mut threads));
+            }
+            match job {
+                Some(text) => {
+                    let job_threads = cmd.run(shell, redir.copy());
+                    shell.add_job(text.clone(), job_threads);
+                    shell.status = EXIT_SUCCESS;
+                }
+                None => threads = cmd.run(shell, redir.copy()),
+            }
+        }
+        threads
+    }
+}
+
+#[derive(Debug)]
+enum AndOr {
+    And,
+    Or,
+}
+
+// `left && right` runs `right` only if `left` succeeded, `left || right` only if it failed
+#[derive(Debug)]
+pub struct AndOrCommand {
     left: Box<dyn Command>,
+    op: AndOr,
     right: Box<dyn Command>,
 }
 
-impl PipeCommand {
-    fn new(left: Box<dyn Command>, right: Box<dyn Command>) -> Self {
-        Self { left, right }
+impl AndOrCommand {
+    fn new(left: Box<dyn Command>, op: AndOr, right: Box<dyn Command>) -> Self {
+        Self { left, op, right }
     }
+}
 
-    fn parse(line: &str) -> (Box<dyn Command>, &str) {
-        let (left_str, right_str) = line.split_at(line.find('|').unwrap_or(line.len()));
+impl Command for AndOrCommand {
+    fn run(&self, shell: &mut Shell, redir: Redir) -> LinkedList<Box<dyn Thread>> {
+        // The left side has to finish before we know whether to run the right side
+        let status = wait(self.left.run(shell, redir.copy()));
+        shell.status = status;
+        match (&self.op, status == EXIT_SUCCESS) {
+            (AndOr::And, true) | (AndOr::Or, false) => self.right.run(shell, redir),
+            _ => exited(status),
+        }
+    }
+}
 
-        let (left_cmd, left_leftover) = RedirCommand::parse(left_str);
-        assert!(left_leftover.is_empty());
+#[derive(Debug)]
+pub struct PipeCommand {
+    left: Box<dyn Command>,
+    right: Box<dyn Command>,
+}
 
-        match right_str.is_empty() {
-            false => {
-                let (right_cmd, right_leftover) = Self::parse(&right_str[1..]);
-                (box Self::new(left_cmd, right_cmd), right_leftover)
-            }
-            true => (left_cmd, left_leftover),
-        }
+impl PipeCommand {
+    fn new(left: Box<dyn Command>, right: Box<dyn Command>) -> Self {
+        Self { left, right }
     }
 }
 
 impl Command for PipeCommand {
-    fn run(&self, redir: Redir) -> LinkedList<Box<dyn Thread>> {
+    fn run(&self, shell: &mut Shell, redir: Redir) -> LinkedList<Box<dyn Thread>> {
         // Setup redirection
-        let (rfd, wfd) = usrlib::syscalls::sys_pipe().unwrap();
+        let (rfd, wfd) = match usrlib::syscalls::sys_pipe() {
+            Ok(fds) => fds,
+            Err(e) => {
+                eprintln!("sh: cannot create pipe. {:?}", e);
+                return exited(EXIT_FAILURE);
+            }
+        };
+        // Only as stdin and stdout. A reader that inherited the write end as well would never
+        // see the end of the pipe.
+        sys_set_close_on_exec(rfd, true).unwrap();
+        sys_set_close_on_exec(wfd, true).unwrap();
         let mut left_redir = redir.copy();
         left_redir.stdout = wfd;
         let mut right_redir = redir.copy();
         right_redir.stdin = rfd;
 
         // Run commands
-        let mut result = self.left.run(left_redir);
-        result.append(&mut self.right.run(right_redir));
+        let mut result = self.left.run(shell, left_redir);
+        result.append(&mut self.right.run(shell, right_redir));
 
         // Cleanup
-        // We are safe to close these fds here because the fdtable is already saved
-        // when sys_spawn_domain returns
+        // We are safe to close these fds here because the new domains got their own
+        // references to the files when sys_spawn_domain returned
         sys_close(rfd).unwrap();
         sys_close(wfd).unwrap();
         result
     }
 }
 
+// `[n]> file` and `[n]< file` open `target`, `[n]>&m` and `[n]<&m` make fd `n` a copy of fd `m`
 #[derive(Debug)]
 pub struct RedirCommand {
     cmd: Box<dyn Command>,
-    file: String,
-    mode: FileMode,
+    target: Word,
+    // How to open the file, `None` if `target` is an fd
+    mode: Option<FileMode>,
     fd: usize,
 }
 
 impl RedirCommand {
-    fn new(cmd: Box<dyn Command>, file: &str, mode: FileMode, fd: usize) -> Self {
+    fn new(cmd: Box<dyn Command>, target: Word, mode: Option<FileMode>, fd: usize) -> Self {
         Self {
             cmd,
-            file: file.to_string(),
+            target,
             mode,
             fd,
         }
     }
-
-    // We only allow the redir part comes after everything else in the command
-    // For example, `ls -a > foo` is allowed but `ls > foo -a' is not allowed
-    fn parse(line: &str) -> (Box<dyn Command>, &str) {
-        // Split the redir part from the reset of the string
-        let (exec_str, mut redir_str) =
-            line.split_at(line.find(&['<', '>'][..]).unwrap_or(line.len()));
-
-        // Parse the possible exec cmd
-        let (mut subcmd, exec_left_over) = ExecCommand::parse(exec_str);
-        assert!(exec_left_over.is_empty());
-
-        // Process the redir_str
-        while !redir_str.is_empty() {
-            // Strip leading whitespaces
-            redir_str = redir_str
-                .strip_prefix(char::is_whitespace)
-                .unwrap_or(redir_str);
-
-            // Get redir token
-            let (redir_token, left_over_redir) =
-                redir_str.split_at(redir_str.find(char::is_whitespace).unwrap());
-            redir_str = &left_over_redir[1..]; // Skip the first char, which is the whitespace
-
-            // Get redir file
-            let (redir_file, left_over_redir) = redir_str.split_at(
-                redir_str
-                    .find(char::is_whitespace)
-                    .unwrap_or(redir_str.len()),
-            );
-            redir_str = left_over_redir;
-
-            // Generate a redir_cmd
-            let redir_cmd = match redir_token {
-                "<" => box Self::new(subcmd, redir_file, FileMode::READ, 0),
-                ">" => box Self::new(subcmd, redir_file, FileMode::WRITE | FileMode::CREATE, 1),
-                c => panic!("unknown redir token: {}", c),
-            };
-
-            // Update redir_str and subcmd for next iteration
-            subcmd = redir_cmd;
-        }
-
-        // Return the result
-        (subcmd, redir_str)
-    }
 }
 
 impl Command for RedirCommand {
-    fn run(&self, mut redir: Redir) -> LinkedList<Box<dyn Thread>> {
-        // Setup redirection
-        let fd = match self.fd {
-            0 => &mut redir.stdin,
-            1 => &mut redir.stdout,
-            2 => &mut redir.stderr,
-            n => panic!("fd {} redirection is not intended", n),
-        };
+    fn run(&self, shell: &mut Shell, mut redir: Redir) -> LinkedList<Box<dyn Thread>> {
+        // Commands only get stdin, stdout and stderr
+        if redir.get_mut(self.fd).is_none() {
+            eprintln!("sh: cannot redirect fd {}", self.fd);
+            return exited(EXIT_FAILURE);
+        }
 
-        let fd1 = sys_open_slice_slow(&self.file, self.mode).unwrap();
+        let target = self.target.expand(shell);
+        let from = match self.mode {
+            None => {
+                let fd = target.parse::<usize>().ok();
+                match fd.and_then(|fd| redir.get_mut(fd)) {
+                    Some(from) => *from,
+                    None => {
+                        eprintln!("sh: bad file descriptor {}", target);
+                        return exited(EXIT_FAILURE);
+                    }
+                }
+            }
+            // Commands only see the file as their fd `fd`, not under the number we get
+            Some(mode) => match sys_open_slice_slow(&target, mode | FileMode::CLOSE_ON_EXEC) {
+                Ok(fd) => fd,
+                Err(e) => {
+                    eprintln!("sh: cannot open {}. {:?}", target, e);
+                    return exited(EXIT_FAILURE);
+                }
+            },
+        };
 
-        *fd = fd1;
+        // Setup redirection
+        *redir.get_mut(self.fd).unwrap() = from;
 
-        // // Run commands
-        let result = self.cmd.run(redir);
+        // Run commands
+        let result = self.cmd.run(shell, redir);
 
-        // // Cleanup
-        // // We are safe to close these fds here because the fdtable is already saved
-        // // when sys_spawn_domain returns
-        sys_close(fd1).unwrap();
+        // Cleanup
+        // We are safe to close the file here because the new domains got their own
+        // references to it when sys_spawn_domain returned
+        if self.mode.is_some() {
+            sys_close(from).unwrap();
+        }
         result
     }
 }
@@ -0,0 +1,274 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, LinkedList};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use interface::rv6::{Thread, EXIT_FAILURE, EXIT_SUCCESS};
+use interface::vfs::FileMode;
+use usrlib::syscalls::{sys_chdir_slice_slow, sys_close, sys_open_slice_slow, sys_read_slice_slow};
+use usrlib::{eprintln, print, println};
+
+use crate::parse::{assignment, wait, Command, Redir};
+
+const PROMPT: &str = "rv6> ";
+
+// A command started with `&`
+struct Job {
+    id: usize,
+    text: String,
+    threads: LinkedList<Box<dyn Thread>>,
+}
+
+impl Job {
+    // Exit status of the job if all its threads have exited
+    fn try_wait(&self) -> Option<i32> {
+        let mut status = EXIT_SUCCESS;
+        for thread in self.threads.iter() {
+            status = thread.try_join().unwrap()?;
+        }
+        Some(status)
+    }
+}
+
+pub struct Shell {
+    /// Exit status of the last command, for `$?`
+    pub status: i32,
+    vars: BTreeMap<String, String>,
+    // Variables passed on to the programs we run
+    exported: BTreeSet<String>,
+    jobs: Vec<Job>,
+    next_job: usize,
+    // Set by the `exit` builtin
+    exit: Option<i32>,
+}
+
+impl Shell {
+    /// Starts with the variables of our own environment, which stay exported
+    pub fn new() -> Self {
+        let mut shell = Self {
+            status: EXIT_SUCCESS,
+            vars: BTreeMap::new(),
+            exported: BTreeSet::new(),
+            jobs: Vec::new(),
+            next_job: 1,
+            exit: None,
+        };
+        for (name, value) in usrlib::env::vars().iter().filter_map(|var| assignment(var)) {
+            shell.set_var(name, value);
+            shell.exported.insert(name.to_string());
+        }
+        shell
+    }
+
+    /// Value of a variable, unset variables expand to nothing
+    pub fn var(&self, name: &str) -> String {
+        match name {
+            "?" => self.status.to_string(),
+            _ => self.vars.get(name).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.vars.insert(name.to_string(), value.to_string());
+    }
+
+    /// The environment of the programs we run, as `NAME=value` strings
+    pub fn env(&self) -> Vec<String> {
+        self.exported
+            .iter()
+            .map(|name| alloc::format!("{}={}", name, self.var(name)))
+            .collect()
+    }
+
+    pub fn add_job(&mut self, text: String, threads: LinkedList<Box<dyn Thread>>) {
+        let id = self.next_job;
+        self.next_job += 1;
+        println!("[{}] {}", id, text);
+        self.jobs.push(Job { id, text, threads });
+    }
+
+    // Reports and forgets the jobs that are done
+    fn reap_jobs(&mut self) {
+        self.jobs.retain(|job| match job.try_wait() {
+            Some(status) => {
+                println!("[{}] Done ({}) {}", job.id, status, job.text);
+                false
+            }
+            None => true,
+        });
+    }
+
+    /// Runs `words` if it is a builtin and returns its exit status
+    pub fn builtin(&mut self, words: &[String]) -> Option<i32> {
+        let result = match words[0].as_str() {
+            // The working directory belongs to the shell's thread, so `cd` can't be a child domain
+            "cd" => {
+                let path = words.get(1).map_or("/", |p| p.as_str());
+                sys_chdir_slice_slow(path)
+                    .map(|_| EXIT_SUCCESS)
+                    .map_err(|e| alloc::format!("cd: cannot cd to {}. {:?}", path, e))
+            }
+            "jobs" => {
+                for job in self.jobs.iter() {
+                    match job.try_wait() {
+                        Some(status) => println!("[{}] Done ({}) {}", job.id, status, job.text),
+                        None => println!("[{}] Running {}", job.id, job.text),
+                    }
+                }
+                Ok(EXIT_SUCCESS)
+            }
+            "wait" => self.wait_jobs(&words[1..]),
+            "export" => self.export(&words[1..]),
+            "exit" => match words.get(1).map(|n| n.parse::<i32>()) {
+                None => {
+                    self.exit = Some(self.status);
+                    Ok(self.status)
+                }
+                Some(Ok(n)) => {
+                    self.exit = Some(n);
+                    Ok(n)
+                }
+                Some(Err(_)) => Err(alloc::format!("exit: {} is not a number", words[1])),
+            },
+            _ => return None,
+        };
+        Some(result.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }))
+    }
+
+    // `export NAME` passes an existing variable on to the programs we run, `export NAME=value`
+    // also sets it
+    fn export(&mut self, args: &[String]) -> Result<i32, String> {
+        for arg in args {
+            let name = match assignment(arg) {
+                Some((name, value)) => {
+                    self.set_var(name, value);
+                    name
+                }
+                None if assignment(&alloc::format!("{}=", arg)).is_some() => arg.as_str(),
+                None => return Err(alloc::format!("export: bad variable name {}", arg)),
+            };
+            self.exported.insert(name.to_string());
+        }
+        Ok(EXIT_SUCCESS)
+    }
+
+    // `wait` waits for every job, `wait %1 %3` only for jobs 1 and 3
+    fn wait_jobs(&mut self, args: &[String]) -> Result<i32, String> {
+        let mut ids = Vec::new();
+        for arg in args {
+            let id = arg
+                .strip_prefix('%')
+                .unwrap_or(arg)
+                .parse::<usize>()
+                .ok()
+                .filter(|id| self.jobs.iter().any(|job| job.id == *id))
+                .ok_or_else(|| alloc::format!("wait: no such job {}", arg))?;
+            ids.push(id);
+        }
+
+        let mut status = EXIT_SUCCESS;
+        let mut i = 0;
+        while i < self.jobs.len() {
+            if ids.is_empty() || ids.contains(&self.jobs[i].id) {
+                let job = self.jobs.remove(i);
+                status = wait(job.threads);
+            } else {
+                i += 1;
+            }
+        }
+        Ok(status)
+    }
+
+    /// Parses and runs a line. `location` prefixes parse errors, e.g. the line of a script.
+    pub fn run_line(&mut self, line: &str, location: &str) {
+        match <dyn Command>::parse(line) {
+            Err(e) => {
+                eprintln!("{}{}", location, e);
+                self.status = EXIT_FAILURE;
+            }
+            Ok(None) => {}
+            Ok(Some(cmd)) => {
+                println!("Parsed command: {:?}", cmd);
+                self.status = wait(cmd.run(self, Redir::new()));
+            }
+        }
+    }
+
+    /// Runs the script at `path` line by line and returns the status of its last command
+    pub fn run_script(&mut self, path: &str) -> i32 {
+        let script = match read_file(path) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("sh: {}", e);
+                return EXIT_FAILURE;
+            }
+        };
+
+        for (i, line) in script.lines().enumerate() {
+            self.run_line(line, &alloc::format!("{}:{}: ", path, i + 1));
+            if let Some(status) = self.exit {
+                return status;
+            }
+        }
+        self.status
+    }
+
+    /// Reads commands from stdin until it is closed or `exit` is run
+    pub fn run_interactive(&mut self) -> i32 {
+        loop {
+            self.reap_jobs();
+            print!("{}", PROMPT);
+            let line = match read_line(0) {
+                Some(line) => line,
+                None => return self.status,
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.run_line(line, "sh: ");
+            if let Some(status) = self.exit {
+                return status;
+            }
+        }
+    }
+}
+
+// Returns `None` at the end of the input
+fn read_line(fd: usize) -> Option<String> {
+    let mut line = Vec::new();
+    let mut c = [0u8; 1];
+    loop {
+        match sys_read_slice_slow(fd, &mut c) {
+            Ok(1) => {
+                line.push(c[0]);
+                if c[0] == b'\n' {
+                    break;
+                }
+            }
+            _ if line.is_empty() => return None,
+            _ => break,
+        }
+    }
+    Some(String::from_utf8_lossy(&line).into_owned())
+}
+
+fn read_file(path: &str) -> Result<String, String> {
+    let fd = sys_open_slice_slow(path, FileMode::READ)
+        .map_err(|e| alloc::format!("cannot open {}. {:?}", path, e))?;
+    let mut bytes = Vec::new();
+    let mut buffer = [0u8; 512];
+    let result = loop {
+        match sys_read_slice_slow(fd, &mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(n) => bytes.extend_from_slice(&buffer[..n]),
+            Err(e) => break Err(alloc::format!("cannot read {}. {:?}", path, e)),
+        }
+    };
+    sys_close(fd).unwrap();
+    result?;
+    String::from_utf8(bytes).map_err(|_| alloc::format!("{} is not a text file", path))
+}
@@ -5,23 +5,43 @@
 
 extern crate alloc;
 extern crate malloc;
-use crate::println;
-use crate::syscalls::{sys_close, sys_open_slice_slow, sys_read, sys_seek, sys_write};
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::for_each_block;
+use crate::{eprintln, println};
+use crate::syscalls::{
+    sys_close, sys_fs_stats, sys_open_slice_slow, sys_read, sys_seek, sys_write,
+};
+use crate::thread;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::panic::PanicInfo;
+use interface::bdev::BSIZE;
 use interface::rref::RRefVec;
 use interface::rv6::Rv6;
-use interface::vfs::{DirectoryEntry, DirectoryEntryRef, FileMode, INodeFileType};
+use interface::vfs::{
+    DirectoryEntry, DirectoryEntryRef, FileMode, INodeFileType, SeekFrom, NDIRECT,
+};
 use syscalls::{Heap, Syscall};
 
 const ONE_MS: u64 = 2_400_000;
 const TEN_MS: u64 = 10 * ONE_MS;
 const ONE_SEC: u64 = 2_400_000_000;
 
-pub fn main(rv6: Box<dyn Rv6>, args: &str) {
-    println!("Starting rv6 benchfs with args: {}", args);
+// The stress test rewrites the whole file this many times while it is being read
+const STRESS_ROUNDS: usize = 64;
+const STRESS_FILE_SIZE: usize = 64 * 1024;
 
-    let mut args = args.split_whitespace();
+// Block addresses in an indirect table
+const NINDIRECT: usize = BSIZE / core::mem::size_of::<u32>();
+// The indirect test file ends one block into the third level 2 table, past the blocks a
+// single indirect table would reach
+const INDIRECT_FILE_BLOCKS: usize = NDIRECT + 2 * NINDIRECT + 1;
+
+pub fn main(rv6: Box<dyn Rv6>, args: &[&str]) -> i32 {
+    println!("Starting rv6 benchfs with args: {:?}", args);
+
+    let mut args = args.iter().copied();
     args.next().unwrap();
     let test = args.next().unwrap_or("throughput");
     let options = args.next().unwrap_or("r");
@@ -30,8 +50,14 @@ pub fn main(rv6: Box<dyn Rv6>, args: &str) {
     match test {
         "throughput" => bench_throughput(&*rv6, options, file),
         "restart" => bench_restart(&*rv6, options, file),
-        _ => panic!("{}", test),
+        "stress" => return stress(options, file),
+        "indirect" => return indirect(options, file),
+        _ => {
+            eprintln!("benchfs: unknown test {}", test);
+            return EXIT_FAILURE;
+        }
     }
+    EXIT_SUCCESS
 }
 
 fn bench_throughput(_rv6: &dyn Rv6, options: &str, file: &str) {
@@ -50,6 +76,8 @@ fn bench_throughput(_rv6: &dyn Rv6, options: &str, file: &str) {
 
     for bsize in sizes.iter() {
         let mut buffer = RRefVec::new(123u8, *bsize);
+        // Bytes and cycles of the write pass, to compare the read pass with
+        let mut write_result = None;
 
         if options.contains('w') {
             let fd = sys_open_slice_slow(file, FileMode::WRITE | FileMode::CREATE).unwrap();
@@ -67,12 +95,12 @@ fn bench_throughput(_rv6: &dyn Rv6, options: &str, file: &str) {
                 buffer = buffer_back;
                 total_size += size;
             }
+            let cycles = libtime::get_rdtsc() - start;
             println!(
                 "Write: buffer size: {}, total bytes: {}, cycles: {}",
-                bsize,
-                total_size,
-                libtime::get_rdtsc() - start
+                bsize, total_size, cycles
             );
+            write_result = Some((total_size, cycles));
 
             sys_close(fd).unwrap();
         }
@@ -83,6 +111,7 @@ fn bench_throughput(_rv6: &dyn Rv6, options: &str, file: &str) {
             // warm up
             buffer = sys_read(fd, buffer).unwrap().1;
 
+            let stats = sys_fs_stats().unwrap();
             let start = libtime::get_rdtsc();
             let mut total_size = 0;
             loop {
@@ -93,16 +122,236 @@ fn bench_throughput(_rv6: &dyn Rv6, options: &str, file: &str) {
                 }
                 total_size += size;
             }
+            let cycles = libtime::get_rdtsc() - start;
             println!(
                 "Read: buffer size: {}, total bytes: {}, cycles: {}",
-                bsize,
-                total_size,
-                libtime::get_rdtsc() - start
+                bsize, total_size, cycles
+            );
+            if let Some((write_size, write_cycles)) = write_result {
+                // Cycles per byte, so passes of different sizes compare
+                let write_cost = write_cycles as f64 / write_size as f64;
+                let read_cost = cycles as f64 / total_size as f64;
+                println!(
+                    "Write vs read: buffer size: {}, write/read cycles per byte: {:.2}",
+                    bsize,
+                    write_cost / read_cost
+                );
+            }
+            let stats_after = sys_fs_stats().unwrap();
+            println!(
+                "Read: bcache misses: {}, blocks read ahead: {}, device read requests: {}",
+                stats_after.bcache_misses - stats.bcache_misses,
+                stats_after.readahead_blocks - stats.readahead_blocks,
+                stats_after.read_requests - stats.read_requests
             );
 
             sys_close(fd).unwrap();
         }
     }
+
+    if options.contains('m') {
+        let fd = sys_open_slice_slow(file, FileMode::READ).unwrap();
+
+        // The first pass may read the file from the disk, the second one finds it in the buffer
+        // cache. Neither copies it.
+        for pass in ["first", "second"].iter() {
+            let start = libtime::get_rdtsc();
+            let mut size = 0;
+            let mut checksum = 0u8;
+            for_each_block(fd, |block| {
+                size += block.len();
+                checksum = block
+                    .iter()
+                    .fold(checksum, |sum, byte| sum.wrapping_add(*byte));
+                Ok(())
+            })
+            .unwrap();
+            println!(
+                "Mmap: {} mapping, total bytes: {}, cycles: {}, checksum: {}",
+                pass,
+                size,
+                libtime::get_rdtsc() - start,
+                checksum
+            );
+        }
+
+        sys_close(fd).unwrap();
+    }
+}
+
+// Rewrites `file` in one thread while another one reads and maps it, see `options`. Fails if a
+// read sees bytes that no write wrote. Reads used to lock the inode before they began their
+// transaction, which deadlocked with a concurrent write once the flush thread held back new
+// transactions.
+fn stress(options: &str, file: &str) -> i32 {
+    // Every byte of the file is the round that last wrote it
+    let fd = sys_open_slice_slow(file, FileMode::WRITE | FileMode::CREATE).unwrap();
+    sys_write(fd, RRefVec::new(0u8, STRESS_FILE_SIZE)).unwrap();
+    sys_close(fd).unwrap();
+
+    let mut threads = Vec::new();
+    if options.contains('w') {
+        let file = String::from(file);
+        match thread::spawn("benchfs writer", move || stress_write(&file)) {
+            Ok(thread) => threads.push(thread),
+            Err(e) => eprintln!("benchfs: cannot start the writer. {:?}", e),
+        }
+    }
+    if options.contains('r') {
+        let file = String::from(file);
+        match thread::spawn("benchfs reader", move || stress_read(&file)) {
+            Ok(thread) => threads.push(thread),
+            Err(e) => eprintln!("benchfs: cannot start the reader. {:?}", e),
+        }
+    }
+
+    let start = libtime::get_rdtsc();
+    let mut status = EXIT_SUCCESS;
+    for thread in threads {
+        match thread.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                eprintln!("benchfs: {}", e);
+                status = EXIT_FAILURE;
+            }
+            Err(exit_status) => {
+                eprintln!("benchfs: stress thread exited with {}", exit_status);
+                status = EXIT_FAILURE;
+            }
+        }
+    }
+    println!(
+        "Stress: rounds: {}, file size: {}, cycles: {}",
+        STRESS_ROUNDS,
+        STRESS_FILE_SIZE,
+        libtime::get_rdtsc() - start
+    );
+    status
+}
+
+fn stress_write(file: &str) -> Result<(), String> {
+    let fd = sys_open_slice_slow(file, FileMode::WRITE)
+        .map_err(|e| alloc::format!("cannot open {}. {:?}", file, e))?;
+    let mut buffer = RRefVec::new(0u8, STRESS_FILE_SIZE);
+    for round in 1..=STRESS_ROUNDS {
+        buffer.as_mut_slice().fill(round as u8);
+        sys_seek(fd, SeekFrom::Start(0)).unwrap();
+        buffer = sys_write(fd, buffer)
+            .map_err(|e| alloc::format!("cannot write {}. {:?}", file, e))?
+            .1;
+    }
+    sys_close(fd).unwrap();
+    Ok(())
+}
+
+fn stress_read(file: &str) -> Result<(), String> {
+    let written = |byte: &u8| *byte as usize <= STRESS_ROUNDS;
+    let mut buffer = RRefVec::new(0u8, 4096);
+    for _ in 0..STRESS_ROUNDS {
+        let fd = sys_open_slice_slow(file, FileMode::READ)
+            .map_err(|e| alloc::format!("cannot open {}. {:?}", file, e))?;
+        let mut total_size = 0;
+        loop {
+            let (size, buffer_back) = sys_read(fd, buffer)
+                .map_err(|e| alloc::format!("cannot read {}. {:?}", file, e))?;
+            buffer = buffer_back;
+            if size == 0 {
+                break;
+            }
+            if !buffer.as_slice()[..size].iter().all(written) {
+                return Err(alloc::format!("read garbage from {}", file));
+            }
+            total_size += size;
+        }
+        if total_size != STRESS_FILE_SIZE {
+            return Err(alloc::format!("read {} bytes of {}", total_size, file));
+        }
+
+        let mut garbage = false;
+        for_each_block(fd, |block| {
+            garbage |= !block.iter().all(written);
+            Ok(())
+        })
+        .map_err(|e| alloc::format!("cannot map {}. {:?}", file, e))?;
+        if garbage {
+            return Err(alloc::format!("mapped garbage from {}", file));
+        }
+        sys_close(fd).unwrap();
+    }
+    Ok(())
+}
+
+// Writes `file` block by block if `options` has a 'w', then reads it back if it has an 'r'.
+// Every block holds its own index, so a block that the double-indirect tables map twice, or
+// lose, reads back wrong. `block_map` used to drop the level 2 tables it allocated.
+fn indirect(options: &str, file: &str) -> i32 {
+    let mut buffer = RRefVec::new(0u8, BSIZE);
+    let start = libtime::get_rdtsc();
+
+    if options.contains('w') {
+        let fd = sys_open_slice_slow(file, FileMode::WRITE | FileMode::CREATE).unwrap();
+        for block in 0..INDIRECT_FILE_BLOCKS {
+            fill_block(buffer.as_mut_slice(), block);
+            let (size, buffer_back) = match sys_write(fd, buffer) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("benchfs: cannot write block {} of {}. {:?}", block, file, e);
+                    return EXIT_FAILURE;
+                }
+            };
+            buffer = buffer_back;
+            if size != BSIZE {
+                eprintln!(
+                    "benchfs: wrote {} bytes of block {} of {}",
+                    size, block, file
+                );
+                return EXIT_FAILURE;
+            }
+        }
+        sys_close(fd).unwrap();
+    }
+
+    if options.contains('r') {
+        let mut expected = [0u8; BSIZE];
+        let fd = sys_open_slice_slow(file, FileMode::READ).unwrap();
+        for block in 0..INDIRECT_FILE_BLOCKS {
+            let (size, buffer_back) = match sys_read(fd, buffer) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("benchfs: cannot read block {} of {}. {:?}", block, file, e);
+                    return EXIT_FAILURE;
+                }
+            };
+            buffer = buffer_back;
+            fill_block(&mut expected, block);
+            if size != BSIZE || buffer.as_slice() != &expected[..] {
+                eprintln!("benchfs: block {} of {} reads back wrong", block, file);
+                return EXIT_FAILURE;
+            }
+        }
+        if sys_read(fd, buffer).unwrap().0 != 0 {
+            eprintln!(
+                "benchfs: {} is longer than {} blocks",
+                file, INDIRECT_FILE_BLOCKS
+            );
+            return EXIT_FAILURE;
+        }
+        sys_close(fd).unwrap();
+    }
+
+    println!(
+        "Indirect: blocks: {}, cycles: {}",
+        INDIRECT_FILE_BLOCKS,
+        libtime::get_rdtsc() - start
+    );
+    EXIT_SUCCESS
+}
+
+// Fills a block of the indirect test file with its index
+fn fill_block(buffer: &mut [u8], block: usize) {
+    for chunk in buffer.chunks_mut(core::mem::size_of::<u32>()) {
+        chunk.copy_from_slice(&(block as u32).to_ne_bytes());
+    }
 }
 
 fn bench_restart(_rv6: &dyn Rv6, options: &str, file: &str) {
@@ -124,7 +373,7 @@ fn bench_restart(_rv6: &dyn Rv6, options: &str, file: &str) {
 
             // warm up
             buffer = sys_write(fd, buffer).unwrap().1;
-            sys_seek(fd, 0).unwrap();
+            sys_seek(fd, SeekFrom::Start(0)).unwrap();
 
             let mut recording: [(u64, f64); 100_000] = [(0, 0.0); 100_000];
             let mut recording_index = 0;
@@ -148,7 +397,7 @@ fn bench_restart(_rv6: &dyn Rv6, options: &str, file: &str) {
                     interval_read = 0;
                 }
                 if offset % file_size == 0 {
-                    sys_seek(fd, 0).unwrap();
+                    sys_seek(fd, SeekFrom::Start(0)).unwrap();
                     seek_count += 1;
                 }
                 let (bytes_read, buffer_back) = sys_write(fd, buffer).unwrap();
@@ -194,7 +443,7 @@ fn bench_restart(_rv6: &dyn Rv6, options: &str, file: &str) {
 
             // warm up
             buffer = sys_read(fd, buffer).unwrap().1;
-            sys_seek(fd, 0).unwrap();
+            sys_seek(fd, SeekFrom::Start(0)).unwrap();
 
             let mut recording: [(u64, f64); 100_000] = [(0, 0.0); 100_000];
             let mut recording_index = 0;
@@ -218,7 +467,7 @@ fn bench_restart(_rv6: &dyn Rv6, options: &str, file: &str) {
                     interval_read = 0;
                 }
                 if offset % file_size == 0 {
-                    sys_seek(fd, 0).unwrap();
+                    sys_seek(fd, SeekFrom::Start(0)).unwrap();
                     seek_count += 1;
                 }
                 let (bytes_read, buffer_back) = sys_read(fd, buffer).unwrap();
@@ -13,8 +13,8 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(rv6: Box<dyn Rv6>, args: &str) {
-    println!("Starting rv6 benchnet with args: {}", args);
+pub fn main(rv6: Box<dyn Rv6>, args: &[&str]) -> i32 {
+    println!("Starting rv6 benchnet with args: {:?}", args);
 
     let net = rv6.as_net().unwrap();
 
@@ -46,4 +46,5 @@ pub fn main(rv6: Box<dyn Rv6>, args: &str) {
     libbenchnet::run_tx_udptest_rref(&*net, 64, false);
     libbenchnet::run_fwd_udptest_rref(&*net, 64);
     libbenchnet::run_maglev_fwd_udptest_rref(&*net, 64);
+    crate::exit::EXIT_SUCCESS
 }
@@ -8,25 +8,36 @@ extern crate malloc;
 
 #[macro_use]
 use alloc::boxed::Box;
-use crate::println;
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::{eprintln, println};
 use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(rv6: Box<dyn Rv6>, args: &str) {
-    println!("Starting rv6 benchnet with args: {}", args);
+pub fn main(rv6: Box<dyn Rv6>, args: &[&str]) -> i32 {
+    println!("Starting rv6 benchnet with args: {:?}", args);
 
     let mut nvme = rv6.as_nvme().unwrap();
 
+    let mut status = EXIT_SUCCESS;
+
     for _ in 0..=6 {
-        let _ = libbenchnvme::run_blocktest_rref(
+        if let Err(e) = libbenchnvme::run_blocktest_rref(
             &mut *nvme, 4096, /*is_write=*/ true, /*is_random=*/ false,
-        );
+        ) {
+            eprintln!("benchnvme: write test failed. {:?}", e);
+            status = EXIT_FAILURE;
+        }
     }
 
     for _ in 0..=6 {
-        let _ = libbenchnvme::run_blocktest_rref(
+        if let Err(e) = libbenchnvme::run_blocktest_rref(
             &mut *nvme, 4096, /*is_write=*/ false, /*is_random=*/ false,
-        );
+        ) {
+            eprintln!("benchnvme: read test failed. {:?}", e);
+            status = EXIT_FAILURE;
+        }
     }
+
+    status
 }
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::{copy, STDIN_FD, STDOUT_FD};
+use crate::syscalls::{sys_close, sys_open_slice_slow};
+use alloc::string::String;
+use interface::vfs::FileMode;
+
+// Like the other filters, cat doesn't print a banner: its stdout is often a pipe
+pub fn main(args: &[&str]) -> i32 {
+    let files = &args[1..];
+    if files.is_empty() {
+        return crate::exit::status(cat(STDIN_FD, "-"));
+    }
+
+    // Keep going past files that can't be read, but report the failure
+    let mut status = EXIT_SUCCESS;
+    for &file in files {
+        let result = match file {
+            "-" => cat(STDIN_FD, file),
+            _ => sys_open_slice_slow(file, FileMode::READ)
+                .map_err(|e| alloc::format!("cat: cannot open {}. {:?}", file, e))
+                .and_then(|fd| {
+                    let result = cat(fd, file);
+                    sys_close(fd).unwrap();
+                    result
+                }),
+        };
+        if crate::exit::status(result) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+fn cat(fd: usize, name: &str) -> Result<(), String> {
+    copy(fd, STDOUT_FD).map_err(|e| alloc::format!("cat: cannot read {}. {:?}", name, e))?;
+    Ok(())
+}
@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+use crate::syscalls::sys_chmod_slice_slow;
+use crate::eprintln;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::panic::PanicInfo;
+use interface::rv6::Rv6;
+use interface::vfs::Permissions;
+use syscalls::{Heap, Syscall};
+
+pub fn main(args: &[&str]) -> i32 {
+    match args[1..] {
+        [mode, path] => crate::exit::status(chmod(mode, path)),
+        _ => crate::exit::status(Err(String::from("usage: chmod mode file"))),
+    }
+}
+
+// `mode` is given in octal, e.g. 644
+fn chmod(mode: &str, path: &str) -> Result<(), String> {
+    let bits = u16::from_str_radix(mode, 8)
+        .ok()
+        .and_then(Permissions::from_bits)
+        .ok_or_else(|| alloc::format!("chmod: invalid mode {}", mode))?;
+    sys_chmod_slice_slow(path, bits)
+        .map_err(|e| alloc::format!("chmod: cannot chmod {}. {:?}", path, e))?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::{copy, for_each_block, write_all};
+use crate::syscalls::{
+    sys_chmod_slice_slow, sys_close, sys_fstat, sys_ftruncate, sys_open_slice_slow,
+};
+use alloc::string::String;
+use interface::rv6::ErrorKind;
+use interface::vfs::{FileMode, INodeFileType};
+
+/// `cp src dst` copies a file, `cp src... dir` copies files into a directory
+pub fn main(args: &[&str]) -> i32 {
+    let (dest, sources) = match args[1..].split_last() {
+        Some((dest, sources)) if !sources.is_empty() => (*dest, sources),
+        _ => return crate::exit::status(Err(String::from("usage: cp src... dst"))),
+    };
+    let into_dir = crate::mv::is_directory(dest);
+    if sources.len() > 1 && !into_dir {
+        return crate::exit::status(Err(alloc::format!("cp: {} is not a directory", dest)));
+    }
+
+    // Keep going past files that can't be copied, but report the failure
+    let mut status = EXIT_SUCCESS;
+    for &source in sources {
+        let dest = match into_dir {
+            true => {
+                let name = source.trim_end_matches('/').rsplit('/').next().unwrap();
+                alloc::format!("{}/{}", dest.trim_end_matches('/'), name)
+            }
+            false => String::from(dest),
+        };
+        if crate::exit::status(cp(source, &dest)) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+fn cp(source: &str, dest: &str) -> Result<(), String> {
+    let from = sys_open_slice_slow(source, FileMode::READ)
+        .map_err(|e| alloc::format!("cp: cannot open {}. {:?}", source, e))?;
+    let result = sys_fstat(from)
+        .map_err(|e| alloc::format!("cp: cannot stat {}. {:?}", source, e))
+        .and_then(|stat| match stat.file_type {
+            INodeFileType::Directory => Err(alloc::format!("cp: {} is a directory", source)),
+            _ => {
+                let to = sys_open_slice_slow(dest, FileMode::WRITE | FileMode::CREATE)
+                    .map_err(|e| alloc::format!("cp: cannot create {}. {:?}", dest, e))?;
+                // Truncating the destination would also empty the source
+                let result = sys_fstat(to)
+                    .map_err(|e| alloc::format!("cp: cannot stat {}. {:?}", dest, e))
+                    .and_then(|to_stat| {
+                        if (to_stat.device, to_stat.inum) == (stat.device, stat.inum) {
+                            return Err(alloc::format!(
+                                "cp: {} and {} are the same file",
+                                source,
+                                dest
+                            ));
+                        }
+                        copy_contents(from, to).map_err(|e| {
+                            alloc::format!("cp: cannot copy {} to {}. {:?}", source, dest, e)
+                        })
+                    });
+                sys_close(to).unwrap();
+                result?;
+                sys_chmod_slice_slow(dest, stat.permissions)
+                    .map_err(|e| alloc::format!("cp: cannot chmod {}. {:?}", dest, e))
+            }
+        });
+    sys_close(from).unwrap();
+    result
+}
+
+// An existing destination is overwritten, not appended to
+fn copy_contents(from: usize, to: usize) -> interface::rv6::Result<()> {
+    sys_ftruncate(to, 0)?;
+    // Regular files are copied straight out of the buffer cache
+    match for_each_block(from, |block| write_all(to, block)) {
+        Err(ErrorKind::InvalidFileType) => copy(from, to).map(|_| ()),
+        result => result,
+    }
+}
@@ -13,10 +13,10 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 dump_inode with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 dump_inode with args: {:?}", args);
 
-    dump_inode().unwrap();
+    crate::exit::status(dump_inode())
 }
 
 fn dump_inode() -> Result<(), String> {
@@ -0,0 +1,26 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::io::{write_all, STDOUT_FD};
+
+/// `echo [-n] args...` prints its arguments separated by spaces, `-n` leaves out the newline
+pub fn main(args: &[&str]) -> i32 {
+    let (newline, words) = match args.get(1) {
+        Some(&"-n") => (false, &args[2..]),
+        _ => (true, &args[1..]),
+    };
+
+    let mut line = words.join(" ");
+    if newline {
+        line.push('\n');
+    }
+    crate::exit::status(
+        write_all(STDOUT_FD, line.as_bytes())
+            .map_err(|e| alloc::format!("echo: cannot write. {:?}", e)),
+    )
+}
@@ -0,0 +1,36 @@
+//! Arguments and environment of the running rv6 program
+//!
+//! `trusted_entry` gets the `argv` and `envp` the program was spawned with and hands them
+//! to `init`. Environment variables are `NAME=value` strings.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use spin::Once;
+
+static ARGS: Once<Vec<String>> = Once::new();
+static VARS: Once<Vec<String>> = Once::new();
+
+pub fn init(argv: &[&str], envp: &[&str]) {
+    ARGS.call_once(|| argv.iter().map(|arg| arg.to_string()).collect());
+    VARS.call_once(|| envp.iter().map(|var| var.to_string()).collect());
+}
+
+/// The arguments of the program, starting with its name
+pub fn args() -> &'static [String] {
+    ARGS.r#try().map_or(&[], |args| args.as_slice())
+}
+
+/// The environment of the program, as `NAME=value` strings
+pub fn vars() -> &'static [String] {
+    VARS.r#try().map_or(&[], |vars| vars.as_slice())
+}
+
+pub fn var(name: &str) -> Option<&'static str> {
+    vars().iter().find_map(|var| split_var(var).filter(|(n, _)| *n == name).map(|(_, v)| v))
+}
+
+/// Splits `NAME=value`
+pub fn split_var(var: &str) -> Option<(&str, &str)> {
+    let i = var.find('=')?;
+    Some((&var[..i], &var[i + 1..]))
+}
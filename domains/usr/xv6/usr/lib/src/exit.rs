@@ -0,0 +1,33 @@
+//! Exit statuses of rv6 programs
+//!
+//! The `main` of every program returns its exit status, which the parent gets back from
+//! `Thread::join`.
+
+use crate::eprintln;
+use alloc::string::String;
+use libsyscalls::syscalls::{sys_exit_domain, sys_unwind};
+
+pub use interface::rv6::{EXIT_FAILURE, EXIT_PANIC, EXIT_SUCCESS};
+
+/// Exit status of a program that ran to completion with `result`. The error, if any, is
+/// printed to stderr.
+pub fn status(result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => EXIT_SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// Leaves the program from a panic handler: unwinds the thread back to rv6, which exits it with
+/// `EXIT_PANIC`. The last thread of the program also frees the shared heap of the program.
+pub fn unwind() -> ! {
+    if crate::thread::leave() {
+        sys_exit_domain();
+    } else {
+        sys_unwind();
+    }
+    unreachable!("unwinding out of the program returned")
+}
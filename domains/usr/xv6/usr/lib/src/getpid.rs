@@ -13,10 +13,10 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 getpid with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 getpid with args: {:?}", args);
 
-    getpid().unwrap();
+    crate::exit::status(getpid())
 }
 
 fn getpid() -> Result<(), String> {
@@ -0,0 +1,223 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::eprintln;
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::{write_all, LineReader, STDIN_FD, STDOUT_FD};
+use crate::syscalls::{sys_close, sys_open_slice_slow};
+use alloc::string::String;
+use alloc::vec::Vec;
+use interface::vfs::FileMode;
+
+// Exit status when a file couldn't be searched, whether or not something matched
+const EXIT_TROUBLE: i32 = 2;
+
+const USAGE: &str = "usage: grep [-cinqvx] pattern [file...]";
+
+#[derive(Default)]
+struct Options {
+    // Only print the number of matching lines
+    count: bool,
+    ignore_case: bool,
+    line_numbers: bool,
+    // Print nothing, only exit with whether a line matched
+    quiet: bool,
+    invert: bool,
+    whole_line: bool,
+}
+
+/// `grep pattern files...` prints the lines that match `pattern`. Patterns support `.`, `*`,
+/// `^` and `$`, e.g. `^fs.*img$`.
+pub fn main(args: &[&str]) -> i32 {
+    let mut options = Options::default();
+    let mut args = args.iter().copied().skip(1).peekable();
+    while let Some(flags) = args.peek().copied().and_then(|arg| arg.strip_prefix('-')) {
+        if flags.is_empty() {
+            break;
+        }
+        for flag in flags.chars() {
+            match flag {
+                'c' => options.count = true,
+                'i' => options.ignore_case = true,
+                'n' => options.line_numbers = true,
+                'q' => options.quiet = true,
+                'v' => options.invert = true,
+                'x' => options.whole_line = true,
+                _ => {
+                    eprintln!("grep: unknown option -{}\n{}", flag, USAGE);
+                    return EXIT_TROUBLE;
+                }
+            }
+        }
+        args.next();
+    }
+    let pattern = match args.next() {
+        Some(pattern) => Pattern::new(pattern, &options),
+        None => {
+            eprintln!("{}", USAGE);
+            return EXIT_TROUBLE;
+        }
+    };
+    let files: Vec<&str> = args.collect();
+
+    let mut matched = false;
+    let mut trouble = false;
+    let search = |fd: usize, name: &str, matched: &mut bool| {
+        // Lines are prefixed with the file they come from when there is more than one
+        let prefix = match files.len() > 1 {
+            true => Some(name),
+            false => None,
+        };
+        match grep(fd, &pattern, &options, prefix) {
+            Ok(found) => {
+                *matched |= found;
+                Ok(())
+            }
+            Err(e) => Err(alloc::format!("grep: cannot read {}. {:?}", name, e)),
+        }
+    };
+
+    if files.is_empty() {
+        trouble = crate::exit::status(search(STDIN_FD, "-", &mut matched)) != EXIT_SUCCESS;
+    }
+    for &file in files.iter() {
+        let result = sys_open_slice_slow(file, FileMode::READ)
+            .map_err(|e| alloc::format!("grep: cannot open {}. {:?}", file, e))
+            .and_then(|fd| {
+                let result = search(fd, file, &mut matched);
+                sys_close(fd).unwrap();
+                result
+            });
+        if crate::exit::status(result) != EXIT_SUCCESS {
+            trouble = true;
+        }
+        // One match is all -q needs
+        if matched && options.quiet {
+            return EXIT_SUCCESS;
+        }
+    }
+
+    match (trouble, matched) {
+        (true, _) => EXIT_TROUBLE,
+        (false, true) => EXIT_SUCCESS,
+        (false, false) => EXIT_FAILURE,
+    }
+}
+
+// Searches `fd` and returns whether a line matched
+fn grep(
+    fd: usize,
+    pattern: &Pattern,
+    options: &Options,
+    prefix: Option<&str>,
+) -> interface::rv6::Result<bool> {
+    let mut lines = LineReader::new(fd);
+    let mut count = 0;
+    let mut number = 0;
+    while let Some(line) = lines.read_line()? {
+        number += 1;
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        if pattern.matches(text) == options.invert {
+            continue;
+        }
+        count += 1;
+        if options.quiet {
+            return Ok(true);
+        }
+        if options.count {
+            continue;
+        }
+
+        let mut out = String::new();
+        if let Some(name) = prefix {
+            out.push_str(name);
+            out.push(':');
+        }
+        if options.line_numbers {
+            out.push_str(&alloc::format!("{}:", number));
+        }
+        write_all(STDOUT_FD, out.as_bytes())?;
+        write_all(STDOUT_FD, text)?;
+        write_all(STDOUT_FD, b"\n")?;
+    }
+
+    if options.count && !options.quiet {
+        let out = match prefix {
+            Some(name) => alloc::format!("{}:{}\n", name, count),
+            None => alloc::format!("{}\n", count),
+        };
+        write_all(STDOUT_FD, out.as_bytes())?;
+    }
+    Ok(count > 0)
+}
+
+struct Pattern {
+    pattern: Vec<u8>,
+    ignore_case: bool,
+}
+
+impl Pattern {
+    fn new(pattern: &str, options: &Options) -> Self {
+        let mut bytes = Vec::new();
+        if options.whole_line && !pattern.starts_with('^') {
+            bytes.push(b'^');
+        }
+        bytes.extend(pattern.bytes().map(|b| match options.ignore_case {
+            true => b.to_ascii_lowercase(),
+            false => b,
+        }));
+        if options.whole_line && !pattern.ends_with('$') {
+            bytes.push(b'$');
+        }
+        Self {
+            pattern: bytes,
+            ignore_case: options.ignore_case,
+        }
+    }
+
+    fn matches(&self, text: &[u8]) -> bool {
+        let lowered;
+        let text = match self.ignore_case {
+            true => {
+                lowered = text.to_ascii_lowercase();
+                &lowered[..]
+            }
+            false => text,
+        };
+        match &self.pattern[..] {
+            [b'^', pattern @ ..] => match_here(pattern, text),
+            pattern => (0..=text.len()).any(|i| match_here(pattern, &text[i..])),
+        }
+    }
+}
+
+// Whether `pattern` matches the start of `text`
+fn match_here(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => true,
+        [c, b'*', rest @ ..] => match_star(*c, rest, text),
+        [b'$'] => text.is_empty(),
+        [c, rest @ ..] => match text.split_first() {
+            Some((t, text)) if *c == b'.' || c == t => match_here(rest, text),
+            _ => false,
+        },
+    }
+}
+
+// Whether `c*` followed by `pattern` matches the start of `text`, shortest first
+fn match_star(c: u8, pattern: &[u8], mut text: &[u8]) -> bool {
+    loop {
+        if match_here(pattern, text) {
+            return true;
+        }
+        match text.split_first() {
+            Some((t, rest)) if c == b'.' || c == *t => text = rest,
+            _ => return false,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::{write_all, LineReader, STDIN_FD, STDOUT_FD};
+use crate::print;
+use crate::syscalls::{sys_close, sys_open_slice_slow};
+use alloc::string::String;
+use interface::vfs::FileMode;
+
+/// `head [-n lines] [file...]` prints the first lines of each file, 10 by default
+pub fn main(args: &[&str]) -> i32 {
+    let (lines, files) = match parse_args(args, "head") {
+        Ok(parsed) => parsed,
+        Err(e) => return crate::exit::status(Err(e)),
+    };
+    if files.is_empty() {
+        return crate::exit::status(head(STDIN_FD, "-", lines));
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for (i, &file) in files.iter().enumerate() {
+        if files.len() > 1 {
+            let separator = if i == 0 { "" } else { "\n" };
+            print!("{}==> {} <==\n", separator, file);
+        }
+        let result = sys_open_slice_slow(file, FileMode::READ)
+            .map_err(|e| alloc::format!("head: cannot open {}. {:?}", file, e))
+            .and_then(|fd| {
+                let result = head(fd, file, lines);
+                sys_close(fd).unwrap();
+                result
+            });
+        if crate::exit::status(result) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+/// Splits `prog [-n lines] [file...]` into the line count and the files. Shared with tail.
+pub fn parse_args<'a>(args: &'a [&'a str], prog: &str) -> Result<(usize, &'a [&'a str]), String> {
+    match args.get(1) {
+        Some(&"-n") => {
+            let lines = args
+                .get(2)
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| alloc::format!("usage: {} [-n lines] [file...]", prog))?;
+            Ok((lines, &args[3..]))
+        }
+        _ => Ok((10, &args[1..])),
+    }
+}
+
+fn head(fd: usize, name: &str, lines: usize) -> Result<(), String> {
+    let mut reader = LineReader::new(fd);
+    for _ in 0..lines {
+        match reader.read_line() {
+            Ok(Some(line)) => write_all(STDOUT_FD, &line)
+                .map_err(|e| alloc::format!("head: cannot write. {:?}", e))?,
+            Ok(None) => break,
+            Err(e) => return Err(alloc::format!("head: cannot read {}. {:?}", name, e)),
+        }
+    }
+    Ok(())
+}
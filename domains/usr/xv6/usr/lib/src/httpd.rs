@@ -17,10 +17,10 @@ use syscalls::{Heap, Syscall};
 #[macro_use]
 use redhttpd::usrnet::Httpd;
 
-pub fn main(rv6: Box<dyn Rv6>, args: &str) {
-    println!("Starting rv6 httpd with args: {}", args);
+pub fn main(rv6: Box<dyn Rv6>, args: &[&str]) -> i32 {
+    println!("Starting rv6 httpd with args: {:?}", args);
 
-    main_loop(rv6).unwrap();
+    crate::exit::status(main_loop(rv6).map_err(|e| alloc::format!("httpd: {:?}", e)))
 }
 
 fn main_loop(rv6: Box<dyn Rv6>) -> Result<()> {
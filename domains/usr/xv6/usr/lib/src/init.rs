@@ -15,7 +15,7 @@ use interface::rv6::Rv6;
 use interface::vfs::FileMode;
 use syscalls::{Heap, Syscall};
 
-pub fn main(_args: &str) {
+pub fn main(_args: &[&str]) -> i32 {
     // stdout not initialized yet so we can't print it there yet
 
     // Create console device if it not there yet
@@ -36,5 +36,8 @@ pub fn main(_args: &str) {
     assert_eq!(sys_dup(0).unwrap(), 2);
 
     dbg!("Init finished");
-    sys_spawn_domain_slice_slow("/sh", "", &[Some(0), Some(1), Some(2)]).unwrap();
+    // The shell inherits the environment the kernel started us with, e.g. `PATH`
+    sys_spawn_domain_slice_slow("sh", &["sh"], crate::env::vars(), &[Some(0), Some(1), Some(2)])
+        .unwrap();
+    crate::exit::EXIT_SUCCESS
 }
@@ -1,8 +1,9 @@
+use alloc::vec::Vec;
 use core::fmt;
 
 mod error;
 
-use crate::syscalls::sys_write_slice_slow;
+use crate::syscalls::{sys_mmap, sys_read_slice_slow, sys_write_slice_slow};
 
 use error::{ErrorKind, Result};
 
@@ -10,6 +11,100 @@ pub static STDIN_FD: usize = 0;
 pub static STDOUT_FD: usize = 1;
 pub static STDERR_FD: usize = 2;
 
+// Size of the reads of `LineReader` and `copy`
+const CHUNK: usize = 4096;
+
+/// Writes all of `bytes`, which may take more than one `sys_write`
+pub fn write_all(fd: usize, mut bytes: &[u8]) -> interface::rv6::Result<()> {
+    while !bytes.is_empty() {
+        match sys_write_slice_slow(fd, bytes)? {
+            0 => return Err(interface::rv6::ErrorKind::WriteZero),
+            n => bytes = &bytes[n..],
+        }
+    }
+    Ok(())
+}
+
+/// Copies `from` to `to` until the end of `from`, returns the number of bytes copied
+pub fn copy(from: usize, to: usize) -> interface::rv6::Result<usize> {
+    let mut buffer = [0u8; CHUNK];
+    let mut total = 0;
+    loop {
+        match sys_read_slice_slow(from, &mut buffer)? {
+            0 => return Ok(total),
+            n => {
+                write_all(to, &buffer[..n])?;
+                total += n;
+            }
+        }
+    }
+}
+
+/// Hands the whole file to `f` one block at a time, straight from the buffer cache of the file
+/// system. Fails with `InvalidFileType` before calling `f` if `fd` is not a regular file.
+pub fn for_each_block<F>(fd: usize, mut f: F) -> interface::rv6::Result<()>
+where
+    F: FnMut(&[u8]) -> interface::rv6::Result<()>,
+{
+    let mut offset = 0;
+    loop {
+        let block = sys_mmap(fd, offset)?;
+        if block.size() == 0 {
+            return Ok(());
+        }
+        f(block.as_slice())?;
+        offset += block.size();
+    }
+}
+
+/// Reads a file descriptor one line at a time
+pub struct LineReader {
+    fd: usize,
+    buffer: Vec<u8>,
+    // Start of the first line not returned yet
+    start: usize,
+    eof: bool,
+}
+
+impl LineReader {
+    pub fn new(fd: usize) -> Self {
+        Self {
+            fd,
+            buffer: Vec::new(),
+            start: 0,
+            eof: false,
+        }
+    }
+
+    /// Returns the next line with its `\n`, if it has one, or `None` at the end of the file
+    pub fn read_line(&mut self) -> interface::rv6::Result<Option<Vec<u8>>> {
+        loop {
+            let pending = &self.buffer[self.start..];
+            if let Some(i) = pending.iter().position(|&b| b == b'\n') {
+                let line = pending[..i + 1].to_vec();
+                self.start += i + 1;
+                return Ok(Some(line));
+            }
+            if self.eof {
+                let line = match pending.is_empty() {
+                    true => None,
+                    false => Some(pending.to_vec()),
+                };
+                self.start = self.buffer.len();
+                return Ok(line);
+            }
+
+            self.buffer.drain(..self.start);
+            self.start = 0;
+            let mut chunk = [0u8; CHUNK];
+            match sys_read_slice_slow(self.fd, &mut chunk)? {
+                0 => self.eof = true,
+                n => self.buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
 pub fn write_fmt(fd: usize, fmt: fmt::Arguments<'_>) -> Result<()> {
     // Create a shim which translates a Write to a fmt::Write and saves
     // off I/O errors. instead of discarding them
@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+use crate::syscalls::sys_kill;
+use crate::eprintln;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::panic::PanicInfo;
+use interface::rv6::Rv6;
+use syscalls::{Heap, Syscall};
+
+pub fn main(args: &[&str]) -> i32 {
+    let mut args = args.iter().copied().peekable();
+    assert!(args.next().is_some());
+    if args.peek().is_none() {
+        return crate::exit::status(Err(String::from("usage: kill pid...")));
+    }
+
+    // Keep going past pids that can't be killed, but report the failure
+    let mut status = crate::exit::EXIT_SUCCESS;
+    for pid in args {
+        if crate::exit::status(kill(pid)) != crate::exit::EXIT_SUCCESS {
+            status = crate::exit::EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+fn kill(pid: &str) -> Result<(), String> {
+    let pid = pid
+        .parse::<u64>()
+        .map_err(|e| alloc::format!("kill: invalid pid {}. {:?}", pid, e))?;
+    sys_kill(pid).map_err(|e| alloc::format!("kill: cannot kill {}. {:?}", pid, e))?;
+    Ok(())
+}
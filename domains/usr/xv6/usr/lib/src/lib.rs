@@ -2,8 +2,11 @@
 #![forbid(unsafe_code)]
 extern crate alloc;
 
+pub mod env;
+pub mod exit;
 pub mod io;
 pub mod syscalls;
+pub mod thread;
 
 #[macro_use]
 pub mod macros;
@@ -14,15 +17,28 @@ pub use crate::syscalls::init;
 pub mod benchfs;
 pub mod benchnet;
 pub mod benchnvme;
+pub mod cat;
+pub mod chmod;
+pub mod cp;
 pub mod dump_inode;
+pub mod echo;
 pub mod getpid;
+pub mod grep;
+pub mod head;
 pub mod httpd;
 pub mod init;
+pub mod kill;
 pub mod ln;
 pub mod ls;
 pub mod mkdir;
+pub mod mv;
+pub mod pwc;
+pub mod pwd;
 pub mod rm;
 pub mod rv6_testtpm;
 pub mod sleep;
+pub mod stat;
+pub mod sync;
+pub mod tail;
 pub mod uptime;
 pub mod wc;
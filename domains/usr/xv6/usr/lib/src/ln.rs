@@ -5,7 +5,7 @@
 
 extern crate alloc;
 extern crate malloc;
-use crate::syscalls::sys_link_slice_slow;
+use crate::syscalls::{sys_link_slice_slow, sys_symlink_slice_slow};
 use crate::{eprintln, println};
 use alloc::boxed::Box;
 use alloc::string::String;
@@ -13,15 +13,23 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 ln with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 ln with args: {:?}", args);
 
-    let mut args = args.split_whitespace();
+    let mut args = args.iter().copied().peekable();
     assert!(args.next().is_some());
+    let symbolic = args.peek() == Some(&"-s");
+    if symbolic {
+        args.next();
+    }
     let old_path = args.next().unwrap();
     let new_path = args.next().unwrap();
 
-    ln(old_path, new_path).unwrap();
+    crate::exit::status(if symbolic {
+        symlink(old_path, new_path)
+    } else {
+        ln(old_path, new_path)
+    })
 }
 
 fn ln(old_path: &str, new_path: &str) -> Result<(), String> {
@@ -29,3 +37,10 @@ fn ln(old_path: &str, new_path: &str) -> Result<(), String> {
     sys_link_slice_slow(old_path, new_path).map_err(|e| alloc::format!("ln: cannot ln {:?}", e))?;
     Ok(())
 }
+
+fn symlink(target: &str, path: &str) -> Result<(), String> {
+    println!("ln -s <{}> <{}>", target, path);
+    sys_symlink_slice_slow(target, path)
+        .map_err(|e| alloc::format!("ln: cannot create symlink {:?}", e))?;
+    Ok(())
+}
@@ -6,23 +6,25 @@
 extern crate alloc;
 extern crate malloc;
 
-use crate::syscalls::{sys_close, sys_fstat, sys_open_slice_slow, sys_read_slice_slow};
+use crate::syscalls::{
+    sys_close, sys_fstat, sys_open_slice_slow, sys_readdir, sys_readlink_slice_slow,
+};
 use crate::{eprintln, println};
 use alloc::boxed::Box;
 use alloc::string::String;
 use core::panic::PanicInfo;
 use interface::rv6::Rv6;
-use interface::vfs::{DirectoryEntry, DirectoryEntryRef, FileMode, INodeFileType};
+use interface::vfs::{FileMode, FileStat, INodeFileType, Permissions, UtcTime};
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 ls with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 ls with args: {:?}", args);
 
-    let mut args = args.split_whitespace();
+    let mut args = args.iter().copied();
     assert!(args.next().is_some());
-    let path = args.next().or(Some("")).unwrap();
+    let path = args.next().unwrap_or(".");
 
-    ls(path).unwrap();
+    crate::exit::status(ls(path))
 }
 
 fn ls(path: &str) -> Result<(), String> {
@@ -31,40 +33,73 @@ fn ls(path: &str) -> Result<(), String> {
         .map_err(|e| alloc::format!("ls: cannot open {}. {:?}", path, e))?;
     let stat = sys_fstat(fd).map_err(|e| alloc::format!("ls: cannot stat {}. {:?}", path, e))?;
 
-    const DIRENT_SIZE: usize = core::mem::size_of::<DirectoryEntry>();
-    let mut buffer = [0_u8; DIRENT_SIZE];
     match &stat.file_type {
-        INodeFileType::File => {
-            println!(
-                "ls path:{} type:{:?} inum:{} size:{}",
-                path, stat.file_type, stat.inum, stat.size
-            );
-        }
         INodeFileType::Directory => {
-            // Assuming DIRENT_SIZE > 0
-            while sys_read_slice_slow(fd, &mut buffer[..]).unwrap_or(0) == DIRENT_SIZE {
-                let de = DirectoryEntryRef::from_bytes(&buffer[..]);
-                if de.inum == 0 {
-                    continue;
-                }
+            while let Some(de) = sys_readdir(fd)
+                .map_err(|e| alloc::format!("ls: cannot read {}. {:?}", path, e))?
+            {
                 // null-terminated string to String
-                let filename = utils::cstr::to_string(de.name)
+                let filename = utils::cstr::to_string(&de.name)
                     .map_err(|_| String::from("ls: cannot convert filename to utf8 string"))?;
                 let file_path = alloc::format!("{}/{}", path, filename);
-                let file_fd = sys_open_slice_slow(&file_path, FileMode::READ)
-                    .map_err(|e| alloc::format!("ls: cannot open {} {:?}", file_path, e))?;
+                // list symbolic links themselves rather than what they point to
+                if let Ok(target) = sys_readlink_slice_slow(&file_path) {
+                    println!(
+                        "ls: path:{} type:{:?} -> {}",
+                        file_path,
+                        INodeFileType::Symlink,
+                        target
+                    );
+                    continue;
+                }
+                // an entry we may not open shouldn't stop the listing
+                let file_fd = match sys_open_slice_slow(&file_path, FileMode::READ) {
+                    Ok(file_fd) => file_fd,
+                    Err(e) => {
+                        eprintln!("ls: cannot open {} {:?}", file_path, e);
+                        continue;
+                    }
+                };
                 let file_stat = sys_fstat(file_fd)
                     .map_err(|e| alloc::format!("ls: cannot stat {} {:?}", file_path, e))?;
                 sys_close(file_fd)
                     .map_err(|e| alloc::format!("ls: cannot close {} {} {:?}", file_path, fd, e))?;
-                println!(
-                    "ls: path:{} type:{:?} inum:{} size:{}",
-                    file_path, file_stat.file_type, file_stat.inum, file_stat.size
-                );
+                print_stat(&file_path, &file_stat);
             }
         }
-        _ => unimplemented!(),
+        _ => print_stat(path, &stat),
     }
 
     Ok(())
 }
+
+fn print_stat(path: &str, stat: &FileStat) {
+    println!(
+        "ls: path:{} type:{:?} inum:{} size:{} mode:{} uid:{} mtime:{}",
+        path,
+        stat.file_type,
+        stat.inum,
+        stat.size,
+        mode_string(stat.permissions),
+        stat.uid,
+        UtcTime(stat.mtime)
+    );
+}
+
+// e.g. rwxr-xr-x
+pub fn mode_string(permissions: Permissions) -> String {
+    let bits = [
+        (Permissions::OWNER_READ, 'r'),
+        (Permissions::OWNER_WRITE, 'w'),
+        (Permissions::OWNER_EXEC, 'x'),
+        (Permissions::GROUP_READ, 'r'),
+        (Permissions::GROUP_WRITE, 'w'),
+        (Permissions::GROUP_EXEC, 'x'),
+        (Permissions::OTHER_READ, 'r'),
+        (Permissions::OTHER_WRITE, 'w'),
+        (Permissions::OTHER_EXEC, 'x'),
+    ];
+    bits.iter()
+        .map(|(bit, c)| if permissions.contains(*bit) { *c } else { '-' })
+        .collect()
+}
@@ -13,14 +13,14 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 mkdir with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 mkdir with args: {:?}", args);
 
-    let mut args = args.split_whitespace();
+    let mut args = args.iter().copied();
     assert!(args.next().is_some());
     let path = args.next().unwrap();
 
-    mkdir(path).unwrap();
+    crate::exit::status(mkdir(path))
 }
 
 fn mkdir(path: &str) -> Result<(), String> {
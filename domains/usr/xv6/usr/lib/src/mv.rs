@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+use crate::syscalls::{sys_close, sys_fstat, sys_open_slice_slow, sys_rename_slice_slow};
+use crate::eprintln;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::panic::PanicInfo;
+use interface::rv6::Rv6;
+use interface::vfs::{FileMode, INodeFileType};
+use syscalls::{Heap, Syscall};
+
+pub fn main(args: &[&str]) -> i32 {
+    // `mv src dst`, or `mv src... dir` to move several files into a directory
+    let (new_path, old_paths) = match args[1..].split_last() {
+        Some((new_path, old_paths)) if !old_paths.is_empty() => (*new_path, old_paths),
+        _ => return crate::exit::status(Err(String::from("usage: mv src... dst"))),
+    };
+    if old_paths.len() > 1 && !is_directory(new_path) {
+        return crate::exit::status(Err(alloc::format!("mv: {} is not a directory", new_path)));
+    }
+
+    // Keep going past files that can't be moved, but report the failure
+    let mut status = crate::exit::EXIT_SUCCESS;
+    for &old_path in old_paths {
+        if crate::exit::status(mv(old_path, new_path)) != crate::exit::EXIT_SUCCESS {
+            status = crate::exit::EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+pub fn is_directory(path: &str) -> bool {
+    match sys_open_slice_slow(path, FileMode::READ) {
+        Ok(fd) => {
+            let stat = sys_fstat(fd);
+            sys_close(fd).unwrap();
+            stat.map_or(false, |stat| stat.file_type == INodeFileType::Directory)
+        }
+        Err(_) => false,
+    }
+}
+
+fn mv(old_path: &str, new_path: &str) -> Result<(), String> {
+    // Moving into a directory keeps the file name
+    let new_path = if is_directory(new_path) {
+        let name = old_path.trim_end_matches('/').rsplit('/').next().unwrap();
+        alloc::format!("{}/{}", new_path.trim_end_matches('/'), name)
+    } else {
+        String::from(new_path)
+    };
+
+    sys_rename_slice_slow(old_path, &new_path)
+        .map_err(|e| alloc::format!("mv: cannot move {} to {}. {:?}", old_path, new_path, e))?;
+    Ok(())
+}
@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::eprintln;
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::println;
+use crate::syscalls::{sys_close, sys_open_slice_slow};
+use crate::thread;
+use crate::wc::{count, Counts};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use interface::vfs::FileMode;
+use libsyscalls::sync::{CondVar, SleepMutex};
+
+// Most threads counting at the same time
+const WORKERS: usize = 4;
+
+const USAGE: &str = "usage: pwc file...";
+
+struct Work {
+    files: Vec<String>,
+    // The next file a worker picks up
+    next: usize,
+    // What `wc` would print for each file, filled in by the workers in any order
+    results: Vec<Option<Result<Counts, String>>>,
+}
+
+/// `pwc files...` counts like `wc`, but counts up to `WORKERS` files at the same time. The
+/// results are printed in the order of the files as soon as they are ready.
+pub fn main(args: &[&str]) -> i32 {
+    let files: Vec<String> = args[1..].iter().copied().map(String::from).collect();
+    if files.is_empty() {
+        eprintln!("{}", USAGE);
+        return EXIT_FAILURE;
+    }
+
+    let results = files.iter().map(|_| None).collect();
+    let work = Arc::new((
+        SleepMutex::new(Work {
+            files,
+            next: 0,
+            results,
+        }),
+        CondVar::new(),
+    ));
+    let len = work.0.lock().files.len();
+
+    let mut workers = Vec::new();
+    for i in 0..core::cmp::min(WORKERS, len) {
+        let work = work.clone();
+        match thread::spawn(&alloc::format!("pwc worker {}", i), move || worker(&work)) {
+            Ok(worker) => workers.push(worker),
+            Err(e) => eprintln!("pwc: cannot start a worker. {:?}", e),
+        }
+    }
+    if workers.is_empty() {
+        return EXIT_FAILURE;
+    }
+
+    let mut status = EXIT_SUCCESS;
+    let (lock, ready) = &*work;
+    for i in 0..len {
+        let mut work = lock.lock();
+        while work.results[i].is_none() {
+            work = ready.wait(work);
+        }
+        let name = work.files[i].clone();
+        let result = work.results[i].take().unwrap();
+        // Print without the lock, the workers are waiting for it
+        drop(work);
+        match result {
+            Ok(counts) => println!("pwc: {} name:{}", counts, name),
+            Err(e) => {
+                eprintln!("{}", e);
+                status = EXIT_FAILURE;
+            }
+        }
+    }
+
+    for worker in workers {
+        if worker.join().is_err() {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+// Counts files until there are none left
+fn worker(work: &(SleepMutex<Work>, CondVar)) {
+    let (lock, ready) = work;
+    loop {
+        let mut work = lock.lock();
+        let i = work.next;
+        if i == work.files.len() {
+            return;
+        }
+        work.next += 1;
+        let file = work.files[i].clone();
+        drop(work);
+
+        let result = sys_open_slice_slow(&file, FileMode::READ)
+            .map_err(|e| alloc::format!("pwc: cannot open {}. {:?}", file, e))
+            .map(|fd| {
+                let counts = count(fd);
+                sys_close(fd).unwrap();
+                counts
+            });
+        lock.lock().results[i] = Some(result);
+        ready.wakeup_all();
+    }
+}
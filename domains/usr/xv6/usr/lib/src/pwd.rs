@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+use crate::syscalls::sys_getcwd_slice_slow;
+use crate::{eprintln, println};
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::panic::PanicInfo;
+use interface::rv6::Rv6;
+use syscalls::{Heap, Syscall};
+
+pub fn main(_args: &[&str]) -> i32 {
+    crate::exit::status(pwd())
+}
+
+fn pwd() -> Result<(), String> {
+    let cwd = sys_getcwd_slice_slow().map_err(|e| alloc::format!("pwd: cannot getcwd. {:?}", e))?;
+    println!("{}", cwd);
+    Ok(())
+}
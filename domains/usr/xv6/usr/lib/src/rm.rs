@@ -13,14 +13,14 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(args: &str) {
-    println!("Starting rv6 rm with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 rm with args: {:?}", args);
 
-    let mut args = args.split_whitespace();
+    let mut args = args.iter().copied();
     assert!(args.next().is_some());
     let path = args.next().unwrap();
 
-    rm(path).unwrap();
+    crate::exit::status(rm(path))
 }
 
 fn rm(path: &str) -> Result<(), String> {
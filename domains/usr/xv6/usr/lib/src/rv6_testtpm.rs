@@ -11,8 +11,9 @@ use core::panic::PanicInfo;
 use interface::rv6::Rv6;
 use syscalls::{Heap, Syscall};
 
-pub fn main(rv6: Box<dyn interface::rv6::Rv6>, args: &str) {
-    println!("Starting rv6 testtpm with args: {}", args);
+pub fn main(rv6: Box<dyn interface::rv6::Rv6>, args: &[&str]) -> i32 {
+    println!("Starting rv6 testtpm with args: {:?}", args);
 
     libbenchtpm::test_tpm(&*rv6.get_usrtpm().unwrap());
+    crate::exit::EXIT_SUCCESS
 }
@@ -15,14 +15,18 @@ use crate::syscalls::sys_sleep;
 use crate::{eprintln, println};
 use interface::rv6::Rv6;
 
-pub fn main(args: &str) {
-    println!("Starting rv6 sleep with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 sleep with args: {:?}", args);
 
-    let mut args = args.split_whitespace();
+    let mut args = args.iter().copied();
     assert!(args.next().is_some());
     let ns = args.next().or(Some("")).unwrap();
 
-    sleep(ns.parse::<u64>().unwrap()).unwrap();
+    crate::exit::status(
+        ns.parse::<u64>()
+            .map_err(|e| alloc::format!("sleep: invalid duration {}. {:?}", ns, e))
+            .and_then(sleep),
+    )
 }
 
 fn sleep(ns: u64) -> Result<(), String> {
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::ls::mode_string;
+use crate::println;
+use crate::syscalls::{sys_close, sys_fstat, sys_open_slice_slow, sys_readlink_slice_slow};
+use alloc::string::String;
+use interface::vfs::{FileMode, UtcTime};
+
+/// `stat file...` prints the inode of each file
+pub fn main(args: &[&str]) -> i32 {
+    if args.len() < 2 {
+        return crate::exit::status(Err(String::from("usage: stat file...")));
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for &file in args[1..].iter() {
+        if crate::exit::status(stat(file)) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+fn stat(path: &str) -> Result<(), String> {
+    let fd = sys_open_slice_slow(path, FileMode::READ)
+        .map_err(|e| alloc::format!("stat: cannot open {}. {:?}", path, e))?;
+    let stat = sys_fstat(fd);
+    sys_close(fd).unwrap();
+    let stat = stat.map_err(|e| alloc::format!("stat: cannot stat {}. {:?}", path, e))?;
+
+    // Opening follows symbolic links, so say where it led
+    match sys_readlink_slice_slow(path) {
+        Ok(target) => println!("  File: {} -> {}", path, target),
+        Err(_) => println!("  File: {}", path),
+    }
+    println!(
+        "  Size: {}  Type: {:?}  Inode: {}  Links: {}  Device: {}",
+        stat.size, stat.file_type, stat.inum, stat.nlink, stat.device
+    );
+    println!(
+        "Access: {}  Uid: {}",
+        mode_string(stat.permissions),
+        stat.uid
+    );
+    println!("Modify: {}", UtcTime(stat.mtime));
+    println!("Change: {}", UtcTime(stat.ctime));
+    Ok(())
+}
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+use crate::syscalls::sys_sync;
+use crate::eprintln;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::panic::PanicInfo;
+use interface::rv6::Rv6;
+use syscalls::{Heap, Syscall};
+
+pub fn main(_args: &[&str]) -> i32 {
+    crate::exit::status(sync())
+}
+
+fn sync() -> Result<(), String> {
+    sys_sync().map_err(|e| alloc::format!("sync failed {:?}", e))?;
+    Ok(())
+}
@@ -4,11 +4,12 @@
 /// &str arguments to RRefVec<u8>. They are Slower than the `sys_xxx` variants
 /// but they are easier to use and good for prototyping.
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::vec::Vec;
-use interface::rref::RRefVec;
+use interface::rref::{RRefSharedVec, RRefVec};
 use spin::Once;
-use interface::rv6::{FileMode, FileStat, Result, Rv6, Thread};
-use interface::vfs::{UsrVFS, NFILE};
+use interface::rv6::{ErrorKind, FileMode, FileStat, Result, Rv6, Thread};
+use interface::vfs::{DirectoryEntry, FsStats, Permissions, SeekFrom, UsrVFS, MAXPATH, NFILE};
 
 static SYSCALL: Once<Box<dyn Rv6>> = Once::new();
 static FS: Once<Box<dyn UsrVFS>> = Once::new();
@@ -17,42 +18,95 @@ pub fn init(s: Box<dyn Rv6>) {
     let fs = s.as_vfs().unwrap();
     FS.call_once(|| fs);
     SYSCALL.call_once(|| s);
+    // Lets `sys_kill` unwind the thread while it runs our code and is the only thread of the
+    // program, see `thread::spawn`
+    libsyscalls::syscalls::sys_set_interruptible(true);
 }
 
-pub fn sys_spawn_domain_slice_slow(
+// A system call of a killed thread fails with `Killed`. The thread then unwinds out of the
+// domain as if it had panicked.
+fn killable<T>(result: Result<T>) -> Result<T> {
+    if let Err(ErrorKind::Killed) = result {
+        crate::exit::unwind();
+    }
+    result
+}
+
+pub fn sys_spawn_thread_slice_slow(
+    name: &str,
+    func: Box<dyn FnOnce() + Send>,
+) -> Result<Box<dyn Thread>> {
+    sys_spawn_thread(RRefVec::from_slice(name.as_bytes()), func)
+}
+
+// The thread shares the file descriptors of the calling program
+pub fn sys_spawn_thread(
+    name: RRefVec<u8>,
+    func: Box<dyn FnOnce() + Send>,
+) -> Result<Box<dyn Thread>> {
+    killable(SYSCALL.r#try().unwrap().sys_spawn_thread(name, func)?)
+}
+
+// Waits for `thread` to exit and returns its exit status
+pub fn sys_join(thread: &dyn Thread) -> Result<i32> {
+    killable(thread.join()?)
+}
+
+pub fn sys_spawn_domain_slice_slow<A: AsRef<str>, E: AsRef<str>>(
     path: &str,
-    args: &str,
+    argv: &[A],
+    envp: &[E],
     fds: &[Option<usize>],
 ) -> Result<Box<dyn Thread>> {
     sys_spawn_domain(
         RRefVec::from_slice(path.as_bytes()),
-        RRefVec::from_slice(args.as_bytes()),
+        pack_strs(argv)?,
+        pack_strs(envp)?,
         fds,
     )
 }
 
+// Joins strings into the NUL terminated list `sys_spawn_domain` takes for `argv` and `envp`
+fn pack_strs<S: AsRef<str>>(strs: &[S]) -> Result<RRefVec<u8>> {
+    let mut buffer = Vec::new();
+    for s in strs {
+        let s = s.as_ref();
+        if s.contains('\0') {
+            return Err(ErrorKind::InvalidParameter);
+        }
+        buffer.extend_from_slice(s.as_bytes());
+        buffer.push(0);
+    }
+    Ok(RRefVec::from_slice(&buffer))
+}
+
 pub fn sys_spawn_domain(
     path: RRefVec<u8>,
-    args: RRefVec<u8>,
+    argv: RRefVec<u8>,
+    envp: RRefVec<u8>,
     fds: &[Option<usize>],
 ) -> Result<Box<dyn Thread>> {
     assert!(fds.len() <= NFILE);
     let mut arr: [Option<usize>; NFILE] = array_init::array_init(|_| None);
     arr[..fds.len()].clone_from_slice(&fds);
     let rv6 = &**SYSCALL.r#try().unwrap();
-    rv6.sys_spawn_domain(rv6.clone_rv6()?, path, args, arr)?
+    killable(rv6.sys_spawn_domain(rv6.clone_rv6()?, path, argv, envp, arr)?)
 }
 
 pub fn sys_getpid() -> Result<u64> {
-    SYSCALL.r#try().unwrap().sys_getpid()?
+    killable(SYSCALL.r#try().unwrap().sys_getpid()?)
+}
+
+pub fn sys_kill(pid: u64) -> Result<()> {
+    SYSCALL.r#try().unwrap().sys_kill(pid)?
 }
 
 pub fn sys_uptime() -> Result<u64> {
-    SYSCALL.r#try().unwrap().sys_uptime()?
+    killable(SYSCALL.r#try().unwrap().sys_uptime()?)
 }
 
 pub fn sys_sleep(ns: u64) -> Result<()> {
-    SYSCALL.r#try().unwrap().sys_sleep(ns)?
+    killable(SYSCALL.r#try().unwrap().sys_sleep(ns)?)
 }
 
 pub fn sys_open_slice_slow(path: &str, mode: FileMode) -> Result<usize> {
@@ -61,11 +115,11 @@ pub fn sys_open_slice_slow(path: &str, mode: FileMode) -> Result<usize> {
 }
 
 pub fn sys_open(path: RRefVec<u8>, mode: FileMode) -> Result<(usize, RRefVec<u8>)> {
-    FS.r#try().unwrap().sys_open(path, mode)?
+    killable(FS.r#try().unwrap().sys_open(path, mode)?)
 }
 
 pub fn sys_close(fd: usize) -> Result<()> {
-    FS.r#try().unwrap().sys_close(fd)?
+    killable(FS.r#try().unwrap().sys_close(fd)?)
 }
 
 // See comment for `sys_write_slice_slow`
@@ -79,7 +133,7 @@ pub fn sys_read_slice_slow(fd: usize, buffer: &mut [u8]) -> Result<usize> {
 }
 
 pub fn sys_read(fd: usize, buffer: RRefVec<u8>) -> Result<(usize, RRefVec<u8>)> {
-    FS.r#try().unwrap().sys_read(fd, buffer)?
+    killable(FS.r#try().unwrap().sys_read(fd, buffer)?)
 }
 
 pub fn sys_write_slice_slow(fd: usize, buffer: &[u8]) -> Result<usize> {
@@ -89,11 +143,11 @@ pub fn sys_write_slice_slow(fd: usize, buffer: &[u8]) -> Result<usize> {
 }
 
 pub fn sys_write(fd: usize, buffer: RRefVec<u8>) -> Result<(usize, RRefVec<u8>)> {
-    FS.r#try().unwrap().sys_write(fd, buffer)?
+    killable(FS.r#try().unwrap().sys_write(fd, buffer)?)
 }
 
 pub fn sys_fstat(fd: usize) -> Result<FileStat> {
-    FS.r#try().unwrap().sys_fstat(fd)?
+    killable(FS.r#try().unwrap().sys_fstat(fd)?)
 }
 
 pub fn sys_mknod_slice_slow(path: &str, major: i16, minor: i16) -> Result<()> {
@@ -101,15 +155,27 @@ pub fn sys_mknod_slice_slow(path: &str, major: i16, minor: i16) -> Result<()> {
 }
 
 pub fn sys_mknod(path: RRefVec<u8>, major: i16, minor: i16) -> Result<()> {
-    FS.r#try().unwrap().sys_mknod(path, major, minor)?
+    killable(FS.r#try().unwrap().sys_mknod(path, major, minor)?)
 }
 
 pub fn sys_dup(fd: usize) -> Result<usize> {
-    FS.r#try().unwrap().sys_dup(fd)?
+    killable(FS.r#try().unwrap().sys_dup(fd)?)
+}
+
+pub fn sys_dup2(fd: usize, new_fd: usize) -> Result<usize> {
+    killable(FS.r#try().unwrap().sys_dup2(fd, new_fd)?)
+}
+
+pub fn sys_set_close_on_exec(fd: usize, close_on_exec: bool) -> Result<()> {
+    killable(
+        FS.r#try()
+            .unwrap()
+            .sys_set_close_on_exec(fd, close_on_exec)?,
+    )
 }
 
 pub fn sys_pipe() -> Result<(usize, usize)> {
-    FS.r#try().unwrap().sys_pipe()?
+    killable(FS.r#try().unwrap().sys_pipe()?)
 }
 
 pub fn sys_link_slice_slow(old_path: &str, new_path: &str) -> Result<()> {
@@ -120,7 +186,7 @@ pub fn sys_link_slice_slow(old_path: &str, new_path: &str) -> Result<()> {
 }
 
 pub fn sys_link(old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> Result<()> {
-    FS.r#try().unwrap().sys_link(old_path, new_path)?
+    killable(FS.r#try().unwrap().sys_link(old_path, new_path)?)
 }
 
 pub fn sys_unlink_slice_slow(path: &str) -> Result<()> {
@@ -128,7 +194,7 @@ pub fn sys_unlink_slice_slow(path: &str) -> Result<()> {
 }
 
 pub fn sys_unlink(path: RRefVec<u8>) -> Result<()> {
-    FS.r#try().unwrap().sys_unlink(path)?
+    killable(FS.r#try().unwrap().sys_unlink(path)?)
 }
 
 pub fn sys_mkdir_slice_slow(path: &str) -> Result<()> {
@@ -136,13 +202,96 @@ pub fn sys_mkdir_slice_slow(path: &str) -> Result<()> {
 }
 
 pub fn sys_mkdir(path: RRefVec<u8>) -> Result<()> {
-    FS.r#try().unwrap().sys_mkdir(path)?
+    killable(FS.r#try().unwrap().sys_mkdir(path)?)
+}
+
+// Returns the new offset
+pub fn sys_seek(fd: usize, pos: SeekFrom) -> Result<usize> {
+    killable(FS.r#try().unwrap().sys_seek(fd, pos)?)
+}
+
+pub fn sys_ftruncate(fd: usize, size: u64) -> Result<()> {
+    killable(FS.r#try().unwrap().sys_ftruncate(fd, size)?)
+}
+
+// Returns a read-only view of the file from `offset` to the end of the block holding it, empty
+// past the end of the file. Later changes to the file do not show up in it.
+pub fn sys_mmap(fd: usize, offset: usize) -> Result<RRefSharedVec<u8>> {
+    killable(FS.r#try().unwrap().sys_mmap(fd, offset)?)
+}
+
+// Returns the next entry of the directory, or None once all entries were read
+pub fn sys_readdir(fd: usize) -> Result<Option<DirectoryEntry>> {
+    killable(FS.r#try().unwrap().sys_readdir(fd)?)
+}
+
+pub fn sys_rename_slice_slow(old_path: &str, new_path: &str) -> Result<()> {
+    sys_rename(
+        RRefVec::from_slice(old_path.as_bytes()),
+        RRefVec::from_slice(new_path.as_bytes()),
+    )
+}
+
+pub fn sys_rename(old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> Result<()> {
+    killable(FS.r#try().unwrap().sys_rename(old_path, new_path)?)
+}
+
+pub fn sys_chdir_slice_slow(path: &str) -> Result<()> {
+    sys_chdir(RRefVec::from_slice(path.as_bytes()))
+}
+
+pub fn sys_chdir(path: RRefVec<u8>) -> Result<()> {
+    killable(FS.r#try().unwrap().sys_chdir(path)?)
+}
+
+pub fn sys_getcwd_slice_slow() -> Result<String> {
+    let (len, buffer) = sys_getcwd(RRefVec::new(0, MAXPATH))?;
+    String::from_utf8(buffer.as_slice()[..len].to_vec()).map_err(|_| ErrorKind::Utf8Error)
+}
+
+pub fn sys_getcwd(buffer: RRefVec<u8>) -> Result<(usize, RRefVec<u8>)> {
+    killable(FS.r#try().unwrap().sys_getcwd(buffer)?)
+}
+
+pub fn sys_symlink_slice_slow(target: &str, path: &str) -> Result<()> {
+    sys_symlink(
+        RRefVec::from_slice(target.as_bytes()),
+        RRefVec::from_slice(path.as_bytes()),
+    )
+}
+
+pub fn sys_symlink(target: RRefVec<u8>, path: RRefVec<u8>) -> Result<()> {
+    killable(FS.r#try().unwrap().sys_symlink(target, path)?)
+}
+
+pub fn sys_readlink_slice_slow(path: &str) -> Result<String> {
+    let (len, buffer) = sys_readlink(
+        RRefVec::from_slice(path.as_bytes()),
+        RRefVec::new(0, MAXPATH),
+    )?;
+    String::from_utf8(buffer.as_slice()[..len].to_vec()).map_err(|_| ErrorKind::Utf8Error)
+}
+
+pub fn sys_readlink(path: RRefVec<u8>, buffer: RRefVec<u8>) -> Result<(usize, RRefVec<u8>)> {
+    killable(FS.r#try().unwrap().sys_readlink(path, buffer)?)
+}
+
+pub fn sys_chmod_slice_slow(path: &str, permissions: Permissions) -> Result<()> {
+    sys_chmod(RRefVec::from_slice(path.as_bytes()), permissions)
+}
+
+pub fn sys_chmod(path: RRefVec<u8>, permissions: Permissions) -> Result<()> {
+    killable(FS.r#try().unwrap().sys_chmod(path, permissions)?)
+}
+
+pub fn sys_sync() -> Result<()> {
+    killable(FS.r#try().unwrap().sys_sync()?)
 }
 
-pub fn sys_seek(fs: usize, offset: usize) -> Result<()> {
-    FS.r#try().unwrap().sys_seek(fs, offset)?
+pub fn sys_fs_stats() -> Result<FsStats> {
+    killable(FS.r#try().unwrap().sys_fs_stats()?)
 }
 
 pub fn sys_dump_inode() -> Result<()> {
-    FS.r#try().unwrap().sys_dump_inode()?
+    killable(FS.r#try().unwrap().sys_dump_inode()?)
 }
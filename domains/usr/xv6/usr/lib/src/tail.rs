@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+#![forbid(unsafe_code)]
+#![feature(untagged_unions)]
+
+extern crate alloc;
+extern crate malloc;
+
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::{write_all, LineReader, STDIN_FD, STDOUT_FD};
+use crate::print;
+use crate::syscalls::{sys_close, sys_open_slice_slow};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use interface::vfs::FileMode;
+
+/// `tail [-n lines] [file...]` prints the last lines of each file, 10 by default
+pub fn main(args: &[&str]) -> i32 {
+    let (lines, files) = match crate::head::parse_args(args, "tail") {
+        Ok(parsed) => parsed,
+        Err(e) => return crate::exit::status(Err(e)),
+    };
+    if files.is_empty() {
+        return crate::exit::status(tail(STDIN_FD, "-", lines));
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for (i, &file) in files.iter().enumerate() {
+        if files.len() > 1 {
+            let separator = if i == 0 { "" } else { "\n" };
+            print!("{}==> {} <==\n", separator, file);
+        }
+        let result = sys_open_slice_slow(file, FileMode::READ)
+            .map_err(|e| alloc::format!("tail: cannot open {}. {:?}", file, e))
+            .and_then(|fd| {
+                let result = tail(fd, file, lines);
+                sys_close(fd).unwrap();
+                result
+            });
+        if crate::exit::status(result) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
+    }
+    status
+}
+
+fn tail(fd: usize, name: &str, lines: usize) -> Result<(), String> {
+    // Only the last `lines` lines are kept while reading, so pipes of any length work
+    let mut last = VecDeque::new();
+    let mut reader = LineReader::new(fd);
+    while let Some(line) = reader
+        .read_line()
+        .map_err(|e| alloc::format!("tail: cannot read {}. {:?}", name, e))?
+    {
+        if lines == 0 {
+            continue;
+        }
+        if last.len() == lines {
+            last.pop_front();
+        }
+        last.push_back(line);
+    }
+
+    for line in last.iter() {
+        write_all(STDOUT_FD, line).map_err(|e| alloc::format!("tail: cannot write. {:?}", e))?;
+    }
+    Ok(())
+}
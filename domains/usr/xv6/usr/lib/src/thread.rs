@@ -0,0 +1,77 @@
+//! Threads of rv6 programs
+//!
+//! `spawn` starts a thread that shares the file descriptors of the program. The threads wait
+//! for each other with `libsyscalls::sync::{SleepMutex, CondVar}`, which sleep in the kernel
+//! instead of spinning.
+//!
+//! A killed program with a single thread is unwound right away, wherever it runs. Once it has
+//! several threads, the kernel can't tell whether a thread holds a lock another one will need,
+//! so they are only unwound at their next system call until the program is down to one thread
+//! again and joins the others.
+
+use crate::syscalls::{sys_join, sys_spawn_thread_slice_slow};
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use interface::rv6::{Result, Thread};
+use libsyscalls::sync::SpinMutex;
+use libsyscalls::syscalls::sys_set_interruptible;
+
+/// A running thread, see `spawn`
+pub struct JoinHandle<T> {
+    thread: Box<dyn Thread>,
+    result: Arc<SpinMutex<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Waits for the thread to finish and returns what it returned. If it panicked or was
+    /// killed, returns its exit status instead.
+    pub fn join(self) -> core::result::Result<T, i32> {
+        let status = sys_join(&*self.thread).unwrap();
+        interruptible_if_alone();
+        self.result.lock().take().ok_or(status)
+    }
+}
+
+// Threads of the program that are still running, including the one that started it
+static RUNNING: AtomicUsize = AtomicUsize::new(1);
+
+// Called when a thread leaves the program. Returns whether it was the last one.
+pub(crate) fn leave() -> bool {
+    RUNNING.fetch_sub(1, Ordering::SeqCst) == 1
+}
+
+// Lets the kernel unwind the calling thread right away again once it is the only one left
+fn interruptible_if_alone() {
+    if RUNNING.load(Ordering::SeqCst) == 1 {
+        sys_set_interruptible(true);
+    }
+}
+
+/// Starts a thread named `name` that runs `f`
+pub fn spawn<T, F>(name: &str, f: F) -> Result<JoinHandle<T>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let result = Arc::new(SpinMutex::new(None));
+    let thread_result = result.clone();
+    // Neither this thread nor the new one may be unwound while the other could be waiting for a
+    // lock it holds
+    sys_set_interruptible(false);
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    let thread = sys_spawn_thread_slice_slow(
+        name,
+        Box::new(move || {
+            let value = f();
+            *thread_result.lock() = Some(value);
+            leave();
+        }),
+    )
+    .map_err(|e| {
+        leave();
+        interruptible_if_alone();
+        e
+    })?;
+    Ok(JoinHandle { thread, result })
+}
@@ -15,10 +15,10 @@ use crate::syscalls::sys_uptime;
 use crate::{eprintln, println};
 use interface::rv6::Rv6;
 
-pub fn main(args: &str) {
-    println!("Starting rv6 uptime with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 uptime with args: {:?}", args);
 
-    uptime().unwrap();
+    crate::exit::status(uptime())
 }
 
 fn uptime() -> Result<(), String> {
@@ -6,63 +6,111 @@
 extern crate alloc;
 extern crate malloc;
 
+use crate::exit::{EXIT_FAILURE, EXIT_SUCCESS};
+use crate::io::for_each_block;
 use crate::println;
 use crate::syscalls::{
     sys_close, sys_fstat, sys_open_slice_slow, sys_read_slice_slow, sys_write_slice_slow,
 };
 use alloc::boxed::Box;
+use alloc::string::String;
 use core::panic::PanicInfo;
+use interface::rv6::ErrorKind;
 use interface::vfs::FileMode;
 
-pub fn main(args: &str) {
-    println!("Starting rv6 wc with args: {}", args);
+pub fn main(args: &[&str]) -> i32 {
+    println!("Starting rv6 wc with args: {:?}", args);
 
-    let mut args = args.split_whitespace().peekable();
+    let mut args = args.iter().copied().peekable();
     assert!(args.next().is_some());
 
     if args.peek().is_none() {
         // Read from STDIN
-        wc(0, "");
+        return crate::exit::status(wc(0, "").map_err(String::from));
     }
 
+    // Keep going past files that can't be counted, but report the failure
+    let mut status = EXIT_SUCCESS;
     for arg in args {
-        let fd = sys_open_slice_slow(arg, FileMode::READ).unwrap();
-        wc(fd, arg).unwrap();
-        sys_close(fd);
+        let result = sys_open_slice_slow(arg, FileMode::READ)
+            .map_err(|e| alloc::format!("wc: cannot open {}. {:?}", arg, e))
+            .and_then(|fd| {
+                let result = wc(fd, arg).map_err(String::from);
+                sys_close(fd);
+                result
+            });
+        if crate::exit::status(result) != EXIT_SUCCESS {
+            status = EXIT_FAILURE;
+        }
     }
+    status
 }
 
-fn wc(fd: usize, name: &str) -> Result<(), &'static str> {
-    let mut line_cnt = 0;
-    let mut word_cnt = 0;
-    let mut char_cnt = 0;
-    let mut in_word = false;
-
-    let mut buff = [0u8; 512];
-    loop {
-        let bytes_read = sys_read_slice_slow(fd, &mut buff).unwrap();
-        if bytes_read == 0 {
-            break;
-        }
+#[derive(Default)]
+pub(crate) struct Counts {
+    lines: usize,
+    words: usize,
+    chars: usize,
+    in_word: bool,
+}
 
-        for c in &buff[..bytes_read] {
+impl Counts {
+    fn add(&mut self, bytes: &[u8]) {
+        for c in bytes {
             let c = *c as char;
-            char_cnt += 1;
+            self.chars += 1;
             if c == '\n' {
-                line_cnt += 1;
+                self.lines += 1;
             }
             if c.is_ascii_whitespace() {
-                in_word = false;
-            } else if !in_word {
-                word_cnt += 1;
-                in_word = true;
+                self.in_word = false;
+            } else if !self.in_word {
+                self.words += 1;
+                self.in_word = true;
             }
         }
     }
+}
+
+impl core::fmt::Display for Counts {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "line:{} word:{} char:{}",
+            self.lines, self.words, self.chars
+        )
+    }
+}
 
-    println!(
-        "wc: line:{} word:{} char:{} name:{}",
-        line_cnt, word_cnt, char_cnt, name
-    );
+fn wc(fd: usize, name: &str) -> Result<(), &'static str> {
+    println!("wc: {} name:{}", count(fd), name);
     Ok(())
 }
+
+// Counts what is left to read from `fd`
+pub(crate) fn count(fd: usize) -> Counts {
+    let mut counts = Counts::default();
+
+    // Regular files are counted in the buffer cache, anything else (e.g. a pipe) is read in
+    // chunks
+    let mapped = for_each_block(fd, |block| {
+        counts.add(block);
+        Ok(())
+    });
+    match mapped {
+        Ok(()) => {}
+        Err(ErrorKind::InvalidFileType) => {
+            let mut buff = [0u8; 512];
+            loop {
+                let bytes_read = sys_read_slice_slow(fd, &mut buff).unwrap();
+                if bytes_read == 0 {
+                    break;
+                }
+                counts.add(&buff[..bytes_read]);
+            }
+        }
+        Err(e) => panic!("wc: cannot map the file. {:?}", e),
+    }
+
+    counts
+}
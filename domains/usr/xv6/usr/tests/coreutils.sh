@@ -0,0 +1,53 @@
+# Checks the rv6 coreutils against the files rv6-mkfs puts in the image, run by
+# `make test-coreutils`. Each failed check prints a line starting with FAIL.
+echo coreutils: start
+
+echo hello world | grep -qx 'hello world' || echo FAIL: echo
+echo -n a b | grep -qx 'a b' || echo FAIL: echo -n
+
+cat fruits.txt | grep -c '' | grep -qx 12 || echo FAIL: cat
+cat fruits.txt fruits.txt | grep -c '' | grep -qx 24 || echo FAIL: cat of two files
+cat < fruits.txt | grep -qx fig || echo FAIL: cat of stdin
+cat missing.txt && echo FAIL: cat of a missing file
+
+grep -c an fruits.txt | grep -qx 2 || echo FAIL: grep -c
+grep -ic apple fruits.txt | grep -qx 2 || echo FAIL: grep -i
+grep -vc e fruits.txt | grep -qx 4 || echo FAIL: grep -v
+grep -n '^fig$' fruits.txt | grep -qx 6:fig || echo FAIL: grep -n
+grep -x 'a.*e' fruits.txt | grep -qx apple || echo FAIL: grep -x
+grep '^k' fruits.txt fruits.txt | grep -c fruits.txt:kiwi | grep -qx 2 || echo FAIL: grep of two files
+grep -q zzz fruits.txt && echo FAIL: grep without a match
+grep -q apple missing.txt; echo $? | grep -qx 2 || echo FAIL: grep of a missing file
+
+head fruits.txt | grep -c '' | grep -qx 10 || echo FAIL: head
+head -n 2 fruits.txt | tail -n 1 | grep -qx banana || echo FAIL: head -n
+head -n 0 fruits.txt | grep -c '' | grep -qx 0 || echo FAIL: head -n 0
+tail fruits.txt | head -n 1 | grep -qx cherry || echo FAIL: tail
+tail -n 1 fruits.txt | grep -qx 'Apple pie' || echo FAIL: tail -n
+cat fruits.txt | tail -n 2 | head -n 1 | grep -qx mango || echo FAIL: tail of stdin
+
+stat fruits.txt | grep -q 'Size: 82 ' || echo FAIL: stat
+stat missing.txt && echo FAIL: stat of a missing file
+
+cp fruits.txt fruits.txt && echo FAIL: cp onto itself
+cp fruits.txt . && echo FAIL: cp into its own directory
+grep -c '' fruits.txt | grep -qx 12 || echo FAIL: cp onto itself truncated the source
+
+mkdir /coreutils
+cp fruits.txt /coreutils/copy.txt || echo FAIL: cp
+grep -c '' /coreutils/copy.txt | grep -qx 12 || echo FAIL: cp contents
+cp fruits.txt /coreutils || echo FAIL: cp into a directory
+grep -qx fig /coreutils/fruits.txt || echo FAIL: cp into a directory contents
+echo short > /coreutils/short.txt
+cp /coreutils/short.txt /coreutils/fruits.txt
+grep -c '' /coreutils/fruits.txt | grep -qx 1 || echo FAIL: cp over an existing file
+mv /coreutils/copy.txt /coreutils/moved.txt || echo FAIL: mv
+cat /coreutils/copy.txt && echo FAIL: mv left the source behind
+grep -qx fig /coreutils/moved.txt || echo FAIL: mv contents
+mkdir /coreutils/sub
+mv /coreutils/moved.txt /coreutils/short.txt /coreutils/sub || echo FAIL: mv into a directory
+grep -qx short /coreutils/sub/short.txt || echo FAIL: mv into a directory contents
+
+rm /coreutils/sub/moved.txt; rm /coreutils/sub/short.txt; rm /coreutils/sub
+rm /coreutils/fruits.txt; rm /coreutils
+echo coreutils: done
@@ -0,0 +1,17 @@
+# Checks the device nodes rv6-mkfs puts in /dev, run by `make test-dev`. Each failed check
+# prints a line starting with FAIL.
+echo dev: start
+
+ls /dev | grep -q random || echo FAIL: ls /dev
+stat /dev/null | grep -q 'Type: Device ' || echo FAIL: stat of /dev/null
+
+cat /dev/null | grep -c '' | grep -qx 0 || echo FAIL: /dev/null is empty
+echo hello > /dev/null || echo FAIL: write to /dev/null
+cat /fruits.txt > /dev/null || echo FAIL: copy to /dev/null
+echo hello > /dev/zero || echo FAIL: write to /dev/zero
+
+# Reads return what the device handed out, so a line of random bytes ends at its newline
+head -n 1 /dev/random | grep -c '' | grep -qx 1 || echo FAIL: read /dev/random
+echo x > /dev/random && echo FAIL: /dev/random is writable
+
+echo dev: done
@@ -0,0 +1,32 @@
+# Checks the fd redirections of the shell and the fd tables behind them, run by `make test-fds`.
+# Each failed check prints a line starting with FAIL.
+echo fds: start
+mkdir /fds
+
+# Errors go to fd 2, which is left alone by `|` and `>`
+cat /fds/missing | grep -q . && echo FAIL: stderr went into the pipe
+cat /fds/missing > /fds/out
+grep -q . /fds/out && echo FAIL: stderr went into the file
+cat /fds/missing 2>&1 | grep -q 'cannot open' || echo FAIL: 2>&1 into a pipe
+cat /fds/missing 2> /fds/err
+grep -q 'cannot open' /fds/err || echo FAIL: 2> file
+
+# Redirections apply left to right
+cat /fds/missing > /fds/both 2>&1
+grep -q 'cannot open' /fds/both || echo FAIL: > file 2>&1
+cat /fds/missing 2>&1 > /fds/out | grep -q 'cannot open' || echo FAIL: 2>&1 > file
+echo hello 1> /fds/out
+grep -qx hello /fds/out || echo FAIL: 1> file
+cat 0< /fds/out | grep -qx hello || echo FAIL: 0< file
+
+echo hello 5> /fds/out && echo FAIL: only fds 0 to 2 can be redirected
+echo hello >&5 && echo FAIL: >&5 of a closed fd
+
+# The shell keeps its pipe and file fds to itself, otherwise the readers never see the end of
+# the pipe and these hang
+cat /fruits.txt | cat | grep -c '' | grep -qx 0 && echo FAIL: pipeline lost its input
+cat /fruits.txt > /fds/copy
+cat /fds/copy | grep -c '' | grep -qx 0 && echo FAIL: pipe from a redirected copy
+
+rm /fds/out; rm /fds/err; rm /fds/both; rm /fds/copy; rm /fds
+echo fds: done
@@ -0,0 +1,12 @@
+# Writes a file past the blocks a single indirect table reaches and reads it back with
+# `benchfs indirect`, run by `make test-indirect`. Each failed check prints a line starting
+# with FAIL.
+echo indirect: start
+
+benchfs indirect rw /indirect.bin > /dev/null || echo FAIL: benchfs indirect
+# Read it again through a fresh open, after the writes went through the log
+benchfs indirect r /indirect.bin > /dev/null || echo FAIL: benchfs indirect reread
+rm /indirect.bin
+cat /indirect.bin && echo FAIL: rm of a double-indirect file
+
+echo indirect: done
@@ -0,0 +1,21 @@
+# Checks the /proc files of the rv6 file system, run by `make test-proc`. Each failed check
+# prints a line starting with FAIL.
+echo proc: start
+
+ls /proc | grep -q domains || echo FAIL: ls /proc
+stat /proc/meminfo | grep -q 'Type: Device ' || echo FAIL: stat of a /proc file
+
+grep -q '^ *ID NAME ' /proc/domains || echo FAIL: domains header
+grep -q ' xv6fs .* 1$' /proc/domains || echo FAIL: domains lists xv6fs
+grep -q ' /grep ' /proc/domains || echo FAIL: domains lists the reader
+cat /proc/domains /proc/domains | grep -c 'ID NAME' | grep -qx 2 || echo FAIL: every open reads from the start
+
+grep -q ' /grep$' /proc/threads || echo FAIL: threads lists the reader
+grep -q '^total: .* kB$' /proc/meminfo || echo FAIL: meminfo total
+grep -q '^free: .* kB$' /proc/meminfo || echo FAIL: meminfo free
+grep -q . /proc/net || echo FAIL: net
+grep -q . /proc/nvme || echo FAIL: nvme
+
+echo x > /proc/domains && echo FAIL: /proc is writable
+
+echo proc: done
@@ -0,0 +1,9 @@
+# Reads, maps and rewrites one file from two threads at once with `benchfs stress`, run by
+# `make test-stress`. A deadlock between the readers and the log shows up as a timeout. Each
+# failed check prints a line starting with FAIL.
+echo stress: start
+
+benchfs stress rw /stress.bin > /dev/null || echo FAIL: benchfs stress
+rm /stress.bin
+
+echo stress: done
@@ -0,0 +1,15 @@
+# Checks threads in rv6 programs with `pwc`, which counts files in worker threads, run by
+# `make test-threads`. Each failed check prints a line starting with FAIL.
+echo threads: start
+
+pwc /fruits.txt | grep -qx 'pwc: line:12 word:13 char:82 name:/fruits.txt' || echo FAIL: pwc of a file
+
+# More files than workers, printed in order
+pwc /fruits.txt /fruits.txt /fruits.txt /fruits.txt /fruits.txt /fruits.txt | grep -c 'line:12 ' | grep -qx 6 || echo FAIL: pwc of many files
+pwc /threads.sh /fruits.txt | head -n 1 | grep -q 'name:/threads.sh$' || echo FAIL: pwc keeps the order
+
+pwc /fruits.txt /missing.txt /fruits.txt > /dev/null && echo FAIL: pwc of a missing file succeeded
+pwc /fruits.txt /missing.txt /fruits.txt 2>&1 | grep -q 'cannot open /missing.txt' || echo FAIL: pwc reports a missing file
+pwc /fruits.txt /missing.txt /fruits.txt 2> /dev/null | grep -c '^pwc: ' | grep -qx 2 || echo FAIL: pwc goes on past a missing file
+
+echo threads: done
@@ -0,0 +1,94 @@
+// Computes a fingerprint for every `#[interface]` trait in `src/` and writes them to
+// `$OUT_DIR/interface_fingerprints.rs`, which is included by `src/fingerprint.rs`. The traits
+// and type definitions are read with `tools/interface-parser`.
+//
+// The hash covers the trait declaration with comments and whitespace removed, so editing
+// documentation does not change it but changing a signature does. The definitions of the
+// structs, enums, unions, type aliases and constants of this crate that the trait uses, directly
+// or through other definitions, are folded in as well, so changing the layout of e.g. `BlkReq`
+// changes the fingerprint of every interface passing it. Types from other crates are not
+// covered. An explicit version can be given with `#[interface(version = N)]`; it defaults to 0.
+
+use interface_parser::{
+    collect_sources, find_definitions, find_interfaces, normalize, referenced_definitions,
+    strip_comments, Definitions,
+};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Must match `fingerprint::NAME_LEN`
+const NAME_LEN: usize = 32;
+
+struct Fingerprint {
+    name: String,
+    version: u32,
+    hash: u64,
+}
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let src = Path::new(&manifest_dir).join("src");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    let sources: Vec<String> = collect_sources(&src)
+        .iter()
+        .map(|file| strip_comments(&fs::read_to_string(file).unwrap()))
+        .collect();
+
+    let mut definitions = Definitions::new();
+    for source in &sources {
+        find_definitions(source, &mut definitions);
+    }
+
+    let mut fingerprints = Vec::new();
+    for source in &sources {
+        for interface in find_interfaces(source) {
+            let mut normalized = normalize(&interface.decl);
+            for definition in referenced_definitions(&interface.decl, &definitions) {
+                normalized.push_str(definition);
+            }
+            fingerprints.push(Fingerprint {
+                name: interface.name,
+                version: interface.version,
+                hash: fnv1a(normalized.as_bytes()),
+            });
+        }
+    }
+    fingerprints.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "pub const INTERFACE_FINGERPRINTS: [InterfaceFingerprint; {}] = [\n",
+        fingerprints.len()
+    ));
+    for fp in &fingerprints {
+        assert!(
+            fp.name.len() <= NAME_LEN,
+            "interface name {} is longer than {} bytes",
+            fp.name,
+            NAME_LEN
+        );
+        let mut name = [0u8; NAME_LEN];
+        name[..fp.name.len()].copy_from_slice(fp.name.as_bytes());
+        out.push_str(&format!(
+            "    InterfaceFingerprint {{ name: {:?}, version: {}, _reserved: 0, hash: {:#018x} }}, // {}\n",
+            name, fp.version, fp.hash, fp.name
+        ));
+    }
+    out.push_str("];\n");
+
+    fs::write(Path::new(&out_dir).join("interface_fingerprints.rs"), out).unwrap();
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
@@ -10,6 +10,11 @@ pub const BSIZE: usize =        4096;   // block size
 pub trait BDev: Send + Sync {
     fn read(&self, block: u32, data: RRef<[u8; BSIZE]>) -> RpcResult<RRef<[u8; BSIZE]>>;
     fn write(&self, block: u32, data: &RRef<[u8; BSIZE]>) -> RpcResult<()>;
+    /// Reads a batch of blocks. As for `read`, `block` of each request is the first sector
+    /// of the block. Requests are returned once all of them completed, in any order.
+    fn read_blocks(&self, reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>>;
+    /// Writes a batch of blocks, see `read_blocks`
+    fn write_blocks(&self, reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>>;
 }
 
 // pub trait SyncBDev {
@@ -65,5 +70,9 @@ pub trait NvmeBDev : Send {
     fn poll_rref(&self, collect: RRefDeque<BlkReq, 1024>) ->
             RpcResult<Result<(usize, RRefDeque<BlkReq, 1024>)>>;
 
+    /// Submitted and completed requests since the last `get_stats`, which resets the counters
     fn get_stats(&self) -> RpcResult<Result<(u64, u64)>>;
+
+    /// Like `get_stats`, but leaves the counters alone
+    fn peek_stats(&self) -> RpcResult<Result<(u64, u64)>>;
 }
@@ -1,4 +1,4 @@
-use crate::error::Result;
+use crate::error::ErrorKind;
 use crate::tpm::UsrTpm;
 use crate::{
     bdev::{BDev, NvmeBDev},
@@ -11,13 +11,16 @@ use crate::{
 };
 /// Domain create related interfaces
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::sync::Arc;
-use syscalls::{Domain, Heap, Interrupt};
+use core::fmt;
+use syscalls::{Domain, Heap, Interrupt, Syscall};
 
 #[domain_create(path = "proxy", relative_path = "usr/proxy")]
 pub trait CreateProxy {
     fn create_domain_proxy(
         &self,
+        create_domain: Arc<dyn CreateDomain>,
         create_pci: Arc<dyn CreatePCI>,
         create_membdev: Arc<dyn CreateMemBDev>,
         create_bdev_shadow: Arc<dyn CreateBDevShadow>,
@@ -112,7 +115,23 @@ pub trait CreateNvme: Send + Sync {
 
 #[domain_create(path = "xv6fs", relative_path = "usr/xv6/kernel/fs")]
 pub trait CreateRv6FS: Send + Sync {
-    fn create_domain_xv6fs(&self, bdev: Box<dyn BDev>) -> (Box<dyn Domain>, Box<dyn VFS>);
+    /// `net` and `nvme` are only read for the statistics in /proc, `tpm` backs /dev/random
+    fn create_domain_xv6fs(
+        &self,
+        bdev: Box<dyn BDev>,
+        net: Option<Box<dyn Net>>,
+        nvme: Option<Box<dyn NvmeBDev>>,
+        tpm: Option<Box<dyn UsrTpm>>,
+    ) -> (Box<dyn Domain>, Box<dyn VFS>);
+    /// Replaces `dom` with a fresh instance mounting `bdev`
+    fn recreate_domain_xv6fs(
+        &self,
+        dom: Box<dyn Domain>,
+        bdev: Box<dyn BDev>,
+        net: Option<Box<dyn Net>>,
+        nvme: Option<Box<dyn NvmeBDev>>,
+        tpm: Option<Box<dyn UsrTpm>>,
+    ) -> (Box<dyn Domain>, Box<dyn VFS>);
 }
 
 #[domain_create(path = "xv6net", relative_path = "usr/xv6/kernel/net")]
@@ -136,8 +155,9 @@ pub trait CreateRv6Usr: Send + Sync {
         name: &str,
         blob: &[u8],
         xv6: Box<dyn crate::rv6::Rv6>,
-        args: &str,
-    ) -> (Box<dyn syscalls::Domain>, ());
+        argv: &[&str],
+        envp: &[&str],
+    ) -> (Box<dyn syscalls::Domain>, i32);
 }
 pub type CreateRv6UsrPtr = Box<dyn CreateRv6Usr + Send + Sync>;
 
@@ -195,3 +215,296 @@ pub trait CreateHashStore: Send + Sync {
 pub trait CreateTpm: Send + Sync {
     fn create_domain_tpm(&self) -> (Box<dyn Domain>, Box<dyn UsrTpm>);
 }
+
+/* Unified domain creation
+ *
+ * Instead of one `Create*` trait per domain, the kernel exports a single
+ * `CreateDomain` capability that loads a domain binary, either one linked
+ * into the kernel image or a blob supplied by the caller. The entry point of
+ * the loaded domain is then called with typed arguments through
+ * `create_domain`/`create_domain_from_blob`. The types are checked per domain
+ * by the `DomainEntry` implementations at the bottom of this file, and the
+ * interface fingerprints (see `crate::fingerprint`) make sure both sides were
+ * built against the same interfaces.
+ *
+ * The capability is a registry keyed by domain name: the proxy only hands it
+ * out, so a new domain needs an entry below and a line in the kernel's
+ * `linked_domains!`, but no new parameter in init or the proxy.
+ */
+
+/// Where `CreateDomain::load_domain` takes the domain binary from
+pub enum DomainSource<'a> {
+    /// A domain binary linked into the kernel image, e.g. "xv6fs"
+    Named(&'a str),
+    /// A binary supplied by the caller, e.g. an rv6 user program read from the file system
+    Blob { name: &'a str, blob: &'a [u8] },
+}
+
+impl<'a> DomainSource<'a> {
+    pub fn name(&self) -> &'a str {
+        match *self {
+            DomainSource::Named(name) => name,
+            DomainSource::Blob { name, .. } => name,
+        }
+    }
+}
+
+/// A domain that has been loaded, but whose entry point has not been called yet
+pub struct LoadedDomain {
+    domain: Box<dyn Domain>,
+    entry: *const (),
+    syscall: Box<dyn Syscall + Send + Sync>,
+    heap: Box<dyn Heap + Send + Sync>,
+}
+
+impl LoadedDomain {
+    /// # Safety
+    /// `entry` must be the address of the `trusted_entry` of the domain `domain` was
+    /// created for, and `syscall` and `heap` the capabilities of that domain.
+    pub unsafe fn new(
+        domain: Box<dyn Domain>,
+        entry: *const (),
+        syscall: Box<dyn Syscall + Send + Sync>,
+        heap: Box<dyn Heap + Send + Sync>,
+    ) -> Self {
+        Self {
+            domain,
+            entry,
+            syscall,
+            heap,
+        }
+    }
+
+    pub fn domain_id(&self) -> u64 {
+        self.domain.get_domain_id()
+    }
+
+    /// Calls the entry point of the domain with `args` on behalf of the new domain.
+    ///
+    /// # Safety
+    /// The entry point must take `A` and return `R`. Use `create_domain` or
+    /// `create_domain_from_blob` which get both from a `DomainEntry`.
+    pub unsafe fn enter<A: EntryArgs, R>(self, args: A) -> (Box<dyn Domain>, R) {
+        let caller_id =
+            libsyscalls::syscalls::sys_update_current_domain_id(self.domain.get_domain_id());
+        let output = args.call(self.entry, self.syscall, self.heap);
+        libsyscalls::syscalls::sys_update_current_domain_id(caller_id);
+        (self.domain, output)
+    }
+}
+
+/// Why the kernel refused to load a domain
+#[derive(Debug)]
+pub enum LoadError {
+    /// No domain binary with the name is linked into the kernel
+    NotFound,
+    InvalidElf(&'static str),
+    /// The domain was built against a different version of `interface`
+    InterfaceMismatch {
+        interface: String,
+        domain_version: u32,
+        domain_hash: u64,
+        kernel_version: u32,
+        kernel_hash: u64,
+    },
+    LoadFailed(&'static str),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::NotFound => write!(f, "no such domain binary"),
+            LoadError::InvalidElf(e) => write!(f, "invalid ELF file: {}", e),
+            LoadError::InterfaceMismatch {
+                interface,
+                domain_version,
+                domain_hash,
+                kernel_version,
+                kernel_hash,
+            } => write!(
+                f,
+                "interface {} mismatch: domain was built against version {} ({:#018x}), kernel has version {} ({:#018x})",
+                interface, domain_version, domain_hash, kernel_version, kernel_hash
+            ),
+            LoadError::LoadFailed(e) => write!(f, "cannot load binary: {}", e),
+        }
+    }
+}
+
+impl From<LoadError> for ErrorKind {
+    fn from(e: LoadError) -> Self {
+        match e {
+            LoadError::NotFound => ErrorKind::DomainNotFound,
+            LoadError::InterfaceMismatch { .. } => ErrorKind::InterfaceMismatch,
+            LoadError::InvalidElf(_) | LoadError::LoadFailed(_) => ErrorKind::DomainLoadFailed,
+        }
+    }
+}
+
+/// Unified domain create capability, implemented by the kernel
+pub trait CreateDomain: Send + Sync {
+    /// Loads a domain binary and sets up its address space. Fails with
+    /// `LoadError::NotFound` if no binary with the name is linked into the
+    /// kernel, and with `LoadError::InterfaceMismatch` if the domain was built
+    /// against a different version of an interface.
+    fn load_domain(&self, source: DomainSource) -> Result<LoadedDomain, LoadError>;
+}
+
+/// Describes the entry point of a domain: `trusted_entry(s, heap, Args...) -> Output`
+pub trait DomainEntry<'a> {
+    /// Name of the domain binary in the kernel image (the `path` of its `domain_create`)
+    const NAME: &'static str;
+    /// Arguments of `trusted_entry` after the syscall and heap capabilities
+    type Args: EntryArgs;
+    type Output;
+}
+
+/// Argument tuples that can be passed to a domain entry point
+pub trait EntryArgs {
+    /// # Safety
+    /// `entry` must be a `trusted_entry` taking the syscall and heap capabilities
+    /// followed by the elements of `Self`, and returning `R`.
+    unsafe fn call<R>(
+        self,
+        entry: *const (),
+        s: Box<dyn Syscall + Send + Sync>,
+        heap: Box<dyn Heap + Send + Sync>,
+    ) -> R;
+}
+
+macro_rules! entry_args {
+    ($($arg:ident),*) => {
+        impl<$($arg),*> EntryArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            unsafe fn call<R>(
+                self,
+                entry: *const (),
+                s: Box<dyn Syscall + Send + Sync>,
+                heap: Box<dyn Heap + Send + Sync>,
+            ) -> R {
+                let entry: fn(Box<dyn Syscall + Send + Sync>, Box<dyn Heap + Send + Sync>, $($arg),*) -> R =
+                    core::mem::transmute(entry);
+                let ($($arg,)*) = self;
+                entry(s, heap, $($arg),*)
+            }
+        }
+    };
+}
+
+entry_args!();
+entry_args!(A0);
+entry_args!(A0, A1);
+entry_args!(A0, A1, A2);
+entry_args!(A0, A1, A2, A3);
+entry_args!(A0, A1, A2, A3, A4);
+entry_args!(A0, A1, A2, A3, A4, A5);
+entry_args!(A0, A1, A2, A3, A4, A5, A6);
+entry_args!(A0, A1, A2, A3, A4, A5, A6, A7);
+entry_args!(A0, A1, A2, A3, A4, A5, A6, A7, A8);
+entry_args!(A0, A1, A2, A3, A4, A5, A6, A7, A8, A9);
+
+/// Creates the domain `E` from the binary linked into the kernel
+pub fn create_domain<'a, E: DomainEntry<'a>>(
+    create: &dyn CreateDomain,
+    args: E::Args,
+) -> Result<(Box<dyn Domain>, E::Output), LoadError> {
+    let loaded = create.load_domain(DomainSource::Named(E::NAME))?;
+    Ok(unsafe { loaded.enter(args) })
+}
+
+/// Creates a domain from `blob`, which must have the entry point described by `E`.
+/// Unlike `create_domain` the binary is not known to the kernel, so only the interface
+/// fingerprints stand between a wrong `E` and undefined behavior.
+pub fn create_domain_from_blob<'a, E: DomainEntry<'a>>(
+    create: &dyn CreateDomain,
+    name: &str,
+    blob: &[u8],
+    args: E::Args,
+) -> Result<(Box<dyn Domain>, E::Output), LoadError> {
+    if blob.is_empty() {
+        return Err(LoadError::InvalidElf("empty binary"));
+    }
+    let loaded = create.load_domain(DomainSource::Blob { name, blob })?;
+    Ok(unsafe { loaded.enter(args) })
+}
+
+/// Entry points of the domains built with the system
+pub mod entry {
+    use super::*;
+
+    macro_rules! domain_entry {
+        ($(#[$attr:meta])* $entry:ident, $name:expr, ($($arg:ty),*) -> $output:ty) => {
+            $(#[$attr])*
+            pub enum $entry {}
+
+            impl DomainEntry<'_> for $entry {
+                const NAME: &'static str = $name;
+                type Args = ($($arg,)*);
+                type Output = $output;
+            }
+        };
+    }
+
+    domain_entry!(Pci, "pci", () -> Box<dyn PCI>);
+    domain_entry!(MemBDev, "membdev", (&'static mut [u8]) -> Box<dyn BDev>);
+    domain_entry!(BDevShadow, "bdev_shadow", (Arc<dyn CreateMemBDev>) -> Box<dyn BDev>);
+    domain_entry!(Ixgbe, "ixgbe", (Box<dyn PCI>) -> Box<dyn Net>);
+    domain_entry!(VirtioNet, "virtio_net", (Box<dyn PCI>) -> Box<dyn Net>);
+    domain_entry!(VirtioBlock, "virtio_block", (Box<dyn PCI>) -> Box<dyn NvmeBDev>);
+    domain_entry!(NetShadow, "net_shadow", (Arc<dyn CreateIxgbe>, Box<dyn PCI>) -> Box<dyn Net>);
+    domain_entry!(Nvme, "nvme", (Box<dyn PCI>) -> Box<dyn NvmeBDev>);
+    domain_entry!(NvmeShadow, "nvme_shadow", (Arc<dyn CreateNvme>, Box<dyn PCI>) -> Box<dyn NvmeBDev>);
+    domain_entry!(
+        /// Exposes an `NvmeBDev` as a batching `BDev`
+        NvmeBDevAdapter,
+        "nvme_bdev",
+        (Box<dyn NvmeBDev>) -> Box<dyn BDev>
+    );
+    domain_entry!(
+        Rv6FS,
+        "xv6fs",
+        (
+            Box<dyn BDev>,
+            Option<Box<dyn Net>>,
+            Option<Box<dyn NvmeBDev>>,
+            Option<Box<dyn UsrTpm>>
+        ) -> Box<dyn VFS>
+    );
+    domain_entry!(Rv6Net, "xv6net", (Box<dyn Net>) -> Box<dyn UsrNet>);
+    domain_entry!(Rv6NetShadow, "xv6net_shadow", (Arc<dyn CreateRv6Net>, Box<dyn Net>) -> Box<dyn UsrNet>);
+    domain_entry!(
+        Rv6Kernel,
+        "xv6kernel",
+        (
+            Box<dyn Interrupt + Send + Sync>,
+            Arc<dyn CreateRv6FS>,
+            Arc<dyn CreateRv6Net>,
+            Arc<dyn CreateRv6NetShadow>,
+            Arc<dyn CreateRv6Usr>,
+            Box<dyn BDev>,
+            Box<dyn Net>,
+            Box<dyn NvmeBDev>,
+            Box<dyn UsrTpm>
+        ) -> Box<dyn Rv6>
+    );
+    domain_entry!(DomC, "dom_c", () -> Box<dyn crate::dom_c::DomC>);
+    domain_entry!(DomD, "dom_d", (Box<dyn crate::dom_c::DomC>) -> ());
+    domain_entry!(Shadow, "shadow", (Arc<dyn CreateDomC>) -> Box<dyn crate::dom_c::DomC>);
+    domain_entry!(Benchnvme, "benchnvme", (Box<dyn NvmeBDev>) -> ());
+    domain_entry!(Tpm, "tpm", () -> Box<dyn UsrTpm>);
+    domain_entry!(
+        /// Crash-consistency test of rv6, run on a copy-on-write view of the `BDev`
+        CrashFs,
+        "crashfs",
+        (Arc<dyn CreateRv6FS>, Box<dyn BDev>) -> ()
+    );
+
+    /// rv6 user programs, which are always loaded from a blob and return their exit status
+    pub enum Rv6Usr {}
+
+    impl<'a> DomainEntry<'a> for Rv6Usr {
+        const NAME: &'static str = "xv6_user";
+        type Args = (Box<dyn Rv6>, &'a [&'a str], &'a [&'a str]);
+        type Output = i32;
+    }
+}
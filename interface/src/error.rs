@@ -152,6 +152,18 @@ pub enum ErrorKind {
     InvalidPciClass,
     /// Failed to find Pci device/vendor ID.
     InvalidPciDeviceID,
+    /// The file would grow beyond the largest size the file system supports
+    FileTooLarge,
+    /// Too many symbolic links were followed while resolving a path
+    TooManySymlinks,
+    /// The calling thread has been killed
+    Killed,
+    /// No domain binary with the given name is linked into the kernel
+    DomainNotFound,
+    /// The domain binary could not be loaded
+    DomainLoadFailed,
+    /// The domain was built against a different version of an interface
+    InterfaceMismatch,
 }
 
 // impl ErrorKind {
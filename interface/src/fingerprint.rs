@@ -0,0 +1,60 @@
+//! Fingerprints of the `#[interface]` traits this crate was built with.
+//!
+//! Every domain links this crate, so the table below ends up in the `.redleaf_interfaces`
+//! section of each domain ELF. The kernel compares it against its own copy of the table when
+//! loading a domain, and refuses to load a domain that was built against a different shape of
+//! an interface it shares with the kernel.
+
+/// Must match `NAME_LEN` in `build.rs`
+pub const NAME_LEN: usize = 32;
+
+/// Name of the ELF section holding the fingerprints of a domain.
+pub const SECTION_NAME: &str = ".redleaf_interfaces";
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceFingerprint {
+    pub name: [u8; NAME_LEN],
+    pub version: u32,
+    pub _reserved: u32,
+    pub hash: u64,
+}
+
+impl InterfaceFingerprint {
+    pub const SIZE: usize = core::mem::size_of::<InterfaceFingerprint>();
+
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|c| *c == 0).unwrap_or(NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("<invalid>")
+    }
+
+    /// Decodes a single record as laid out in the `.redleaf_interfaces` section.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::SIZE {
+            return None;
+        }
+        let mut name = [0u8; NAME_LEN];
+        name.copy_from_slice(&bytes[..NAME_LEN]);
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&bytes[NAME_LEN..NAME_LEN + 4]);
+        let mut hash = [0u8; 8];
+        hash.copy_from_slice(&bytes[NAME_LEN + 8..NAME_LEN + 16]);
+        Some(Self {
+            name,
+            version: u32::from_le_bytes(version),
+            _reserved: 0,
+            hash: u64::from_le_bytes(hash),
+        })
+    }
+
+    pub fn lookup(name: &str) -> Option<&'static InterfaceFingerprint> {
+        INTERFACE_FINGERPRINTS.iter().find(|fp| fp.name() == name)
+    }
+}
+
+// Generated by build.rs
+include!(concat!(env!("OUT_DIR"), "/interface_fingerprints.rs"));
+
+#[used]
+#[link_section = ".redleaf_interfaces"]
+static EMBEDDED_FINGERPRINTS: [InterfaceFingerprint; INTERFACE_FINGERPRINTS.len()] = INTERFACE_FINGERPRINTS;
@@ -37,6 +37,7 @@ pub mod rv6;
 pub mod tpm;
 pub mod rref;
 pub mod typeid;
+pub mod fingerprint;
 
 pub mod proxy;
 
@@ -7,6 +7,7 @@ use crate::error::Result;
 use crate::rpc::RpcResult;
 use core::fmt;
 
+#[derive(Clone, Copy)]
 pub struct NetworkStats {
     pub tx_count: u64,
     pub rx_count: u64,
@@ -28,6 +29,15 @@ impl NetworkStats {
         }
     }
 
+    pub fn add(&mut self, other: &NetworkStats) {
+        self.tx_count += other.tx_count;
+        self.rx_count += other.rx_count;
+        self.tx_dma_ok += other.tx_dma_ok;
+        self.rx_dma_ok += other.rx_dma_ok;
+        self.rx_missed += other.rx_missed;
+        self.rx_crc_err += other.rx_crc_err;
+    }
+
     pub fn stats_diff(&mut self, start: NetworkStats) {
         self.tx_count.saturating_sub(start.tx_count);
         self.rx_count.saturating_sub(start.rx_count);
@@ -67,7 +77,11 @@ pub trait Net: Send + Sync {
 
     fn poll_rref(&self, collect: RRefDeque<[u8; 1514], 512>, tx: bool) -> RpcResult<Result<(usize, RRefDeque<[u8; 1514], 512>)>>;
 
+    /// Counts since the last `get_stats`, which resets the counters
     fn get_stats(&self) -> RpcResult<Result<NetworkStats>>;
+
+    /// Like `get_stats`, but leaves the counters alone
+    fn peek_stats(&self) -> RpcResult<Result<NetworkStats>>;
     
     fn test_domain_crossing(&self) -> RpcResult<()>;
 }
@@ -24,6 +24,7 @@ pub trait Proxy:
     + CreateShadow
 {
     // necessary because rust doesn't support trait object upcasting
+    fn as_domain_create_CreateDomain(&self) -> Arc<dyn crate::domain_create::CreateDomain>;
     fn as_domain_create_CreateVirtioNet(&self) -> Arc<dyn crate::domain_create::CreateVirtioNet>;
     fn as_domain_create_CreateVirtioBlock(
         &self,
@@ -5,6 +5,8 @@ pub mod rref;
 pub mod rref_deque;
 pub mod rref_array;
 pub mod rref_vec;
+pub mod rref_shared_vec;
+pub mod rref_channel;
 pub mod traits;
 pub mod owned;
 
@@ -13,7 +15,11 @@ pub use self::rref::RRef as RRef;
 pub use self::rref_array::RRefArray as RRefArray;
 pub use self::rref_deque::RRefDeque as RRefDeque;
 pub use self::rref_vec::RRefVec as RRefVec;
+pub use self::rref_shared_vec::RRefSharedVec as RRefSharedVec;
 pub use self::owned::Owned as Owned;
+pub use self::rref_channel::RRefChannel as RRefChannel;
+pub use self::rref_channel::RRefSender as RRefSender;
+pub use self::rref_channel::RRefReceiver as RRefReceiver;
 
 #[cfg(test)]
 mod tests {
@@ -96,6 +102,12 @@ mod tests {
             drop_map.add_type::<[Option<RRef<usize>>; 10]>();
             drop_map.add_type::<[Option<RRef<CleanupTest>>; 4]>();
             drop_map.add_type::<Owner>();
+            drop_map.add_type::<RRefChannel<usize, 3>>();
+            drop_map.add_type::<rref_channel::ChannelEndpoint<usize, 3>>();
+            drop_map.add_type::<u8>();
+            drop_map.add_type::<[u8; 4]>();
+            drop_map.add_type::<rref_shared_vec::SharedVec<u8>>();
+            drop_map.add_type::<rref_shared_vec::SharedVecHandle<u8>>();
 
 
             TestHeap {
@@ -162,10 +174,20 @@ mod tests {
         fn sys_free_huge(&self, p: *mut u8) {}
         fn sys_backtrace(&self) {}
         fn sys_dummy(&self) {}
+        fn sys_get_time(&self) -> u64 { 0 }
         fn sys_readch_kbd(&self) -> core::result::Result<Option<pc_keyboard::DecodedKey>, &'static str> { todo!() }
+        fn sys_readch(&self) -> core::result::Result<Option<pc_keyboard::DecodedKey>, &'static str> { todo!() }
+        fn sys_console_interrupt(&self) -> bool { todo!() }
         fn sys_make_condvar(&self) -> Box<(dyn syscalls::CondVar + Send + Sync + 'static)> { todo!() }
+        fn sys_domains(&self, _: &mut [syscalls::DomainInfo]) -> usize { todo!() }
+        fn sys_threads(&self, _: &mut [syscalls::ThreadInfo]) -> usize { todo!() }
+        fn sys_memory(&self) -> syscalls::MemoryInfo { todo!() }
         unsafe fn sys_register_cont(&self, _: &syscalls::Continuation) { todo!() }
         unsafe fn sys_discard_cont(&self) { todo!() }
+        fn sys_unwind(&self) { todo!() }
+        fn sys_exit_domain(&self) { todo!() }
+        fn sys_interrupted(&self) -> bool { todo!() }
+        fn sys_set_interruptible(&self, _: bool) { todo!() }
         fn sys_test_unwind(&self) { todo!() }
     }
 
@@ -436,6 +458,22 @@ mod tests {
         assert!(deque.pop_front().is_none());
     }
 
+    #[test]
+    fn rref_deque_push_front() {
+        init_heap();
+        init_syscall();
+        let mut deque = RRefDeque::<usize, 3>::new(Default::default());
+        assert!(deque.push_back(RRef::new(2)).is_none());
+        assert!(deque.push_front(RRef::new(1)).is_none());
+        assert!(deque.push_back(RRef::new(3)).is_none());
+        assert_eq!(deque.push_front(RRef::new(0)).map(|r| *r), Some(0));
+        assert_eq!(deque.len(), 3);
+        assert_eq!(deque.pop_front().map(|r| *r), Some(1));
+        assert_eq!(deque.pop_front().map(|r| *r), Some(2));
+        assert_eq!(deque.pop_front().map(|r| *r), Some(3));
+        assert!(deque.pop_front().is_none());
+    }
+
     #[test]
     fn rref_deque_len() {
         init_heap();
@@ -572,6 +610,136 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rref_channel_send_recv() {
+        init_heap();
+        init_syscall();
+
+        let (mut tx, mut rx) = RRefChannel::<usize, 3>::new();
+        assert_eq!(rx.try_recv().err(), Some(rref_channel::TryRecvError::Empty));
+
+        assert!(tx.try_send(RRef::new(1)).is_ok());
+        assert!(tx.try_send(RRef::new(2)).is_ok());
+        assert!(tx.try_send(RRef::new(3)).is_ok());
+        match tx.try_send(RRef::new(4)) {
+            Err(rref_channel::TrySendError::Full(r)) => assert_eq!(*r, 4),
+            _ => panic!("channel should be full"),
+        }
+        assert_eq!(rx.len(), 3);
+
+        assert_eq!(rx.try_recv().ok().map(|r| *r), Some(1));
+        assert!(tx.try_send(RRef::new(4)).is_ok());
+        assert_eq!(rx.try_recv().ok().map(|r| *r), Some(2));
+        assert_eq!(rx.try_recv().ok().map(|r| *r), Some(3));
+        assert_eq!(rx.try_recv().ok().map(|r| *r), Some(4));
+        assert_eq!(rx.try_recv().err(), Some(rref_channel::TryRecvError::Empty));
+    }
+
+    #[test]
+    fn rref_channel_closed() {
+        init_heap();
+        init_syscall();
+
+        let (mut tx, mut rx) = RRefChannel::<usize, 3>::new();
+        assert!(tx.try_send(RRef::new(1)).is_ok());
+        drop(tx);
+
+        // elements sent before the sender went away are still delivered
+        assert!(rx.is_closed());
+        assert_eq!(rx.try_recv().ok().map(|r| *r), Some(1));
+        assert_eq!(rx.try_recv().err(), Some(rref_channel::TryRecvError::Closed));
+
+        let (mut tx, rx) = RRefChannel::<usize, 3>::new();
+        drop(rx);
+        match tx.try_send(RRef::new(1)) {
+            Err(rref_channel::TrySendError::Closed(r)) => assert_eq!(*r, 1),
+            _ => panic!("channel should be closed"),
+        }
+    }
+
+    #[test]
+    fn rref_channel_batch() {
+        init_heap();
+        init_syscall();
+
+        let (mut tx, mut rx) = RRefChannel::<usize, 3>::new();
+        let mut deque = RRefDeque::<usize, 10>::default();
+        for i in 1..=5 {
+            deque.push_back(RRef::new(i));
+        }
+
+        assert_eq!(tx.send_batch(&mut deque), 3);
+        assert_eq!(deque.len(), 2);
+
+        let mut collect = RRefDeque::<usize, 10>::default();
+        assert_eq!(rx.recv_batch(&mut collect), 3);
+        assert_eq!(tx.send_batch(&mut deque), 2);
+        assert_eq!(rx.recv_batch(&mut collect), 2);
+
+        let mut i = 1;
+        for n in collect.iter() {
+            assert_eq!(&i, n);
+            i += 1;
+        }
+
+        // a closed channel leaves the batch as it was
+        let (mut tx, rx) = RRefChannel::<usize, 3>::new();
+        drop(rx);
+        for i in 1..=3 {
+            deque.push_back(RRef::new(i));
+        }
+        assert_eq!(tx.send_batch(&mut deque), 0);
+        assert_eq!(deque.len(), 3);
+        for i in 1..=3 {
+            assert_eq!(deque.pop_front().map(|r| *r), Some(i));
+        }
+    }
+
+    #[test]
+    fn rref_shared_vec() {
+        init_heap();
+        init_syscall();
+
+        let bytes = RRefSharedVec::from_slice(b"hello");
+        assert_eq!(bytes.as_slice(), b"hello");
+        assert_eq!(bytes.handles(), 1);
+
+        let copy = bytes.share();
+        assert_eq!(copy.handles(), 2);
+        // both handles see the same array
+        assert_eq!(copy.as_slice().as_ptr(), bytes.as_slice().as_ptr());
+
+        // the array survives its creator's handle
+        drop(bytes);
+        assert_eq!(copy.handles(), 1);
+        assert_eq!(copy.as_slice(), b"hello");
+
+        let filled = RRefSharedVec::new_with(0u8, 4, |bytes| bytes[1] = 7);
+        assert_eq!(filled.as_slice(), &[0, 7, 0, 0]);
+        assert_eq!(filled.size(), 4);
+
+        let empty = RRefSharedVec::<u8>::from_slice(&[]);
+        assert!(empty.as_slice().is_empty());
+
+        // slices see part of the array, and slices of slices part of that
+        let middle = copy.slice(1..4);
+        assert_eq!(middle.as_slice(), b"ell");
+        assert_eq!(middle.slice(1..3).as_slice(), b"ll");
+        assert_eq!(copy.handles(), 2);
+        drop(middle);
+        assert_eq!(copy.handles(), 1);
+
+        // arrays are shared in place, and their creator can still read them
+        let array = RRef::new([1u8, 2, 3, 4]);
+        let pointer = array.as_ptr();
+        let shared = RRefSharedVec::from_array(array);
+        assert_eq!(shared.as_slice(), &[1, 2, 3, 4]);
+        assert_eq!(shared.as_slice().as_ptr(), pointer);
+        let alias = unsafe { shared.as_array::<4>() };
+        assert_eq!(*alias, [1, 2, 3, 4]);
+        core::mem::forget(alias);
+    }
+
     struct Owner {
         inner: Owned<usize>,
     }
@@ -115,6 +115,26 @@ impl<T: RRefable> RRef<T> {
         }
     }
 
+    // Creates a second handle to the same shared heap allocation. The caller is responsible
+    //   for making sure that only one of the handles is ever cleaned up.
+    pub(crate) unsafe fn alias(&self) -> RRef<T> {
+        RRef {
+            domain_id_pointer: self.domain_id_pointer,
+            borrow_count_pointer: self.borrow_count_pointer,
+            value_pointer: self.value_pointer
+        }
+    }
+
+    // Reinterprets the allocation as one of `U`. The caller makes sure `U` fits its layout.
+    pub(crate) unsafe fn cast<U: RRefable>(self) -> RRef<U> {
+        let this = core::mem::ManuallyDrop::new(self);
+        RRef {
+            domain_id_pointer: this.domain_id_pointer,
+            borrow_count_pointer: this.borrow_count_pointer,
+            value_pointer: this.value_pointer as *mut U
+        }
+    }
+
     pub(crate) fn domain_id(&self) -> u64 {
         unsafe {
             *self.domain_id_pointer
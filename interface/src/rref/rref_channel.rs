@@ -0,0 +1,250 @@
+use super::rref::RRef;
+use super::rref_deque::RRefDeque;
+use super::traits::{RRefable, CustomCleanup, TypeIdentifiable};
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Single-producer/single-consumer ring of `RRef`s on the shared heap.
+///
+/// The ring itself is never owned by a domain (its domain id is 0), so it survives the
+/// death of either side. Each side instead holds a `ChannelEndpoint` allocation that is
+/// owned by its domain. When an endpoint is cleaned up, either because it was dropped or
+/// because `drop_domain` reclaimed the heap of a dead domain, the channel is marked closed
+/// and the peer observes it on its next `try_send`/`try_recv`. The last endpoint to go
+/// away frees the ring together with any elements still in flight.
+///
+/// Once both endpoints are handed out (usually by passing one of them through a single
+/// `#[interface]` call) the two domains exchange elements with no domain crossings.
+pub struct RRefChannel<T, const N: usize> where T: 'static + RRefable {
+    slots: [Option<RRef<T>>; N],
+    head: AtomicUsize, // number of elements ever sent, only written by the sender
+    tail: AtomicUsize, // number of elements ever received, only written by the receiver
+    closed: AtomicBool,
+    endpoints: AtomicUsize,
+}
+
+unsafe impl<T: RRefable, const N: usize> RRefable for RRefChannel<T, N> {}
+
+impl<T: RRefable, const N: usize> CustomCleanup for RRefChannel<T, N> {
+    fn cleanup(&mut self) {
+        #[cfg(features = "rref_dbg")]
+        println!("CustomCleanup::{}::cleanup()", core::any::type_name_of_val(self));
+        self.slots.cleanup();
+    }
+}
+
+impl<T: RRefable, const N: usize> RRefChannel<T, N>
+    where RRefChannel<T, N>: TypeIdentifiable, ChannelEndpoint<T, N>: TypeIdentifiable
+{
+    /// Allocates a new channel on the shared heap and returns both of its ends.
+    pub fn new() -> (RRefSender<T, N>, RRefReceiver<T, N>) {
+        assert!(N > 0, "RRefChannel must have at least one slot");
+
+        // https://www.joshmcguigan.com/blog/array-initialization-rust/
+        let slots = unsafe {
+            let mut slots: [Option<RRef<T>>; N] = core::mem::uninitialized();
+            for item in &mut slots[..] {
+                core::ptr::write(item, None);
+            }
+            slots
+        };
+
+        let ring = RRef::new(RRefChannel {
+            slots,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            endpoints: AtomicUsize::new(2),
+        });
+        // the ring is shared by both endpoints, so no domain owns it
+        ring.move_to(0);
+
+        let sender_ring = unsafe { ring.alias() };
+        (
+            RRefSender { endpoint: RRef::new(ChannelEndpoint { ring: sender_ring }) },
+            RRefReceiver { endpoint: RRef::new(ChannelEndpoint { ring }) },
+        )
+    }
+}
+
+/// One side of an `RRefChannel`, allocated on the shared heap and owned by the domain
+/// holding the matching `RRefSender` or `RRefReceiver`.
+pub struct ChannelEndpoint<T, const N: usize> where T: 'static + RRefable {
+    ring: RRef<RRefChannel<T, N>>,
+}
+
+unsafe impl<T: RRefable, const N: usize> RRefable for ChannelEndpoint<T, N> {}
+
+impl<T: RRefable, const N: usize> CustomCleanup for ChannelEndpoint<T, N> {
+    fn cleanup(&mut self) {
+        #[cfg(features = "rref_dbg")]
+        println!("CustomCleanup::{}::cleanup()", core::any::type_name_of_val(self));
+        self.ring.closed.store(true, Ordering::Release);
+        // both endpoints hold an alias of the ring, only the last one frees it
+        if self.ring.endpoints.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.ring.cleanup();
+        }
+    }
+}
+
+pub enum TrySendError<T> where T: 'static + RRefable {
+    /// The ring has no free slots; the value is handed back.
+    Full(RRef<T>),
+    /// The receiving end is gone; the value is handed back.
+    Closed(RRef<T>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// Nothing to receive yet.
+    Empty,
+    /// The sending end is gone and the ring has been drained.
+    Closed,
+}
+
+pub struct RRefSender<T, const N: usize> where T: 'static + RRefable {
+    endpoint: RRef<ChannelEndpoint<T, N>>,
+}
+
+unsafe impl<T: RRefable, const N: usize> RRefable for RRefSender<T, N> {}
+unsafe impl<T: RRefable, const N: usize> Send for RRefSender<T, N> where T: Send {}
+
+impl<T: RRefable, const N: usize> CustomCleanup for RRefSender<T, N> {
+    fn cleanup(&mut self) {
+        self.endpoint.cleanup();
+    }
+}
+
+impl<T: RRefable, const N: usize> RRefSender<T, N> {
+    // TODO: mark unsafe?
+    pub fn move_to(&self, new_domain_id: u64) {
+        self.endpoint.move_to(new_domain_id);
+    }
+
+    pub fn borrow(&self) {
+        self.endpoint.borrow();
+    }
+
+    pub fn forfeit(&self) {
+        self.endpoint.forfeit();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.endpoint.ring.closed.load(Ordering::Acquire)
+    }
+
+    pub fn len(&self) -> usize {
+        let ring = &self.endpoint.ring;
+        ring.head.load(Ordering::Relaxed).wrapping_sub(ring.tail.load(Ordering::Acquire))
+    }
+
+    pub fn try_send(&mut self, value: RRef<T>) -> Result<(), TrySendError<T>> {
+        if self.is_closed() {
+            return Err(TrySendError::Closed(value));
+        }
+        let ring = &self.endpoint.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        if head.wrapping_sub(ring.tail.load(Ordering::Acquire)) == N {
+            return Err(TrySendError::Full(value));
+        }
+        value.move_to(0); // mark as owned by the ring
+        // the receiver does not touch this slot until head is published
+        unsafe { ring.ptr_mut().slots[head % N].replace(value) };
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Moves as many elements as fit from the front of `batch` into the channel and
+    /// returns how many were sent. Nothing is sent once the channel is closed, and elements
+    /// that weren't sent stay in `batch` in their order.
+    pub fn send_batch<const M: usize>(&mut self, batch: &mut RRefDeque<T, M>) -> usize {
+        let free = N - self.len();
+        let mut sent = 0;
+        while sent < free {
+            let value = match batch.pop_front() {
+                Some(value) => value,
+                None => break,
+            };
+            match self.try_send(value) {
+                Ok(()) => sent += 1,
+                Err(TrySendError::Full(value)) | Err(TrySendError::Closed(value)) => {
+                    // `pop_front` just freed the slot in front, so it goes back where it was
+                    let rejected = batch.push_front(value);
+                    assert!(rejected.is_none(), "pop_front freed no slot");
+                    break;
+                }
+            }
+        }
+        sent
+    }
+}
+
+pub struct RRefReceiver<T, const N: usize> where T: 'static + RRefable {
+    endpoint: RRef<ChannelEndpoint<T, N>>,
+}
+
+unsafe impl<T: RRefable, const N: usize> RRefable for RRefReceiver<T, N> {}
+unsafe impl<T: RRefable, const N: usize> Send for RRefReceiver<T, N> where T: Send {}
+
+impl<T: RRefable, const N: usize> CustomCleanup for RRefReceiver<T, N> {
+    fn cleanup(&mut self) {
+        self.endpoint.cleanup();
+    }
+}
+
+impl<T: RRefable, const N: usize> RRefReceiver<T, N> {
+    // TODO: mark unsafe?
+    pub fn move_to(&self, new_domain_id: u64) {
+        self.endpoint.move_to(new_domain_id);
+    }
+
+    pub fn borrow(&self) {
+        self.endpoint.borrow();
+    }
+
+    pub fn forfeit(&self) {
+        self.endpoint.forfeit();
+    }
+
+    /// Returns true once the sender is gone. Elements sent before that can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.endpoint.ring.closed.load(Ordering::Acquire)
+    }
+
+    pub fn len(&self) -> usize {
+        let ring = &self.endpoint.ring;
+        ring.head.load(Ordering::Acquire).wrapping_sub(ring.tail.load(Ordering::Relaxed))
+    }
+
+    pub fn try_recv(&mut self) -> Result<RRef<T>, TryRecvError> {
+        // read closed before head, so that everything sent before closing is drained
+        let closed = self.is_closed();
+        let ring = &self.endpoint.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if ring.head.load(Ordering::Acquire) == tail {
+            return Err(if closed { TryRecvError::Closed } else { TryRecvError::Empty });
+        }
+        // the sender does not touch this slot until tail is published
+        let value = unsafe { ring.ptr_mut().slots[tail % N].take() }
+            .expect("RRefChannel slot is empty");
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        unsafe { value.move_to_current() };
+        Ok(value)
+    }
+
+    /// Moves as many elements as are available and fit into `batch` and returns how many
+    /// were received.
+    pub fn recv_batch<const M: usize>(&mut self, batch: &mut RRefDeque<T, M>) -> usize {
+        let mut received = 0;
+        while batch.len() < M {
+            match self.try_recv() {
+                Ok(value) => {
+                    batch.push_back(value);
+                    received += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        received
+    }
+}
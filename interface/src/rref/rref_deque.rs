@@ -76,6 +76,16 @@ impl<T: RRefable, const N: usize> RRefDeque<T, N> {
         return None;
     }
 
+    pub fn push_front(&mut self, value: RRef<T>) -> Option<RRef<T>> {
+        let tail = (self.tail + N - 1) % N;
+        if self.arr.has(tail) {
+            return Some(value);
+        }
+        self.arr.set(tail, value);
+        self.tail = tail;
+        return None;
+    }
+
     pub fn pop_front(&mut self) -> Option<RRef<T>> {
         let value = self.arr.get(self.tail);
         if value.is_some() {
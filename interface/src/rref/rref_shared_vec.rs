@@ -0,0 +1,189 @@
+// although unsafe function's don't need unsafe blocks, it helps readability
+#![allow(unused_unsafe)]
+use super::traits::{RRefable, TypeIdentifiable, CustomCleanup};
+use super::rref::RRef;
+
+use core::alloc::Layout;
+use core::mem::MaybeUninit;
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Read-only, reference counted runtime constant size array on the shared heap.
+///
+/// Unlike `RRefVec`, the same array can be held by any number of domains at once, so
+/// large immutable data (e.g. the contents of a file) can be handed out without a copy.
+/// Like the ring of an `RRefChannel`, the array itself is never owned by a domain (its
+/// domain id is 0). Each holder instead owns a `SharedVecHandle` allocation, which is
+/// cleaned up when the `RRefSharedVec` is dropped or when `drop_domain` reclaims the heap
+/// of a dead domain. The last handle to go away frees the array.
+///
+/// A handle may only see part of the array, see `slice`.
+pub struct RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    handle: RRef<SharedVecHandle<T>>,
+}
+
+unsafe impl<T> RRefable for RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {}
+unsafe impl<T> Send for RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {}
+
+/// The array and its handle count, shared by every `SharedVecHandle`.
+pub struct SharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    data: RRef<T>,
+    size: usize,
+    handles: AtomicUsize,
+}
+
+unsafe impl<T> RRefable for SharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {}
+
+impl<T> CustomCleanup for SharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    fn cleanup(&mut self) {
+        #[cfg(features = "rref_dbg")]
+        println!("CustomCleanup::{}::cleanup()", core::any::type_name_of_val(self));
+        // elements are Copy, so deallocating the array is all there is to do
+        self.data.cleanup();
+    }
+}
+
+/// One holder of an `RRefSharedVec`, allocated on the shared heap and owned by its domain.
+pub struct SharedVecHandle<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    shared: RRef<SharedVec<T>>,
+    // The part of the array this holder sees
+    start: usize,
+    len: usize,
+}
+
+unsafe impl<T> RRefable for SharedVecHandle<T> where T: 'static + RRefable + Copy + TypeIdentifiable {}
+
+impl<T> CustomCleanup for SharedVecHandle<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    fn cleanup(&mut self) {
+        #[cfg(features = "rref_dbg")]
+        println!("CustomCleanup::{}::cleanup()", core::any::type_name_of_val(self));
+        // every handle holds an alias of the shared array, only the last one frees it
+        if self.shared.handles.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.cleanup();
+        }
+    }
+}
+
+impl<T> RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    /// Allocates an array of `size` elements set to `initial_value`, lets `init` fill it in
+    /// and then freezes it.
+    pub fn new_with<F: FnOnce(&mut [T])>(initial_value: T, size: usize, init: F) -> Self {
+        let layout = Self::layout(size);
+        let data: RRef<T> = unsafe { RRef::new_with_layout(initial_value, layout) };
+        let slice = unsafe { core::slice::from_raw_parts_mut(data.ptr_mut() as *mut T, size) };
+        for e in slice.iter_mut() {
+            *e = initial_value;
+        }
+        init(slice);
+        Self::freeze(data, size)
+    }
+
+    /// Shares `data` without copying it. Whoever passed it in may keep reading it through
+    /// `as_array`, but must not write to it any more.
+    pub fn from_array<const N: usize>(data: RRef<[T; N]>) -> Self where [T; N]: RRefable {
+        Self::freeze(unsafe { data.cast() }, N)
+    }
+
+    pub fn from_slice(slice: &[T]) -> Self {
+        let size = slice.len();
+        let layout = Self::layout(size);
+        let data: RRef<T> = unsafe { RRef::new_with_layout(MaybeUninit::uninit().assume_init(), layout) };
+        let dest = unsafe { core::slice::from_raw_parts_mut(data.ptr_mut() as *mut T, size) };
+        dest.copy_from_slice(slice);
+        Self::freeze(data, size)
+    }
+
+    // `new_with_layout` writes the first element, so empty arrays still get one
+    fn layout(size: usize) -> Layout {
+        Layout::array::<T>(core::cmp::max(size, 1)).unwrap()
+    }
+
+    fn freeze(data: RRef<T>, size: usize) -> Self {
+        // the array outlives the domain that created it as long as someone holds a handle
+        data.move_to(0);
+        let shared = RRef::new(SharedVec {
+            data,
+            size,
+            handles: AtomicUsize::new(1),
+        });
+        shared.move_to(0);
+        Self {
+            handle: RRef::new(SharedVecHandle { shared, start: 0, len: size }),
+        }
+    }
+
+    /// Returns a new handle to the same array, owned by the current domain.
+    pub fn share(&self) -> Self {
+        self.slice(0..self.size())
+    }
+
+    /// Returns a new handle that only sees `range` of what this one sees.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        assert!(range.start <= range.end && range.end <= self.size());
+        let shared = &self.handle.shared;
+        shared.handles.fetch_add(1, Ordering::AcqRel);
+        Self {
+            handle: RRef::new(SharedVecHandle {
+                shared: unsafe { shared.alias() },
+                start: self.handle.start + range.start,
+                len: range.end - range.start,
+            }),
+        }
+    }
+
+    /// A second handle to the array passed to `from_array`, for its creator to keep reading
+    /// it. The handle must neither be written through nor dropped, it has to be forgotten.
+    pub unsafe fn as_array<const N: usize>(&self) -> RRef<[T; N]> where [T; N]: RRefable {
+        let shared = &self.handle.shared;
+        assert_eq!(shared.size, N);
+        unsafe { shared.data.alias().cast() }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        let handle = &self.handle;
+        unsafe { core::slice::from_raw_parts((&*handle.shared.data as *const T).add(handle.start), handle.len) }
+    }
+
+    pub fn size(&self) -> usize {
+        self.handle.len
+    }
+
+    /// Number of handles currently sharing the array.
+    pub fn handles(&self) -> usize {
+        self.handle.shared.handles.load(Ordering::Acquire)
+    }
+
+    // TODO: mark unsafe?
+    pub fn move_to(&self, new_domain_id: u64) {
+        self.handle.move_to(new_domain_id);
+    }
+
+    pub fn borrow(&self) {
+        self.handle.borrow();
+    }
+
+    pub fn forfeit(&self) {
+        self.handle.forfeit();
+    }
+}
+
+impl<T> CustomCleanup for RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    fn cleanup(&mut self) {
+        self.handle.cleanup();
+    }
+}
+
+impl<T> Clone for RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    fn clone(&self) -> Self {
+        self.share()
+    }
+}
+
+impl<T> core::fmt::Debug for RRefSharedVec<T> where T: 'static + RRefable + Copy + TypeIdentifiable {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RRefSharedVec")
+            .field("size", &self.size())
+            .field("handles", &self.handles())
+            .finish()
+    }
+}
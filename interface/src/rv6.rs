@@ -25,12 +25,29 @@ pub trait Rv6: Send + Sync {
     fn get_usrtpm(&self) -> RpcResult<Box<dyn UsrTpm>>;
     fn sys_spawn_thread(&self, name: RRefVec<u8>, func: alloc::boxed::Box<dyn FnOnce() + Send>) -> RpcResult<Result<Box<dyn Thread>>>;
     // We need to pass a new instance of `rv6` as a parameter so that the proxy can be properly propagated.
-    fn sys_spawn_domain(&self, rv6: Box<dyn Rv6>, path: RRefVec<u8>, args: RRefVec<u8>, fds: [Option<usize>; NFILE]) -> RpcResult<Result<Box<dyn Thread>>>;
+    // `argv` and `envp` hold NUL terminated strings, e.g. "ls\0-l\0" and "PATH=/\0". A `path`
+    // without a `/` is looked up in the directories of the `PATH` variable of `envp`.
+    // The new domain's fd `i` is the caller's fd `fds[i]`, or if that is `None`, the caller's fd
+    // `i` unless it is close-on-exec. E.g. `[None, Some(3), Some(3)]` redirects stdout and stderr.
+    fn sys_spawn_domain(&self, rv6: Box<dyn Rv6>, path: RRefVec<u8>, argv: RRefVec<u8>, envp: RRefVec<u8>, fds: [Option<usize>; NFILE]) -> RpcResult<Result<Box<dyn Thread>>>;
     fn sys_getpid(&self) -> RpcResult<Result<u64>>;
+    // Kills the thread with the given pid, which then exits with `EXIT_KILLED`. It is woken up
+    // if it waits, e.g. for a pipe or a thread, and unwound right away if it runs the code of a
+    // program with a single thread. A system call it is in fails with `ErrorKind::Killed`.
+    fn sys_kill(&self, pid: u64) -> RpcResult<Result<()>>;
     fn sys_uptime(&self) -> RpcResult<Result<u64>>;
     fn sys_sleep(&self, ns: u64) -> RpcResult<Result<()>>;
 }
 
+/// Exit status of a user program that succeeded
+pub const EXIT_SUCCESS: i32 = 0;
+/// Exit status of a user program that failed
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit status of a user program that panicked
+pub const EXIT_PANIC: i32 = 101;
+/// Exit status of a user program that was killed, by `sys_kill` or Ctrl-C
+pub const EXIT_KILLED: i32 = 137;
+
 pub trait File: Send {
     fn read(&self, data: &mut [u8]) -> usize;
     fn write(&self, data: &[u8]) -> usize;
@@ -38,5 +55,10 @@ pub trait File: Send {
 
 #[interface]
 pub trait Thread: Send {
-    fn join(&self) -> RpcResult<()>;
+    // Waits for the thread to exit and returns its exit status. Threads started by
+    // `sys_spawn_thread` exit with `EXIT_SUCCESS` unless they panic. Fails with
+    // `ErrorKind::Killed` if the waiting thread gets killed.
+    fn join(&self) -> RpcResult<Result<i32>>;
+    // Returns the exit status without waiting, `None` if the thread is still running
+    fn try_join(&self) -> RpcResult<Option<i32>>;
 }
@@ -1,12 +1,12 @@
 use byteorder::{ByteOrder, LittleEndian};
 use core::convert::TryFrom;
 
-/// Max size of directory name
-const DIRSIZ: usize = 14;
+/// Max size of directory name. Together with the inode number, a directory entry takes 64 bytes.
+pub const DIRSIZ: usize = 62;
 
 // Correspond to dirent in xv6
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 pub struct DirectoryEntry {
     pub inum: u16,
     pub name: [u8; DIRSIZ],
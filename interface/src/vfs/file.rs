@@ -4,10 +4,35 @@ bitflags! {
         const READ = 0b001;
         const WRITE = 0b010;
         const CREATE = 0b100;
+        // The file descriptor is not inherited by spawned domains
+        const CLOSE_ON_EXEC = 0b1000;
         const READWRITE = Self::READ.bits | Self::WRITE.bits;
     }
 }
 
+bitflags! {
+    /// Access permission bits of an inode, laid out like the Unix mode bits
+    pub struct Permissions: u16 {
+        const OWNER_READ = 0o400;
+        const OWNER_WRITE = 0o200;
+        const OWNER_EXEC = 0o100;
+        const GROUP_READ = 0o040;
+        const GROUP_WRITE = 0o020;
+        const GROUP_EXEC = 0o010;
+        const OTHER_READ = 0o004;
+        const OTHER_WRITE = 0o002;
+        const OTHER_EXEC = 0o001;
+    }
+}
+
+/// Position to seek to, relative to the start, the end or the current offset of a file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct FileStat {
     pub device: u32,
@@ -15,6 +40,13 @@ pub struct FileStat {
     pub file_type: INodeFileType,
     pub nlink: i16,
     pub size: u64,
+    // User id of the owner
+    pub uid: u16,
+    pub permissions: Permissions,
+    // Last modification of the content, in seconds since the Unix epoch
+    pub mtime: u64,
+    // Last change of the inode itself, in seconds since the Unix epoch
+    pub ctime: u64,
 }
 
 #[repr(u16)]
@@ -28,4 +60,6 @@ pub enum INodeFileType {
     File,
     // Correspond to
     Device,
+    // Symbolic link; the content of the inode is the path it points to
+    Symlink,
 }
@@ -3,17 +3,27 @@
 /// Some of the syscalls do no return the buffer back to the caller. Feel free
 /// to change it if it's needed.
 use alloc::boxed::Box;
-use crate::rref::RRefVec;
+use crate::rref::{RRefSharedVec, RRefVec};
 
-pub use crate::vfs::file::{FileMode, FileStat, INodeFileType};
-pub use crate::vfs::directory::{DirectoryEntry, DirectoryEntryRef};
+pub use crate::vfs::file::{FileMode, FileStat, INodeFileType, Permissions, SeekFrom};
+pub use crate::vfs::directory::{DirectoryEntry, DirectoryEntryRef, DIRSIZ};
+pub use crate::vfs::stats::FsStats;
+pub use crate::vfs::time::UtcTime;
 pub use crate::error::{Result, ErrorKind};
 use crate::rpc::RpcResult;
 
 pub mod file;
 pub mod directory;
+pub mod stats;
+pub mod time;
 
 pub const NFILE: usize =       100;     // open files per system
+pub const MAXPATH: usize =     1024;    // max length of a path returned by sys_getcwd or sys_readlink
+
+// On-disk inodes of rv6, for the tools reading its images without going through the file system
+pub const NDIRECT: usize =     12;      // direct block addresses of an inode
+pub const DINODE_SIZE: usize = 88;      // size of an on-disk inode
+pub const DINODE_ADDRESSES: usize = 32; // offset of the block addresses in an on-disk inode
 
 // syscalls that are exposed to both the kernel and the users
 #[interface]
@@ -22,25 +32,53 @@ pub trait UsrVFS: Send + Sync {
     fn sys_close(&self, fd: usize) -> RpcResult<Result<()>>;
     fn sys_read(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
     fn sys_write(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
-    fn sys_seek(&self, fd: usize, offset: usize) -> RpcResult<Result<()>>;
+    fn sys_seek(&self, fd: usize, pos: SeekFrom) -> RpcResult<Result<usize>>;
     fn sys_fstat(&self, fd: usize) -> RpcResult<Result<FileStat>>;
     fn sys_mknod(&self, path: RRefVec<u8>, major: i16, minor: i16) -> RpcResult<Result<()>>;
     fn sys_dup(&self, fd: usize) -> RpcResult<Result<usize>>;
+    // Makes `new_fd` refer to the file of `fd`, closing whatever `new_fd` referred to before
+    fn sys_dup2(&self, fd: usize, new_fd: usize) -> RpcResult<Result<usize>>;
+    // Whether `fd` is closed in the domains spawned by this one instead of being inherited.
+    // New file descriptors are inherited, unless opened with `FileMode::CLOSE_ON_EXEC`.
+    fn sys_set_close_on_exec(&self, fd: usize, close_on_exec: bool) -> RpcResult<Result<()>>;
     fn sys_pipe(&self) -> RpcResult<Result<(usize, usize)>>;
     fn sys_link(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>>;
     fn sys_unlink(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
     fn sys_mkdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    fn sys_rename(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    fn sys_ftruncate(&self, fd: usize, size: u64) -> RpcResult<Result<()>>;
+    // Returns a read-only view of the file from `offset` to the end of the block holding it, or
+    // an empty one past the end of the file. The view shares the block with the buffer cache,
+    // later changes to the file do not show up in it. Only regular files can be mapped, anything
+    // else fails with `InvalidFileType`.
+    fn sys_mmap(&self, fd: usize, offset: usize) -> RpcResult<Result<RRefSharedVec<u8>>>;
+    // Returns the next entry of the directory, or None once all entries were read
+    fn sys_readdir(&self, fd: usize) -> RpcResult<Result<Option<DirectoryEntry>>>;
+    fn sys_chdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    // Writes the absolute path of the working directory into `buffer` and returns its length
+    fn sys_getcwd(&self, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
+    fn sys_symlink(&self, target: RRefVec<u8>, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    // Writes the target of the symbolic link at `path` into `buffer` and returns its length
+    fn sys_readlink(&self, path: RRefVec<u8>, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
+    fn sys_chmod(&self, path: RRefVec<u8>, permissions: Permissions) -> RpcResult<Result<()>>;
+    // Returns once the changes of every system call that already returned are on disk
+    fn sys_sync(&self) -> RpcResult<Result<()>>;
+    fn sys_fs_stats(&self) -> RpcResult<Result<FsStats>>;
     fn sys_dump_inode(&self) -> RpcResult<Result<()>>;
 }
 
 // syscalls that are only exposed to the kernel
 pub trait KernelVFS: Send + Sync  {
-    // Save threadlocal objects to a temporary storage and return its id
-    // For fdtable, only save the selected ones specified by `fds`
-    fn sys_save_threadlocal(&self, fds: [Option<usize>; NFILE]) -> RpcResult<Result<usize>>;
-    // Set threadlocal objects to a temporary object identify by the `id`
-    fn sys_set_threadlocal(&self, id: usize) -> RpcResult<Result<()>>;
-    // Tell the file system that this thread is exiting and thread local objects should be cleaned up
+    // Create the process of a domain spawned by the calling thread and return the id to enter
+    // it with. Its fd `i` refers to the file of the caller's fd `fds[i]`, or if that is `None`,
+    // inherits the caller's fd `i` unless it is close-on-exec.
+    fn sys_spawn_process(&self, fds: [Option<usize>; NFILE]) -> RpcResult<Result<usize>>;
+    // Return the id another thread enters the caller's process with, to share its files
+    fn sys_share_process(&self) -> RpcResult<Result<usize>>;
+    // Move the calling thread into the process identified by `id`
+    fn sys_enter_process(&self, id: usize) -> RpcResult<Result<()>>;
+    // Tell the file system that this thread is exiting. The last thread of a process closes
+    // the files it left open.
     fn sys_thread_exit(&self) -> RpcResult<()>;
 }
 
@@ -53,23 +91,51 @@ pub trait VFS: Send + Sync {
     fn sys_close(&self, fd: usize) -> RpcResult<Result<()>>;
     fn sys_read(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
     fn sys_write(&self, fd: usize, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
-    fn sys_seek(&self, fd: usize, offset: usize) -> RpcResult<Result<()>>;
+    fn sys_seek(&self, fd: usize, pos: SeekFrom) -> RpcResult<Result<usize>>;
     fn sys_fstat(&self, fd: usize) -> RpcResult<Result<FileStat>>;
     fn sys_mknod(&self, path: RRefVec<u8>, major: i16, minor: i16) -> RpcResult<Result<()>>;
     fn sys_dup(&self, fd: usize) -> RpcResult<Result<usize>>;
+    // Makes `new_fd` refer to the file of `fd`, closing whatever `new_fd` referred to before
+    fn sys_dup2(&self, fd: usize, new_fd: usize) -> RpcResult<Result<usize>>;
+    // Whether `fd` is closed in the domains spawned by this one instead of being inherited.
+    // New file descriptors are inherited, unless opened with `FileMode::CLOSE_ON_EXEC`.
+    fn sys_set_close_on_exec(&self, fd: usize, close_on_exec: bool) -> RpcResult<Result<()>>;
     fn sys_pipe(&self) -> RpcResult<Result<(usize, usize)>>;
     fn sys_link(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>>;
     fn sys_unlink(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
     fn sys_mkdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    fn sys_rename(&self, old_path: RRefVec<u8>, new_path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    fn sys_ftruncate(&self, fd: usize, size: u64) -> RpcResult<Result<()>>;
+    // Returns a read-only view of the file from `offset` to the end of the block holding it, or
+    // an empty one past the end of the file. The view shares the block with the buffer cache,
+    // later changes to the file do not show up in it. Only regular files can be mapped, anything
+    // else fails with `InvalidFileType`.
+    fn sys_mmap(&self, fd: usize, offset: usize) -> RpcResult<Result<RRefSharedVec<u8>>>;
+    // Returns the next entry of the directory, or None once all entries were read
+    fn sys_readdir(&self, fd: usize) -> RpcResult<Result<Option<DirectoryEntry>>>;
+    fn sys_chdir(&self, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    // Writes the absolute path of the working directory into `buffer` and returns its length
+    fn sys_getcwd(&self, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
+    fn sys_symlink(&self, target: RRefVec<u8>, path: RRefVec<u8>) -> RpcResult<Result<()>>;
+    // Writes the target of the symbolic link at `path` into `buffer` and returns its length
+    fn sys_readlink(&self, path: RRefVec<u8>, buffer: RRefVec<u8>) -> RpcResult<Result<(usize, RRefVec<u8>)>>;
+    fn sys_chmod(&self, path: RRefVec<u8>, permissions: Permissions) -> RpcResult<Result<()>>;
+    // Returns once the changes of every system call that already returned are on disk
+    fn sys_sync(&self) -> RpcResult<Result<()>>;
+    fn sys_fs_stats(&self) -> RpcResult<Result<FsStats>>;
     fn sys_dump_inode(&self) -> RpcResult<Result<()>>;
 
     // KernelVFS starts.
-    // Save threadlocal objects to a temporary storage and return its id
-    // For fdtable, only save the selected ones specified by `fds`
-    fn sys_save_threadlocal(&self, fds: [Option<usize>; NFILE]) -> RpcResult<Result<usize>>;
-    // Set threadlocal objects to a temporary object identify by the `id`
-    fn sys_set_threadlocal(&self, id: usize) -> RpcResult<Result<()>>;
-    // Tell the file system that this thread is exiting and thread local objects should be cleaned up
+    // Create the process of a domain spawned by the calling thread and return the id to enter
+    // it with. Its fd `i` refers to the file of the caller's fd `fds[i]`, or if that is `None`,
+    // inherits the caller's fd `i` unless it is close-on-exec.
+    fn sys_spawn_process(&self, fds: [Option<usize>; NFILE]) -> RpcResult<Result<usize>>;
+    // Return the id another thread enters the caller's process with, to share its files
+    fn sys_share_process(&self) -> RpcResult<Result<usize>>;
+    // Move the calling thread into the process identified by `id`
+    fn sys_enter_process(&self, id: usize) -> RpcResult<Result<()>>;
+    // Tell the file system that this thread is exiting. The last thread of a process closes
+    // the files it left open.
     fn sys_thread_exit(&self) -> RpcResult<()>;
 
     fn clone(&self) -> RpcResult<Box<dyn VFS>>;
@@ -0,0 +1,14 @@
+/// Counters of the file system since it was mounted
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FsStats {
+    /// Block lookups that found the block in the buffer cache
+    pub bcache_hits: u64,
+    /// Block lookups that had to read the block from the device
+    pub bcache_misses: u64,
+    /// Blocks read into the cache ahead of a sequential read
+    pub readahead_blocks: u64,
+    /// Read requests sent to the block device, a batch of blocks counting as one
+    pub read_requests: u64,
+    /// Write requests sent to the block device, a batch of blocks counting as one
+    pub write_requests: u64,
+}
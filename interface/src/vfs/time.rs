@@ -0,0 +1,89 @@
+// Inode timestamps are seconds since the Unix epoch, in UTC. The kernel's RTC driver turns its
+// calendar date into one, `ls`, `stat` and rv6-mkfs turn them back into dates.
+use core::fmt;
+
+/// Days between 1970-01-01 and a date of the proleptic Gregorian calendar
+pub fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // Count years from March, so that the leap day is the last day of a year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_since_epoch`, returns the (year, month, day) of a day
+pub fn date_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Displays seconds since the Unix epoch as a UTC date, e.g. 2020-04-01 12:00:00
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UtcTime(pub u64);
+
+impl fmt::Display for UtcTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (days, seconds) = (self.0 / 86_400, self.0 % 86_400);
+        let (year, month, day) = date_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds / 3_600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn known_dates() {
+        assert_eq!(days_since_epoch(1970, 1, 1), 0);
+        assert_eq!(days_since_epoch(2000, 3, 1), 11_017);
+        assert_eq!(days_since_epoch(2020, 2, 29), 18_321);
+        assert_eq!(date_from_days(18_321), (2020, 2, 29));
+    }
+
+    #[test]
+    fn days_round_trip() {
+        // Every day up to the year 2517, across leap days and century years
+        for days in 0..200_000 {
+            let (year, month, day) = date_from_days(days);
+            assert_eq!(
+                days_since_epoch(year, month, day),
+                days,
+                "{}-{}-{}",
+                year,
+                month,
+                day
+            );
+        }
+    }
+
+    #[test]
+    fn utc_time() {
+        assert_eq!(format!("{}", UtcTime(0)), "1970-01-01 00:00:00");
+        assert_eq!(
+            format!("{}", UtcTime(11_017 * 86_400 + 45_296)),
+            "2000-03-01 12:34:56"
+        );
+    }
+}
@@ -4,11 +4,16 @@ mod vga;
 
 use crate::console::serial::{EMERGENCY_SERIAL1, EMERGENCY_SERIAL2, SERIAL1, SERIAL2};
 use crate::console::vga::WRITER;
+pub use crate::console::serial::{COM1_IRQ, SERIAL1_RX};
 use core::fmt::Write;
+use core::sync::atomic::AtomicBool;
 use x86::cpuid::CpuId;
 
 pub static mut IN_A_CRASH: bool = false;
 
+/// Set when Ctrl-C is typed on the console, taken by `sys_console_interrupt`
+pub static CONSOLE_INTERRUPT: AtomicBool = AtomicBool::new(false);
+
 pub fn unlock_console() {
     unsafe {
         IN_A_CRASH = true;
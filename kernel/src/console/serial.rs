@@ -1,7 +1,18 @@
 use x86::io::{inb, outb};
 
+use crate::cb::CircularBuffer;
+use crate::drivers::Driver;
+use crate::redsys::IRQRegistrar;
+use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
+use pc_keyboard::DecodedKey;
+
 const COM1_PORT: u16 = 0x3F8;
 const COM2_PORT: u16 = 0x2F8;
+// ISA IRQ line of COM1
+pub const COM1_IRQ: u8 = 4;
+// What terminals send for Ctrl-C
+const ETX: u8 = 0x03;
 
 pub struct SerialPort {
     base_port: u16,
@@ -93,3 +104,77 @@ lazy_static! {
         }
     };
 }
+
+// Interrupt-driven receive side of a serial port. Bytes are queued by the IRQ handler
+// and handed out as keys by `readch`, so the serial console can stand in for the
+// keyboard (e.g. under `make qemu-nox`).
+pub struct SerialReceiver {
+    base_port: u16,
+    key_buf: CircularBuffer<u8>,
+    // The last byte was a carriage return, so a following line feed is dropped
+    after_cr: bool,
+}
+
+impl SerialReceiver {
+    fn new(base_port: u16) -> SerialReceiver {
+        SerialReceiver {
+            base_port,
+            key_buf: CircularBuffer::<u8>::new_with_size(256),
+            after_cr: false,
+        }
+    }
+
+    pub fn irq_handler(&mut self) {
+        // Drain the FIFO, bytes that don't fit are dropped
+        unsafe {
+            while inb(self.base_port + 5) & 0x01 != 0 {
+                let byte = inb(self.base_port);
+                // Flag Ctrl-C right away, the program to interrupt may not be reading the
+                // console. It is still queued so that the console can discard the line.
+                if byte == ETX {
+                    crate::console::CONSOLE_INTERRUPT.store(true, Ordering::Release);
+                }
+                let _ = self.key_buf.push(byte);
+            }
+        }
+    }
+
+    // Terminals send \r (or \r\n) for enter and DEL for backspace, translate them
+    // to what the keyboard produces
+    pub fn readch(&mut self) -> Option<DecodedKey> {
+        while let Ok(byte) = self.key_buf.pop() {
+            let after_cr = core::mem::replace(&mut self.after_cr, byte == b'\r');
+            let key = match byte {
+                b'\r' => '\n',
+                b'\n' if after_cr => continue,
+                0x7F => '\x08',
+                _ => byte as char,
+            };
+            // Echo back to user
+            if !key.is_control() {
+                crate::print!("{}", key);
+            }
+            return Some(DecodedKey::Unicode(key));
+        }
+        None
+    }
+}
+
+impl Driver for SerialReceiver {
+    fn set_irq_registrar(&mut self, registrar: IRQRegistrar<SerialReceiver>) {
+        registrar
+            .request_irq(COM1_IRQ, SerialReceiver::irq_handler)
+            .unwrap();
+        unsafe {
+            outb(self.base_port + 4, 0x0B); // RTS/DSR set, OUT2 routes the IRQ to the PIC
+            outb(self.base_port + 1, 0x01); // Interrupt when data is received
+        }
+        // Bytes that arrived before the interrupt was enabled won't raise one
+        self.irq_handler();
+    }
+}
+
+lazy_static! {
+    pub static ref SERIAL1_RX: Arc<Mutex<SerialReceiver>> =
+        Arc::new(Mutex::new(SerialReceiver::new(COM1_PORT)));
+}
@@ -0,0 +1,89 @@
+use super::load_domain::try_load_domain;
+use crate::heap::PHeap;
+use crate::interrupt::{disable_irq, enable_irq};
+use crate::syscalls::PDomain;
+use alloc::boxed::Box;
+use interface::domain_create::{DomainSource, LoadError, LoadedDomain};
+
+// Domain binaries linked into the kernel image (see `domain_list` in the top level Makefile)
+macro_rules! linked_domains {
+    ($($name:literal => $start:ident, $end:ident;)*) => {
+        fn linked_binary_range(name: &str) -> Option<(*const u8, *const u8)> {
+            extern "C" {
+                $(
+                    static $start: u8;
+                    static $end: u8;
+                )*
+            }
+
+            unsafe {
+                match name {
+                    $($name => Some((&$start as *const u8, &$end as *const u8)),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+linked_domains! {
+    "proxy" => _binary_domains_build_proxy_start, _binary_domains_build_proxy_end;
+    "dom_c" => _binary_domains_build_dom_c_start, _binary_domains_build_dom_c_end;
+    "dom_d" => _binary_domains_build_dom_d_start, _binary_domains_build_dom_d_end;
+    "shadow" => _binary_domains_build_shadow_start, _binary_domains_build_shadow_end;
+    "xv6kernel" => _binary_domains_build_xv6kernel_start, _binary_domains_build_xv6kernel_end;
+    "xv6fs" => _binary_domains_build_xv6fs_start, _binary_domains_build_xv6fs_end;
+    "xv6net" => _binary_domains_build_xv6net_start, _binary_domains_build_xv6net_end;
+    "xv6net_shadow" => _binary_domains_build_xv6net_shadow_start, _binary_domains_build_xv6net_shadow_end;
+    "pci" => _binary_domains_build_pci_start, _binary_domains_build_pci_end;
+    "ixgbe" => _binary_domains_build_ixgbe_start, _binary_domains_build_ixgbe_end;
+    "virtio_net" => _binary_domains_build_virtio_net_start, _binary_domains_build_virtio_net_end;
+    "virtio_block" => _binary_domains_build_virtio_block_start, _binary_domains_build_virtio_block_end;
+    "nvme" => _binary_domains_build_nvme_start, _binary_domains_build_nvme_end;
+    "tpm" => _binary_domains_build_tpm_start, _binary_domains_build_tpm_end;
+    "bdev_shadow" => _binary_domains_build_bdev_shadow_start, _binary_domains_build_bdev_shadow_end;
+    "net_shadow" => _binary_domains_build_net_shadow_start, _binary_domains_build_net_shadow_end;
+    "nvme_shadow" => _binary_domains_build_nvme_shadow_start, _binary_domains_build_nvme_shadow_end;
+    "nvme_bdev" => _binary_domains_build_nvme_bdev_start, _binary_domains_build_nvme_bdev_end;
+    "membdev" => _binary_domains_build_membdev_start, _binary_domains_build_membdev_end;
+    "benchnvme" => _binary_domains_build_benchnvme_start, _binary_domains_build_benchnvme_end;
+    "crashfs" => _binary_domains_build_crashfs_start, _binary_domains_build_crashfs_end;
+}
+
+/// Kernel implementation of the unified `CreateDomain` capability
+pub struct PCreateDomain;
+
+impl PCreateDomain {
+    pub const fn new() -> PCreateDomain {
+        PCreateDomain {}
+    }
+}
+
+impl interface::domain_create::CreateDomain for PCreateDomain {
+    fn load_domain(&self, source: DomainSource) -> Result<LoadedDomain, LoadError> {
+        let binary_range = match source {
+            DomainSource::Named(name) => linked_binary_range(name).ok_or(LoadError::NotFound)?,
+            DomainSource::Blob { blob, .. } => {
+                (blob.as_ptr(), unsafe { blob.as_ptr().add(blob.len()) })
+            }
+        };
+
+        disable_irq();
+        let loaded = unsafe { try_load_domain(source.name(), binary_range) };
+        enable_irq();
+
+        let (dom, entry) = loaded.map_err(|e| {
+            println!("domain/{}: {}", source.name(), e);
+            e
+        })?;
+
+        unsafe {
+            Ok(LoadedDomain::new(
+                Box::new(PDomain::new(dom.clone())),
+                entry,
+                Box::new(PDomain::new(dom)),
+                Box::new(PHeap::new()),
+            ))
+        }
+    }
+}
@@ -6,7 +6,8 @@ use crate::alloc::vec::Vec;
 use crate::arch::vspace::{MapAction, ResourceType, VSpace};
 use crate::memory::VSPACE;
 use crate::thread::Thread;
-use alloc::sync::Arc;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
 use log::{debug, info, trace};
 use spin::Mutex;
 use x86::bits64::paging::{PAddr, VAddr, BASE_PAGE_SHIFT, BASE_PAGE_SIZE};
@@ -26,6 +27,15 @@ static DOMAIN_ID: AtomicU64 = AtomicU64::new(0);
 /// Global Domain list
 pub static KERNEL_DOMAIN: Once<Arc<Mutex<Domain>>> = Once::new();
 
+/// Every domain created by `new_domain`, for `sys_domains`. Entries of
+/// dropped domains are pruned by `domains`
+static DOMAINS: Mutex<Vec<Weak<Mutex<Domain>>>> = Mutex::new(Vec::new());
+
+lazy_static! {
+    /// How many times each domain name was loaded
+    static ref LOADS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+}
+
 //#[thread_local]
 //pub static BOOTING_DOMAIN: RefCell<Option<Box<PDomain>>> = RefCell::new(None);
 
@@ -40,6 +50,8 @@ pub struct Domain {
     /// List of threads in the domain
     //threads: Option<Arc<Mutex<Rc<RefCell<Thread>>>>>,
     threads: DomainThreads,
+    /// Loads of a domain with this name so far, including this one
+    pub loads: u64,
 }
 
 pub struct DomainThreads {
@@ -64,6 +76,7 @@ impl Domain {
             offset: VAddr::from(0usize),
             entry_point: VAddr::from(0usize),
             threads: DomainThreads::new(),
+            loads: 0,
         }
     }
 
@@ -80,12 +93,55 @@ impl Domain {
 
         self.threads.head = Some(t);
     }
+
+    /// Threads of the domain, newest first
+    pub fn threads(&self) -> Vec<Arc<Mutex<Thread>>> {
+        let mut threads = Vec::new();
+        let mut next = self.threads.head.clone();
+        while let Some(t) = next {
+            next = t.lock().next_domain.clone();
+            threads.push(t);
+        }
+        threads
+    }
+
+    pub fn info(&self) -> syscalls::DomainInfo {
+        syscalls::DomainInfo {
+            id: self.id,
+            name: syscalls::InfoName::new(&self.name),
+            threads: self.threads().len(),
+            shared_heap: crate::heap::shared_heap_usage(Some(self.id)).0,
+            loads: self.loads,
+        }
+    }
+}
+
+/// Creates a domain and registers it for `domains`
+pub fn new_domain(name: &str) -> Arc<Mutex<Domain>> {
+    let mut domain = Domain::new(name);
+    {
+        let mut loads = LOADS.lock();
+        let count = loads.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        domain.loads = *count;
+    }
+
+    let domain = Arc::new(Mutex::new(domain));
+    DOMAINS.lock().push(Arc::downgrade(&domain));
+    domain
+}
+
+/// Domains that are still alive, oldest first
+pub fn domains() -> Vec<Arc<Mutex<Domain>>> {
+    let mut domains = DOMAINS.lock();
+    domains.retain(|d| d.strong_count() > 0);
+    domains.iter().filter_map(|d| d.upgrade()).collect()
 }
 
 /// Create kernel domain (must be called before any threads are
 /// created)
 pub fn init_domains() {
-    let kernel = Arc::new(Mutex::new(Domain::new("kernel")));
+    let kernel = new_domain("kernel");
     libsyscalls::syscalls::init(Box::new(PDomain::new(Arc::clone(&kernel))));
     KERNEL_DOMAIN.call_once(|| kernel);
 
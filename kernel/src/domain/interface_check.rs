@@ -0,0 +1,45 @@
+use elfloader::ElfBinary;
+use interface::fingerprint::{InterfaceFingerprint, SECTION_NAME};
+
+pub enum InterfaceCheckResult {
+    /// The binary carries no interface fingerprints
+    Unversioned,
+    /// Every interface known to both sides has the same fingerprint
+    Compatible,
+    /// The domain was built against a different version of an interface
+    Mismatch {
+        domain: InterfaceFingerprint,
+        kernel: InterfaceFingerprint,
+    },
+}
+
+pub fn verify(domain_elf: &ElfBinary) -> InterfaceCheckResult {
+    let section = match domain_elf.file.find_section_by_name(SECTION_NAME) {
+        Some(section) => section,
+        None => return InterfaceCheckResult::Unversioned,
+    };
+
+    let records = section.raw_data(&domain_elf.file);
+    if records.is_empty() {
+        return InterfaceCheckResult::Unversioned;
+    }
+
+    for record in records.chunks(InterfaceFingerprint::SIZE) {
+        let domain = match InterfaceFingerprint::from_bytes(record) {
+            Some(domain) => domain,
+            None => continue,
+        };
+
+        // Interfaces the kernel doesn't know about can't be passed through it
+        if let Some(kernel) = InterfaceFingerprint::lookup(domain.name()) {
+            if kernel.version != domain.version || kernel.hash != domain.hash {
+                return InterfaceCheckResult::Mismatch {
+                    domain,
+                    kernel: *kernel,
+                };
+            }
+        }
+    }
+
+    InterfaceCheckResult::Compatible
+}
@@ -1,9 +1,12 @@
-use super::domain::Domain;
+use super::domain::{new_domain, Domain};
+use super::interface_check;
+use super::interface_check::InterfaceCheckResult;
 use super::trusted_binary;
 use super::trusted_binary::SignatureCheckResult;
 use alloc::string::String;
 use alloc::sync::Arc;
 use elfloader::ElfBinary;
+use interface::domain_create::LoadError;
 use spin::Mutex;
 
 #[cfg(feature = "gdb_domain_variables")]
@@ -11,10 +14,22 @@ use spin::Mutex;
 /// This is a dummy function. It exists only to have a breakpoint set on it which will allow the gdb helper script to handle changes
 pub(crate) fn gdb_notify_new_domain_loaded() {}
 
+/// Loads a domain for the generated per-domain `Create*` traits, which have no way to report
+/// an error. Domains created through `CreateDomain` get the `LoadError` instead.
 pub unsafe fn load_domain(
     name: &str,
     binary_range: (*const u8, *const u8),
 ) -> (Arc<Mutex<Domain>>, *const ()) {
+    match try_load_domain(name, binary_range) {
+        Ok(domain) => domain,
+        Err(e) => panic!("domain/{}: {}", name, e),
+    }
+}
+
+pub unsafe fn try_load_domain(
+    name: &str,
+    binary_range: (*const u8, *const u8),
+) -> Result<(Arc<Mutex<Domain>>, *const ()), LoadError> {
     let (binary_start, binary_end) = binary_range;
 
     let num_bytes = ((binary_end as usize) - (binary_start as usize)) as usize;
@@ -48,7 +63,7 @@ pub unsafe fn load_domain(
         binary_vec.as_slice()
     };
 
-    let domain_elf = ElfBinary::new(name, binary).expect("Invalid ELF file");
+    let domain_elf = ElfBinary::new(name, binary).map_err(LoadError::InvalidElf)?;
 
     // Verify signature in binary
     // FIXME: Actually enforce this
@@ -64,13 +79,34 @@ pub unsafe fn load_domain(
         }
     }
 
+    // Check that the domain agrees with the kernel on the shape of every interface
+    match interface_check::verify(&domain_elf) {
+        InterfaceCheckResult::Unversioned => {
+            println!("domain/{}: Binary has no interface fingerprints", name);
+        }
+        InterfaceCheckResult::Compatible => {
+            println!("domain/{}: Binary interfaces match", name);
+        }
+        InterfaceCheckResult::Mismatch { domain, kernel } => {
+            return Err(LoadError::InterfaceMismatch {
+                interface: String::from(domain.name()),
+                domain_version: domain.version,
+                domain_hash: domain.hash,
+                kernel_version: kernel.version,
+                kernel_hash: kernel.hash,
+            });
+        }
+    }
+
     // Create a domain for the to-be-loaded elf file
-    let dom = Arc::new(Mutex::new(Domain::new(name)));
+    let dom = new_domain(name);
 
     let mut loader = dom.lock();
 
     // load the binary
-    domain_elf.load(&mut *loader).expect("Cannot load binary");
+    domain_elf
+        .load(&mut *loader)
+        .map_err(LoadError::LoadFailed)?;
 
     // print its entry point for now
     println!(
@@ -115,5 +151,5 @@ pub unsafe fn load_domain(
     // deadlock
     drop(loader);
 
-    (dom, user_ep)
+    Ok((dom, user_ep))
 }
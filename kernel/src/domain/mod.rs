@@ -2,8 +2,12 @@ pub mod domain;
 pub use domain::Domain;
 
 mod load_domain;
-pub use load_domain::load_domain;
+pub use load_domain::{load_domain, try_load_domain};
+
+mod create_domain;
+pub use create_domain::PCreateDomain;
 
 mod trusted_binary;
+mod interface_check;
 
 pub mod sys_init;
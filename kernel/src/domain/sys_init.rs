@@ -1,11 +1,10 @@
-use super::Domain;
+use super::domain::new_domain;
 use crate::interrupt::{disable_irq, enable_irq};
 use crate::syscalls::PDomain;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
 use core::mem::transmute;
 use elfloader::ElfBinary;
-use spin::Mutex;
 use syscalls::Syscall;
 //use syscalls::BootSyscall;
 //use crate::domain::domain::BOOTING_DOMAIN;
@@ -47,7 +46,7 @@ pub unsafe fn load_sys_init() {
     .expect("Got ELF file");
 
     // Create a domain for the to-be-loaded elf file
-    let dom = Arc::new(Mutex::new(Domain::new("sys_init")));
+    let dom = new_domain("sys_init");
 
     // Create a domain for the to-be-loaded elf file
     let mut loader = dom.lock();
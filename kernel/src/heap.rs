@@ -83,6 +83,19 @@ unsafe fn dealloc_heap(ptr: *mut u8) {
     }
 }
 
+/// Bytes and number of shared heap allocations, of one domain or of all of them
+pub fn shared_heap_usage(domain_id: Option<u64>) -> (usize, usize) {
+    let mut bytes = 0;
+    let mut count = 0;
+    for allocation in allocations.lock().values() {
+        if domain_id.map_or(true, |id| unsafe { *allocation.domain_id_pointer } == id) {
+            bytes += allocation.layout.size();
+            count += 1;
+        }
+    }
+    (bytes, count)
+}
+
 pub unsafe fn drop_domain(domain_id: u64) {
     // the list of allocations belonging to the domain
     let mut queue = Vec::<SharedHeapAllocation>::new();
@@ -199,6 +199,7 @@ pub fn init_irqs() {
         ioapic::init();
         // TODO: Fix kbd and enable this
         //ioapic::irqen(1, 0);
+        ioapic::irqen(crate::console::COM1_IRQ as u32, 0);
     }
 }
 
@@ -475,6 +476,8 @@ fn timer_interrupt_handler(#[allow(unused_variables)] pt_regs: &mut PtRegs) {
 
     crate::waitqueue::signal_interrupt_threads(32);
     crate::thread::schedule();
+    // The thread we return to may have been interrupted while it was preempted
+    crate::thread::unwind_if_interrupted();
 }
 
 #[no_mangle]
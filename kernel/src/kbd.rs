@@ -113,6 +113,11 @@ impl Kbd_ctrlr {
                     if let Some(key) = self.kbd.process_keyevent(key_event) {
                         // Echo back to user
                         match key {
+                            // Unlike on the serial port, Ctrl-C is only noticed once read
+                            DecodedKey::Unicode('\x03') => {
+                                crate::console::CONSOLE_INTERRUPT
+                                    .store(true, core::sync::atomic::Ordering::Release);
+                            }
                             DecodedKey::Unicode(key) => {
                                 if !key.is_control() {
                                     print!("{}", key)
@@ -352,6 +352,11 @@ pub extern "C" fn rust_main_ap() -> ! {
 
     if cpu_id == 0 {
         domain::domain::init_domains();
+        {
+            use crate::drivers::Driver;
+            let registrar = unsafe { interrupt::get_irq_registrar(console::SERIAL1_RX.clone()) };
+            console::SERIAL1_RX.lock().set_irq_registrar(registrar);
+        }
         // FIXME: kbd irqhandler is broken. disable temporarily
         /*use kbd::KBDCTRL;
         use crate::drivers::Driver;
@@ -202,6 +202,25 @@ impl BuddyFrameAllocator {
         self.region
     }
 
+    /// Size of the heap, `add_memory` only uses the largest power of two that fits in
+    /// the region
+    pub fn total_bytes(&self) -> usize {
+        self.region.size.next_power_of_two() >> 1
+    }
+
+    /// Sum of the blocks on the free lists
+    pub fn free_bytes(&self) -> usize {
+        let mut free = 0;
+        for (order, head) in self.free_lists.iter().enumerate() {
+            let mut block = *head;
+            while !block.is_null() {
+                free += self.order_to_size(order);
+                block = unsafe { (*block).next };
+            }
+        }
+        free
+    }
+
     /// Get block size for allocation request.
     fn allocation_size(&self, layout: Layout) -> Option<usize> {
         // Don't try to align more than our heap base alignment
@@ -1,6 +1,7 @@
 #![no_std]
 
 use core::fmt;
+use interface::vfs::time::days_since_epoch;
 use x86::io::{inb, outb};
 
 pub const NMI_DISABLE: u8 = 1 << 7;
@@ -12,8 +13,11 @@ pub const DAY_REG: u8 = 0x7;
 pub const MONTH_REG: u8 = 0x8;
 pub const YEAR_REG: u8 = 0x9;
 pub const CENTURY_REG: u8 = 0x32;
+pub const STATUS_A_REG: u8 = 0xA;
 pub const STATUS_B_REG: u8 = 0xB;
 
+pub const STATUS_A_UPDATE_IN_PROGRESS: u8 = 0x80;
+
 pub const STATUS_B_ENCODING_MASK: u8 = 0x4;
 pub const STATUS_B_HOUR_FORMAT_MASK: u8 = 0x2;
 
@@ -77,7 +81,7 @@ impl CMOSDate {
     pub fn new() -> CMOSDate {
         let status_b = read_cmos_reg(0xb);
         let is_bcd = { (status_b & STATUS_B_ENCODING_MASK) == 0 };
-        let is_24_hour = { (status_b & STATUS_B_HOUR_FORMAT_MASK) == 1 };
+        let is_24_hour = { (status_b & STATUS_B_HOUR_FORMAT_MASK) != 0 };
         let mut is_pm = false;
         let sec;
         let min;
@@ -106,7 +110,13 @@ impl CMOSDate {
         } else {
             sec = read_cmos_reg(SECOND_REG);
             min = read_cmos_reg(MINUTE_REG);
-            hour = read_cmos_reg(HOUR_REG);
+            if is_24_hour {
+                hour = read_cmos_reg(HOUR_REG);
+            } else {
+                let hour_reg = read_cmos_reg(HOUR_REG);
+                is_pm = ((hour_reg & 0x80) >> 7) == 1;
+                hour = hour_reg & 0x7F;
+            }
             weekday = read_cmos_reg(WEEKDAY_REG);
             day = read_cmos_reg(DAY_REG);
             month = read_cmos_reg(MONTH_REG);
@@ -126,6 +136,29 @@ impl CMOSDate {
             is_pm,
         }
     }
+
+    // Seconds since the Unix epoch, assuming the clock runs in UTC
+    fn unix_time(&self) -> u64 {
+        let mut hour = self.hour as u64;
+        if !self.is_24_hour {
+            hour %= 12;
+            if self.is_pm {
+                hour += 12;
+            }
+        }
+        // Not every machine implements the century register
+        let century = if self.century == 0 { 20 } else { self.century as u64 };
+        let year = century * 100 + self.year as u64;
+
+        let days = days_since_epoch(year, self.month as u64, self.day as u64);
+        ((days * 24 + hour) * 60 + self.min as u64) * 60 + self.sec as u64
+    }
+}
+
+pub fn unix_time() -> u64 {
+    // Registers read while the RTC is updating them can be inconsistent
+    while read_cmos_reg(STATUS_A_REG) & STATUS_A_UPDATE_IN_PROGRESS != 0 {}
+    CMOSDate::new().unix_time()
 }
 
 pub fn print_date() {
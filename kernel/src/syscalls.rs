@@ -11,6 +11,7 @@ use crate::thread::{pop_continuation, push_continuation};
 use crate::unwind::unwind;
 use alloc::boxed::Box;
 use alloc::sync::Arc;
+use core::sync::atomic::Ordering;
 use pc_keyboard::DecodedKey;
 use platform::PciBarAddr;
 use spin::Mutex;
@@ -214,6 +215,35 @@ impl syscalls::Syscall for PDomain {
         enable_irq();
     }
 
+    fn sys_unwind(&self) {
+        disable_irq();
+        unwind();
+        enable_irq();
+    }
+
+    fn sys_exit_domain(&self) {
+        disable_irq();
+        let domain_id = thread::get_current_ref().lock().current_domain_id;
+        unsafe {
+            crate::heap::drop_domain(domain_id);
+        }
+        unwind();
+        enable_irq();
+    }
+
+    fn sys_interrupted(&self) -> bool {
+        disable_irq();
+        let interrupted = thread::get_current_ref().lock().interrupted;
+        enable_irq();
+        interrupted
+    }
+
+    fn sys_set_interruptible(&self, interruptible: bool) {
+        disable_irq();
+        thread::set_interruptible(interruptible);
+        enable_irq();
+    }
+
     /* AB: XXX: Remove this system it's for testing only */
     fn sys_test_unwind(&self) {
         disable_irq();
@@ -233,6 +263,13 @@ impl syscalls::Syscall for PDomain {
         enable_irq();
     }
 
+    fn sys_get_time(&self) -> u64 {
+        disable_irq();
+        let time = crate::rtc::unix_time();
+        enable_irq();
+        time
+    }
+
     fn sys_readch_kbd(&self) -> Result<Option<DecodedKey>, &'static str> {
         disable_irq();
         let rtn = KBDCTRL.lock().readch();
@@ -240,12 +277,74 @@ impl syscalls::Syscall for PDomain {
         rtn
     }
 
+    fn sys_readch(&self) -> Result<Option<DecodedKey>, &'static str> {
+        disable_irq();
+        // Serial input first, it is the only input under `make qemu-nox`
+        let rtn = match crate::console::SERIAL1_RX.lock().readch() {
+            Some(key) => Ok(Some(key)),
+            None => KBDCTRL.lock().readch(),
+        };
+        enable_irq();
+        rtn
+    }
+
+    fn sys_console_interrupt(&self) -> bool {
+        disable_irq();
+        let rtn = crate::console::CONSOLE_INTERRUPT.swap(false, Ordering::AcqRel);
+        enable_irq();
+        rtn
+    }
+
     fn sys_make_condvar(&self) -> syscalls::CondVarPtr {
         disable_irq();
         let rtn = crate::sync::condvar::make_condvar();
         enable_irq();
         rtn
     }
+
+    fn sys_domains(&self, domains: &mut [syscalls::DomainInfo]) -> usize {
+        disable_irq();
+        let live = crate::domain::domain::domains();
+        for (info, domain) in domains.iter_mut().zip(live.iter()) {
+            *info = domain.lock().info();
+        }
+        let count = live.len();
+        drop(live);
+        enable_irq();
+        count
+    }
+
+    fn sys_threads(&self, threads: &mut [syscalls::ThreadInfo]) -> usize {
+        disable_irq();
+        let mut count = 0;
+        for domain in crate::domain::domain::domains() {
+            let domain = domain.lock();
+            for t in domain.threads() {
+                if let Some(info) = threads.get_mut(count) {
+                    *info = t.lock().info(domain.id);
+                }
+                count += 1;
+            }
+        }
+        enable_irq();
+        count
+    }
+
+    fn sys_memory(&self) -> syscalls::MemoryInfo {
+        disable_irq();
+        let (total, free) = match *crate::memory::buddy::BUDDY.lock() {
+            Some(ref buddy) => (buddy.total_bytes(), buddy.free_bytes()),
+            None => (0, 0),
+        };
+        let (shared_heap, shared_heap_allocations) = crate::heap::shared_heap_usage(None);
+        enable_irq();
+        syscalls::MemoryInfo {
+            total,
+            free,
+            shared_heap,
+            shared_heap_allocations,
+        }
+    }
 }
 
 #[derive(Clone)]
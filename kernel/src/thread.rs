@@ -243,6 +243,19 @@ pub enum ThreadState {
     Rebalanced = 5,
 }
 
+impl ThreadState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThreadState::Running => "running",
+            ThreadState::Runnable => "runnable",
+            ThreadState::Paused => "paused",
+            ThreadState::Waiting => "waiting",
+            ThreadState::Idle => "idle",
+            ThreadState::Rebalanced => "rebalanced",
+        }
+    }
+}
+
 // AB: Watch out! if you change format of this line
 // you need to update the grep arguments in checkstack.mk
 // Right now we have it as:
@@ -291,6 +304,12 @@ pub struct Thread {
     /// A stack of continuations
     continuations: [Continuation; MAX_CONT],
 
+    /// Set by `PThread::interrupt`, an interrupted thread doesn't sleep any more
+    pub interrupted: bool,
+    /// The continuation the thread can be unwound to once interrupted, null if none. See
+    /// `sys_set_interruptible`.
+    interruptible: *mut Continuation,
+
     // HACK
     continuation_ptr: *mut Continuation,
 }
@@ -453,6 +472,8 @@ impl Thread {
             next_domain: None,
             next_iwq: None,
             continuations: [Continuation::zeroed(); MAX_CONT],
+            interrupted: false,
+            interruptible: 0 as *mut _,
 
             // We will update this when we switch to it the first time
             continuation_ptr: 0 as *mut _,
@@ -462,6 +483,18 @@ impl Thread {
 
         t
     }
+
+    /// Snapshot for `sys_threads`, `domain_id` is the domain that created the thread
+    pub fn info(&self, domain_id: u64) -> syscalls::ThreadInfo {
+        syscalls::ThreadInfo {
+            id: self.id,
+            name: syscalls::InfoName::new(&self.name),
+            domain_id,
+            current_domain_id: self.current_domain_id,
+            state: self.state.name(),
+            cpu: self.affinity,
+        }
+    }
 }
 
 impl SchedulerQueue {
@@ -821,6 +854,48 @@ pub fn schedule() {
     }
 }
 
+/// Lets an interrupted current thread be unwound while the current continuation is its top
+/// one, see `syscalls::Syscall::sys_set_interruptible`. Assumes IRQs are off.
+pub fn set_interruptible(interruptible: bool) {
+    let cont = match interruptible {
+        true => unsafe { CONT_STATE.cur },
+        false => 0 as *mut _,
+    };
+    get_current_ref().lock().interruptible = cont;
+}
+
+/// Unwinds the current thread if it has been interrupted while running interruptible code.
+/// Called with IRQs off on the way back from the timer interrupt, the thread may have been
+/// preempted at any instruction.
+///
+/// Only the last thread of a domain is interruptible, so nothing else can run into the locks
+/// it abandons, and the domain is gone once it unwinds: its shared heap is freed as by
+/// `sys_exit_domain`.
+pub fn unwind_if_interrupted() {
+    let current = get_current_ref();
+    let unwind = {
+        let mut thread = current.lock();
+        let unwind = thread.interrupted
+            && !thread.interruptible.is_null()
+            && thread.interruptible == unsafe { CONT_STATE.cur };
+        if unwind {
+            thread.interruptible = 0 as *mut _;
+        }
+        match unwind {
+            true => Some(thread.current_domain_id),
+            false => None,
+        }
+    };
+    drop(current);
+
+    if let Some(domain_id) = unwind {
+        unsafe {
+            crate::heap::drop_domain(domain_id);
+        }
+        crate::unwind::unwind();
+    }
+}
+
 // yield is a reserved keyword
 pub fn do_yield() {
     trace_sched!("Yield");
@@ -938,7 +1013,10 @@ impl syscalls::Thread for PThread {
 
         {
             let mut thread = self.thread.lock();
-            thread.state = ThreadState::Waiting;
+            // An interrupted thread only yields, so that it gets to notice the interrupt
+            if !thread.interrupted {
+                thread.state = ThreadState::Waiting;
+            }
             drop(guard);
             drop(thread);
         }
@@ -947,6 +1025,20 @@ impl syscalls::Thread for PThread {
 
         enable_irq();
     }
+
+    fn interrupt(&self) {
+        disable_irq();
+
+        {
+            let mut thread = self.thread.lock();
+            thread.interrupted = true;
+            if let ThreadState::Waiting = thread.state {
+                thread.state = ThreadState::Runnable;
+            }
+        }
+
+        enable_irq();
+    }
 }
 
 pub fn init_threads() {
@@ -93,9 +93,34 @@ pub trait Syscall {
     fn sys_free_huge(&self, p: *mut u8);
     fn sys_backtrace(&self);
     fn sys_dummy(&self);
+    // Wall clock time, in seconds since the Unix epoch
+    fn sys_get_time(&self) -> u64;
     // call this one to read a character from keyboard
     fn sys_readch_kbd(&self) -> Result<Option<DecodedKey>, &'static str>;
+    // call this one to read a character from either the keyboard or the serial console
+    fn sys_readch(&self) -> Result<Option<DecodedKey>, &'static str>;
+    // Whether Ctrl-C was typed on the console since the last call
+    fn sys_console_interrupt(&self) -> bool;
     fn sys_make_condvar(&self) -> CondVarPtr;
+    // Snapshots of kernel state for introspection (rv6's /proc). The two lists are filled in
+    // as far as the slice goes, and the number of entries is returned, so a caller with a
+    // short slice can retry with a longer one.
+    fn sys_domains(&self, domains: &mut [DomainInfo]) -> usize;
+    fn sys_threads(&self, threads: &mut [ThreadInfo]) -> usize;
+    fn sys_memory(&self) -> MemoryInfo;
+    // Unwinds the calling thread to its last continuation, i.e. out of the domain it runs in
+    // and back to the caller of that domain
+    fn sys_unwind(&self);
+    // Like `sys_unwind`, but first frees the shared heap owned by the domain the thread runs
+    // in. For the last thread leaving a domain that died.
+    fn sys_exit_domain(&self);
+    // Whether `Thread::interrupt` was called for the calling thread
+    fn sys_interrupted(&self) -> bool;
+    // Makes the calling thread interruptible while its current continuation is the top one:
+    // once the thread is interrupted, the timer interrupt unwinds it there and frees the shared
+    // heap of its domain, like `sys_exit_domain`. Only for the last thread running the code of a
+    // domain, which can be abandoned at any instruction, e.g. a single-threaded rv6 program.
+    fn sys_set_interruptible(&self, interruptible: bool);
 
     /* AB: XXX: Remove this system it's for testing only */
     fn sys_test_unwind(&self);
@@ -108,6 +133,74 @@ pub enum ThreadState {
     Waiting = 3,
 }
 
+/// A name copied out of the kernel, cut to `InfoName::LEN` bytes
+#[derive(Clone, Copy, Default)]
+pub struct InfoName {
+    bytes: [u8; InfoName::LEN],
+    len: usize,
+}
+
+impl InfoName {
+    pub const LEN: usize = 32;
+
+    pub fn new(name: &str) -> Self {
+        let mut len = core::cmp::min(name.len(), Self::LEN);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut bytes = [0u8; Self::LEN];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self { bytes, len }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // `new` only cuts at char boundaries
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("?")
+    }
+}
+
+impl core::fmt::Debug for InfoName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// A live domain, see `Syscall::sys_domains`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DomainInfo {
+    pub id: u64,
+    pub name: InfoName,
+    pub threads: usize,
+    /// Bytes of shared heap owned by the domain
+    pub shared_heap: usize,
+    /// How many domains of the same name were loaded so far, including this one. This counts
+    /// every load, not only restarts by a shadow.
+    pub loads: u64,
+}
+
+/// A thread of a live domain, see `Syscall::sys_threads`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadInfo {
+    pub id: u64,
+    pub name: InfoName,
+    /// The domain that created the thread
+    pub domain_id: u64,
+    /// The domain the thread is running in, which differs from `domain_id` during a call
+    /// into another domain
+    pub current_domain_id: u64,
+    pub state: &'static str,
+    pub cpu: u64,
+}
+
+/// Physical memory and shared heap usage in bytes, see `Syscall::sys_memory`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryInfo {
+    pub total: usize,
+    pub free: usize,
+    pub shared_heap: usize,
+    pub shared_heap_allocations: usize,
+}
+
 /// RedLeaf thread interface
 pub trait Thread: Send {
     fn get_id(&self) -> u64;
@@ -115,6 +208,9 @@ pub trait Thread: Send {
     fn set_priority(&self, prio: u64);
     fn set_state(&self, state: ThreadState);
     fn sleep(&self, guard: MutexGuard<()>);
+    // Wakes the thread if it sleeps and keeps it from sleeping again. See
+    // `Syscall::sys_interrupted` and `Syscall::sys_set_interruptible`.
+    fn interrupt(&self);
 }
 
 /// RedLeaf Domain interface
@@ -1,7 +1,14 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::syscalls::{sys_interrupted, sys_yield};
 use spin::{Mutex, MutexGuard};
+use crate::sync::SleepMutexGuard;
 
 pub struct CondVar {
+    // Held from checking the predicate until the kernel put the thread to sleep, and by
+    // `wakeup`, so a wakeup can't get lost in between
     intr_mutex: Mutex<()>,
+    // Threads sleeping on `cv`, the kernel wakes up one per `cv.wakeup()`
+    sleepers: AtomicUsize,
     cv: syscalls::CondVarPtr,
 }
 
@@ -9,22 +16,20 @@ impl CondVar {
     pub fn new() -> Self {
         Self {
             intr_mutex: Mutex::new(()),
+            sleepers: AtomicUsize::new(0),
             cv: crate::syscalls::sys_make_condvar(),
         }
     }
 
-    pub fn sleep(&self) {
-        let m = Mutex::new(());
-        self.sleep_until(&m, |_| true);
-    }
-
-    pub fn sleep_until<'a, T, F>(&self, mutex: &'a Mutex<T>, pred: F) -> MutexGuard<'a, T> where F: Fn(&mut T) -> bool {
+    // `wakeup` and `wakeup_all` must not be called while holding `mutex`, they wait for the
+    // threads checking `pred`
+    pub fn sleep_until<'a, T, F>(&self, mutex: &'a Mutex<T>, mut pred: F) -> MutexGuard<'a, T> where F: FnMut(&mut T) -> bool {
         let mut intr_guard = self.intr_mutex.lock();
         let mut data_guard = mutex.lock();
         while !pred(&mut data_guard) {
             // Goes to sleep
             drop(data_guard);
-            self.cv.sleep(intr_guard); // Atomically releases the guard and goes to sleep
+            self.sleep_locked(intr_guard); // Atomically releases the guard and goes to sleep
 
             // After being waken up
             intr_guard = self.intr_mutex.lock();
@@ -34,9 +39,65 @@ impl CondVar {
         drop(intr_guard);
         data_guard
     }
-    
+
+    // Like `sleep_until`, but gives up and returns `None` once the thread is interrupted, see
+    // `syscalls::Thread::interrupt`
+    pub fn sleep_until_interruptible<'a, T, F>(&self, mutex: &'a Mutex<T>, mut pred: F) -> Option<MutexGuard<'a, T>> where F: FnMut(&mut T) -> bool {
+        let mut intr_guard = self.intr_mutex.lock();
+        let mut data_guard = mutex.lock();
+        while !pred(&mut data_guard) {
+            if sys_interrupted() {
+                return None;
+            }
+
+            drop(data_guard);
+            self.sleep_locked(intr_guard);
+
+            intr_guard = self.intr_mutex.lock();
+            data_guard = mutex.lock();
+        }
+
+        drop(intr_guard);
+        Some(data_guard)
+    }
+
+    // Unlocks the `SleepMutex` of `guard`, sleeps until woken up and locks it again. The thread
+    // may wake up without the change it waits for, so check for it in a loop. Unlike with
+    // `sleep_until`, the waking thread may hold the mutex.
+    pub fn wait<'a, T>(&self, guard: SleepMutexGuard<'a, T>) -> SleepMutexGuard<'a, T> {
+        // Waking threads change the data while holding the mutex, so they can't wake us up
+        // before we sleep
+        let intr_guard = self.intr_mutex.lock();
+        let mutex = guard.unlock();
+        self.sleep_locked(intr_guard);
+        mutex.lock()
+    }
+
+    fn sleep_locked(&self, intr_guard: MutexGuard<()>) {
+        // The kernel doesn't put interrupted threads to sleep, don't pile them up on `cv`
+        if sys_interrupted() {
+            drop(intr_guard);
+            sys_yield();
+            return;
+        }
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        self.cv.sleep(intr_guard);
+    }
+
+    // Wakes up one sleeping thread
     pub fn wakeup(&self) {
-        self.cv.wakeup()
+        let _intr_guard = self.intr_mutex.lock();
+        if self.sleepers.load(Ordering::SeqCst) > 0 {
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
+            self.cv.wakeup();
+        }
     }
-}
 
+    pub fn wakeup_all(&self) {
+        let _intr_guard = self.intr_mutex.lock();
+        while self.sleepers.load(Ordering::SeqCst) > 0 {
+            self.sleepers.fetch_sub(1, Ordering::SeqCst);
+            self.cv.wakeup();
+        }
+    }
+}
@@ -3,4 +3,4 @@ pub mod sleepmutex;
 
 pub use condvar::CondVar;
 pub use spin::{Mutex as SpinMutex, MutexGuard as SpinMutexGuard};
-pub use sleepmutex::SleepMutex as SleepMutex;
\ No newline at end of file
+pub use sleepmutex::{SleepMutex, SleepMutexGuard};
\ No newline at end of file
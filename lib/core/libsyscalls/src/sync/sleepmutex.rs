@@ -2,6 +2,8 @@ use crate::sync::SpinMutex;
 use crate::sync::SpinMutexGuard;
 
 pub struct SleepMutex<T> {
+    // Held from failing to take `m` until the kernel put the thread to sleep, and by the
+    // unlocking thread while it wakes one up, so the wakeup can't get lost in between
     intr_mutex: SpinMutex<()>,
     m: SpinMutex<T>,
     cv: syscalls::CondVarPtr,
@@ -36,28 +38,43 @@ impl<T> SleepMutex<T> {
 
 pub struct SleepMutexGuard<'a, T> {
     mutex: &'a SleepMutex<T>,
-    guard: SpinMutexGuard<'a, T>,
+    // Only `None` while the guard is dropped
+    guard: Option<SpinMutexGuard<'a, T>>,
 }
 
 impl<'a, T> SleepMutexGuard<'a, T> {
     fn new(mutex: &'a SleepMutex<T>, guard: SpinMutexGuard<'a, T>) -> Self {
         Self {
             mutex,
-            guard,
+            guard: Some(guard),
         }
     }
+
+    // Unlocks the mutex, for `CondVar::wait` to lock it again
+    pub(crate) fn unlock(self) -> &'a SleepMutex<T> {
+        self.mutex
+    }
 }
 
 impl<'a, T> core::ops::Deref for SleepMutexGuard<'a, T> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
-        self.guard.deref()
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<'a, T> core::ops::DerefMut for SleepMutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.guard.as_mut().unwrap()
     }
 }
 
 impl<'a, T> core::ops::Drop for SleepMutexGuard<'a, T> {
     fn drop(&mut self) {
+        // Unlock first, so that the thread we wake up finds the mutex unlocked
+        drop(self.guard.take());
+        let _intr_guard = self.mutex.intr_mutex.lock();
         self.mutex.cv.wakeup();
     }
 }
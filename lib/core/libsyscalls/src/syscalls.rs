@@ -1,7 +1,7 @@
 extern crate alloc;
 use spin::Once;
 use alloc::boxed::Box;
-use syscalls::{Syscall, Thread, Interrupt, Mmap, Continuation};
+use syscalls::{Syscall, Thread, Interrupt, Mmap, Continuation, DomainInfo, ThreadInfo, MemoryInfo};
 use pc_keyboard::{DecodedKey};
 use platform::PciBarAddr;
 
@@ -107,16 +107,46 @@ pub fn sys_dummy() {
     scalls.sys_dummy()
 }
 
+pub fn sys_get_time() -> u64 {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_get_time()
+}
+
 pub fn sys_readch_kbd() -> Result<Option<DecodedKey>, &'static str> {
     let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
     scalls.sys_readch_kbd()
 }
 
+pub fn sys_readch() -> Result<Option<DecodedKey>, &'static str> {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_readch()
+}
+
+pub fn sys_console_interrupt() -> bool {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_console_interrupt()
+}
+
 pub fn sys_make_condvar() -> syscalls::CondVarPtr {
     let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
     scalls.sys_make_condvar()
 }
 
+pub fn sys_domains(domains: &mut [DomainInfo]) -> usize {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_domains(domains)
+}
+
+pub fn sys_threads(threads: &mut [ThreadInfo]) -> usize {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_threads(threads)
+}
+
+pub fn sys_memory() -> MemoryInfo {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    scalls.sys_memory()
+}
+
 pub unsafe fn sys_register_cont(cont: &Continuation) {
     let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
     return scalls.sys_register_cont(cont);
@@ -127,6 +157,26 @@ pub unsafe fn sys_discard_cont() {
     return scalls.sys_discard_cont();
 }
 
+pub fn sys_unwind() {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    return scalls.sys_unwind();
+}
+
+pub fn sys_exit_domain() {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    return scalls.sys_exit_domain();
+}
+
+pub fn sys_interrupted() -> bool {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    return scalls.sys_interrupted();
+}
+
+pub fn sys_set_interruptible(interruptible: bool) {
+    let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
+    return scalls.sys_set_interruptible(interruptible);
+}
+
 /* AB: XXX: Remove this system it's for testing only */
 pub fn sys_test_unwind() {
     let scalls = SYSCALL.r#try().expect("System call interface is not initialized.");
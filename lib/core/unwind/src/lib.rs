@@ -162,3 +162,63 @@ pub extern "C" fn discard_cont()  {
     }
 }
 
+/*
+ * Catching an unwind without a generated proxy.
+ *
+ * `catch_unwind` calls the closure through the `catch_unwind_call`
+ * trampoline. If a domain called from the closure panics and its panic
+ * handler calls `sys_unwind`, the stack is rewound to the trampoline
+ * and `catch_unwind_call_err` runs instead, with the same context.
+ */
+
+use libsyscalls::syscalls::{sys_get_current_domain_id, sys_update_current_domain_id};
+
+#[repr(C)]
+pub struct CatchContext<'a> {
+    call: &'a mut dyn FnMut(),
+    // Domain to switch back to if the call unwinds
+    domain_id: u64,
+}
+
+#[no_mangle]
+pub extern "C" fn catch_unwind_call(context: &mut CatchContext) -> bool {
+    (context.call)();
+    discard_cont();
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn catch_unwind_call_err(context: &mut CatchContext) -> bool {
+    // The domain died before it could switch back to us
+    unsafe {
+        sys_update_current_domain_id(context.domain_id);
+    }
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn catch_unwind_call_addr() -> u64 {
+    catch_unwind_call_err as u64
+}
+
+extern "C" {
+    fn catch_unwind_call_tramp(context: &mut CatchContext) -> bool;
+}
+
+trampoline!(catch_unwind_call);
+
+/// Calls `f`, returning `None` if the thread unwound before `f` returned
+pub fn catch_unwind<R, F: FnOnce() -> R>(f: F) -> Option<R> {
+    let mut f = Some(f);
+    let mut output = None;
+    let mut call = || output = Some((f.take().unwrap())());
+    let mut context = CatchContext {
+        call: &mut call,
+        domain_id: sys_get_current_domain_id(),
+    };
+    if unsafe { catch_unwind_call_tramp(&mut context) } {
+        output
+    } else {
+        None
+    }
+}
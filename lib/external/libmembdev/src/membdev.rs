@@ -1,7 +1,8 @@
 use spin::Mutex;
 
-use interface::rref::RRef;
-use interface::bdev::{BDev, BSIZE};
+use interface::rref::{RRef, RRefDeque};
+use interface::bdev::{BDev, BlkReq, BSIZE};
+use interface::error::Result;
 use interface::rpc::RpcResult;
 
 pub struct MemBDev {
@@ -41,4 +42,26 @@ impl BDev for MemBDev {
         
         Ok(())
     }
+    fn read_blocks(&self, mut reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        let memdisk = self.memdisk.lock();
+        for req in reqs.iter_mut() {
+            let start = req.block as usize * Self::SECTOR_SIZE;
+            let size = req.data_len;
+
+            req.data[..size].copy_from_slice(&memdisk[start..start+size]);
+        }
+
+        Ok(Ok(reqs))
+    }
+    fn write_blocks(&self, mut reqs: RRefDeque<BlkReq, 128>) -> RpcResult<Result<RRefDeque<BlkReq, 128>>> {
+        let mut memdisk = self.memdisk.lock();
+        for req in reqs.iter_mut() {
+            let start = req.block as usize * Self::SECTOR_SIZE;
+            let size = req.data_len;
+
+            memdisk[start..start+size].copy_from_slice(&req.data[..size]);
+        }
+
+        Ok(Ok(reqs))
+    }
 }
@@ -0,0 +1,48 @@
+#!/bin/bash
+# Runs an rv6 test script from the file system image in headless QEMU.
+# Waits for the shell prompt, types `sh SCRIPT` and waits for the script to print
+# "NAME: done", where SCRIPT is NAME.sh. Fails if that takes longer than
+# $RV6_TEST_TIMEOUT seconds or if the script printed a line starting with FAIL.
+# Sample usage (this is what `make test-coreutils` does):
+#   ./rv6-test.sh coreutils.sh qemu-system-x86_64 <qemu-nox arguments>
+
+SCRIPT=$1
+shift
+NAME=${SCRIPT%.sh}
+TIMEOUT=${RV6_TEST_TIMEOUT:-300}
+# Written by the qemu-nox serial configuration
+LOG=serial.log
+
+FIFO=$(mktemp -u)
+mkfifo $FIFO
+trap 'kill $QEMU_PID 2>/dev/null; rm -f $FIFO' EXIT
+
+rm -f $LOG
+"$@" < $FIFO > /dev/null &
+QEMU_PID=$!
+exec 3> $FIFO
+
+# Waits until a line of the serial log matches $1
+wait_for() {
+    for ((i = 0; i < TIMEOUT; i++)); do
+        grep -aq "$1" $LOG 2>/dev/null && return 0
+        kill -0 $QEMU_PID 2>/dev/null || return 1
+        sleep 1
+    done
+    return 1
+}
+
+if ! wait_for "rv6> "; then
+    echo "$NAME: the rv6 shell did not start, see $LOG"
+    exit 1
+fi
+echo "sh $SCRIPT" >&3
+
+if ! wait_for "^$NAME: done"; then
+    echo "$NAME: did not finish, see $LOG"
+    exit 1
+fi
+if grep -a "^FAIL" $LOG; then
+    exit 1
+fi
+echo "$NAME: passed"
@@ -0,0 +1,352 @@
+//! Reads the `#[interface]` traits out of the sources of the `interface` crate.
+//!
+//! Shared by the build scripts that need the interface definitions: `interface/build.rs`
+//! fingerprints them and `tools/proxy-fuzz/build.rs` generates fuzzing calls from them. This is
+//! a textual scan rather than a Rust parser, it relies on the layout rustfmt gives the crate.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An `#[interface]` trait
+pub struct Interface {
+    pub name: String,
+    /// Given with `#[interface(version = N)]`, 0 by default
+    pub version: u32,
+    /// The trait declaration, from `trait` to its closing brace
+    pub decl: String,
+    pub methods: Vec<Method>,
+}
+
+pub struct Method {
+    pub name: String,
+    /// Names and types of the arguments after `self`, types without whitespace
+    pub args: Vec<(String, String)>,
+}
+
+/// Definitions of the types and constants of a crate by name. A name defined in several
+/// modules maps to all of its definitions, each without whitespace.
+pub type Definitions = HashMap<String, Vec<String>>;
+
+/// Returns the `.rs` files below `dir`, sorted
+pub fn collect_sources(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_into(dir, &mut files);
+    files.sort();
+    files
+}
+
+fn collect_into(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_into(&path, files);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// Replaces `//` comments by the newline ending them and `/* */` comments by a space
+pub fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                out.push(' ');
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Finds the `#[interface]` traits in `source`, which must be stripped of comments
+pub fn find_interfaces(source: &str) -> Vec<Interface> {
+    let mut interfaces = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("#[interface") {
+        rest = &rest[start + "#[interface".len()..];
+
+        // `#[interface]` or `#[interface(version = N)]`
+        let attr_end = rest.find(']').unwrap();
+        let version = parse_version(&rest[..attr_end]);
+        rest = &rest[attr_end + 1..];
+
+        let trait_start = rest
+            .find("trait ")
+            .expect("#[interface] must be applied to a trait");
+        let decl = &rest[trait_start..];
+        let name: String = decl["trait ".len()..]
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+
+        let body_start = decl.find('{').unwrap();
+        let body_end = matching(decl, body_start, '{', '}');
+
+        interfaces.push(Interface {
+            name,
+            version,
+            decl: String::from(&decl[..body_end + 1]),
+            methods: find_methods(&decl[body_start + 1..body_end]),
+        });
+        rest = &rest[trait_start + body_end + 1..];
+    }
+    interfaces
+}
+
+fn parse_version(args: &str) -> u32 {
+    let args: String = args.chars().filter(|c| !c.is_whitespace()).collect();
+    match args.find("version=") {
+        Some(i) => args[i + "version=".len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse()
+            .expect("invalid interface version"),
+        None => 0,
+    }
+}
+
+fn find_methods(body: &str) -> Vec<Method> {
+    let mut methods = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("fn ") {
+        rest = &rest[start + "fn ".len()..];
+        let name: String = rest
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+
+        let args_start = rest.find('(').unwrap();
+        let args_end = matching(rest, args_start, '(', ')');
+        let args = split_args(&rest[args_start + 1..args_end])
+            .into_iter()
+            .filter(|arg| !arg.contains("self"))
+            .map(|arg| {
+                let colon = arg.find(':').unwrap();
+                (
+                    String::from(arg[..colon].trim()),
+                    normalize(&arg[colon + 1..]),
+                )
+            })
+            .collect();
+
+        methods.push(Method { name, args });
+        rest = &rest[args_end..];
+    }
+    methods
+}
+
+/// Returns the index of the delimiter closing the one at `open_at`
+pub fn matching(s: &str, open_at: usize, open: char, close: char) -> usize {
+    let mut depth = 0;
+    for (i, c) in s[open_at..].char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return open_at + i;
+            }
+        }
+    }
+    panic!("unbalanced {}{}", open, close);
+}
+
+// Splits on commas that are not nested inside generics, tuples or arrays
+fn split_args(args: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in args.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(current.clone());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+/// Records the structs, enums, unions, type aliases and constants defined in `source`, which
+/// must be stripped of comments. Only capitalized names count, which skips `const fn`.
+pub fn find_definitions(source: &str, definitions: &mut Definitions) {
+    let idents = identifiers(source);
+    for pair in idents.windows(2) {
+        let ((start, keyword), (_, name)) = (pair[0], pair[1]);
+        if !["struct", "enum", "union", "type", "const"].contains(&keyword)
+            || !name.starts_with(|c: char| c.is_ascii_uppercase())
+            || !at_item_start(source, start)
+        {
+            continue;
+        }
+
+        // A definition ends with its body or, for tuple structs, aliases and constants, at `;`
+        let def = &source[start..];
+        let end = match (def.find('{'), def.find(';')) {
+            (Some(brace), Some(semi)) if brace < semi => matching(def, brace, '{', '}') + 1,
+            (Some(brace), None) => matching(def, brace, '{', '}') + 1,
+            (_, Some(semi)) => semi + 1,
+            (None, None) => continue,
+        };
+        definitions
+            .entry(name.to_string())
+            .or_default()
+            .push(normalize(&def[..end]));
+    }
+}
+
+// Whether `source[start..]` begins an item, unlike the `const` of `*const T` or of a const
+// generic parameter
+fn at_item_start(source: &str, start: usize) -> bool {
+    let before = source[..start].trim_end();
+    let before = before.strip_suffix("pub").unwrap_or(before).trim_end();
+    matches!(
+        before.chars().last(),
+        None | Some(';') | Some('{') | Some('}') | Some(']') | Some(')')
+    )
+}
+
+/// Definitions of the names used in `decl`, followed transitively, in order of name
+pub fn referenced_definitions<'a>(decl: &'a str, definitions: &'a Definitions) -> Vec<&'a str> {
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<&str> = identifiers(decl).into_iter().map(|(_, id)| id).collect();
+    while let Some(id) = pending.pop() {
+        if let Some(defs) = definitions.get(id) {
+            if seen.insert(id) {
+                for def in defs {
+                    pending.extend(identifiers(def).into_iter().map(|(_, id)| id));
+                }
+            }
+        }
+    }
+    seen.into_iter()
+        .flat_map(|name| definitions[name].iter().map(String::as_str))
+        .collect()
+}
+
+// Identifiers and keywords in `s` with their byte offsets
+fn identifiers(s: &str) -> Vec<(usize, &str)> {
+    let mut idents = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        match (start, c.is_alphanumeric() || c == '_') {
+            (None, true) => start = Some(i),
+            (Some(begin), false) => {
+                idents.push((begin, &s[begin..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        idents.push((begin, &s[begin..]));
+    }
+    idents
+}
+
+/// Removes all whitespace
+pub fn normalize(s: &str) -> String {
+    s.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "
+        pub const BSIZE: usize = 4096;
+
+        /// A request
+        #[derive(Clone, Copy)]
+        pub struct BlkReq {
+            pub data: [u8; BSIZE],
+            pub block: u64,
+        }
+
+        pub struct Unused(u32);
+
+        impl BlkReq {
+            pub const fn new() -> Self { unimplemented!() }
+            fn raw(&self) -> *const BlkReq { self }
+        }
+
+        #[interface(version = 2)]
+        pub trait BDev: Send {
+            // Reads a block
+            fn read(&self, block: u32, data: &mut RRef<[u8; BSIZE]>) -> RpcResult<()>;
+            fn submit(&self, req: BlkReq) -> RpcResult<()>;
+        }
+    ";
+
+    #[test]
+    fn finds_interfaces_and_methods() {
+        let interfaces = find_interfaces(&strip_comments(SOURCE));
+        assert_eq!(interfaces.len(), 1);
+        let bdev = &interfaces[0];
+        assert_eq!(bdev.name, "BDev");
+        assert_eq!(bdev.version, 2);
+        assert!(bdev.decl.starts_with("trait BDev") && bdev.decl.ends_with('}'));
+
+        let methods: Vec<&str> = bdev.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, ["read", "submit"]);
+        assert_eq!(
+            bdev.methods[0].args,
+            [
+                (String::from("block"), String::from("u32")),
+                (String::from("data"), String::from("&mutRRef<[u8;BSIZE]>")),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_referenced_definitions() {
+        let source = strip_comments(SOURCE);
+        let mut definitions = Definitions::new();
+        find_definitions(&source, &mut definitions);
+
+        // Neither `const fn` nor `*const BlkReq` define anything
+        let mut names: Vec<&str> = definitions.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["BSIZE", "BlkReq", "Unused"]);
+
+        let bdev = &find_interfaces(&source)[0];
+        assert_eq!(
+            referenced_definitions(&bdev.decl, &definitions),
+            [
+                "constBSIZE:usize=4096;",
+                "structBlkReq{pubdata:[u8;BSIZE],pubblock:u64,}"
+            ]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+// Generates `$OUT_DIR/calls.rs` from the `#[interface]` traits in `interface/src`, as read by
+// `tools/interface-parser`.
+//
+// For every interface trait we emit
+//   - an `<Trait>Call` enum deriving `Arbitrary`, with one variant per method, and
+//   - a `dispatch_<trait>(&dyn Trait, <Trait>Call)` function invoking the method.
+//
+// Arguments are generated as plain host types (see `map_type`) and converted into the RPC
+// types at dispatch time. Methods taking an argument we don't know how to generate are left out
+// and listed in a comment in the generated file.
+
+use interface_parser::{collect_sources, find_interfaces, strip_comments, Interface, Method};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let src = Path::new(&manifest_dir).join("../../interface/src");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", src.display());
+
+    let mut out = String::new();
+    for file in collect_sources(&src) {
+        let source = strip_comments(&fs::read_to_string(&file).unwrap());
+        let module = module_path(&src, &file);
+        for interface in find_interfaces(&source) {
+            generate(&mut out, &module, &interface);
+        }
+    }
+
+    fs::write(Path::new(&out_dir).join("calls.rs"), out).unwrap();
+}
+
+// `src/vfs/mod.rs` -> `interface::vfs`, `src/bdev.rs` -> `interface::bdev`
+fn module_path(src: &Path, file: &Path) -> String {
+    let mut path = String::from("interface");
+    let relative = file.strip_prefix(src).unwrap().with_extension("");
+    for component in relative.components() {
+        let component = component.as_os_str().to_str().unwrap();
+        if component != "mod" && component != "lib" {
+            path.push_str("::");
+            path.push_str(component);
+        }
+    }
+    path
+}
+
+// Maps an RPC argument type to the host type we fuzz with and the expression converting a
+// binding `$` of the host type into the argument.
+fn map_type(ty: &str) -> Option<(&'static str, &'static str)> {
+    Some(match ty {
+        "u8" => ("u8", "$"),
+        "u16" => ("u16", "$"),
+        "u32" => ("u32", "$"),
+        "u64" => ("u64", "$"),
+        "usize" => ("usize", "$"),
+        "i8" => ("i8", "$"),
+        "i16" => ("i16", "$"),
+        "i32" => ("i32", "$"),
+        "i64" => ("i64", "$"),
+        "isize" => ("isize", "$"),
+        "bool" => ("bool", "$"),
+        "RRef<usize>" => ("usize", "interface::rref::RRef::new($)"),
+        "RRefVec<u8>" => ("Vec<u8>", "interface::rref::RRefVec::from_slice(&$)"),
+        "RRef<[u8;BSIZE]>" => ("Vec<u8>", "crate::convert::block(&$)"),
+        "&RRef<[u8;BSIZE]>" => ("Vec<u8>", "&crate::convert::block(&$)"),
+        "FileMode" => ("u32", "interface::vfs::FileMode::from_bits_truncate($)"),
+        "Permissions" => ("u16", "interface::vfs::Permissions::from_bits_truncate($)"),
+        "&[u8]" => ("Vec<u8>", "&$"),
+        "&mut[u8]" => ("Vec<u8>", "&mut $"),
+        "&mutVec<u8>" => ("Vec<u8>", "&mut $"),
+        "&mutu32" => ("u32", "&mut $"),
+        _ => return None,
+    })
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn generate(out: &mut String, module: &str, interface: &Interface) {
+    let (supported, skipped): (Vec<&Method>, Vec<&Method>) = interface
+        .methods
+        .iter()
+        .partition(|m| m.args.iter().all(|(_, ty)| map_type(ty).is_some()));
+
+    out.push_str(&format!("// {}::{}\n", module, interface.name));
+    for method in &skipped {
+        out.push_str(&format!("//   skipped {}: unsupported argument type\n", method.name));
+    }
+    if supported.is_empty() {
+        out.push('\n');
+        return;
+    }
+
+    let call = format!("{}Call", interface.name);
+
+    out.push_str("#[derive(Arbitrary, Debug)]\n");
+    out.push_str(&format!("pub enum {} {{\n", call));
+    for method in &supported {
+        out.push_str(&format!("    {} {{", camel_case(&method.name)));
+        for (name, ty) in &method.args {
+            out.push_str(&format!(" {}: {},", name, map_type(ty).unwrap().0));
+        }
+        out.push_str(" },\n");
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(&format!(
+        "#[allow(unused_mut)]\npub fn dispatch_{}(target: &dyn {}::{}, call: {}) {{\n    match call {{\n",
+        interface.name.to_lowercase(),
+        module,
+        interface.name,
+        call
+    ));
+    for method in &supported {
+        let bindings: Vec<String> = method.args.iter().map(|(name, _)| format!("mut {}", name)).collect();
+        let args: Vec<String> = method
+            .args
+            .iter()
+            .map(|(name, ty)| map_type(ty).unwrap().1.replace("$", name))
+            .collect();
+        out.push_str(&format!(
+            "        {}::{} {{ {} }} => {{\n            let _ = target.{}({});\n        }}\n",
+            call,
+            camel_case(&method.name),
+            bindings.join(", "),
+            method.name,
+            args.join(", ")
+        ));
+    }
+    out.push_str("    }\n}\n\n");
+}
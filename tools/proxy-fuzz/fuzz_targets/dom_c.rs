@@ -0,0 +1,21 @@
+#![no_main]
+extern crate alloc;
+
+use libfuzzer_sys::fuzz_target;
+use proxy_fuzz::calls::{dispatch_domc, DomCCall};
+use std::sync::Once;
+
+// Build the domain's DomC implementation from source, the dom_c crate itself is a `no_std`
+// domain with its own panic handler.
+#[path = "../../../domains/usr/test/dom_c/src/dom_c.rs"]
+mod dom_c;
+
+static INIT: Once = Once::new();
+
+fuzz_target!(|calls: Vec<DomCCall>| {
+    INIT.call_once(proxy_fuzz::init);
+    let domc = dom_c::DomC::new();
+    for call in calls {
+        dispatch_domc(&domc, call);
+    }
+});
@@ -0,0 +1,26 @@
+#![no_main]
+#[macro_use]
+extern crate lazy_static;
+
+use libfuzzer_sys::fuzz_target;
+use proxy_fuzz::calls::{dispatch_bdev, BDevCall};
+
+// Build the domain's BDev implementation from source, the membdev crate itself pulls in the
+// domain allocator and the rv6 user binaries.
+#[path = "../../../lib/external/libmembdev/src/membdev.rs"]
+mod membdev;
+
+const DISK_SIZE: usize = 4 * 1024 * 1024;
+
+lazy_static! {
+    static ref BDEV: membdev::MemBDev = {
+        proxy_fuzz::init();
+        membdev::MemBDev::new(Box::leak(vec![0u8; DISK_SIZE].into_boxed_slice()))
+    };
+}
+
+fuzz_target!(|calls: Vec<BDevCall>| {
+    for call in calls {
+        dispatch_bdev(&*BDEV, call);
+    }
+});
@@ -0,0 +1,71 @@
+#![no_main]
+#![feature(box_syntax)]
+#[macro_use]
+extern crate lazy_static;
+extern crate alloc;
+
+use interface::vfs::VFS;
+use libfuzzer_sys::fuzz_target;
+use proxy_fuzz::calls::{dispatch_vfs, VFSCall};
+
+// Build the file system's modules from source, the xv6fs crate itself is a `no_std` domain
+// with the domain allocator and its own panic handler. The modules refer to each other through
+// `crate::`, so they are declared at the root here, like in the domain.
+#[path = "../../../domains/usr/xv6/kernel/fs/src/bcache.rs"]
+mod bcache;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/block.rs"]
+mod block;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/clock.rs"]
+mod clock;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/console_device.rs"]
+mod console_device;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/cross_thread_temp_store.rs"]
+mod cross_thread_temp_store;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/cwd.rs"]
+mod cwd;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/devices.rs"]
+mod devices;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/fs.rs"]
+mod fs;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/icache/mod.rs"]
+mod icache;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/log/mod.rs"]
+mod log;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/net/mod.rs"]
+mod net;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/opened_file.rs"]
+mod opened_file;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/params.rs"]
+mod params;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/pipe.rs"]
+mod pipe;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/proc_device.rs"]
+mod proc_device;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/process.rs"]
+mod process;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/rv6fs.rs"]
+mod rv6fs;
+#[path = "../../../domains/usr/xv6/kernel/fs/src/sysfile.rs"]
+mod sysfile;
+
+#[path = "../../../lib/external/libmembdev/src/membdev.rs"]
+mod membdev;
+
+// An empty file system, see the README
+const IMAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../rv6-mkfs/build/fuzz.img");
+
+lazy_static! {
+    // The file system keeps its state in statics, so there is a single instance for all runs
+    static ref FS: Box<dyn VFS> = {
+        proxy_fuzz::init();
+        let image = std::fs::read(IMAGE).expect("cannot read the file system image");
+        let bdev = membdev::MemBDev::new(Box::leak(image.into_boxed_slice()));
+        rv6fs::init(Box::new(bdev), None, None, None)
+    };
+}
+
+fuzz_target!(|calls: Vec<VFSCall>| {
+    for call in calls {
+        dispatch_vfs(&**FS, call);
+    }
+});
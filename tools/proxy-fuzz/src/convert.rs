@@ -0,0 +1,10 @@
+use interface::bdev::BSIZE;
+use interface::rref::RRef;
+
+// Builds a block buffer from fuzzer-provided bytes, zero-padded or truncated to BSIZE
+pub fn block(data: &[u8]) -> RRef<[u8; BSIZE]> {
+    let mut block = RRef::new([0u8; BSIZE]);
+    let len = data.len().min(BSIZE);
+    block[..len].copy_from_slice(&data[..len]);
+    block
+}
@@ -0,0 +1,59 @@
+use std::alloc::{alloc, dealloc, Layout};
+use std::collections::HashMap;
+
+use spin::Mutex;
+use syscalls::SharedHeapAllocation;
+
+/// Host stand-in for the kernel's shared heap, modelled after the `TestHeap` in
+/// `interface/src/rref/mod.rs`.
+///
+/// Unlike the kernel it accepts every type id, since the host build of `interface` uses the dummy
+/// `TypeIdentifiable`, and it frees allocations without running `CustomCleanup`. Arguments of the
+/// interfaces we fuzz don't nest `RRef`s, so nothing leaks.
+pub struct FuzzHeap {
+    map: Mutex<HashMap<usize, SharedHeapAllocation>>,
+}
+
+impl FuzzHeap {
+    pub fn new() -> FuzzHeap {
+        FuzzHeap {
+            map: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl syscalls::Heap for FuzzHeap {
+    unsafe fn alloc(&self, layout: Layout, type_id: u64) -> Option<SharedHeapAllocation> {
+        let domain_id_pointer = Box::into_raw(Box::<u64>::new(0));
+        let borrow_count_pointer = Box::into_raw(Box::<u64>::new(0));
+        // zero sized RRefs still need a unique address
+        let value_pointer = alloc(Layout::from_size_align(layout.size().max(1), layout.align()).unwrap());
+
+        let allocation = SharedHeapAllocation {
+            value_pointer,
+            domain_id_pointer,
+            borrow_count_pointer,
+            layout,
+            type_id,
+        };
+
+        self.map.lock().insert(value_pointer as usize, allocation);
+
+        Some(allocation)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8) {
+        let allocation = match self.map.lock().remove(&(ptr as usize)) {
+            Some(allocation) => allocation,
+            None => panic!("dealloc twice"),
+        };
+
+        let layout = allocation.layout;
+        dealloc(
+            allocation.value_pointer,
+            Layout::from_size_align(layout.size().max(1), layout.align()).unwrap(),
+        );
+        drop(Box::from_raw(allocation.domain_id_pointer));
+        drop(Box::from_raw(allocation.borrow_count_pointer));
+    }
+}
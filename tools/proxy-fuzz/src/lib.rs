@@ -0,0 +1,22 @@
+//! Host-side harness for fuzzing `#[interface]` implementations.
+//!
+//! `calls` is generated by `build.rs` from the interface definitions: one `Arbitrary` enum of
+//! calls per interface, plus a `dispatch_*` function that invokes them on a trait object. Fuzz
+//! targets in `fuzz_targets/` construct a domain's implementation and feed it the calls.
+
+mod convert;
+pub mod heap;
+pub mod syscall;
+
+pub mod calls {
+    #![allow(dead_code)]
+    use arbitrary::Arbitrary;
+
+    include!(concat!(env!("OUT_DIR"), "/calls.rs"));
+}
+
+/// Sets up the shared heap and system call stand-ins. Safe to call once per fuzz iteration.
+pub fn init() {
+    interface::rref::init(Box::new(heap::FuzzHeap::new()), 1);
+    libsyscalls::syscalls::init(Box::new(syscall::FuzzSyscall::new()));
+}
@@ -0,0 +1,107 @@
+use std::alloc::{alloc_zeroed, Layout};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use spin::MutexGuard;
+use syscalls::{CondVar, CondVarPtr, Syscall, Thread, ThreadState};
+
+const PAGE_SIZE: usize = 4096;
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+std::thread_local! {
+    // Thread-locals of the domains, e.g. `tls::ThreadLocal`, are keyed by this id
+    static THREAD_ID: u64 = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+fn current_thread_id() -> u64 {
+    THREAD_ID.with(|id| *id)
+}
+
+/// Host stand-in for the kernel's system calls. Printing goes to stdout so that panics in the
+/// fuzzed domain come with their log. Threads are host threads with ids of their own, and pages
+/// come from the host allocator and, like in the kernel, are never freed.
+pub struct FuzzSyscall();
+
+impl FuzzSyscall {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[allow(unused_variables)]
+impl Syscall for FuzzSyscall {
+    fn sys_print(&self, s: &str) { print!("{}", s) }
+    fn sys_println(&self, s: &str) { println!("{}", s) }
+    fn sys_cpuid(&self) -> u32 { 0 }
+    fn sys_yield(&self) { std::thread::yield_now() }
+    fn sys_create_thread(&self, name: &str, func: extern "C" fn()) -> Box<dyn Thread> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name(String::from(name))
+            .spawn(move || {
+                sender.send(current_thread_id()).unwrap();
+                func()
+            })
+            .unwrap();
+        Box::new(FuzzThread(receiver.recv().unwrap()))
+    }
+    fn sys_current_thread(&self) -> Box<dyn Thread> { Box::new(FuzzThread(current_thread_id())) }
+    fn sys_current_thread_id(&self) -> u64 { current_thread_id() }
+    fn sys_get_current_domain_id(&self) -> u64 { 1 }
+    unsafe fn sys_update_current_domain_id(&self, new_domain_id: u64) -> u64 { 1 }
+    unsafe fn sys_register_cont(&self, cont: &syscalls::Continuation) {}
+    unsafe fn sys_discard_cont(&self) {}
+    fn sys_alloc(&self) -> *mut u8 { alloc_pages(PAGE_SIZE) }
+    fn sys_free(&self, p: *mut u8) {}
+    fn sys_alloc_huge(&self, sz: u64) -> *mut u8 { alloc_pages(sz as usize) }
+    fn sys_free_huge(&self, p: *mut u8) {}
+    fn sys_backtrace(&self) {}
+    fn sys_dummy(&self) {}
+    fn sys_get_time(&self) -> u64 { 0 }
+    fn sys_readch_kbd(&self) -> Result<Option<pc_keyboard::DecodedKey>, &'static str> { Ok(None) }
+    fn sys_readch(&self) -> Result<Option<pc_keyboard::DecodedKey>, &'static str> { Ok(None) }
+    fn sys_console_interrupt(&self) -> bool { false }
+    fn sys_make_condvar(&self) -> CondVarPtr { Box::new(FuzzCondVar()) }
+    fn sys_domains(&self, _domains: &mut [syscalls::DomainInfo]) -> usize { 0 }
+    fn sys_threads(&self, _threads: &mut [syscalls::ThreadInfo]) -> usize { 0 }
+    fn sys_memory(&self) -> syscalls::MemoryInfo { Default::default() }
+    fn sys_unwind(&self) {}
+    fn sys_exit_domain(&self) {}
+    fn sys_interrupted(&self) -> bool { false }
+    fn sys_set_interruptible(&self, _interruptible: bool) {}
+    fn sys_test_unwind(&self) {}
+}
+
+fn alloc_pages(size: usize) -> *mut u8 {
+    let size = (size.max(1) + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+    let ptr = unsafe { alloc_zeroed(Layout::from_size_align(size, PAGE_SIZE).unwrap()) };
+    assert!(!ptr.is_null(), "host allocator out of memory");
+    ptr
+}
+
+/// Handle for a host thread and its id. Scheduling hints are ignored and sleeping only drops the
+/// guard, the callers of `sleep` re-check their condition.
+struct FuzzThread(u64);
+
+impl Thread for FuzzThread {
+    fn get_id(&self) -> u64 { self.0 }
+    fn set_affinity(&self, _affinity: u64) {}
+    fn set_priority(&self, _prio: u64) {}
+    fn set_state(&self, _state: ThreadState) {}
+    fn sleep(&self, guard: MutexGuard<()>) {
+        drop(guard);
+        std::thread::yield_now();
+    }
+    fn interrupt(&self) {}
+}
+
+/// Condition variable whose `sleep` returns right away, i.e. every wait is a spurious wakeup.
+struct FuzzCondVar();
+
+impl CondVar for FuzzCondVar {
+    fn sleep<'a>(&self, guard: MutexGuard<'a, ()>) {
+        drop(guard);
+        std::thread::yield_now();
+    }
+    fn wakeup(&self) {}
+}
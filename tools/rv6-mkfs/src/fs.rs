@@ -1,16 +1,20 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::SystemTime;
 use std::{
     fs::{File, OpenOptions},
     io::{Read, Seek, SeekFrom, Write},
     mem, slice,
 };
 
+use byteorder::{ByteOrder, LittleEndian};
+
 use crate::inode::INodeFileType;
 use crate::superblock::SuperBlock;
 use crate::{
     inode::DINode,
     layer::{Layer, LayerType},
-    params,
+    params, utils,
 };
 
 #[derive(Debug)]
@@ -21,27 +25,100 @@ pub struct FSHandler {
     dinode_size: usize,
     freeblock: u32,
     freeinode: u32,
+    // Blocks of a committed transaction that are read in place of their home location
+    overlay: HashMap<u32, Vec<u8>>,
 }
 
 impl FSHandler {
-    pub fn new(filename: &String) -> Self {
+    pub fn new(filename: &String, super_block: SuperBlock) -> Self {
         let path = Path::new(&filename);
+        let raw_disk_img = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap();
+
+        // Extending the file fills it with zeroes, without writing out every block of a
+        // possibly multi-GB image
+        raw_disk_img
+            .set_len(super_block.size as u64 * params::BSIZE as u64)
+            .unwrap();
 
         FSHandler {
-            super_block: SuperBlock::init(),
-            raw_disk_img: OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(path)
-                .unwrap(),
+            super_block,
+            raw_disk_img,
             dinode_size: mem::size_of::<DINode>(),
             freeinode: 1,
             freeblock: 0,
+            overlay: HashMap::new(),
+        }
+    }
+
+    pub fn open(filename: &str, writable: bool) -> Self {
+        /// Opens an existing image, reading its geometry from the superblock
+        let mut raw_disk_img = OpenOptions::new()
+            .read(true)
+            .write(writable)
+            .open(filename)
+            .unwrap_or_else(|e| {
+                eprintln!("error: cannot open {}: {}", filename, e);
+                std::process::exit(1);
+            });
+
+        let mut buf = [0u8; params::BSIZE];
+        raw_disk_img
+            .seek(SeekFrom::Start(params::BSIZE as u64))
+            .and_then(|_| raw_disk_img.read_exact(&mut buf))
+            .unwrap_or_else(|e| {
+                eprintln!("error: cannot read the superblock of {}: {}", filename, e);
+                std::process::exit(1);
+            });
+        let super_block = SuperBlock::from_bytes(&buf);
+
+        if super_block.magic != params::FSMAGIC {
+            eprintln!(
+                "error: {} is not an image of this rv6 version (magic {:#x}, expected {:#x})",
+                filename,
+                super_block.magic,
+                params::FSMAGIC
+            );
+            std::process::exit(1);
+        }
+
+        let len = raw_disk_img.metadata().unwrap().len();
+        if super_block.nblocks == 0
+            || super_block.nblocks >= super_block.size
+            || super_block.size as u64 * params::BSIZE as u64 > len
+            || super_block.bmapstart >= super_block.nmeta()
+            || super_block.logstart < 2
+            || super_block.nlog == 0
+            || super_block.logstart as u64 + super_block.nlog as u64 > super_block.inodestart as u64
+            || super_block.inodestart as u64 + super_block.ninodes as u64 / params::IPB as u64
+                >= super_block.bmapstart as u64
+        {
+            eprintln!(
+                "error: {} has an invalid superblock ({:?}) for an image of {} bytes",
+                filename, super_block, len
+            );
+            std::process::exit(1);
+        }
+
+        FSHandler {
+            super_block,
+            raw_disk_img,
+            dinode_size: mem::size_of::<DINode>(),
+            freeinode: 0,
+            freeblock: 0,
+            overlay: HashMap::new(),
         }
     }
 
+    pub fn super_block(&self) -> &SuperBlock {
+        &self.super_block
+    }
+
     fn iblock(&self, i: u32) -> u32 {
         i / params::IPB as u32 + self.super_block.inodestart
     }
@@ -56,7 +133,7 @@ impl FSHandler {
 
     pub fn alloc_disk_block(&mut self, blocks: i32) {
         let mut used = blocks;
-        for block_offset in 0..params::NBITMAP {
+        for block_offset in 0..self.super_block.nbitmap() {
             let mut buf: [u8; params::BSIZE] = [0; params::BSIZE];
 
             if used <= 0 {
@@ -76,9 +153,9 @@ impl FSHandler {
             }
             println!(
                 "Block Alloc: write bitmap block at sector {}",
-                self.super_block.bmapstart + block_offset as u32
+                self.super_block.bmapstart + block_offset
             );
-            self.write(self.super_block.bmapstart + block_offset as u32, &mut buf);
+            self.write(self.super_block.bmapstart + block_offset, &mut buf);
             used -= params::BPB as i32;
         }
         if used > 0 {
@@ -113,15 +190,41 @@ impl FSHandler {
         }
     }
 
-    pub fn alloc_inode(&mut self, t: INodeFileType) -> u32 {
-        /// Allocates a new inode and returns its inode number
+    pub fn try_read_inode(&mut self, inum: u32) -> Result<DINode, String> {
+        /// Like `read_inode`, but fails instead of panicking on an inode number past the inode
+        /// table or an unknown file type, as found in corrupt images
+        if inum == 0 || inum >= self.super_block.ninodes {
+            return Err(format!(
+                "inode {} is outside of the inode table of {} inodes",
+                inum, self.super_block.ninodes
+            ));
+        }
+        let mut buf = [0u8; params::BSIZE];
+        self.read(self.iblock(inum), &mut buf);
+        let offset = (inum as usize % params::IPB) * self.dinode_size;
+        DINode::try_from_bytes(&buf[offset..offset + self.dinode_size])
+            .map_err(|file_type| format!("inode {} has unknown type {}", inum, file_type))
+    }
+
+    pub fn alloc_inode(&mut self, t: INodeFileType, permissions: u16) -> u32 {
+        /// Allocates a new inode owned by root and returns its inode number
         let inum = self.freeinode;
+        if inum >= self.super_block.ninodes {
+            eprintln!(
+                "error: out of inodes. increase the inode count with --ninodes to fix this."
+            );
+            panic!("alloc_inode");
+        }
         self.freeinode += 1;
 
         let mut dinode: DINode = DINode::new();
         dinode.file_type = t;
         dinode.nlink = 1 as i16;
         dinode.size = 0 as u32;
+        dinode.uid = params::ROOTUID;
+        dinode.permissions = permissions;
+        dinode.mtime = utils::unix_time(SystemTime::now());
+        dinode.ctime = dinode.mtime;
         self.write_inode(inum, &mut dinode);
 
         inum
@@ -145,6 +248,15 @@ impl FSHandler {
 
         let mut indirect = Layer::new(LayerType::Indirect);
 
+        if offset + data.len() > params::MAXFILESIZE {
+            eprintln!(
+                "error: inode {} would grow past the largest file size of {} bytes.",
+                inum,
+                params::MAXFILESIZE
+            );
+            panic!("append_data_to_inode");
+        }
+
         let mut bytes_left = data.len();
         while bytes_left > 0 {
             let block_num: usize = offset / params::BSIZE;
@@ -229,6 +341,175 @@ impl FSHandler {
         self.write_inode(inum, &dinode);
     }
 
+    pub fn set_mtime(&mut self, inum: u32, mtime: u64) {
+        /// Sets the modification time of an inode, e.g. to the one of the file it was copied from
+        let mut dinode: DINode = DINode::new();
+        self.read_inode(inum, &mut dinode);
+        dinode.mtime = mtime;
+        self.write_inode(inum, &dinode);
+    }
+
+    pub fn committed_log(&mut self) -> Result<Vec<(u32, Vec<u8>)>, String> {
+        /// Returns the blocks of the transaction committed to the log, as (home block, contents)
+        /// pairs. rv6 installs them when it mounts the image; until then, their home blocks may
+        /// hold stale data.
+        let mut header = [0u8; params::BSIZE];
+        self.read(self.super_block.logstart, &mut header);
+
+        // The header block holds the count and one home block number per log block
+        let n = LittleEndian::read_u32(&header[0..4]);
+        let room = (self.super_block.nlog - 1).min(params::MAXLOGSIZE as u32 - 1);
+        if n > room {
+            return Err(format!(
+                "log header holds {} blocks, but the log only has room for {}",
+                n, room
+            ));
+        }
+
+        let mut blocks = Vec::new();
+        for i in 0..n {
+            let home = LittleEndian::read_u32(&header[4 + i as usize * 4..]);
+            // Only inode, bitmap and data blocks go through the log
+            if home < self.super_block.inodestart || home >= self.super_block.size {
+                return Err(format!("log entry {} has invalid home block {}", i, home));
+            }
+            let mut data = vec![0u8; params::BSIZE];
+            self.read(self.super_block.logstart + 1 + i, &mut data);
+            blocks.push((home, data));
+        }
+        Ok(blocks)
+    }
+
+    pub fn overlay_log(&mut self, blocks: Vec<(u32, Vec<u8>)>) {
+        /// Reads the image as if the committed log was installed, without writing to it
+        self.overlay.extend(blocks);
+    }
+
+    pub fn install_log(&mut self, blocks: Vec<(u32, Vec<u8>)>) {
+        /// Copies the committed blocks to their home locations and clears the log, like rv6
+        /// does when recovering at mount time
+        for (home, mut data) in blocks {
+            self.write(home, &mut data);
+        }
+
+        let mut header = [0u8; params::BSIZE];
+        self.read(self.super_block.logstart, &mut header);
+        LittleEndian::write_u32(&mut header[0..4], 0);
+        self.write(self.super_block.logstart, &mut header);
+    }
+
+    fn check_block(&self, block: u32) -> Result<u32, String> {
+        if block < self.super_block.nmeta() || block >= self.super_block.size {
+            return Err(format!("block {} is outside of the data area", block));
+        }
+        Ok(block)
+    }
+
+    pub fn block_address(&mut self, dinode: &DINode, n: usize) -> Result<u32, String> {
+        /// Returns the disk block holding the nth block of an inode, or 0 for a hole.
+        /// Unlike `append_data_to_inode`, this never allocates.
+        if n < params::NDIRECT {
+            return Ok(dinode.addresses[n]);
+        }
+
+        let n = n - params::NDIRECT;
+        if n >= params::NINDIRECT * params::NINDIRECT {
+            return Err(format!("block {} is past the largest file", n + params::NDIRECT));
+        }
+
+        let mut table = Layer::new(LayerType::Indirect);
+        let mut address = dinode.addresses[params::NDIRECT];
+        for index in [n / params::NINDIRECT, n % params::NINDIRECT].iter() {
+            if address == 0 {
+                return Ok(0);
+            }
+            self.read(self.check_block(address)?, table.as_mut_slice());
+            address = table.get(*index);
+        }
+        Ok(address)
+    }
+
+    pub fn read_inode_data(&mut self, dinode: &DINode) -> Result<Vec<u8>, String> {
+        /// Returns the contents of an inode
+        let size = dinode.size as usize;
+        let mut data = vec![0u8; (size + params::BSIZE - 1) / params::BSIZE * params::BSIZE];
+        for (n, chunk) in data.chunks_mut(params::BSIZE).enumerate() {
+            let address = self.block_address(dinode, n)?;
+            if address != 0 {
+                self.read(self.check_block(address)?, chunk);
+            }
+        }
+        data.truncate(size);
+        Ok(data)
+    }
+
+    pub fn read_dir(&mut self, inum: u32) -> Result<Vec<(String, u32)>, String> {
+        /// Returns the (name, inode number) pairs of the used entries of a directory
+        let dinode = self.try_read_inode(inum)?;
+        if dinode.file_type != INodeFileType::Directory {
+            return Err(format!("inode {} is not a directory", inum));
+        }
+
+        let data = self.read_inode_data(&dinode)?;
+        let mut entries = Vec::new();
+        for entry in data.chunks_exact(mem::size_of::<DirEntry>()) {
+            let inum = LittleEndian::read_u16(entry) as u32;
+            if inum == 0 {
+                continue;
+            }
+            let name = &entry[2..];
+            let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+            entries.push((String::from_utf8_lossy(&name[..len]).into_owned(), inum));
+        }
+        Ok(entries)
+    }
+
+    pub fn namei(&mut self, path: &str) -> Result<u32, String> {
+        /// Resolves a path from the root directory, following symbolic links like rv6 does
+        self.namex(path, true)
+    }
+
+    pub fn namei_nofollow(&mut self, path: &str) -> Result<u32, String> {
+        /// Like `namei`, but returns a symbolic link in the last component itself
+        self.namex(path, false)
+    }
+
+    fn namex(&mut self, path: &str, follow: bool) -> Result<u32, String> {
+        let mut components: Vec<String> = path
+            .split('/')
+            .filter(|n| !n.is_empty())
+            .rev()
+            .map(String::from)
+            .collect();
+        let mut inum = params::ROOTINO as u32;
+        let mut links = 0;
+
+        while let Some(component) = components.pop() {
+            let (_, next) = self
+                .read_dir(inum)?
+                .into_iter()
+                .find(|(name, _)| *name == component)
+                .ok_or_else(|| format!("{}: no such file or directory", path))?;
+
+            let dinode = self.try_read_inode(next)?;
+            if dinode.file_type != INodeFileType::Symlink || (!follow && components.is_empty()) {
+                inum = next;
+                continue;
+            }
+
+            links += 1;
+            if links > params::MAXSYMLINKS {
+                return Err(format!("{}: too many symbolic links", path));
+            }
+            let target = String::from_utf8_lossy(&self.read_inode_data(&dinode)?).into_owned();
+            if target.starts_with('/') {
+                inum = params::ROOTINO as u32;
+            }
+            components.extend(target.split('/').filter(|n| !n.is_empty()).rev().map(String::from));
+        }
+        Ok(inum)
+    }
+
     pub fn superblock_bytes(&self) -> &[u8] {
         /// Returns the bytes of the superblock as a slice
         self.super_block.bytes()
@@ -240,6 +521,11 @@ impl FSHandler {
         /// # Arguments
         /// * `sec` - The location within the file to read from
         /// * `buf` - The buffer which the contents of the raw image will be read into
+        if let Some(block) = self.overlay.get(&sec) {
+            buf.copy_from_slice(block);
+            return;
+        }
+
         let offset: u64 = sec as u64 * params::BSIZE as u64;
 
         if self.raw_disk_img.seek(SeekFrom::Start(offset)).unwrap() != offset {
@@ -250,7 +536,7 @@ impl FSHandler {
         if bytes_read != params::BSIZE {
             eprint!(
                 "error: read {} bytes. usually caused by not having enough space.
-                    increase FSZIE in params.rs to fix this. \n",
+                    increase the image size with --size to fix this. \n",
                 bytes_read
             );
             panic!("read");
@@ -264,6 +550,13 @@ impl FSHandler {
         /// * `sec` - The location within the file to write into
         /// * `buf` - The buffer whose contents will be written into the raw disk image
         assert_eq!(buf.len(), params::BSIZE);
+        if sec >= self.super_block.size {
+            eprintln!(
+                "error: block {} is past the end of the image. increase the image size with --size to fix this.",
+                sec
+            );
+            panic!("write");
+        }
 
         let location: u64 = (sec as usize * params::BSIZE) as u64;
         if self.raw_disk_img.seek(SeekFrom::Start(location)).unwrap() != location {
@@ -280,24 +573,30 @@ impl FSHandler {
 #[repr(C)]
 pub struct DirEntry {
     inum: u16,
-    pub name: [u8; params::DIRSIZE],
+    pub name: [u8; params::DIRSIZ],
 }
 
 impl DirEntry {
     pub fn default() -> Self {
         DirEntry {
             inum: 0,
-            name: [0; params::DIRSIZE],
+            name: [0; params::DIRSIZ],
         }
     }
 
     pub fn new(n: u16, string: &str) -> Self {
         let mut dir = DirEntry {
             inum: n,
-            name: [0; params::DIRSIZE],
+            name: [0; params::DIRSIZ],
         };
 
         let str_bytes = string.as_bytes();
+        assert!(
+            str_bytes.len() <= params::DIRSIZ,
+            "file name {} is longer than {} bytes",
+            string,
+            params::DIRSIZ
+        );
         let mut i = 0;
         for byte in str_bytes {
             dir.name[i] = *byte;
@@ -0,0 +1,288 @@
+// Consistency check of an rv6 image.
+//
+// Mirrors what rv6 itself relies on: a committed log is installed first, then every allocated
+// inode is checked for valid block addresses, the directory tree is walked from the root,
+// link counts are compared against the directory entries found, and the free-block bitmap
+// is compared against the blocks actually in use.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::fs::FSHandler;
+use crate::inode::{DINode, INodeFileType};
+use crate::layer::{Layer, LayerType};
+use crate::params;
+
+// Only the first few leaked blocks are listed
+const MAX_LISTED: usize = 10;
+
+struct Checker {
+    fs: FSHandler,
+    problems: usize,
+    // Allocated inodes, by inode number
+    inodes: HashMap<u32, DINode>,
+    // Data blocks in use, and the inode using each of them
+    blocks: HashMap<u32, u32>,
+    // Directory entries pointing to each inode, not counting "." and ".."
+    links: HashMap<u32, usize>,
+    // Subdirectories of each directory
+    subdirs: HashMap<u32, usize>,
+}
+
+impl Checker {
+    fn problem(&mut self, message: String) {
+        println!("{}", message);
+        self.problems += 1;
+    }
+
+    fn read_inodes(&mut self) {
+        let ninodes = self.fs.super_block().ninodes;
+        let inodestart = self.fs.super_block().inodestart;
+        let dinode_size = std::mem::size_of::<DINode>();
+
+        let mut buf = [0u8; params::BSIZE];
+        for inum in 1..ninodes {
+            if inum as usize % params::IPB == 0 || inum == 1 {
+                self.fs.read(inodestart + inum / params::IPB as u32, &mut buf);
+            }
+            let offset = (inum as usize % params::IPB) * dinode_size;
+            match DINode::try_from_bytes(&buf[offset..offset + dinode_size]) {
+                Ok(dinode) if dinode.file_type == INodeFileType::Uninitialized => {}
+                Ok(dinode) => {
+                    self.inodes.insert(inum, dinode);
+                }
+                Err(file_type) => self.problem(format!("inode {}: unknown type {}", inum, file_type)),
+            }
+        }
+    }
+
+    fn use_block(&mut self, inum: u32, block: u32) -> bool {
+        let sb = self.fs.super_block();
+        if block < sb.nmeta() || block >= sb.size {
+            self.problem(format!("inode {}: block {} is outside of the data area", inum, block));
+            return false;
+        }
+        if let Some(owner) = self.blocks.insert(block, inum) {
+            self.problem(format!("inode {}: block {} is also used by inode {}", inum, block, owner));
+            return false;
+        }
+        true
+    }
+
+    fn check_blocks(&mut self, inum: u32, dinode: &DINode) {
+        let nblocks = (dinode.size as usize + params::BSIZE - 1) / params::BSIZE;
+        if dinode.size as usize > params::MAXFILESIZE {
+            self.problem(format!("inode {}: size {} is too large", inum, dinode.size));
+        }
+
+        for (n, address) in dinode.addresses[..params::NDIRECT].iter().enumerate() {
+            if *address != 0 && self.use_block(inum, *address) && n >= nblocks {
+                self.problem(format!("inode {}: block {} is past the end of the file", inum, n));
+            }
+        }
+
+        let l1 = dinode.addresses[params::NDIRECT];
+        if l1 == 0 || !self.use_block(inum, l1) {
+            return;
+        }
+        let mut table = Layer::new(LayerType::Indirect);
+        self.fs.read(l1, table.as_mut_slice());
+        for i in 0..params::NINDIRECT {
+            let l2 = table.get(i);
+            if l2 == 0 || !self.use_block(inum, l2) {
+                continue;
+            }
+            let mut entries = Layer::new(LayerType::Indirect);
+            self.fs.read(l2, entries.as_mut_slice());
+            for j in 0..params::NINDIRECT {
+                let address = entries.get(j);
+                let n = params::NDIRECT + i * params::NINDIRECT + j;
+                if address != 0 && self.use_block(inum, address) && n >= nblocks {
+                    self.problem(format!("inode {}: block {} is past the end of the file", inum, n));
+                }
+            }
+        }
+    }
+
+    fn walk_tree(&mut self) {
+        let root = params::ROOTINO as u32;
+        match self.inodes.get(&root) {
+            Some(dinode) if dinode.file_type == INodeFileType::Directory => {}
+            _ => {
+                self.problem(String::from("root inode is not a directory"));
+                return;
+            }
+        }
+
+        let mut visited = HashSet::new();
+        visited.insert(root);
+        // (directory, parent)
+        let mut stack = vec![(root, root)];
+        while let Some((dir, parent)) = stack.pop() {
+            let entries = match self.fs.read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.problem(format!("directory {}: {}", dir, e));
+                    continue;
+                }
+            };
+
+            let mut dot = false;
+            let mut dotdot = false;
+            for (name, inum) in entries {
+                match name.as_str() {
+                    "." => {
+                        dot = true;
+                        if inum != dir {
+                            self.problem(format!("directory {}: \".\" points to {}", dir, inum));
+                        }
+                        continue;
+                    }
+                    ".." => {
+                        dotdot = true;
+                        if inum != parent {
+                            self.problem(format!("directory {}: \"..\" points to {} instead of {}", dir, inum, parent));
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+
+                let file_type = match self.inodes.get(&inum) {
+                    Some(dinode) => dinode.file_type,
+                    None => {
+                        self.problem(format!("directory {}: {} points to free inode {}", dir, name, inum));
+                        continue;
+                    }
+                };
+                *self.links.entry(inum).or_insert(0) += 1;
+
+                if file_type == INodeFileType::Directory {
+                    *self.subdirs.entry(dir).or_insert(0) += 1;
+                    if visited.insert(inum) {
+                        stack.push((inum, dir));
+                    } else {
+                        self.problem(format!("directory {}: {} links directory {} a second time", dir, name, inum));
+                    }
+                }
+            }
+
+            if !dot || !dotdot {
+                self.problem(format!("directory {}: missing \".\" or \"..\"", dir));
+            }
+        }
+    }
+
+    fn check_links(&mut self) {
+        let mut inums: Vec<u32> = self.inodes.keys().cloned().collect();
+        inums.sort();
+
+        for inum in inums {
+            let dinode = &self.inodes[&inum];
+            let links = self.links.get(&inum).cloned().unwrap_or(0);
+            let expected = if dinode.file_type == INodeFileType::Directory {
+                // Named once by its parent (the root by nobody), and by the ".." of every
+                // subdirectory
+                let named = if inum == params::ROOTINO as u32 { 1 } else { links };
+                named + self.subdirs.get(&inum).cloned().unwrap_or(0)
+            } else {
+                links
+            };
+
+            if links == 0 && inum != params::ROOTINO as u32 {
+                let message = format!("inode {}: allocated but not reachable from the root", inum);
+                self.problem(message);
+            } else if dinode.nlink as usize != expected {
+                let message = format!("inode {}: nlink is {}, expected {}", inum, dinode.nlink, expected);
+                self.problem(message);
+            }
+        }
+    }
+
+    fn check_bitmap(&mut self) {
+        let (size, nmeta, bmapstart) = {
+            let sb = self.fs.super_block();
+            (sb.size, sb.nmeta(), sb.bmapstart)
+        };
+
+        let mut leaked = Vec::new();
+        let mut buf = [0u8; params::BSIZE];
+        for block in 0..size {
+            let bit = block as usize % params::BPB;
+            if bit == 0 {
+                self.fs.read(bmapstart + block / params::BPB as u32, &mut buf);
+            }
+            let marked = buf[bit / 8] & (1 << (bit % 8)) != 0;
+            let used = block < nmeta || self.blocks.contains_key(&block);
+            if used && !marked {
+                let message = match self.blocks.get(&block) {
+                    Some(inum) => format!("block {}: used by inode {} but marked free", block, inum),
+                    None => format!("block {}: metadata block marked free", block),
+                };
+                self.problem(message);
+            } else if marked && !used {
+                leaked.push(block);
+            }
+        }
+
+        if !leaked.is_empty() {
+            let listed: Vec<String> = leaked.iter().take(MAX_LISTED).map(|b| b.to_string()).collect();
+            let more = if leaked.len() > MAX_LISTED { ", ..." } else { "" };
+            self.problem(format!(
+                "{} blocks marked used but not referenced: {}{}",
+                leaked.len(),
+                listed.join(", "),
+                more
+            ));
+        }
+    }
+}
+
+/// Checks the image and returns the number of problems found. Unless `dry_run` is set, a
+/// committed log is installed first, like rv6 does at mount time.
+pub fn fsck(image: &str, dry_run: bool) -> usize {
+    let mut fs = FSHandler::open(image, !dry_run);
+    let mut problems = 0;
+
+    match fs.committed_log() {
+        Ok(blocks) if blocks.is_empty() => {}
+        Ok(blocks) if dry_run => {
+            println!("log holds a committed transaction of {} blocks, checking as if installed", blocks.len());
+            fs.overlay_log(blocks);
+        }
+        Ok(blocks) => {
+            println!("installing a committed transaction of {} blocks from the log", blocks.len());
+            fs.install_log(blocks);
+        }
+        Err(e) => {
+            println!("log: {}, ignoring it", e);
+            problems += 1;
+        }
+    }
+
+    let mut checker = Checker {
+        fs,
+        problems,
+        inodes: HashMap::new(),
+        blocks: HashMap::new(),
+        links: HashMap::new(),
+        subdirs: HashMap::new(),
+    };
+
+    checker.read_inodes();
+    let inodes: Vec<(u32, DINode)> = checker.inodes.iter().map(|(inum, d)| (*inum, DINode::new_from(d))).collect();
+    for (inum, dinode) in inodes.iter() {
+        checker.check_blocks(*inum, dinode);
+    }
+    checker.walk_tree();
+    checker.check_links();
+    checker.check_bitmap();
+
+    println!(
+        "{}: {} inodes, {} data blocks in use, {} problems",
+        image,
+        checker.inodes.len(),
+        checker.blocks.len(),
+        checker.problems
+    );
+    checker.problems
+}
@@ -15,6 +15,8 @@ pub enum INodeFileType {
     File,
     // Correspond to
     Device,
+    // Symbolic link; the content of the inode is the path it points to
+    Symlink,
 }
 
 #[repr(C)]
@@ -30,6 +32,14 @@ pub struct INodeData {
     pub nlink: i16,
     // Size of file (bytes)
     pub size: u32,
+    // User id of the owner
+    pub uid: u16,
+    // Access permission bits, laid out like the Unix mode bits
+    pub permissions: u16,
+    // Last modification of the content (seconds since the Unix epoch)
+    pub mtime: u64,
+    // Last change of the inode (seconds since the Unix epoch)
+    pub ctime: u64,
     // Data block addresses
     pub addresses: [u32; params::NDIRECT + 1],
 }
@@ -44,6 +54,10 @@ impl INodeData {
             minor: 0,
             nlink: 0,
             size: 0,
+            uid: 0,
+            permissions: 0,
+            mtime: 0,
+            ctime: 0,
             addresses: [0; params::NDIRECT + 1],
         }
     }
@@ -55,6 +69,10 @@ impl INodeData {
             minor: rhs.minor,
             nlink: rhs.nlink,
             size: rhs.size,
+            uid: rhs.uid,
+            permissions: rhs.permissions,
+            mtime: rhs.mtime,
+            ctime: rhs.ctime,
             addresses: rhs.addresses.clone(),
         }
     }
@@ -77,6 +95,18 @@ impl INodeData {
         self.size = LittleEndian::read_u32(&bytes[offset..]);
         offset += mem::size_of_val(&self.size);
 
+        self.uid = LittleEndian::read_u16(&bytes[offset..]);
+        offset += mem::size_of_val(&self.uid);
+
+        self.permissions = LittleEndian::read_u16(&bytes[offset..]);
+        offset += mem::size_of_val(&self.permissions);
+
+        self.mtime = LittleEndian::read_u64(&bytes[offset..]);
+        offset += mem::size_of_val(&self.mtime);
+
+        self.ctime = LittleEndian::read_u64(&bytes[offset..]);
+        offset += mem::size_of_val(&self.ctime);
+
         for a in &mut self.addresses {
             *a = LittleEndian::read_u32(&bytes[offset..]);
             offset += mem::size_of_val(a);
@@ -89,6 +119,15 @@ impl INodeData {
         dinode
     }
 
+    // Like from_bytes, but hands back the file type instead of panicking if it is unknown
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, u16> {
+        let file_type = LittleEndian::read_u16(bytes);
+        match INodeFileType::from_u16(file_type) {
+            Some(_) => Ok(Self::from_bytes(bytes)),
+            None => Err(file_type),
+        }
+    }
+
     pub fn to_bytes(&self, bytes: &mut [u8]) {
         let mut offset: usize = 0;
         LittleEndian::write_u16(&mut bytes[offset..], self.file_type as u16);
@@ -106,6 +145,18 @@ impl INodeData {
         LittleEndian::write_u32(&mut bytes[offset..], self.size);
         offset += mem::size_of_val(&self.size);
 
+        LittleEndian::write_u16(&mut bytes[offset..], self.uid);
+        offset += mem::size_of_val(&self.uid);
+
+        LittleEndian::write_u16(&mut bytes[offset..], self.permissions);
+        offset += mem::size_of_val(&self.permissions);
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.mtime);
+        offset += mem::size_of_val(&self.mtime);
+
+        LittleEndian::write_u64(&mut bytes[offset..], self.ctime);
+        offset += mem::size_of_val(&self.ctime);
+
         for a in &self.addresses {
             LittleEndian::write_u32(&mut bytes[offset..], *a);
             offset += mem::size_of_val(a);
@@ -0,0 +1,219 @@
+// Read-only access to the files of an rv6 image: `ls`, `cat` and `extract`.
+//
+// The image is never written to. A transaction committed to the log but not installed yet is
+// read in place of the blocks it overwrites, so files look like they will once rv6 mounts
+// the image.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::Path;
+
+use interface::vfs::UtcTime;
+
+use crate::fs::FSHandler;
+use crate::inode::INodeFileType;
+use crate::utils;
+
+pub fn open(image: &str) -> FSHandler {
+    let mut fs = FSHandler::open(image, false);
+    match fs.committed_log() {
+        Ok(blocks) => fs.overlay_log(blocks),
+        Err(e) => eprintln!("warning: ignoring the log: {}", e),
+    }
+    fs
+}
+
+fn type_char(file_type: INodeFileType) -> char {
+    match file_type {
+        INodeFileType::Directory => 'd',
+        INodeFileType::Device => 'c',
+        INodeFileType::Symlink => 'l',
+        _ => '-',
+    }
+}
+
+fn print_entry(fs: &mut FSHandler, name: &str, inum: u32) -> Result<(), String> {
+    let dinode = fs.try_read_inode(inum)?;
+    let mut line = format!(
+        "{}{} {:>5} {:>10} {:>5} {} {}",
+        type_char(dinode.file_type),
+        utils::mode_string(dinode.permissions),
+        inum,
+        dinode.size,
+        dinode.uid,
+        UtcTime(dinode.mtime),
+        name
+    );
+    if dinode.file_type == INodeFileType::Symlink {
+        let target = fs.read_inode_data(&dinode)?;
+        line.push_str(&format!(" -> {}", String::from_utf8_lossy(&target)));
+    }
+    println!("{}", line);
+    Ok(())
+}
+
+/// Lists a directory, or describes a single file
+pub fn ls(image: &str, path: &str) -> Result<(), String> {
+    let mut fs = open(image);
+    let inum = fs.namei_nofollow(path)?;
+
+    if fs.try_read_inode(inum)?.file_type != INodeFileType::Directory {
+        return print_entry(&mut fs, path, inum);
+    }
+    for (name, inum) in fs.read_dir(inum)? {
+        print_entry(&mut fs, &name, inum)?;
+    }
+    Ok(())
+}
+
+/// Writes the content of a file to stdout
+pub fn cat(image: &str, path: &str) -> Result<(), String> {
+    let mut fs = open(image);
+    let inum = fs.namei(path)?;
+    let dinode = fs.try_read_inode(inum)?;
+    if dinode.file_type == INodeFileType::Directory {
+        return Err(format!("{}: is a directory", path));
+    }
+
+    let data = fs.read_inode_data(&dinode)?;
+    std::io::stdout()
+        .write_all(&data)
+        .map_err(|e| format!("cannot write to stdout: {}", e))
+}
+
+/// Copies a file or a directory tree out of the image to `dest` on the host
+pub fn extract(image: &str, path: &str, dest: &str) -> Result<(), String> {
+    let mut fs = open(image);
+    let inum = fs.namei_nofollow(path)?;
+    extract_inode(&mut fs, inum, Path::new(dest))
+}
+
+fn extract_inode(fs: &mut FSHandler, inum: u32, dest: &Path) -> Result<(), String> {
+    let dinode = fs.try_read_inode(inum)?;
+    let host_error = |e: std::io::Error| format!("{}: {}", dest.display(), e);
+
+    match dinode.file_type {
+        INodeFileType::Directory => {
+            fs::create_dir_all(dest).map_err(host_error)?;
+            for (name, child) in fs.read_dir(inum)? {
+                if name == "." || name == ".." {
+                    continue;
+                }
+                extract_inode(fs, child, &dest.join(&name))?;
+            }
+        }
+        INodeFileType::File => {
+            let data = fs.read_inode_data(&dinode)?;
+            File::create(dest)
+                .and_then(|mut file| file.write_all(&data))
+                .map_err(host_error)?;
+        }
+        INodeFileType::Symlink => {
+            let target = fs.read_inode_data(&dinode)?;
+            symlink(String::from_utf8_lossy(&target).as_ref(), dest).map_err(host_error)?;
+            return Ok(());
+        }
+        // Device nodes only make sense inside rv6
+        INodeFileType::Device => {
+            eprintln!("skipping device {}", dest.display());
+            return Ok(());
+        }
+        INodeFileType::Uninitialized => {
+            return Err(format!("{}: inode {} is free", dest.display(), inum));
+        }
+    }
+
+    fs::set_permissions(dest, fs::Permissions::from_mode(dinode.permissions as u32 & 0o777))
+        .map_err(host_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use crate::fs::DirEntry;
+    use crate::params::{self, BSIZE};
+    use crate::superblock::SuperBlock;
+
+    // Writes an image with a root directory and a file "file", and returns its path together
+    // with a handler to corrupt it
+    fn image(name: &str, nlog: usize) -> (String, FSHandler) {
+        let path = std::env::temp_dir()
+            .join(format!("rv6-mkfs-{}-{}.img", std::process::id(), name))
+            .to_str()
+            .unwrap()
+            .to_string();
+        let super_block = SuperBlock::init(2000, 50, nlog);
+        let nmeta = super_block.nmeta();
+        let mut fs = FSHandler::new(&path, super_block);
+        fs.set_freeblock(nmeta);
+
+        let mut buf = [0u8; BSIZE];
+        utils::fill(&mut buf, fs.superblock_bytes(), 0);
+        fs.write(1, &mut buf);
+
+        let root = crate::append_root(&mut fs);
+        let inum = fs.alloc_inode(INodeFileType::File, 0o644);
+        fs.append_data_to_inode(root, DirEntry::new(inum as u16, "file").bytes());
+        fs.append_data_to_inode(inum, &mut b"hello".to_vec());
+        (path, fs)
+    }
+
+    #[test]
+    fn intact_image() {
+        let (path, fs) = image("intact", params::DEFAULT_LOGSIZE);
+        drop(fs);
+        assert!(ls(&path, "/").is_ok());
+        assert!(cat(&path, "/file").is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn log_header_past_its_block() {
+        // A log larger than the header can describe, with a count that indexes past the
+        // end of the header block
+        let (path, mut fs) = image("log", params::MAXLOGSIZE + 50);
+        let mut header = [0u8; BSIZE];
+        LittleEndian::write_u32(&mut header[0..4], params::MAXLOGSIZE as u32 + 10);
+        let logstart = fs.super_block().logstart;
+        fs.write(logstart, &mut header);
+        drop(fs);
+
+        assert!(FSHandler::open(&path, false).committed_log().is_err());
+        // the log is ignored, the files are still there
+        assert!(cat(&path, "/file").is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn entry_past_the_inode_table() {
+        let (path, mut fs) = image("entry", params::DEFAULT_LOGSIZE);
+        let root = params::ROOTINO as u32;
+        fs.append_data_to_inode(root, DirEntry::new(1000, "bad").bytes());
+        drop(fs);
+
+        assert!(ls(&path, "/").is_err());
+        assert!(cat(&path, "/bad").is_err());
+        assert!(cat(&path, "/file").is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unknown_file_type() {
+        let (path, mut fs) = image("type", params::DEFAULT_LOGSIZE);
+        let inum = fs.namei("/file").unwrap();
+        let block = fs.super_block().inodestart + inum / params::IPB as u32;
+        let mut buf = [0u8; BSIZE];
+        fs.read(block, &mut buf);
+        let offset = (inum as usize % params::IPB) * std::mem::size_of::<crate::inode::DINode>();
+        LittleEndian::write_u16(&mut buf[offset..], 0xff);
+        fs.write(block, &mut buf);
+        drop(fs);
+
+        assert!(cat(&path, "/file").is_err());
+        assert!(ls(&path, "/").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
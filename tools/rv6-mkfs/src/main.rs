@@ -6,6 +6,7 @@ use crate::inode::{DINode, INodeFileType};
 use crate::superblock::SuperBlock;
 use crate::params::BSIZE;
 
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::{
     fs::{File, OpenOptions},
@@ -15,32 +16,180 @@ use std::{
 };
 
 mod fs;
+mod fsck;
 mod inode;
+mod inspect;
 mod layer;
 mod params;
 mod superblock;
 mod utils;
 
+const USAGE: &str = "Usage: mkfs [--size N[K|M|G]] [--ninodes N] [--nlog N] [--symlink NAME=TARGET]... [--no-dev] fs.img files...
+       mkfs fsck [-n] fs.img
+       mkfs ls fs.img [PATH]
+       mkfs cat fs.img PATH
+       mkfs extract fs.img PATH DEST
+    --size     size of the image, in blocks or, with a suffix, in bytes (default 15000 blocks)
+    --ninodes  number of inodes (default 200)
+    --nlog     number of log blocks, including the log header (default 256)
+    --symlink  add a symbolic link NAME in the root directory pointing to TARGET
+    --no-dev   don't create /dev with the null, zero and random devices
+    fsck -n    only report problems; don't install a committed log";
+
+struct Options {
+    size: usize,
+    ninodes: usize,
+    nlog: usize,
+    symlinks: Vec<(String, String)>,
+    dev: bool,
+    image: String,
+    files: Vec<String>,
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("error: {}\n{}", error, USAGE);
+    std::process::exit(1);
+}
+
+// Parses a block count, or a byte count if followed by K, M or G
+fn parse_size(arg: &str) -> Option<usize> {
+    let (digits, unit) = match arg.chars().last()? {
+        'K' | 'k' => (&arg[..arg.len() - 1], 1 << 10),
+        'M' | 'm' => (&arg[..arg.len() - 1], 1 << 20),
+        'G' | 'g' => (&arg[..arg.len() - 1], 1 << 30),
+        _ => return arg.parse().ok(),
+    };
+    let bytes: usize = digits.parse::<usize>().ok()?.checked_mul(unit)?;
+    Some(bytes / BSIZE)
+}
+
+fn parse_args(argv: &[String]) -> Options {
+    let mut options = Options {
+        size: params::DEFAULT_FSSIZE,
+        ninodes: params::DEFAULT_NINODES,
+        nlog: params::DEFAULT_LOGSIZE,
+        symlinks: Vec::new(),
+        dev: true,
+        image: String::new(),
+        files: Vec::new(),
+    };
+
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| match args.next() {
+            Some(value) => value.clone(),
+            None => usage(&format!("{} requires a value", name)),
+        };
+        match arg.as_str() {
+            "--size" => {
+                let value = value(arg);
+                options.size = parse_size(&value)
+                    .unwrap_or_else(|| usage(&format!("invalid size {}", value)));
+            }
+            "--ninodes" => {
+                let value = value(arg);
+                options.ninodes = value
+                    .parse()
+                    .unwrap_or_else(|_| usage(&format!("invalid inode count {}", value)));
+            }
+            "--nlog" => {
+                let value = value(arg);
+                options.nlog = value
+                    .parse()
+                    .unwrap_or_else(|_| usage(&format!("invalid log size {}", value)));
+            }
+            "--symlink" => {
+                let value = value(arg);
+                let (name, target) = match value.find('=') {
+                    Some(i) if i > 0 && i + 1 < value.len() => (&value[..i], &value[i + 1..]),
+                    _ => usage(&format!("invalid symlink {}", value)),
+                };
+                if name.contains('/') {
+                    usage(&format!("symlink name {} must not contain '/'", name));
+                }
+                options.symlinks.push((String::from(name), String::from(target)));
+            }
+            "--no-dev" => options.dev = false,
+            _ if options.image.is_empty() => options.image = arg.clone(),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+
+    if options.image.is_empty() {
+        usage("missing image name");
+    }
+    // Block numbers are stored as u32
+    if options.size > u32::MAX as usize {
+        usage(&format!("an image can hold at most {} blocks", u32::MAX));
+    }
+    if options.ninodes <= params::ROOTINO as usize || options.ninodes > params::MAXINODES {
+        usage(&format!("the inode count must be between {} and {}", params::ROOTINO + 1, params::MAXINODES));
+    }
+    // A transaction may log up to MAXOPBLOCKS blocks next to the header
+    if options.nlog <= params::MAXOPBLOCKS || options.nlog > params::MAXLOGSIZE {
+        usage(&format!("the log size must be between {} and {}", params::MAXOPBLOCKS + 1, params::MAXLOGSIZE));
+    }
+    options
+}
+
 fn main() {
-    let mut argv: Vec<String> = std::env::args().collect();
-    if argv.len() < 2 {
-        panic!("Usage: mkfs fs.img files...\n");
+    let argv: Vec<String> = std::env::args().collect();
+    let args: Vec<&str> = argv.iter().skip(2).map(String::as_str).collect();
+
+    let result = match argv.get(1).map(String::as_str) {
+        Some("fsck") => {
+            let (dry_run, args) = match args.split_first() {
+                Some((&"-n", rest)) => (true, rest),
+                _ => (false, &args[..]),
+            };
+            match args {
+                [image] => {
+                    if fsck::fsck(image, dry_run) > 0 {
+                        std::process::exit(1);
+                    }
+                    Ok(())
+                }
+                _ => usage("fsck takes an image"),
+            }
+        }
+        Some("ls") => match args[..] {
+            [image] => inspect::ls(image, "/"),
+            [image, path] => inspect::ls(image, path),
+            _ => usage("ls takes an image and an optional path"),
+        },
+        Some("cat") => match args[..] {
+            [image, path] => inspect::cat(image, path),
+            _ => usage("cat takes an image and a path"),
+        },
+        Some("extract") => match args[..] {
+            [image, path, dest] => inspect::extract(image, path, dest),
+            _ => usage("extract takes an image, a path and a destination"),
+        },
+        _ => {
+            mkfs(&argv);
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
     }
+}
 
-    let mut buf: [u8; BSIZE] = [0; BSIZE];
-    let mut zeroes: [u8; BSIZE] = [0; BSIZE];
-    let mut fs = FSHandler::new(&argv[1]);
+fn mkfs(argv: &[String]) {
+    let options = parse_args(argv);
 
-    let nmeta: usize = 2 + params::LOGSIZE + params::NINODEBLOCKS + params::NBITMAP;
-    let nblocks: usize = params::FSSIZE - nmeta;
+    let mut buf: [u8; BSIZE] = [0; BSIZE];
+    let super_block = SuperBlock::init(options.size, options.ninodes, options.nlog);
+    let nmeta = super_block.nmeta();
 
     print!("nmeta {} (boot, super, log blocks {} inode blocks {}, bitmapblocks {}) blocks {} total {}\n",
-           nmeta, params::LOGSIZE, params::NINODEBLOCKS, params::NBITMAP, nblocks, params::FSSIZE);
-    fs.set_freeblock(nmeta as u32);
+           nmeta, super_block.nlog, super_block.bmapstart - super_block.inodestart,
+           super_block.nbitmap(), super_block.nblocks, super_block.size);
 
-    for i in 0..params::FSSIZE {
-        fs.write(i as u32, &mut zeroes);
-    }
+    let mut fs = FSHandler::new(&options.image, super_block);
+    fs.set_freeblock(nmeta);
 
     // write superblock
     utils::fill(&mut buf, &fs.superblock_bytes(), 0);
@@ -50,12 +199,22 @@ fn main() {
     let rootino = append_root(&mut fs);
 
     // append each additional file
-    for arg in argv.iter_mut().skip(2) {
+    for arg in options.files.iter() {
         println!("adding {:?}", arg);
         assert!(!arg.contains("/"));
         append_file(rootino, arg, &mut fs);
     }
 
+    for (name, target) in options.symlinks.iter() {
+        println!("adding symlink {:?} -> {:?}", name, target);
+        append_symlink(rootino, name, target, &mut fs);
+    }
+
+    if options.dev {
+        println!("adding /dev");
+        append_dev(rootino, &mut fs);
+    }
+
     let mut din = DINode::new();
     fs.read_inode(rootino, &mut din);
 
@@ -73,7 +232,7 @@ pub fn append_root(fs: &mut FSHandler) -> u32 {
     ///
     /// Inode numbers start at  since 0 was used as a return value  when an inode
     /// could not be found
-    let rootino: u32 = fs.alloc_inode(INodeFileType::Directory);
+    let rootino: u32 = fs.alloc_inode(INodeFileType::Directory, 0o755);
     assert_eq!(rootino, params::ROOTINO as u32);
     let mut dir_entry = DirEntry::new(rootino as u16, ".");
 
@@ -104,8 +263,11 @@ pub fn append_file(root_inum: u32, file_path: &String, fs: &mut FSHandler) {
         file_path.chars().next();
     }
 
+    // Keep the permissions and modification time of the host file
+    let metadata = file.metadata().unwrap();
+
     // Allocate a new inode for a directry entry
-    let inum = fs.alloc_inode(INodeFileType::File);
+    let inum = fs.alloc_inode(INodeFileType::File, metadata.permissions().mode() as u16 & 0o777);
     let mut de = DirEntry::new(inum as u16, &file_path);
 
     // append directory entry to the root inode (1) of the filesystem
@@ -121,4 +283,57 @@ pub fn append_file(root_inum: u32, file_path: &String, fs: &mut FSHandler) {
             break;
         }
     }
+    fs.set_mtime(inum, utils::unix_time(metadata.modified().unwrap()));
+}
+
+pub fn append_symlink(root_inum: u32, name: &str, target: &str, fs: &mut FSHandler) {
+    /// Adds a symbolic link `name` to the root directory, pointing to `target`.
+    /// Like in rv6, the target path is stored as the content of the link's inode.
+    let inum = fs.alloc_inode(INodeFileType::Symlink, 0o777);
+    let mut de = DirEntry::new(inum as u16, name);
+    fs.append_data_to_inode(root_inum, &mut de.bytes());
+    fs.append_data_to_inode(inum, &mut target.as_bytes().to_vec());
+}
+
+/// Adds an empty directory `name` to the directory `parent_inum` and returns its inode
+/// number. The ".." of the new directory is another link to the parent.
+pub fn append_dir(parent_inum: u32, name: &str, permissions: u16, fs: &mut FSHandler) -> u32 {
+    let inum = fs.alloc_inode(INodeFileType::Directory, permissions);
+    let mut de = DirEntry::new(inum as u16, name);
+    fs.append_data_to_inode(parent_inum, &mut de.bytes());
+
+    let mut de = DirEntry::new(inum as u16, ".");
+    fs.append_data_to_inode(inum, &mut de.bytes());
+    let mut de = DirEntry::new(parent_inum as u16, "..");
+    fs.append_data_to_inode(inum, &mut de.bytes());
+
+    let mut parent = DINode::new();
+    fs.read_inode(parent_inum, &mut parent);
+    parent.nlink += 1;
+    fs.write_inode(parent_inum, &parent);
+
+    inum
+}
+
+/// Adds a device node `name` to the directory `dir_inum`. rv6 picks the driver by the
+/// major number, like mknod does.
+pub fn append_device(dir_inum: u32, name: &str, major: i16, minor: i16, permissions: u16, fs: &mut FSHandler) {
+    let inum = fs.alloc_inode(INodeFileType::Device, permissions);
+    let mut de = DirEntry::new(inum as u16, name);
+    fs.append_data_to_inode(dir_inum, &mut de.bytes());
+
+    let mut dinode = DINode::new();
+    fs.read_inode(inum, &mut dinode);
+    dinode.major = major;
+    dinode.minor = minor;
+    fs.write_inode(inum, &dinode);
+}
+
+/// Creates /dev with the devices every rv6 image has. /dev/random is read-only, its bytes
+/// come from the TPM.
+pub fn append_dev(root_inum: u32, fs: &mut FSHandler) {
+    let dev = append_dir(root_inum, "dev", 0o755, fs);
+    append_device(dev, "null", params::NULLMAJOR, 0, 0o666, fs);
+    append_device(dev, "zero", params::ZEROMAJOR, 0, 0o666, fs);
+    append_device(dev, "random", params::RANDOMMAJOR, 0, 0o444, fs);
 }
@@ -3,7 +3,9 @@ use std::mem;
 use crate::inode; // https://github.com/mit-pdos/xv6-public/blob/master/param.h
 
 pub use interface::bdev::BSIZE;
-pub const NINODES: usize = 200;
+pub use interface::vfs::DIRSIZ;
+// Default geometry, can be changed on the command line
+pub const DEFAULT_NINODES: usize = 200;
 pub const NFILE: usize = 100;
 pub const NOFILE: usize = 16;
 // open files per process
@@ -11,11 +13,15 @@ pub const NINODE: usize = 50;
 // maximum number of active i-nodes
 pub const NDEV: i16 = 10;
 // maximum major device number
+pub const NULLMAJOR: i16 = 3;
+pub const ZEROMAJOR: i16 = 4;
+pub const RANDOMMAJOR: i16 = 5;
+// major device numbers of the nodes in /dev, the same as in rv6's params.rs
 pub const ROOTDEV: u32 = 1;
 // device number of file system root disk
-pub const MAXOPBLOCKS: usize = 10;
+pub const MAXOPBLOCKS: usize = 32;
 // max # of blocks any FS op writes
-pub const LOGSIZE: usize = MAXOPBLOCKS * 3;
+pub const DEFAULT_LOGSIZE: usize = MAXOPBLOCKS * 8;
 // max data blocks in on-disk log
 pub const NBUF: usize = MAXOPBLOCKS * 3;
 // size of disk block cache
@@ -23,17 +29,33 @@ pub const SECTOR_SIZE: usize = 512;
 
 pub const BPB: usize = BSIZE * 8;
 // bits per block
-pub const FSSIZE: usize = 15000; // size of file system in blocks
+pub const DEFAULT_FSSIZE: usize = 15000; // size of file system in blocks
 
 // https://github.com/mit-pdos/xv6-riscv/blob/riscv/kernel/fs.h
 pub const ROOTINO: u16 = 1;
 // root i-number
-pub const FSMAGIC: usize = 0x1020_3040;
-pub const NDIRECT: usize = 12;
+// rv6 has no users yet, every inode is owned by root
+pub const ROOTUID: u16 = 0;
+// Stored in the superblock. Bump it whenever the on-disk layout changes, so that an image made
+// for another layout is refused instead of misread. 0x1020_3041: 88-byte DINode with owner,
+// permissions and timestamps.
+pub const FSMAGIC: u32 = 0x1020_3041;
+pub use interface::vfs::NDIRECT;
 pub const NINDIRECT: usize = BSIZE / mem::size_of::<u32>();
 pub const MAXFILE: usize = NDIRECT + NINDIRECT * NINDIRECT;
+// max # of symbolic links followed while resolving a path
+pub const MAXSYMLINKS: usize = 10;
+// largest file the 32-bit size field of an inode can describe
+pub const MAXFILESIZE: usize = if MAXFILE * BSIZE < u32::MAX as usize {
+    MAXFILE * BSIZE
+} else {
+    u32::MAX as usize
+};
 
 pub const IPB: usize = BSIZE / mem::size_of::<inode::DINode>();
-pub const DIRSIZE: usize = 14;
-pub const NINODEBLOCKS: usize = NINODES / IPB + 1;
-pub const NBITMAP: usize = FSSIZE / (BSIZE * 8) + 1;
+// the layout in `interface::vfs` that crashfs reads inodes with
+const _: () = assert!(mem::size_of::<inode::DINode>() == interface::vfs::DINODE_SIZE);
+// inode numbers are 16 bits wide in directory entries
+pub const MAXINODES: usize = u16::MAX as usize;
+// the log header holds the count followed by one block number per log block
+pub const MAXLOGSIZE: usize = BSIZE / mem::size_of::<u32>();
@@ -1,4 +1,5 @@
 use crate::params;
+use byteorder::{ByteOrder, LittleEndian};
 use std::{mem, slice};
 
 // #[derive(Debug, Copy, Clone)]
@@ -17,6 +18,7 @@ pub struct SuperBlock {
     pub inodestart: u32,
     // Block number of first inode block
     pub bmapstart: u32, // Block number of first free map block
+    pub magic: u32,     // Must be FSMAGIC
 }
 
 impl SuperBlock {
@@ -29,23 +31,58 @@ impl SuperBlock {
             logstart: 0u32,
             inodestart: 0u32,
             bmapstart: 0u32,
+            magic: 0u32,
         }
     }
-    pub fn init() -> SuperBlock {
+    pub fn init(size: usize, ninodes: usize, nlog: usize) -> SuperBlock {
         let offset = 2;
-        let nmeta: usize = 2 + params::LOGSIZE + params::NINODEBLOCKS + params::NBITMAP;
-        let nblocks: usize = params::FSSIZE - nmeta;
+        let ninodeblocks = ninodes / params::IPB + 1;
+        let nbitmap = size / params::BPB + 1;
+        let nmeta: usize = 2 + nlog + ninodeblocks + nbitmap;
+        assert!(
+            size > nmeta,
+            "{} blocks are too few for {} inodes and {} log blocks",
+            size,
+            ninodes,
+            nlog
+        );
+        let nblocks: usize = size - nmeta;
 
         SuperBlock {
-            size: params::FSSIZE as u32,
+            size: size as u32,
             nblocks: nblocks as u32,
-            ninodes: params::NINODES as u32,
-            nlog: params::LOGSIZE as u32,
+            ninodes: ninodes as u32,
+            nlog: nlog as u32,
             logstart: offset,
-            inodestart: offset + params::LOGSIZE as u32,
-            bmapstart: offset + params::LOGSIZE as u32 + params::NINODEBLOCKS as u32,
+            inodestart: offset + nlog as u32,
+            bmapstart: offset + nlog as u32 + ninodeblocks as u32,
+            magic: params::FSMAGIC,
         }
     }
+
+    // Number of blocks before the first data block
+    pub fn nmeta(&self) -> u32 {
+        self.size - self.nblocks
+    }
+
+    pub fn nbitmap(&self) -> u32 {
+        self.nmeta() - self.bmapstart
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> SuperBlock {
+        let field = |i: usize| LittleEndian::read_u32(&bytes[i * 4..]);
+        SuperBlock {
+            size: field(0),
+            nblocks: field(1),
+            ninodes: field(2),
+            nlog: field(3),
+            logstart: field(4),
+            inodestart: field(5),
+            bmapstart: field(6),
+            magic: field(7),
+        }
+    }
+
     pub fn bytes(&self) -> &[u8] {
         unsafe {
             slice::from_raw_parts(
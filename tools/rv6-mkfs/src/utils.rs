@@ -23,4 +23,22 @@ pub(crate) fn read_up_to(
         }
     }
     Ok(buf_len - buf.len())
-}
\ No newline at end of file
+}
+// Seconds since the Unix epoch
+pub fn unix_time(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// rwx notation of the permission bits, e.g. 0o754 -> "rwxr-xr--"
+pub fn mode_string(permissions: u16) -> String {
+    let mut mode = String::with_capacity(9);
+    for shift in [6, 3, 0].iter() {
+        let bits = permissions >> shift;
+        mode.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        mode.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        mode.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    mode
+}